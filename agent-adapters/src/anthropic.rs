@@ -3,17 +3,15 @@
 use std::{env, fmt, time::Duration};
 
 use async_trait::async_trait;
-use futures::stream;
-use hyper::body::to_bytes;
 use hyper::header::{CONTENT_TYPE, HeaderValue};
 use hyper::{Body, Request, Uri};
 use serde::{Deserialize, Serialize};
-use tokio::time::timeout;
+use serde_json::{Value, json};
 
-use crate::http_client::{HyperClient, build_https_client};
+use crate::http_client::{HttpClientConfig, HyperClient, build_https_client, send_request};
 use crate::traits::{
-    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk, InferenceRequest,
-    MessageRole, ModelAdapter, PromptMessage,
+    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, BlockReason, InferenceRequest,
+    MessageRole, ModelAdapter, PromptMessage, TokenUsage, completion_stream,
 };
 
 use agent_prompts::ContextWindowConfig;
@@ -33,6 +31,7 @@ pub struct AnthropicConfig {
     timeout: Duration,
     default_temperature: Option<f32>,
     default_max_tokens: u32,
+    http: HttpClientConfig,
 }
 
 impl AnthropicConfig {
@@ -43,9 +42,10 @@ impl AnthropicConfig {
             api_key: None,
             model: model.into(),
             base_url: "https://api.anthropic.com/".to_owned(),
-            timeout: Duration::from_secs(60),
+            timeout: Duration::from_mins(1),
             default_temperature: None,
             default_max_tokens: 4096,
+            http: HttpClientConfig::default(),
         }
     }
 
@@ -89,6 +89,13 @@ impl AnthropicConfig {
         self
     }
 
+    /// Configures proxy, TLS trust, client identity, and connection timeouts.
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClientConfig) -> Self {
+        self.http = http;
+        self
+    }
+
     /// Supplies an explicit API key.
     #[must_use]
     pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
@@ -136,7 +143,7 @@ impl AnthropicAdapter {
                 AdapterError::configuration(format!("invalid Anthropic endpoint: {err}"))
             })?;
 
-        let client = build_https_client()?;
+        let client = build_https_client(&config.http)?;
 
         Ok(Self {
             client,
@@ -168,24 +175,46 @@ impl AnthropicAdapter {
 
     fn build_request(&self, request: &InferenceRequest) -> MessagesRequest {
         // Extract system prompt (Anthropic uses a separate parameter)
-        let system = request.system_prompt().map(ToOwned::to_owned);
+        let system = request.system_prompt().map(|prompt| {
+            if request.system_cache_breakpoint() {
+                MessageContent::Blocks(vec![RequestBlock::Text {
+                    text: prompt.to_owned(),
+                    cache_control: Some(CacheControl::EPHEMERAL),
+                }])
+            } else {
+                MessageContent::Text(prompt.to_owned())
+            }
+        });
 
         // Convert messages, filtering out any system role messages
-        let messages: Vec<AnthropicMessage> = request
-            .messages()
-            .iter()
-            .filter(|msg| msg.role() != MessageRole::System)
-            .map(map_prompt_message)
-            .collect();
+        let messages = map_messages(request.messages());
+
+        let mut max_tokens = request
+            .max_output_tokens()
+            .unwrap_or(self.default_max_tokens);
+        let mut temperature = request.temperature().or(self.default_temperature);
+
+        let thinking = request.thinking_budget().map(|budget_tokens| {
+            // The thinking budget counts against `max_tokens`, so keep the
+            // requested answer allowance on top of it.
+            if max_tokens <= budget_tokens {
+                max_tokens = budget_tokens.saturating_add(max_tokens);
+            }
+            // Extended thinking does not accept a custom temperature.
+            temperature = None;
+            ThinkingConfig {
+                kind: "enabled",
+                budget_tokens,
+            }
+        });
 
         MessagesRequest {
             model: self.metadata.model().to_owned(),
             system,
             messages,
-            max_tokens: request
-                .max_output_tokens()
-                .unwrap_or(self.default_max_tokens),
-            temperature: request.temperature().or(self.default_temperature),
+            max_tokens,
+            temperature,
+            thinking,
             stream: false,
         }
     }
@@ -211,19 +240,18 @@ impl ModelAdapter for AnthropicAdapter {
             HeaderValue::from_static(ANTHROPIC_VERSION),
         );
 
-        let request = builder.body(Body::from(body)).map_err(|err| {
+        let http_request = builder.body(Body::from(body)).map_err(|err| {
             AdapterError::transport(format!("failed to build Anthropic request: {err}"))
         })?;
 
-        let response = timeout(self.timeout, self.client.request(request))
-            .await
-            .map_err(|_| AdapterError::transport("Anthropic request timed out"))?
-            .map_err(|err| AdapterError::transport(format!("Anthropic request failed: {err}")))?;
-
-        let status = response.status();
-        let bytes = to_bytes(response.into_body()).await.map_err(|err| {
-            AdapterError::transport(format!("failed to read Anthropic response: {err}"))
-        })?;
+        let (status, bytes) = send_request(
+            &self.client,
+            http_request,
+            self.timeout,
+            &request,
+            "Anthropic",
+        )
+        .await?;
 
         if !status.is_success() {
             let reason = String::from_utf8_lossy(&bytes).to_string();
@@ -237,18 +265,15 @@ impl ModelAdapter for AnthropicAdapter {
                 reason: format!("failed to decode Anthropic response: {err}"),
             })?;
 
-        let content = response
-            .content
-            .into_iter()
-            .map(|block| {
-                let ContentBlock::Text { text } = block;
-                text
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let stream = stream::once(async move { Ok(InferenceChunk::new(content, true)) });
-        Ok(Box::pin(stream))
+        let usage = response.usage.map(AnthropicUsage::into_usage);
+        let (reasoning, content) = split_content(response.content);
+        if content.is_empty() && response.stop_reason.as_deref() == Some("refusal") {
+            return Err(AdapterError::Blocked {
+                reason: BlockReason::Refusal,
+                categories: Vec::new(),
+            });
+        }
+        Ok(completion_stream(reasoning, content, usage))
     }
 }
 
@@ -256,31 +281,167 @@ impl ModelAdapter for AnthropicAdapter {
 struct MessagesRequest {
     model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    system: Option<MessageContent>,
     messages: Vec<AnthropicMessage>,
     max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<ThinkingConfig>,
     #[serde(default)]
     stream: bool,
 }
 
+#[derive(Debug, Serialize)]
+struct ThinkingConfig {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    budget_tokens: u32,
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: MessageContent,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Blocks(Vec<RequestBlock>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RequestBlock {
+    Text {
+        text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+}
+
+impl RequestBlock {
+    fn set_cache_control(&mut self, control: CacheControl) {
+        match self {
+            Self::Text { cache_control, .. }
+            | Self::ToolUse { cache_control, .. }
+            | Self::ToolResult { cache_control, .. } => *cache_control = Some(control),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+impl CacheControl {
+    const EPHEMERAL: Self = Self { kind: "ephemeral" };
 }
 
 #[derive(Debug, Deserialize)]
 struct MessagesResponse {
     #[serde(default)]
     content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+// Field names mirror the provider wire format.
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl AnthropicUsage {
+    const fn into_usage(self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_tokens: self.cache_read_input_tokens,
+            cache_write_tokens: self.cache_creation_input_tokens,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum ContentBlock {
-    Text { text: String },
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Splits response blocks into `(reasoning, answer)` text.
+fn split_content(blocks: Vec<ContentBlock>) -> (String, String) {
+    let mut reasoning = Vec::new();
+    let mut answer = Vec::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text } => answer.push(text),
+            ContentBlock::Thinking { thinking } => reasoning.push(thinking),
+            ContentBlock::Other => {}
+        }
+    }
+    (reasoning.join("\n"), answer.join("\n"))
+}
+
+fn map_messages(messages: &[PromptMessage]) -> Vec<AnthropicMessage> {
+    let mut mapped: Vec<AnthropicMessage> = Vec::new();
+    for message in messages
+        .iter()
+        .filter(|msg| msg.role() != MessageRole::System)
+    {
+        let next = map_prompt_message(message);
+
+        // Anthropic expects every `tool_result` answering an assistant turn to
+        // arrive together in the following user message.
+        if message.tool_call_id().is_some()
+            && let Some(last) = mapped.last_mut()
+            && let MessageContent::Blocks(blocks) = &mut last.content
+            && blocks
+                .iter()
+                .all(|block| matches!(block, RequestBlock::ToolResult { .. }))
+            && let MessageContent::Blocks(extra) = next.content
+        {
+            blocks.extend(extra);
+            continue;
+        }
+
+        mapped.push(next);
+    }
+    mapped
 }
 
 fn map_prompt_message(message: &PromptMessage) -> AnthropicMessage {
@@ -291,10 +452,56 @@ fn map_prompt_message(message: &PromptMessage) -> AnthropicMessage {
         MessageRole::User | MessageRole::Tool | MessageRole::System => "user",
     };
 
-    let content = if message.role() == MessageRole::Tool {
-        format!("[Tool Output]\n{}", message.content())
+    let content = match (message.role(), message.tool_call_id()) {
+        (MessageRole::Tool, Some(id)) => MessageContent::Blocks(vec![RequestBlock::ToolResult {
+            tool_use_id: id.to_owned(),
+            content: message.content().to_owned(),
+            cache_control: None,
+        }]),
+        (MessageRole::Tool, None) => {
+            MessageContent::Text(format!("[Tool Output]\n{}", message.content()))
+        }
+        _ if !message.tool_calls().is_empty() => {
+            let mut blocks = Vec::with_capacity(message.tool_calls().len() + 1);
+            if !message.content().is_empty() {
+                blocks.push(RequestBlock::Text {
+                    text: message.content().to_owned(),
+                    cache_control: None,
+                });
+            }
+            blocks.extend(message.tool_calls().iter().map(|call| {
+                let input = if call.arguments().is_null() {
+                    json!({})
+                } else {
+                    call.arguments().clone()
+                };
+                RequestBlock::ToolUse {
+                    id: call.id().to_owned(),
+                    name: call.name().to_owned(),
+                    input,
+                    cache_control: None,
+                }
+            }));
+            MessageContent::Blocks(blocks)
+        }
+        _ => MessageContent::Text(message.content().to_owned()),
+    };
+
+    // Cache breakpoints attach to the last content block of the message.
+    let content = if message.cache_breakpoint() {
+        let mut blocks = match content {
+            MessageContent::Text(text) => vec![RequestBlock::Text {
+                text,
+                cache_control: None,
+            }],
+            MessageContent::Blocks(blocks) => blocks,
+        };
+        if let Some(last) = blocks.last_mut() {
+            last.set_cache_control(CacheControl::EPHEMERAL);
+        }
+        MessageContent::Blocks(blocks)
     } else {
-        message.content().to_owned()
+        content
     };
 
     AnthropicMessage {
@@ -321,7 +528,7 @@ fn sanitize_base_url(input: &str) -> AdapterResult<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::traits::{InferenceRequest, MessageRole, PromptMessage};
+    use crate::traits::{InferenceRequest, MessageRole, PromptMessage, ToolCall};
 
     #[test]
     fn base_url_requires_scheme() {
@@ -345,7 +552,128 @@ mod tests {
         let message = PromptMessage::new(MessageRole::Tool, "result");
         let mapped = map_prompt_message(&message);
         assert_eq!(mapped.role, "user");
-        assert!(mapped.content.contains("Tool Output"));
+        assert!(
+            matches!(mapped.content, MessageContent::Text(text) if text.contains("Tool Output"))
+        );
+    }
+
+    #[test]
+    fn tool_turns_use_native_blocks() {
+        let messages = vec![
+            PromptMessage::new(MessageRole::User, "check both"),
+            PromptMessage::assistant_tool_calls(
+                "Checking.",
+                vec![
+                    ToolCall::new("toolu_1", "echo", serde_json::json!({"v": 1})),
+                    ToolCall::new("toolu_2", "echo", Value::Null),
+                ],
+            ),
+            PromptMessage::tool_result("toolu_1", "one"),
+            PromptMessage::tool_result("toolu_2", "two"),
+        ];
+
+        let mapped = serde_json::to_value(map_messages(&messages)).unwrap();
+        assert_eq!(mapped.as_array().unwrap().len(), 3);
+
+        let assistant = &mapped[1];
+        assert_eq!(assistant["role"], "assistant");
+        assert_eq!(assistant["content"][0]["type"], "text");
+        assert_eq!(assistant["content"][1]["type"], "tool_use");
+        assert_eq!(assistant["content"][1]["id"], "toolu_1");
+        assert_eq!(assistant["content"][2]["input"], serde_json::json!({}));
+
+        let results = &mapped[2];
+        assert_eq!(results["role"], "user");
+        assert_eq!(results["content"][0]["type"], "tool_result");
+        assert_eq!(results["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(results["content"][1]["tool_use_id"], "toolu_2");
+        assert_eq!(results["content"][1]["content"], "two");
+    }
+
+    #[test]
+    fn thinking_blocks_are_separated_from_answer() {
+        let json = r#"{
+            "content": [
+                {"type": "thinking", "thinking": "consider", "signature": "sig"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "text", "text": "answer"}
+            ]
+        }"#;
+        let parsed: MessagesResponse = serde_json::from_str(json).unwrap();
+        let (reasoning, answer) = split_content(parsed.content);
+        assert_eq!(reasoning, "consider");
+        assert_eq!(answer, "answer");
+    }
+
+    #[test]
+    fn cache_breakpoints_mark_system_and_messages() {
+        let config = AnthropicConfig::new("claude-3-5-sonnet-20241022").with_api_key("test_key");
+        let adapter = AnthropicAdapter::new(config).expect("adapter");
+
+        let request = InferenceRequest::new(vec![
+            PromptMessage::new(MessageRole::User, "long document").with_cache_breakpoint(),
+            PromptMessage::new(MessageRole::User, "question"),
+        ])
+        .unwrap()
+        .with_system_prompt("static instructions")
+        .with_system_cache_breakpoint();
+
+        let body = serde_json::to_value(adapter.build_request(&request)).unwrap();
+        assert_eq!(
+            body["system"],
+            json!([{
+                "type": "text",
+                "text": "static instructions",
+                "cache_control": {"type": "ephemeral"}
+            }])
+        );
+        assert_eq!(
+            body["messages"][0]["content"],
+            json!([{
+                "type": "text",
+                "text": "long document",
+                "cache_control": {"type": "ephemeral"}
+            }])
+        );
+        assert_eq!(body["messages"][1]["content"], json!("question"));
+    }
+
+    #[test]
+    fn response_usage_reports_cache_activity() {
+        let json = r#"{
+            "content": [{"type": "text", "text": "answer"}],
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 12,
+                "output_tokens": 4,
+                "cache_creation_input_tokens": 0,
+                "cache_read_input_tokens": 2048
+            }
+        }"#;
+        let parsed: MessagesResponse = serde_json::from_str(json).unwrap();
+        let usage = parsed.usage.unwrap().into_usage();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 4);
+        assert_eq!(usage.cache_read_tokens, 2048);
+        assert!(usage.cache_hit());
+    }
+
+    #[test]
+    fn build_request_applies_thinking_budget() {
+        let config = AnthropicConfig::new("claude-3-7-sonnet")
+            .with_api_key("test_key")
+            .with_default_temperature(0.5);
+        let adapter = AnthropicAdapter::new(config).expect("adapter");
+        let request = InferenceRequest::new(vec![PromptMessage::new(MessageRole::User, "hi")])
+            .unwrap()
+            .with_max_output_tokens(1_000)
+            .with_thinking_budget(2_048);
+
+        let payload = serde_json::to_value(adapter.build_request(&request)).unwrap();
+        assert_eq!(payload["thinking"]["type"], "enabled");
+        assert_eq!(payload["thinking"]["budget_tokens"], 2_048);
+        assert_eq!(payload["max_tokens"], 3_048);
+        assert!(payload.get("temperature").is_none());
     }
 
     #[test]
@@ -358,7 +686,10 @@ mod tests {
             .with_system_prompt("You are helpful");
 
         let messages_req = adapter.build_request(&request);
-        assert_eq!(messages_req.system, Some("You are helpful".to_owned()));
+        assert_eq!(
+            serde_json::to_value(&messages_req).unwrap()["system"],
+            json!("You are helpful")
+        );
         assert_eq!(messages_req.messages.len(), 1);
         assert_eq!(messages_req.messages[0].role, "user");
     }
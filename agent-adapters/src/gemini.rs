@@ -3,17 +3,15 @@
 use std::{env, fmt, time::Duration};
 
 use async_trait::async_trait;
-use futures::stream;
-use hyper::body::to_bytes;
 use hyper::header::CONTENT_TYPE;
 use hyper::{Body, Request, Uri};
 use serde::{Deserialize, Serialize};
-use tokio::time::timeout;
+use serde_json::{Value, json};
 
-use crate::http_client::{HyperClient, build_https_client};
+use crate::http_client::{HttpClientConfig, HyperClient, build_https_client, send_request};
 use crate::traits::{
-    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk, InferenceRequest,
-    MessageRole, ModelAdapter, PromptMessage,
+    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, BlockReason, InferenceRequest,
+    MessageRole, ModelAdapter, PromptMessage, TokenUsage, completion_stream,
 };
 
 use agent_prompts::ContextWindowConfig;
@@ -21,6 +19,42 @@ use agent_prompts::ContextWindowConfig;
 /// Environment variable used when loading configuration automatically.
 pub const GEMINI_API_KEY_ENV: &str = "GEMINI_API_KEY";
 
+/// Harm category used in Gemini safety settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarmCategory {
+    /// Negative or harmful comments targeting identity or protected attributes.
+    #[serde(rename = "HARM_CATEGORY_HARASSMENT")]
+    Harassment,
+    /// Rude, disrespectful, or profane content.
+    #[serde(rename = "HARM_CATEGORY_HATE_SPEECH")]
+    HateSpeech,
+    /// References to sexual acts or other lewd content.
+    #[serde(rename = "HARM_CATEGORY_SEXUALLY_EXPLICIT")]
+    SexuallyExplicit,
+    /// Content that promotes or facilitates harmful acts.
+    #[serde(rename = "HARM_CATEGORY_DANGEROUS_CONTENT")]
+    DangerousContent,
+    /// Election-related content.
+    #[serde(rename = "HARM_CATEGORY_CIVIC_INTEGRITY")]
+    CivicIntegrity,
+}
+
+/// Probability threshold at which Gemini blocks content in a harm category.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HarmBlockThreshold {
+    /// Block when the probability of harm is low, medium, or high.
+    BlockLowAndAbove,
+    /// Block when the probability of harm is medium or high.
+    BlockMediumAndAbove,
+    /// Block only when the probability of harm is high.
+    BlockOnlyHigh,
+    /// Never block, but still report safety ratings.
+    BlockNone,
+    /// Disable the safety filter for the category.
+    Off,
+}
+
 /// Configuration for the Gemini adapter.
 #[derive(Clone, Debug)]
 pub struct GeminiConfig {
@@ -29,6 +63,8 @@ pub struct GeminiConfig {
     base_url: String,
     timeout: Duration,
     default_temperature: Option<f32>,
+    safety_settings: Vec<SafetySetting>,
+    http: HttpClientConfig,
 }
 
 impl GeminiConfig {
@@ -39,8 +75,10 @@ impl GeminiConfig {
             api_key: None,
             model: model.into(),
             base_url: "https://generativelanguage.googleapis.com/".to_owned(),
-            timeout: Duration::from_secs(60),
+            timeout: Duration::from_mins(1),
             default_temperature: None,
+            safety_settings: Vec::new(),
+            http: HttpClientConfig::default(),
         }
     }
 
@@ -77,6 +115,30 @@ impl GeminiConfig {
         self
     }
 
+    /// Sets the blocking threshold for a harm category, replacing any earlier
+    /// threshold for the same category.
+    #[must_use]
+    pub fn with_safety_setting(
+        mut self,
+        category: HarmCategory,
+        threshold: HarmBlockThreshold,
+    ) -> Self {
+        self.safety_settings
+            .retain(|setting| setting.category != category);
+        self.safety_settings.push(SafetySetting {
+            category,
+            threshold,
+        });
+        self
+    }
+
+    /// Configures proxy, TLS trust, client identity, and connection timeouts.
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClientConfig) -> Self {
+        self.http = http;
+        self
+    }
+
     /// Supplies an explicit API key.
     #[must_use]
     pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
@@ -93,6 +155,7 @@ pub struct GeminiAdapter {
     api_key: String,
     timeout: Duration,
     default_temperature: Option<f32>,
+    safety_settings: Vec<SafetySetting>,
     context_config: Option<ContextWindowConfig>,
 }
 
@@ -122,7 +185,7 @@ impl GeminiAdapter {
             config.base_url, config.model
         );
 
-        let client = build_https_client()?;
+        let client = build_https_client(&config.http)?;
 
         Ok(Self {
             client,
@@ -131,6 +194,7 @@ impl GeminiAdapter {
             api_key,
             timeout: config.timeout,
             default_temperature: config.default_temperature,
+            safety_settings: config.safety_settings,
             context_config: None,
         })
     }
@@ -154,26 +218,26 @@ impl GeminiAdapter {
     fn build_request(&self, request: &InferenceRequest) -> GenerateContentRequest {
         // Extract system instruction (Gemini uses a separate parameter)
         let system_instruction = request.system_prompt().map(|prompt| SystemInstruction {
-            parts: vec![Part {
-                text: prompt.to_owned(),
-            }],
+            parts: vec![Part::text(prompt)],
         });
 
         // Convert messages to Gemini format
-        let contents: Vec<Content> = request
-            .messages()
-            .iter()
-            .filter(|msg| msg.role() != MessageRole::System)
-            .map(map_prompt_message)
-            .collect();
+        let contents = map_messages(request.messages());
 
         let generation_config = if request.temperature().is_some()
             || self.default_temperature.is_some()
             || request.max_output_tokens().is_some()
+            || request.thinking_budget().is_some()
         {
             Some(GenerationConfig {
                 temperature: request.temperature().or(self.default_temperature),
                 max_output_tokens: request.max_output_tokens(),
+                thinking_config: request
+                    .thinking_budget()
+                    .map(|thinking_budget| ThinkingConfig {
+                        thinking_budget,
+                        include_thoughts: true,
+                    }),
             })
         } else {
             None
@@ -183,6 +247,7 @@ impl GeminiAdapter {
             system_instruction,
             contents,
             generation_config,
+            safety_settings: self.safety_settings.clone(),
         }
     }
 
@@ -214,15 +279,8 @@ impl ModelAdapter for GeminiAdapter {
                 AdapterError::transport(format!("failed to build Gemini request: {err}"))
             })?;
 
-        let response = timeout(self.timeout, self.client.request(req))
-            .await
-            .map_err(|_| AdapterError::transport("Gemini request timed out"))?
-            .map_err(|err| AdapterError::transport(format!("Gemini request failed: {err}")))?;
-
-        let status = response.status();
-        let bytes = to_bytes(response.into_body()).await.map_err(|err| {
-            AdapterError::transport(format!("failed to read Gemini response: {err}"))
-        })?;
+        let (status, bytes) =
+            send_request(&self.client, req, self.timeout, &request, "Gemini").await?;
 
         if !status.is_success() {
             let reason = String::from_utf8_lossy(&bytes).to_string();
@@ -236,16 +294,8 @@ impl ModelAdapter for GeminiAdapter {
                 reason: format!("failed to decode Gemini response: {err}"),
             })?;
 
-        let content = response
-            .candidates
-            .into_iter()
-            .flat_map(|candidate| candidate.content.parts)
-            .map(|part| part.text)
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let stream = stream::once(async move { Ok(InferenceChunk::new(content, true)) });
-        Ok(Box::pin(stream))
+        let (reasoning, content, usage) = parse_response(response)?;
+        Ok(completion_stream(reasoning, content, usage))
     }
 }
 
@@ -257,6 +307,14 @@ struct GenerateContentRequest {
     contents: Vec<Content>,
     #[serde(skip_serializing_if = "Option::is_none")]
     generation_config: Option<GenerationConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct SafetySetting {
+    category: HarmCategory,
+    threshold: HarmBlockThreshold,
 }
 
 #[derive(Debug, Serialize)]
@@ -266,13 +324,45 @@ struct SystemInstruction {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Content {
+    #[serde(default)]
     role: String,
     parts: Vec<Part>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Part {
-    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    /// Set on parts that carry thought summaries rather than answer text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thought: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+}
+
+impl Part {
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionResponse {
+    name: String,
+    response: Value,
 }
 
 #[derive(Debug, Serialize)]
@@ -282,35 +372,231 @@ struct GenerationConfig {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<ThinkingConfig>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThinkingConfig {
+    thinking_budget: u32,
+    include_thoughts: bool,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GenerateContentResponse {
     #[serde(default)]
     candidates: Vec<Candidate>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Candidate {
-    content: Content,
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
 }
 
-fn map_prompt_message(message: &PromptMessage) -> Content {
+#[derive(Debug, Deserialize)]
+struct SafetyRating {
+    category: String,
+    #[serde(default)]
+    blocked: bool,
+    #[serde(default)]
+    probability: Option<String>,
+}
+
+// Field names mirror the provider wire format.
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    cached_content_token_count: u32,
+}
+
+/// Extracts `(reasoning, answer, usage)` from a response, surfacing prompt
+/// and candidate blocks as [`AdapterError::Blocked`].
+fn parse_response(
+    response: GenerateContentResponse,
+) -> AdapterResult<(String, String, Option<TokenUsage>)> {
+    if let Some(feedback) = response.prompt_feedback
+        && let Some(reason) = feedback.block_reason
+    {
+        return Err(blocked(&reason, &feedback.safety_ratings));
+    }
+
+    let usage = response.usage_metadata.map(|meta| TokenUsage {
+        // Gemini counts cached tokens as part of the prompt.
+        input_tokens: meta
+            .prompt_token_count
+            .saturating_sub(meta.cached_content_token_count),
+        output_tokens: meta.candidates_token_count,
+        cache_read_tokens: meta.cached_content_token_count,
+        cache_write_tokens: 0,
+    });
+
+    let mut block = None;
+    let mut parts = Vec::new();
+    for candidate in response.candidates {
+        if let Some(content) = candidate.content {
+            parts.extend(content.parts);
+        }
+        if let Some(reason) = candidate.finish_reason
+            && block_reason(&reason).is_some()
+        {
+            block.get_or_insert((reason, candidate.safety_ratings));
+        }
+    }
+
+    let (reasoning, answer) = split_parts(parts);
+    if answer.is_empty()
+        && let Some((reason, ratings)) = block
+    {
+        return Err(blocked(&reason, &ratings));
+    }
+    Ok((reasoning, answer, usage))
+}
+
+/// Maps a Gemini block or finish reason to a [`BlockReason`], returning
+/// `None` for reasons that do not indicate a block.
+fn block_reason(reason: &str) -> Option<BlockReason> {
+    match reason {
+        "SAFETY" | "IMAGE_SAFETY" => Some(BlockReason::Safety),
+        "RECITATION" => Some(BlockReason::Recitation),
+        "BLOCKLIST" => Some(BlockReason::Blocklist),
+        "PROHIBITED_CONTENT" => Some(BlockReason::ProhibitedContent),
+        "SPII" | "OTHER" => Some(BlockReason::Other(reason.to_owned())),
+        _ => None,
+    }
+}
+
+fn blocked(reason: &str, ratings: &[SafetyRating]) -> AdapterError {
+    let categories = ratings
+        .iter()
+        .filter(|rating| {
+            rating.blocked || matches!(rating.probability.as_deref(), Some("MEDIUM" | "HIGH"))
+        })
+        .map(|rating| rating.category.clone())
+        .collect();
+    AdapterError::Blocked {
+        reason: block_reason(reason).unwrap_or_else(|| BlockReason::Other(reason.to_owned())),
+        categories,
+    }
+}
+
+/// Splits response parts into `(reasoning, answer)` text.
+fn split_parts(parts: impl IntoIterator<Item = Part>) -> (String, String) {
+    let mut reasoning = Vec::new();
+    let mut answer = Vec::new();
+    for part in parts {
+        let Some(text) = part.text else {
+            continue;
+        };
+        if part.thought == Some(true) {
+            reasoning.push(text);
+        } else {
+            answer.push(text);
+        }
+    }
+    (reasoning.join("\n"), answer.join("\n"))
+}
+
+fn map_messages(messages: &[PromptMessage]) -> Vec<Content> {
+    let mut contents: Vec<Content> = Vec::new();
+    for message in messages
+        .iter()
+        .filter(|msg| msg.role() != MessageRole::System)
+    {
+        let next = map_prompt_message(message, messages);
+
+        // Gemini expects all function responses for a model turn in one content entry.
+        let is_response = |content: &Content| {
+            content
+                .parts
+                .iter()
+                .all(|part| part.function_response.is_some())
+        };
+        if is_response(&next)
+            && let Some(last) = contents.last_mut()
+            && is_response(last)
+        {
+            last.parts.extend(next.parts);
+            continue;
+        }
+
+        contents.push(next);
+    }
+    contents
+}
+
+fn map_prompt_message(message: &PromptMessage, history: &[PromptMessage]) -> Content {
     let role = match message.role() {
         MessageRole::Assistant => "model", // Gemini uses "model" instead of "assistant"
         // Tool and System map to "user" (system should be filtered out upstream)
         MessageRole::User | MessageRole::Tool | MessageRole::System => "user",
     };
 
-    let text = if message.role() == MessageRole::Tool {
-        format!("[Tool Output]\n{}", message.content())
+    let parts = if message.role() == MessageRole::Tool {
+        match message.resolve_tool_name(history) {
+            Some(name) => vec![Part {
+                function_response: Some(FunctionResponse {
+                    name: name.to_owned(),
+                    response: function_response_body(message.content()),
+                }),
+                ..Part::default()
+            }],
+            None => vec![Part::text(format!("[Tool Output]\n{}", message.content()))],
+        }
     } else {
-        message.content().to_owned()
+        let mut parts = Vec::with_capacity(message.tool_calls().len() + 1);
+        if !message.content().is_empty() || message.tool_calls().is_empty() {
+            parts.push(Part::text(message.content()));
+        }
+        parts.extend(message.tool_calls().iter().map(|call| Part {
+            function_call: Some(FunctionCall {
+                name: call.name().to_owned(),
+                args: call.arguments().clone(),
+            }),
+            ..Part::default()
+        }));
+        parts
     };
 
     Content {
         role: role.to_owned(),
-        parts: vec![Part { text }],
+        parts,
+    }
+}
+
+/// Gemini requires `functionResponse.response` to be a JSON object, so
+/// non-object tool output is wrapped under a `content` key.
+fn function_response_body(content: &str) -> Value {
+    match serde_json::from_str::<Value>(content) {
+        Ok(Value::Object(map)) => Value::Object(map),
+        Ok(other) => json!({ "content": other }),
+        Err(_) => json!({ "content": content }),
     }
 }
 
@@ -332,7 +618,7 @@ fn sanitize_base_url(input: &str) -> AdapterResult<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::traits::{InferenceRequest, MessageRole, PromptMessage};
+    use crate::traits::{InferenceRequest, MessageRole, PromptMessage, ToolCall};
 
     #[test]
     fn base_url_requires_scheme() {
@@ -354,9 +640,158 @@ mod tests {
     #[test]
     fn prompt_mapping_uses_model_role() {
         let message = PromptMessage::new(MessageRole::Assistant, "response");
-        let mapped = map_prompt_message(&message);
+        let mapped = map_prompt_message(&message, &[]);
         assert_eq!(mapped.role, "model");
-        assert_eq!(mapped.parts[0].text, "response");
+        assert_eq!(mapped.parts[0].text.as_deref(), Some("response"));
+    }
+
+    #[test]
+    fn thought_parts_are_separated_from_answer() {
+        let json = r#"{
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        {"text": "thinking it over", "thought": true},
+                        {"text": "final answer"}
+                    ]
+                }
+            }]
+        }"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(json).unwrap();
+        let (reasoning, answer, usage) = parse_response(parsed).unwrap();
+        assert_eq!(reasoning, "thinking it over");
+        assert_eq!(answer, "final answer");
+        assert!(usage.is_none());
+    }
+
+    #[test]
+    fn blocked_prompt_returns_typed_reason() {
+        let json = r#"{
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "HIGH"},
+                    {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "NEGLIGIBLE"}
+                ]
+            }
+        }"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(json).unwrap();
+        let err = parse_response(parsed).expect_err("blocked prompt");
+        let AdapterError::Blocked { reason, categories } = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(reason, BlockReason::Safety);
+        assert_eq!(categories, vec!["HARM_CATEGORY_HARASSMENT".to_owned()]);
+    }
+
+    #[test]
+    fn blocked_candidate_without_text_returns_typed_reason() {
+        let json = r#"{
+            "candidates": [{
+                "finishReason": "RECITATION",
+                "safetyRatings": []
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "candidatesTokenCount": 0,
+                "cachedContentTokenCount": 60
+            }
+        }"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(json).unwrap();
+        let err = parse_response(parsed).expect_err("blocked candidate");
+        assert!(matches!(
+            err,
+            AdapterError::Blocked {
+                reason: BlockReason::Recitation,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn usage_metadata_reports_cached_tokens() {
+        let json = r#"{
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "hi"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "candidatesTokenCount": 5,
+                "cachedContentTokenCount": 60
+            }
+        }"#;
+        let parsed: GenerateContentResponse = serde_json::from_str(json).unwrap();
+        let (_, answer, usage) = parse_response(parsed).unwrap();
+        assert_eq!(answer, "hi");
+        let usage = usage.unwrap();
+        assert_eq!(usage.input_tokens, 40);
+        assert_eq!(usage.output_tokens, 5);
+        assert_eq!(usage.cache_read_tokens, 60);
+        assert!(usage.cache_hit());
+    }
+
+    #[test]
+    fn safety_settings_are_serialized() {
+        let config = GeminiConfig::new("gemini-1.5-pro")
+            .with_api_key("test_key")
+            .with_safety_setting(
+                HarmCategory::Harassment,
+                HarmBlockThreshold::BlockLowAndAbove,
+            )
+            .with_safety_setting(HarmCategory::Harassment, HarmBlockThreshold::BlockOnlyHigh)
+            .with_safety_setting(HarmCategory::DangerousContent, HarmBlockThreshold::Off);
+        let adapter = GeminiAdapter::new(config).expect("adapter");
+
+        let request =
+            InferenceRequest::new(vec![PromptMessage::new(MessageRole::User, "hello")]).unwrap();
+        let payload = serde_json::to_value(adapter.build_request(&request)).unwrap();
+        assert_eq!(
+            payload["safetySettings"],
+            json!([
+                {"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH"},
+                {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "OFF"}
+            ])
+        );
+    }
+
+    #[test]
+    fn tool_turns_use_function_call_parts() {
+        let messages = vec![
+            PromptMessage::new(MessageRole::User, "weather?"),
+            PromptMessage::assistant_tool_calls(
+                "",
+                vec![
+                    ToolCall::new("call_0", "weather", serde_json::json!({"city": "Oslo"})),
+                    ToolCall::new("call_1", "clock", Value::Null),
+                ],
+            ),
+            PromptMessage::tool_result("call_0", "{\"temp\":3}"),
+            PromptMessage::tool_result("call_1", "\"09:00\""),
+        ];
+
+        let contents = serde_json::to_value(map_messages(&messages)).unwrap();
+        assert_eq!(contents.as_array().unwrap().len(), 3);
+
+        let model = &contents[1];
+        assert_eq!(model["role"], "model");
+        assert_eq!(model["parts"].as_array().unwrap().len(), 2);
+        assert_eq!(model["parts"][0]["functionCall"]["name"], "weather");
+        assert_eq!(model["parts"][0]["functionCall"]["args"]["city"], "Oslo");
+
+        let responses = &contents[2];
+        assert_eq!(responses["role"], "user");
+        assert_eq!(responses["parts"][0]["functionResponse"]["name"], "weather");
+        assert_eq!(
+            responses["parts"][0]["functionResponse"]["response"]["temp"],
+            3
+        );
+        assert_eq!(responses["parts"][1]["functionResponse"]["name"], "clock");
+        assert_eq!(
+            responses["parts"][1]["functionResponse"]["response"]["content"],
+            "09:00"
+        );
     }
 
     #[test]
@@ -371,8 +806,8 @@ mod tests {
         let gen_req = adapter.build_request(&request);
         assert!(gen_req.system_instruction.is_some());
         assert_eq!(
-            gen_req.system_instruction.unwrap().parts[0].text,
-            "You are helpful"
+            gen_req.system_instruction.unwrap().parts[0].text.as_deref(),
+            Some("You are helpful")
         );
         assert_eq!(gen_req.contents.len(), 1);
     }
@@ -393,4 +828,19 @@ mod tests {
         assert_eq!(gen_req.contents.len(), 1);
         assert_eq!(gen_req.contents[0].role, "user");
     }
+
+    #[test]
+    fn build_request_applies_thinking_budget() {
+        let config = GeminiConfig::new("gemini-2.5-flash").with_api_key("test_key");
+        let adapter = GeminiAdapter::new(config).expect("adapter");
+
+        let request = InferenceRequest::new(vec![PromptMessage::new(MessageRole::User, "hello")])
+            .unwrap()
+            .with_thinking_budget(1_024);
+
+        let payload = serde_json::to_value(adapter.build_request(&request)).unwrap();
+        let thinking = &payload["generationConfig"]["thinkingConfig"];
+        assert_eq!(thinking["thinkingBudget"], 1_024);
+        assert_eq!(thinking["includeThoughts"], true);
+    }
 }
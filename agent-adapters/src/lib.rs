@@ -7,9 +7,8 @@
 
 pub mod anthropic;
 pub mod gemini;
+pub mod http_client;
 pub mod mxp_model;
 pub mod ollama;
 pub mod openai;
 pub mod traits;
-
-mod http_client;
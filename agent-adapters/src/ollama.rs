@@ -3,17 +3,15 @@
 use std::{fmt, time::Duration};
 
 use async_trait::async_trait;
-use futures::stream;
-use hyper::body::to_bytes;
 use hyper::header::CONTENT_TYPE;
 use hyper::{Body, Request, Uri};
 use serde::{Deserialize, Serialize};
-use tokio::time::timeout;
+use serde_json::Value;
 
-use crate::http_client::{HyperClient, build_https_client};
+use crate::http_client::{HttpClientConfig, HyperClient, build_https_client, send_request};
 use crate::traits::{
-    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk, InferenceRequest,
-    MessageRole, ModelAdapter, PromptMessage,
+    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, InferenceRequest, ModelAdapter,
+    PromptMessage, completion_stream,
 };
 
 use agent_prompts::ContextWindowConfig;
@@ -25,6 +23,7 @@ pub struct OllamaConfig {
     model: String,
     default_temperature: Option<f32>,
     timeout: Duration,
+    http: HttpClientConfig,
 }
 
 impl OllamaConfig {
@@ -40,7 +39,8 @@ impl OllamaConfig {
             base_url: "http://127.0.0.1:11434/".to_owned(),
             model: model.into(),
             default_temperature: None,
-            timeout: Duration::from_secs(60),
+            timeout: Duration::from_mins(1),
+            http: HttpClientConfig::default(),
         }
     }
 
@@ -69,6 +69,13 @@ impl OllamaConfig {
         self.timeout = timeout;
         self
     }
+
+    /// Configures proxy, TLS trust, client identity, and connection timeouts.
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClientConfig) -> Self {
+        self.http = http;
+        self
+    }
 }
 
 /// `Ollama` adapter that calls the local Ollama daemon over HTTP/HTTPS.
@@ -105,7 +112,7 @@ impl OllamaAdapter {
                 AdapterError::configuration(format!("invalid Ollama endpoint: {err}"))
             })?;
 
-        let client = build_https_client()?;
+        let client = build_https_client(&config.http)?;
         let metadata = AdapterMetadata::new("ollama", config.model.clone());
 
         Ok(Self {
@@ -142,11 +149,17 @@ impl OllamaAdapter {
             messages.push(ChatMessage {
                 role: "system".to_owned(),
                 content: system_prompt.to_owned(),
+                ..ChatMessage::default()
             });
         }
 
         // Add conversation messages
-        messages.extend(request.messages().iter().map(map_prompt_message));
+        let history = request.messages();
+        messages.extend(
+            history
+                .iter()
+                .map(|message| map_prompt_message(message, history)),
+        );
 
         let options = if request.temperature().is_some()
             || self.default_temperature.is_some()
@@ -165,6 +178,8 @@ impl OllamaAdapter {
             stream: false,
             messages,
             options,
+            // Ollama has no reasoning budget; any budget enables thinking output.
+            think: request.thinking_budget().map(|_| true),
         }
     }
 }
@@ -188,15 +203,8 @@ impl ModelAdapter for OllamaAdapter {
                 AdapterError::transport(format!("failed to build Ollama request: {err}"))
             })?;
 
-        let response = timeout(self.timeout, self.client.request(req))
-            .await
-            .map_err(|_| AdapterError::transport("Ollama request timed out"))?
-            .map_err(|err| AdapterError::transport(format!("Ollama request failed: {err}")))?;
-
-        let status = response.status();
-        let bytes = to_bytes(response.into_body()).await.map_err(|err| {
-            AdapterError::transport(format!("failed to read Ollama response: {err}"))
-        })?;
+        let (status, bytes) =
+            send_request(&self.client, req, self.timeout, &request, "Ollama").await?;
 
         if !status.is_success() {
             let reason = String::from_utf8_lossy(&bytes).to_string();
@@ -214,14 +222,8 @@ impl ModelAdapter for OllamaAdapter {
             return Err(AdapterError::Response { reason: error });
         }
 
-        let content = response
-            .message
-            .map(|message| message.content)
-            .or(response.response)
-            .unwrap_or_default();
-
-        let stream = stream::once(async move { Ok(InferenceChunk::new(content, true)) });
-        Ok(Box::pin(stream))
+        let (reasoning, content) = split_response(response);
+        Ok(completion_stream(reasoning, content, None))
     }
 }
 
@@ -232,12 +234,33 @@ struct ChatRequest {
     messages: Vec<ChatMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<ChatOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    think: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct ChatMessage {
     role: String,
+    #[serde(default)]
     content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+    #[serde(default, skip_serializing)]
+    thinking: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
 }
 
 #[derive(Debug, Serialize)]
@@ -260,16 +283,46 @@ struct ChatResponse {
     error: Option<String>,
 }
 
-fn map_prompt_message(message: &PromptMessage) -> ChatMessage {
-    match message.role() {
-        MessageRole::Tool => ChatMessage {
-            role: "user".to_owned(),
-            content: format!("[tool output] {}", message.content()),
-        },
-        role => ChatMessage {
-            role: role.to_string(),
-            content: message.content().to_owned(),
-        },
+/// Splits a chat response into `(reasoning, answer)` text.
+///
+/// Reasoning is read from the native `thinking` field when present, otherwise
+/// from a leading `<think>...</think>` section emitted inline by older servers.
+fn split_response(response: ChatResponse) -> (String, String) {
+    let (thinking, content) = match response.message {
+        Some(message) => (message.thinking, message.content),
+        None => (None, response.response.unwrap_or_default()),
+    };
+    if let Some(thinking) = thinking {
+        return (thinking, content);
+    }
+
+    let trimmed = content.trim_start();
+    if let Some(rest) = trimmed.strip_prefix("<think>")
+        && let Some((reasoning, answer)) = rest.split_once("</think>")
+    {
+        return (reasoning.trim().to_owned(), answer.trim_start().to_owned());
+    }
+    (String::new(), content)
+}
+
+fn map_prompt_message(message: &PromptMessage, history: &[PromptMessage]) -> ChatMessage {
+    let tool_calls = message
+        .tool_calls()
+        .iter()
+        .map(|call| OllamaToolCall {
+            function: OllamaFunctionCall {
+                name: call.name().to_owned(),
+                arguments: call.arguments().clone(),
+            },
+        })
+        .collect();
+
+    ChatMessage {
+        role: message.role().to_string(),
+        content: message.content().to_owned(),
+        tool_calls,
+        tool_name: message.resolve_tool_name(history).map(str::to_owned),
+        thinking: None,
     }
 }
 
@@ -291,7 +344,7 @@ fn sanitize_base_url(input: &str) -> AdapterResult<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::traits::{InferenceRequest, MessageRole, PromptMessage};
+    use crate::traits::{InferenceRequest, MessageRole, PromptMessage, ToolCall};
 
     #[test]
     fn rejects_base_url_without_scheme() {
@@ -312,9 +365,36 @@ mod tests {
     #[test]
     fn prompt_mapping_handles_tool_role() {
         let message = PromptMessage::new(MessageRole::Tool, "output");
-        let mapped = map_prompt_message(&message);
-        assert_eq!(mapped.role, "user");
-        assert!(mapped.content.contains("tool output"));
+        let mapped = map_prompt_message(&message, &[]);
+        assert_eq!(mapped.role, "tool");
+        assert_eq!(mapped.content, "output");
+        assert!(mapped.tool_name.is_none());
+    }
+
+    #[test]
+    fn prompt_mapping_preserves_tool_calls_and_names() {
+        let history = vec![
+            PromptMessage::assistant_tool_calls(
+                "",
+                vec![ToolCall::new(
+                    "call_0",
+                    "weather",
+                    serde_json::json!({"city": "Oslo"}),
+                )],
+            ),
+            PromptMessage::tool_result("call_0", "sunny"),
+        ];
+
+        let assistant = map_prompt_message(&history[0], &history);
+        assert_eq!(assistant.role, "assistant");
+        assert_eq!(assistant.tool_calls.len(), 1);
+        assert_eq!(assistant.tool_calls[0].function.name, "weather");
+        assert_eq!(assistant.tool_calls[0].function.arguments["city"], "Oslo");
+
+        let result = map_prompt_message(&history[1], &history);
+        assert_eq!(result.role, "tool");
+        assert_eq!(result.tool_name.as_deref(), Some("weather"));
+        assert_eq!(result.content, "sunny");
     }
 
     #[test]
@@ -340,4 +420,25 @@ mod tests {
         assert_eq!(chat.messages.len(), 1);
         assert!(chat.options.is_some());
     }
+
+    #[test]
+    fn reasoning_is_split_from_answer() {
+        let native: ChatResponse = serde_json::from_str(
+            r#"{"message": {"role": "assistant", "content": "4", "thinking": "2 + 2"}}"#,
+        )
+        .unwrap();
+        assert_eq!(split_response(native), ("2 + 2".to_owned(), "4".to_owned()));
+
+        let inline: ChatResponse = serde_json::from_str(
+            r#"{"message": {"role": "assistant", "content": "<think>\nhmm\n</think>\n\nanswer"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            split_response(inline),
+            ("hmm".to_owned(), "answer".to_owned())
+        );
+
+        let plain: ChatResponse = serde_json::from_str(r#"{"response": "plain"}"#).unwrap();
+        assert_eq!(split_response(plain), (String::new(), "plain".to_owned()));
+    }
 }
@@ -3,17 +3,14 @@
 use std::{env, fmt, time::Duration};
 
 use async_trait::async_trait;
-use futures::stream;
-use hyper::body::to_bytes;
 use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
 use hyper::{Body, Request, Uri};
 use serde::{Deserialize, Serialize};
-use tokio::time::timeout;
 
-use crate::http_client::{HyperClient, build_https_client};
+use crate::http_client::{HttpClientConfig, HyperClient, build_https_client, send_request};
 use crate::traits::{
-    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk, InferenceRequest,
-    ModelAdapter, PromptMessage,
+    AdapterError, AdapterMetadata, AdapterResult, AdapterStream, BlockReason, InferenceRequest,
+    ModelAdapter, PromptMessage, TokenUsage, ToolCall, completion_stream,
 };
 
 use agent_prompts::ContextWindowConfig;
@@ -29,6 +26,7 @@ pub struct OpenAiConfig {
     base_url: String,
     timeout: Duration,
     default_temperature: Option<f32>,
+    http: HttpClientConfig,
 }
 
 impl OpenAiConfig {
@@ -39,8 +37,9 @@ impl OpenAiConfig {
             api_key: None,
             model: model.into(),
             base_url: "https://api.openai.com/".to_owned(),
-            timeout: Duration::from_secs(60),
+            timeout: Duration::from_mins(1),
             default_temperature: None,
+            http: HttpClientConfig::default(),
         }
     }
 
@@ -77,6 +76,13 @@ impl OpenAiConfig {
         self
     }
 
+    /// Configures proxy, TLS trust, client identity, and connection timeouts.
+    #[must_use]
+    pub fn with_http_client(mut self, http: HttpClientConfig) -> Self {
+        self.http = http;
+        self
+    }
+
     /// Supplies an explicit API key.
     #[must_use]
     pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
@@ -123,7 +129,7 @@ impl OpenAiAdapter {
                 AdapterError::configuration(format!("invalid OpenAI endpoint: {err}"))
             })?;
 
-        let client = build_https_client()?;
+        let client = build_https_client(&config.http)?;
 
         Ok(Self {
             client,
@@ -160,17 +166,32 @@ impl OpenAiAdapter {
             messages.push(OpenAiMessage {
                 role: "system".to_owned(),
                 content: system_prompt.to_owned(),
+                tool_calls: Vec::new(),
+                tool_call_id: None,
             });
         }
 
         // Add conversation messages
         messages.extend(request.messages().iter().map(map_prompt_message));
 
+        // Reasoning models reject `temperature` and `max_tokens`.
+        let reasoning_effort = request.thinking_budget().map(reasoning_effort);
+        let (temperature, max_tokens, max_completion_tokens) = if reasoning_effort.is_some() {
+            (None, None, request.max_output_tokens())
+        } else {
+            (
+                request.temperature().or(self.default_temperature),
+                request.max_output_tokens(),
+                None,
+            )
+        };
         ChatCompletionRequest {
             model: self.metadata.model().to_owned(),
             messages,
-            temperature: request.temperature().or(self.default_temperature),
-            max_tokens: request.max_output_tokens(),
+            temperature,
+            max_tokens,
+            max_completion_tokens,
+            reasoning_effort,
             stream: false,
         }
     }
@@ -192,19 +213,12 @@ impl ModelAdapter for OpenAiAdapter {
         builder = builder.header(CONTENT_TYPE, "application/json");
         builder = builder.header(AUTHORIZATION, format!("Bearer {}", self.api_key));
 
-        let request = builder.body(Body::from(body)).map_err(|err| {
+        let http_request = builder.body(Body::from(body)).map_err(|err| {
             AdapterError::transport(format!("failed to build OpenAI request: {err}"))
         })?;
 
-        let response = timeout(self.timeout, self.client.request(request))
-            .await
-            .map_err(|_| AdapterError::transport("OpenAI request timed out"))?
-            .map_err(|err| AdapterError::transport(format!("OpenAI request failed: {err}")))?;
-
-        let status = response.status();
-        let bytes = to_bytes(response.into_body()).await.map_err(|err| {
-            AdapterError::transport(format!("failed to read OpenAI response: {err}"))
-        })?;
+        let (status, bytes) =
+            send_request(&self.client, http_request, self.timeout, &request, "OpenAI").await?;
 
         if !status.is_success() {
             let reason = String::from_utf8_lossy(&bytes).to_string();
@@ -218,14 +232,27 @@ impl ModelAdapter for OpenAiAdapter {
                 reason: format!("failed to decode OpenAI response: {err}"),
             })?;
 
-        let content = response
+        let usage = response.usage.map(OpenAiUsage::into_usage);
+        let message = response
             .choices
             .into_iter()
-            .find_map(|choice| choice.message.and_then(|message| message.content))
+            .find_map(|choice| choice.message)
             .unwrap_or_default();
 
-        let stream = stream::once(async move { Ok(InferenceChunk::new(content, true)) });
-        Ok(Box::pin(stream))
+        if message.content.as_deref().is_none_or(str::is_empty)
+            && let Some(refusal) = message.refusal
+        {
+            return Err(AdapterError::Blocked {
+                reason: BlockReason::Refusal,
+                categories: vec![refusal],
+            });
+        }
+
+        Ok(completion_stream(
+            message.reasoning_content.unwrap_or_default(),
+            message.content.unwrap_or_default(),
+            usage,
+        ))
     }
 }
 
@@ -237,6 +264,10 @@ struct ChatCompletionRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "max_tokens")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
     #[serde(default)]
     stream: bool,
 }
@@ -245,12 +276,65 @@ struct ChatCompletionRequest {
 struct OpenAiMessage {
     role: String,
     content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAiToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OpenAiFunctionCall,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiFunctionCall {
+    name: String,
+    /// `OpenAI` expects arguments as a JSON-encoded string rather than an object.
+    arguments: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatCompletionResponse {
     #[serde(default)]
     choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAiUsage {
+    #[serde(default)]
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+impl OpenAiUsage {
+    fn into_usage(self) -> TokenUsage {
+        // OpenAI caches prompt prefixes automatically and counts cached tokens
+        // as part of the prompt.
+        let cached = self
+            .prompt_tokens_details
+            .map_or(0, |details| details.cached_tokens);
+        TokenUsage {
+            input_tokens: self.prompt_tokens.saturating_sub(cached),
+            output_tokens: self.completion_tokens,
+            cache_read_tokens: cached,
+            cache_write_tokens: 0,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -259,16 +343,43 @@ struct ChatChoice {
     message: Option<ChoiceMessage>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct ChoiceMessage {
     #[serde(default)]
     content: Option<String>,
+    /// Reasoning text returned by `OpenAI`-compatible reasoning servers.
+    #[serde(default)]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    refusal: Option<String>,
+}
+
+/// `OpenAI` exposes coarse effort levels rather than a token budget.
+fn reasoning_effort(budget: u32) -> &'static str {
+    match budget {
+        0..=2_048 => "low",
+        2_049..=8_192 => "medium",
+        _ => "high",
+    }
 }
 
 fn map_prompt_message(message: &PromptMessage) -> OpenAiMessage {
     OpenAiMessage {
         role: message.role().to_string(),
         content: message.content().to_owned(),
+        tool_calls: message.tool_calls().iter().map(map_tool_call).collect(),
+        tool_call_id: message.tool_call_id().map(ToOwned::to_owned),
+    }
+}
+
+fn map_tool_call(call: &ToolCall) -> OpenAiToolCall {
+    OpenAiToolCall {
+        id: call.id().to_owned(),
+        kind: "function",
+        function: OpenAiFunctionCall {
+            name: call.name().to_owned(),
+            arguments: call.arguments().to_string(),
+        },
     }
 }
 
@@ -317,6 +428,29 @@ mod tests {
         assert_eq!(mapped.content, "hello");
     }
 
+    #[test]
+    fn prompt_mapping_preserves_tool_calls_and_results() {
+        let call = PromptMessage::assistant_tool_calls(
+            "",
+            vec![ToolCall::new("call_1", "echo", serde_json::json!({"v": 1}))],
+        );
+        let mapped = serde_json::to_value(map_prompt_message(&call)).unwrap();
+        assert_eq!(mapped["role"], "assistant");
+        assert_eq!(mapped["tool_calls"][0]["id"], "call_1");
+        assert_eq!(mapped["tool_calls"][0]["type"], "function");
+        assert_eq!(mapped["tool_calls"][0]["function"]["name"], "echo");
+        assert_eq!(
+            mapped["tool_calls"][0]["function"]["arguments"],
+            "{\"v\":1}"
+        );
+
+        let result = PromptMessage::tool_result("call_1", "{\"v\":1}");
+        let mapped = serde_json::to_value(map_prompt_message(&result)).unwrap();
+        assert_eq!(mapped["role"], "tool");
+        assert_eq!(mapped["tool_call_id"], "call_1");
+        assert!(mapped.get("tool_calls").is_none());
+    }
+
     #[test]
     fn response_parsing_extracts_content() {
         let json = r#"{
@@ -335,6 +469,50 @@ mod tests {
         assert_eq!(content, "hi");
     }
 
+    #[test]
+    fn reasoning_content_and_budget_are_mapped() {
+        let json = r#"{
+            "choices": [
+                { "message": { "content": "42", "reasoning_content": "6 * 7" } }
+            ]
+        }"#;
+        let parsed: ChatCompletionResponse = serde_json::from_str(json).unwrap();
+        let message = parsed.choices.into_iter().find_map(|c| c.message).unwrap();
+        assert_eq!(message.reasoning_content.as_deref(), Some("6 * 7"));
+        assert_eq!(message.content.as_deref(), Some("42"));
+
+        let adapter =
+            OpenAiAdapter::new(OpenAiConfig::new("o3").with_api_key("test_key")).expect("adapter");
+        let request = InferenceRequest::new(vec![PromptMessage::new(MessageRole::User, "hi")])
+            .unwrap()
+            .with_temperature(0.2)
+            .with_max_output_tokens(512)
+            .with_thinking_budget(16_000);
+        let built = adapter.build_request(&request);
+        assert_eq!(built.reasoning_effort, Some("high"));
+        assert_eq!(built.temperature, None);
+        assert_eq!(built.max_tokens, None);
+        assert_eq!(built.max_completion_tokens, Some(512));
+        assert_eq!(reasoning_effort(1_024), "low");
+    }
+
+    #[test]
+    fn usage_reports_cached_prompt_tokens() {
+        let json = r#"{
+            "choices": [{ "message": { "content": "ok" } }],
+            "usage": {
+                "prompt_tokens": 2000,
+                "completion_tokens": 3,
+                "prompt_tokens_details": { "cached_tokens": 1536 }
+            }
+        }"#;
+        let parsed: ChatCompletionResponse = serde_json::from_str(json).unwrap();
+        let usage = parsed.usage.unwrap().into_usage();
+        assert_eq!(usage.input_tokens, 464);
+        assert_eq!(usage.cache_read_tokens, 1536);
+        assert!(usage.cache_hit());
+    }
+
     #[test]
     fn build_request_uses_defaults() {
         let config = OpenAiConfig::new("gpt-4")
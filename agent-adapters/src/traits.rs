@@ -2,13 +2,16 @@
 
 use std::fmt;
 use std::pin::Pin;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
 
+pub use tokio_util::sync::CancellationToken;
+
 /// Result alias used by model adapters.
 pub type AdapterResult<T> = Result<T, AdapterError>;
 
@@ -52,6 +55,54 @@ pub enum AdapterError {
         /// Additional context about the response failure.
         reason: String,
     },
+
+    /// The request was cancelled through its [`CancellationToken`].
+    #[error("inference request cancelled")]
+    Cancelled,
+
+    /// The request deadline elapsed before the provider responded.
+    #[error("inference request deadline exceeded")]
+    DeadlineExceeded,
+
+    /// The provider withheld the response, for example because of safety filters.
+    #[error("response blocked by provider: {reason}")]
+    Blocked {
+        /// Why the provider blocked the response.
+        reason: BlockReason,
+        /// Provider categories (such as harm categories) that triggered the block.
+        categories: Vec<String>,
+    },
+}
+
+/// Reason a provider withheld a response.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockReason {
+    /// Safety filters flagged the prompt or the response.
+    Safety,
+    /// The response recited protected material.
+    Recitation,
+    /// The prompt or response matched a configured blocklist.
+    Blocklist,
+    /// The content is prohibited by provider policy.
+    ProhibitedContent,
+    /// The model refused to answer.
+    Refusal,
+    /// Provider-specific reason not covered by the other variants.
+    Other(String),
+}
+
+impl fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Safety => f.write_str("safety"),
+            Self::Recitation => f.write_str("recitation"),
+            Self::Blocklist => f.write_str("blocklist"),
+            Self::ProhibitedContent => f.write_str("prohibited content"),
+            Self::Refusal => f.write_str("refusal"),
+            Self::Other(reason) => f.write_str(reason),
+        }
+    }
 }
 
 impl AdapterError {
@@ -145,11 +196,56 @@ impl fmt::Display for MessageRole {
     }
 }
 
+/// Tool invocation requested by the assistant during a conversation turn.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ToolCall {
+    id: String,
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+impl ToolCall {
+    /// Creates a tool call with the supplied identifier, tool name, and JSON arguments.
+    #[must_use]
+    pub fn new(id: impl Into<String>, name: impl Into<String>, arguments: Value) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            arguments,
+        }
+    }
+
+    /// Returns the identifier correlating this call with its tool result.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the name of the tool being invoked.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the JSON arguments supplied to the tool.
+    #[must_use]
+    pub fn arguments(&self) -> &Value {
+        &self.arguments
+    }
+}
+
 /// Represents an instruction or message in a chat-style prompt.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct PromptMessage {
     role: MessageRole,
     content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    cache_breakpoint: bool,
 }
 
 impl PromptMessage {
@@ -159,9 +255,52 @@ impl PromptMessage {
         Self {
             role,
             content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            cache_breakpoint: false,
         }
     }
 
+    /// Creates an assistant turn that requests the supplied tool calls.
+    ///
+    /// `content` carries any text the assistant produced alongside the calls and
+    /// may be empty.
+    #[must_use]
+    pub fn assistant_tool_calls(content: impl Into<String>, calls: Vec<ToolCall>) -> Self {
+        Self::new(MessageRole::Assistant, content).with_tool_calls(calls)
+    }
+
+    /// Creates a tool message carrying the result for the call identified by
+    /// `tool_call_id`.
+    #[must_use]
+    pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::new(MessageRole::Tool, content).with_tool_call_id(tool_call_id)
+    }
+
+    /// Attaches assistant tool calls to the message.
+    #[must_use]
+    pub fn with_tool_calls(mut self, calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = calls;
+        self
+    }
+
+    /// Associates the message with the tool call it answers.
+    #[must_use]
+    pub fn with_tool_call_id(mut self, tool_call_id: impl Into<String>) -> Self {
+        self.tool_call_id = Some(tool_call_id.into());
+        self
+    }
+
+    /// Marks the end of a cacheable prompt prefix at this message.
+    ///
+    /// Providers with explicit prompt caching (Anthropic) place a cache
+    /// breakpoint here; other providers ignore the hint.
+    #[must_use]
+    pub fn with_cache_breakpoint(mut self) -> Self {
+        self.cache_breakpoint = true;
+        self
+    }
+
     /// Returns the message role.
     #[must_use]
     pub const fn role(&self) -> MessageRole {
@@ -173,6 +312,36 @@ impl PromptMessage {
     pub fn content(&self) -> &str {
         &self.content
     }
+
+    /// Returns the tool calls requested by an assistant message.
+    #[must_use]
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+
+    /// Returns the identifier of the tool call answered by a tool message.
+    #[must_use]
+    pub fn tool_call_id(&self) -> Option<&str> {
+        self.tool_call_id.as_deref()
+    }
+
+    /// Returns `true` when a prompt cache breakpoint follows this message.
+    #[must_use]
+    pub const fn cache_breakpoint(&self) -> bool {
+        self.cache_breakpoint
+    }
+
+    /// Resolves the tool name for a tool message by looking up its
+    /// `tool_call_id` in the assistant turns of `history`.
+    #[must_use]
+    pub fn resolve_tool_name<'a>(&self, history: &'a [PromptMessage]) -> Option<&'a str> {
+        let id = self.tool_call_id.as_deref()?;
+        history
+            .iter()
+            .flat_map(|message| message.tool_calls.iter())
+            .find(|call| call.id == id)
+            .map(ToolCall::name)
+    }
 }
 
 /// Request submitted to a model adapter.
@@ -186,6 +355,8 @@ pub struct InferenceRequest {
     /// - `Ollama`: Prepended as `{"role": "system", "content": "..."}`
     #[serde(default, skip_serializing_if = "Option::is_none")]
     system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    system_cache_breakpoint: bool,
     /// Conversation messages (user, assistant, tool).
     messages: Vec<PromptMessage>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -194,6 +365,24 @@ pub struct InferenceRequest {
     temperature: Option<f32>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thinking_budget: Option<u32>,
+    #[serde(skip)]
+    controls: InferenceControls,
+}
+
+/// Runtime controls bounding an in-flight inference. They are not serialized
+/// and do not participate in request equality beyond the deadline.
+#[derive(Clone, Debug, Default)]
+struct InferenceControls {
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl PartialEq for InferenceControls {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
 }
 
 impl InferenceRequest {
@@ -211,10 +400,13 @@ impl InferenceRequest {
 
         Ok(Self {
             system_prompt: None,
+            system_cache_breakpoint: false,
             messages,
             max_output_tokens: None,
             temperature: None,
             tools: Vec::new(),
+            thinking_budget: None,
+            controls: InferenceControls::default(),
         })
     }
 
@@ -225,6 +417,16 @@ impl InferenceRequest {
         self
     }
 
+    /// Places a prompt cache breakpoint after the system prompt.
+    ///
+    /// Use this for large static system prompts; see
+    /// [`PromptMessage::with_cache_breakpoint`].
+    #[must_use]
+    pub fn with_system_cache_breakpoint(mut self) -> Self {
+        self.system_cache_breakpoint = true;
+        self
+    }
+
     /// Sets the maximum output token budget.
     #[must_use]
     pub fn with_max_output_tokens(mut self, tokens: u32) -> Self {
@@ -246,12 +448,28 @@ impl InferenceRequest {
         self
     }
 
+    /// Enables extended reasoning with the supplied token budget.
+    ///
+    /// Providers without a token-level budget treat this as a request to enable
+    /// reasoning output.
+    #[must_use]
+    pub fn with_thinking_budget(mut self, tokens: u32) -> Self {
+        self.thinking_budget = Some(tokens);
+        self
+    }
+
     /// Returns the system prompt if configured.
     #[must_use]
     pub fn system_prompt(&self) -> Option<&str> {
         self.system_prompt.as_deref()
     }
 
+    /// Returns `true` when a prompt cache breakpoint follows the system prompt.
+    #[must_use]
+    pub const fn system_cache_breakpoint(&self) -> bool {
+        self.system_cache_breakpoint
+    }
+
     /// Returns the prompt messages.
     #[must_use]
     pub fn messages(&self) -> &[PromptMessage] {
@@ -270,11 +488,82 @@ impl InferenceRequest {
         self.temperature
     }
 
+    /// Sets an absolute deadline; adapters abort the provider request once it
+    /// passes and return [`AdapterError::DeadlineExceeded`].
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.controls.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets a deadline relative to now.
+    #[must_use]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Attaches a token that aborts the provider request when cancelled.
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.controls.cancellation = Some(token);
+        self
+    }
+
     /// Returns the declared tool names.
     #[must_use]
     pub fn tools(&self) -> &[String] {
         &self.tools
     }
+
+    /// Returns the requested reasoning token budget, if any.
+    #[must_use]
+    pub const fn thinking_budget(&self) -> Option<u32> {
+        self.thinking_budget
+    }
+
+    /// Returns the request deadline, if any.
+    #[must_use]
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.controls.deadline
+    }
+
+    /// Returns the cancellation token, if any.
+    #[must_use]
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.controls.cancellation.as_ref()
+    }
+}
+
+/// Distinguishes model reasoning from answer text within a stream.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkKind {
+    /// Answer text intended for the caller.
+    #[default]
+    Answer,
+    /// Reasoning or thinking output produced before the answer.
+    Reasoning,
+}
+
+/// Token accounting reported by a provider, including prompt cache activity.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Prompt tokens billed at the normal rate.
+    pub input_tokens: u32,
+    /// Generated tokens.
+    pub output_tokens: u32,
+    /// Prompt tokens served from the provider's cache.
+    pub cache_read_tokens: u32,
+    /// Prompt tokens written to the provider's cache.
+    pub cache_write_tokens: u32,
+}
+
+impl TokenUsage {
+    /// Returns `true` when part of the prompt was served from cache.
+    #[must_use]
+    pub const fn cache_hit(&self) -> bool {
+        self.cache_read_tokens > 0
+    }
 }
 
 /// Streaming chunk returned by the adapter.
@@ -284,17 +573,66 @@ pub struct InferenceChunk {
     pub delta: String,
     /// Whether the generation is complete.
     pub done: bool,
+    /// Whether the delta carries answer text or reasoning.
+    #[serde(default)]
+    pub kind: ChunkKind,
+    /// Token usage, reported on the final chunk when the provider supplies it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
 }
 
 impl InferenceChunk {
-    /// Creates a new chunk.
+    /// Creates a new answer chunk.
     #[must_use]
     pub fn new(delta: impl Into<String>, done: bool) -> Self {
         Self {
             delta: delta.into(),
             done,
+            kind: ChunkKind::Answer,
+            usage: None,
+        }
+    }
+
+    /// Creates a reasoning chunk. Reasoning never terminates a stream.
+    #[must_use]
+    pub fn reasoning(delta: impl Into<String>) -> Self {
+        Self {
+            delta: delta.into(),
+            done: false,
+            kind: ChunkKind::Reasoning,
+            usage: None,
         }
     }
+
+    /// Attaches provider token usage to the chunk.
+    #[must_use]
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Returns `true` when the chunk carries reasoning rather than answer text.
+    #[must_use]
+    pub fn is_reasoning(&self) -> bool {
+        self.kind == ChunkKind::Reasoning
+    }
+}
+
+/// Builds the stream returned by non-streaming adapters: an optional
+/// reasoning chunk followed by the final answer chunk carrying usage.
+pub(crate) fn completion_stream(
+    reasoning: String,
+    answer: String,
+    usage: Option<TokenUsage>,
+) -> AdapterStream {
+    let mut chunks = Vec::with_capacity(2);
+    if !reasoning.is_empty() {
+        chunks.push(Ok(InferenceChunk::reasoning(reasoning)));
+    }
+    let mut last = InferenceChunk::new(answer, true);
+    last.usage = usage;
+    chunks.push(Ok(last));
+    Box::pin(futures::stream::iter(chunks))
 }
 
 /// Trait implemented by all model adapters.
@@ -304,6 +642,10 @@ pub trait ModelAdapter: Send + Sync {
     fn metadata(&self) -> &AdapterMetadata;
 
     /// Executes the inference request, returning a streaming response.
+    ///
+    /// Implementations honour [`InferenceRequest::deadline`] and
+    /// [`InferenceRequest::cancellation`]; dropping the returned future aborts
+    /// the underlying provider request.
     async fn infer(&self, request: InferenceRequest) -> AdapterResult<AdapterStream>;
 }
 
@@ -330,4 +672,29 @@ mod tests {
         assert_eq!(request.temperature(), Some(0.7));
         assert_eq!(request.tools(), &["echo".to_owned()]);
     }
+
+    #[test]
+    fn tool_messages_roundtrip_and_resolve_names() {
+        let history = vec![
+            PromptMessage::new(MessageRole::User, "look up ABC"),
+            PromptMessage::assistant_tool_calls(
+                "",
+                vec![ToolCall::new(
+                    "call_0",
+                    "inv_lookup",
+                    serde_json::json!({"sku": "ABC"}),
+                )],
+            ),
+            PromptMessage::tool_result("call_0", "{\"quantity\":42}"),
+        ];
+
+        let encoded = serde_json::to_string(&history).unwrap();
+        let decoded: Vec<PromptMessage> = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, history);
+
+        assert_eq!(decoded[1].tool_calls()[0].name(), "inv_lookup");
+        assert_eq!(decoded[2].tool_call_id(), Some("call_0"));
+        assert_eq!(decoded[2].resolve_tool_name(&decoded), Some("inv_lookup"));
+        assert_eq!(decoded[0].resolve_tool_name(&decoded), None);
+    }
 }
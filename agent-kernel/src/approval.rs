@@ -0,0 +1,1058 @@
+//! Human approval of calls that policy escalates.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use agent_policy::{PolicyDecision, PolicyRequest};
+use agent_primitives::AgentId;
+use chrono::{DateTime, Utc};
+use mxp::{Message, MessageType};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::call::AuditEmitter;
+use crate::delivery::ReliableSender;
+
+const DEFAULT_TTL: Duration = Duration::from_mins(15);
+const DEFAULT_RETENTION: Duration = Duration::from_hours(1);
+
+/// How many approvers must approve an escalated action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApprovalQuorum {
+    /// One approval is enough.
+    #[default]
+    Any,
+    /// Every approver named by the policy decision must approve.
+    All,
+    /// At least this many distinct approvers must approve, capped at the
+    /// number of approvers the policy decision names.
+    AtLeast(NonZeroUsize),
+}
+
+impl ApprovalQuorum {
+    fn required(self, approvers: usize) -> usize {
+        match self {
+            Self::Any => 1,
+            Self::All => approvers.max(1),
+            // More approvals than named approvers could never be collected.
+            Self::AtLeast(count) if approvers > 0 => count.get().min(approvers),
+            Self::AtLeast(count) => count.get(),
+        }
+    }
+}
+
+/// Expiry and quorum applied to approval requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApprovalConfig {
+    ttl: Duration,
+    quorum: ApprovalQuorum,
+    retention: Duration,
+}
+
+impl ApprovalConfig {
+    /// Creates the default configuration: one approval within 15 minutes,
+    /// with resolved records kept for an hour.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            quorum: ApprovalQuorum::Any,
+            retention: DEFAULT_RETENTION,
+        }
+    }
+
+    /// Sets how long a request waits before it expires.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how many approvals are required.
+    #[must_use]
+    pub const fn with_quorum(mut self, quorum: ApprovalQuorum) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Sets how long resolved records are kept before they are discarded.
+    #[must_use]
+    pub const fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Returns how long a request waits before it expires.
+    #[must_use]
+    pub const fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns how long resolved records are kept.
+    #[must_use]
+    pub const fn retention(&self) -> Duration {
+        self.retention
+    }
+
+    /// Returns the quorum rule.
+    #[must_use]
+    pub const fn quorum(&self) -> ApprovalQuorum {
+        self.quorum
+    }
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// State of an approval request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    /// Waiting for approvers.
+    Pending,
+    /// Quorum reached; the action proceeds.
+    Approved,
+    /// An approver denied the action.
+    Denied,
+    /// No quorum was reached before the request expired.
+    Expired,
+    /// The waiting call was cancelled or timed out first.
+    Cancelled,
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Denied => "denied",
+            Self::Expired => "expired",
+            Self::Cancelled => "cancelled",
+        })
+    }
+}
+
+/// Request for approval sent to approvers.
+///
+/// Encoded as an MXP `Event` with payload `{"type": "approval_request", ...}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "approval_request")]
+pub struct ApprovalRequest {
+    approval_id: Uuid,
+    agent_id: AgentId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    call_id: Option<u64>,
+    subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(default)]
+    approvers: Vec<String>,
+    required: usize,
+    expires_at: DateTime<Utc>,
+    #[serde(default)]
+    metadata: Map<String, Value>,
+}
+
+impl ApprovalRequest {
+    /// Returns the id approvers answer with.
+    #[must_use]
+    pub const fn approval_id(&self) -> Uuid {
+        self.approval_id
+    }
+
+    /// Returns the agent whose action is awaiting approval.
+    #[must_use]
+    pub const fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Returns the MXP message id of the parked call, if any.
+    #[must_use]
+    pub const fn call_id(&self) -> Option<u64> {
+        self.call_id
+    }
+
+    /// Returns the escalated action, e.g. ``tool `transfer` ``.
+    #[must_use]
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Returns the reason given by the policy engine.
+    #[must_use]
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// Returns the approvers named by the policy decision; empty means anyone
+    /// may approve.
+    #[must_use]
+    pub fn approvers(&self) -> &[String] {
+        &self.approvers
+    }
+
+    /// Returns how many approvals are required.
+    #[must_use]
+    pub const fn required(&self) -> usize {
+        self.required
+    }
+
+    /// Returns when the request expires.
+    #[must_use]
+    pub const fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+
+    /// Returns the policy request metadata, such as tool input.
+    #[must_use]
+    pub const fn metadata(&self) -> &Map<String, Value> {
+        &self.metadata
+    }
+
+    /// Decodes a request from an `Event` payload.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Builds the MXP `Event` message carrying the request.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(
+            MessageType::Event,
+            serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+}
+
+/// An approver's answer to an [`ApprovalRequest`].
+///
+/// Encoded as an MXP `Event` with payload `{"type": "approval_decision",
+/// "approval_id": ..., "approver": ..., "approved": true, "comment": ...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "approval_decision")]
+pub struct ApprovalDecision {
+    approval_id: Uuid,
+    approver: String,
+    approved: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+impl ApprovalDecision {
+    /// Approves the request on behalf of `approver`.
+    #[must_use]
+    pub fn approve(approval_id: Uuid, approver: impl Into<String>) -> Self {
+        Self {
+            approval_id,
+            approver: approver.into(),
+            approved: true,
+            comment: None,
+        }
+    }
+
+    /// Denies the request on behalf of `approver`.
+    #[must_use]
+    pub fn deny(approval_id: Uuid, approver: impl Into<String>) -> Self {
+        Self {
+            approved: false,
+            ..Self::approve(approval_id, approver)
+        }
+    }
+
+    /// Attaches a comment recorded in the audit trail.
+    #[must_use]
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Returns the request being answered.
+    #[must_use]
+    pub const fn approval_id(&self) -> Uuid {
+        self.approval_id
+    }
+
+    /// Returns the approver.
+    #[must_use]
+    pub fn approver(&self) -> &str {
+        &self.approver
+    }
+
+    /// Returns `true` for an approval and `false` for a denial.
+    #[must_use]
+    pub const fn approved(&self) -> bool {
+        self.approved
+    }
+
+    /// Returns the approver's comment.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Decodes a decision from an `Event` payload.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Builds the MXP `Event` message carrying the decision.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(
+            MessageType::Event,
+            serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+}
+
+/// Step recorded in an approval's audit trail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalAction {
+    /// The request was opened and sent to approvers.
+    Requested,
+    /// An approver approved.
+    Approved,
+    /// An approver denied.
+    Denied,
+    /// The request reached a final status.
+    Resolved(ApprovalStatus),
+}
+
+/// Entry in an approval's audit trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalAuditEntry {
+    at: DateTime<Utc>,
+    action: ApprovalAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+impl ApprovalAuditEntry {
+    /// Returns when the step happened.
+    #[must_use]
+    pub const fn at(&self) -> DateTime<Utc> {
+        self.at
+    }
+
+    /// Returns what happened.
+    #[must_use]
+    pub const fn action(&self) -> ApprovalAction {
+        self.action
+    }
+
+    /// Returns the approver responsible, if any.
+    #[must_use]
+    pub fn actor(&self) -> Option<&str> {
+        self.actor.as_deref()
+    }
+
+    /// Returns the approver's comment, if any.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// An approval request with its votes and audit trail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    request: ApprovalRequest,
+    status: ApprovalStatus,
+    approvals: BTreeSet<String>,
+    trail: Vec<ApprovalAuditEntry>,
+}
+
+impl ApprovalRecord {
+    /// Returns the request sent to approvers.
+    #[must_use]
+    pub const fn request(&self) -> &ApprovalRequest {
+        &self.request
+    }
+
+    /// Returns the current status.
+    #[must_use]
+    pub const fn status(&self) -> ApprovalStatus {
+        self.status
+    }
+
+    /// Returns the approvers who have approved so far.
+    #[must_use]
+    pub const fn approvals(&self) -> &BTreeSet<String> {
+        &self.approvals
+    }
+
+    /// Returns every step taken, oldest first.
+    #[must_use]
+    pub fn trail(&self) -> &[ApprovalAuditEntry] {
+        &self.trail
+    }
+}
+
+/// Errors returned when applying an [`ApprovalDecision`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ApprovalError {
+    /// No request with this id is known.
+    #[error("unknown approval request {0}")]
+    Unknown(Uuid),
+    /// The decision did not come from an authenticated agent.
+    #[error("decision for approval request {0} is not from an authenticated agent")]
+    Unauthenticated(Uuid),
+    /// The approver is not among those named by the policy decision, or is
+    /// not the approver the sending agent acts as.
+    #[error("`{approver}` is not an approver for request {approval_id}")]
+    NotAnApprover {
+        /// Request being answered.
+        approval_id: Uuid,
+        /// Rejected approver.
+        approver: String,
+    },
+    /// The request already reached a final status.
+    #[error("approval request {approval_id} is already {status}")]
+    AlreadyResolved {
+        /// Request being answered.
+        approval_id: Uuid,
+        /// Final status of the request.
+        status: ApprovalStatus,
+    },
+}
+
+/// Delivers approval requests to approvers.
+pub trait ApprovalNotifier: Send + Sync {
+    /// Announces a newly opened request.
+    fn notify(&self, request: &ApprovalRequest);
+}
+
+/// Notifier that logs approval requests.
+#[derive(Debug, Default)]
+pub struct TracingApprovalNotifier;
+
+impl ApprovalNotifier for TracingApprovalNotifier {
+    fn notify(&self, request: &ApprovalRequest) {
+        info!(
+            approval_id = %request.approval_id,
+            agent_id = %request.agent_id,
+            subject = request.subject,
+            approvers = ?request.approvers,
+            required = request.required,
+            expires_at = %request.expires_at,
+            "approval requested"
+        );
+    }
+}
+
+/// Sends approval requests as MXP events to approver agents, retransmitting
+/// until each acknowledges.
+#[derive(Debug, Clone)]
+pub struct MxpApprovalNotifier {
+    sender: ReliableSender,
+    targets: Vec<SocketAddr>,
+}
+
+impl MxpApprovalNotifier {
+    /// Sends each request to every address in `targets`.
+    #[must_use]
+    pub fn new(sender: ReliableSender, targets: Vec<SocketAddr>) -> Self {
+        Self { sender, targets }
+    }
+}
+
+impl ApprovalNotifier for MxpApprovalNotifier {
+    fn notify(&self, request: &ApprovalRequest) {
+        for target in &self.targets {
+            drop(self.sender.send(request.to_message(), *target));
+        }
+    }
+}
+
+struct Pending {
+    record: ApprovalRecord,
+    waiters: Vec<oneshot::Sender<ApprovalStatus>>,
+    expires: Option<Instant>,
+    resolved_at: Option<Instant>,
+}
+
+/// Pending and resolved approval requests.
+///
+/// [`KernelMessageHandler`](crate::KernelMessageHandler) parks an escalated
+/// call on a request in the store until approvers reach quorum, one of them
+/// denies it, or it expires. Each
+/// step is appended to the record's audit trail and, when an audit emitter is
+/// configured, emitted as an `{"type": "approval_audit", ...}` event.
+///
+/// Decisions received over MXP go through [`decide_as`](Self::decide_as),
+/// which binds them to the authenticated agent that sent them. Resolved
+/// records are discarded after the configured retention period.
+#[derive(Clone)]
+pub struct ApprovalStore {
+    config: ApprovalConfig,
+    records: Arc<Mutex<HashMap<Uuid, Pending>>>,
+    approvers: Arc<HashMap<AgentId, String>>,
+    notifier: Arc<dyn ApprovalNotifier>,
+    audit: Option<Arc<dyn AuditEmitter>>,
+}
+
+impl fmt::Debug for ApprovalStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApprovalStore")
+            .field("config", &self.config)
+            .field("records", &self.lock().len())
+            .field("audit_configured", &self.audit.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ApprovalStore {
+    fn default() -> Self {
+        Self::new(ApprovalConfig::default())
+    }
+}
+
+impl ApprovalStore {
+    /// Creates a store that logs new requests.
+    #[must_use]
+    pub fn new(config: ApprovalConfig) -> Self {
+        Self {
+            config,
+            records: Arc::new(Mutex::new(HashMap::new())),
+            approvers: Arc::new(HashMap::new()),
+            notifier: Arc::new(TracingApprovalNotifier),
+            audit: None,
+        }
+    }
+
+    /// Replaces how new requests reach approvers.
+    #[must_use]
+    pub fn with_notifier(mut self, notifier: Arc<dyn ApprovalNotifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Lets `agent_id` vote as the approver `name` in decisions it sends.
+    /// Agents without a name vote under their agent id.
+    #[must_use]
+    pub fn with_approver(mut self, name: impl Into<String>, agent_id: AgentId) -> Self {
+        Arc::make_mut(&mut self.approvers).insert(agent_id, name.into());
+        self
+    }
+
+    /// Emits every audit trail entry through `audit`.
+    #[must_use]
+    pub fn with_audit(mut self, audit: Arc<dyn AuditEmitter>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Returns the expiry and quorum configuration.
+    #[must_use]
+    pub const fn config(&self) -> ApprovalConfig {
+        self.config
+    }
+
+    /// Opens a request for the escalated `request` and waits until it is
+    /// approved, denied, or expires.
+    ///
+    /// Dropping the returned future before then marks the request
+    /// [`ApprovalStatus::Cancelled`].
+    pub async fn request(
+        &self,
+        request: &PolicyRequest,
+        decision: &PolicyDecision,
+        call_id: Option<u64>,
+    ) -> ApprovalStatus {
+        let approval_id = self.open(request, decision, call_id);
+        let mut guard = CancelOnDrop {
+            store: self,
+            approval_id,
+            armed: true,
+        };
+        let status = self.wait(approval_id).await;
+        guard.armed = false;
+        status
+    }
+
+    /// Opens a request for the escalated `request`, announces it to
+    /// approvers, and returns its id without waiting for a decision.
+    pub fn open(
+        &self,
+        request: &PolicyRequest,
+        decision: &PolicyDecision,
+        call_id: Option<u64>,
+    ) -> Uuid {
+        let approvers = decision.required_approvals().to_vec();
+        let approval = ApprovalRequest {
+            approval_id: Uuid::new_v4(),
+            agent_id: request.agent_id(),
+            call_id,
+            subject: request.action().label(),
+            reason: decision.reason().map(str::to_owned),
+            required: self.config.quorum.required(approvers.len()),
+            approvers,
+            expires_at: chrono::Duration::from_std(self.config.ttl)
+                .ok()
+                .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            metadata: request.context().metadata().clone(),
+        };
+        let approval_id = approval.approval_id;
+        let mut record = ApprovalRecord {
+            request: approval.clone(),
+            status: ApprovalStatus::Pending,
+            approvals: BTreeSet::new(),
+            trail: Vec::new(),
+        };
+        self.append(&mut record, ApprovalAction::Requested, None, None);
+        // Record first, so a decision that arrives right away finds it.
+        self.lock().insert(
+            approval_id,
+            Pending {
+                record,
+                waiters: Vec::new(),
+                expires: Instant::now().checked_add(self.config.ttl),
+                resolved_at: None,
+            },
+        );
+        self.notifier.notify(&approval);
+        approval_id
+    }
+
+    /// Waits until the request `approval_id` is approved, denied, or
+    /// expires, and returns its final status.
+    ///
+    /// Returns [`ApprovalStatus::Cancelled`] when the request is unknown,
+    /// for example because its record was already discarded.
+    pub async fn wait(&self, approval_id: Uuid) -> ApprovalStatus {
+        let (waiter, resolved) = oneshot::channel();
+        let expires = {
+            let mut records = self.lock();
+            let Some(pending) = records.get_mut(&approval_id) else {
+                return ApprovalStatus::Cancelled;
+            };
+            if pending.record.status != ApprovalStatus::Pending {
+                return pending.record.status;
+            }
+            pending.waiters.push(waiter);
+            pending.expires
+        };
+        let resolved = match expires {
+            Some(expires) => tokio::time::timeout_at(expires.into(), resolved).await,
+            None => Ok(resolved.await),
+        };
+        match resolved {
+            Ok(Ok(status)) => status,
+            Ok(Err(_)) => ApprovalStatus::Cancelled,
+            Err(_) => self.resolve(approval_id, ApprovalStatus::Expired),
+        }
+    }
+
+    /// Marks the request `approval_id` cancelled unless it already reached
+    /// a final status, and returns its status.
+    #[must_use]
+    pub fn cancel(&self, approval_id: Uuid) -> ApprovalStatus {
+        self.resolve(approval_id, ApprovalStatus::Cancelled)
+    }
+
+    /// Applies a decision sent by the authenticated agent `principal` and
+    /// returns the resulting status.
+    ///
+    /// The decision must name the approver `principal` acts as (see
+    /// [`with_approver`](Self::with_approver)), or its agent id when it has no
+    /// name, so one agent counts as one approver however many names it
+    /// claims.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApprovalError::NotAnApprover`] when the decision names a
+    /// different approver, and otherwise fails like [`decide`](Self::decide).
+    pub fn decide_as(
+        &self,
+        principal: AgentId,
+        decision: &ApprovalDecision,
+    ) -> Result<ApprovalStatus, ApprovalError> {
+        let acts_as = self
+            .approvers
+            .get(&principal)
+            .cloned()
+            .unwrap_or_else(|| principal.to_string());
+        if decision.approver != acts_as {
+            return Err(ApprovalError::NotAnApprover {
+                approval_id: decision.approval_id,
+                approver: decision.approver.clone(),
+            });
+        }
+        self.decide(decision)
+    }
+
+    /// Applies an approver's decision and returns the resulting status.
+    ///
+    /// The approver name is trusted as given; use
+    /// [`decide_as`](Self::decide_as) for decisions from other agents.
+    ///
+    /// A denial from any eligible approver rejects the request; approvals
+    /// resolve it once the quorum is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApprovalError`] when the request is unknown or already
+    /// resolved, or the approver is not eligible.
+    pub fn decide(&self, decision: &ApprovalDecision) -> Result<ApprovalStatus, ApprovalError> {
+        let approval_id = decision.approval_id;
+        let mut records = self.lock();
+        let pending = records
+            .get_mut(&approval_id)
+            .ok_or(ApprovalError::Unknown(approval_id))?;
+        let record = &mut pending.record;
+        if record.status != ApprovalStatus::Pending {
+            return Err(ApprovalError::AlreadyResolved {
+                approval_id,
+                status: record.status,
+            });
+        }
+        let approvers = &record.request.approvers;
+        if !approvers.is_empty() && !approvers.contains(&decision.approver) {
+            return Err(ApprovalError::NotAnApprover {
+                approval_id,
+                approver: decision.approver.clone(),
+            });
+        }
+
+        let action = if decision.approved {
+            record.approvals.insert(decision.approver.clone());
+            ApprovalAction::Approved
+        } else {
+            ApprovalAction::Denied
+        };
+        self.append(
+            record,
+            action,
+            Some(decision.approver.clone()),
+            decision.comment.clone(),
+        );
+
+        let status = if !decision.approved {
+            ApprovalStatus::Denied
+        } else if record.approvals.len() >= record.request.required {
+            ApprovalStatus::Approved
+        } else {
+            return Ok(ApprovalStatus::Pending);
+        };
+        self.finish(pending, status);
+        Ok(status)
+    }
+
+    /// Returns the record for `approval_id`.
+    #[must_use]
+    pub fn get(&self, approval_id: Uuid) -> Option<ApprovalRecord> {
+        self.lock()
+            .get(&approval_id)
+            .map(|pending| pending.record.clone())
+    }
+
+    /// Returns the requests still awaiting approvers.
+    #[must_use]
+    pub fn pending(&self) -> Vec<ApprovalRecord> {
+        self.lock()
+            .values()
+            .filter(|pending| pending.record.status == ApprovalStatus::Pending)
+            .map(|pending| pending.record.clone())
+            .collect()
+    }
+
+    /// Removes and returns resolved records, e.g. for archiving.
+    #[must_use]
+    pub fn take_resolved(&self) -> Vec<ApprovalRecord> {
+        let mut records = self.lock();
+        let resolved = records
+            .iter()
+            .filter(|(_, pending)| pending.record.status != ApprovalStatus::Pending)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        resolved
+            .into_iter()
+            .filter_map(|id| records.remove(&id))
+            .map(|pending| pending.record)
+            .collect()
+    }
+
+    fn resolve(&self, approval_id: Uuid, status: ApprovalStatus) -> ApprovalStatus {
+        let mut records = self.lock();
+        match records.get_mut(&approval_id) {
+            Some(pending) if pending.record.status == ApprovalStatus::Pending => {
+                self.finish(pending, status);
+                status
+            }
+            Some(pending) => pending.record.status,
+            None => status,
+        }
+    }
+
+    fn finish(&self, pending: &mut Pending, status: ApprovalStatus) {
+        pending.record.status = status;
+        pending.resolved_at = Some(Instant::now());
+        self.append(
+            &mut pending.record,
+            ApprovalAction::Resolved(status),
+            None,
+            None,
+        );
+        debug!(approval_id = %pending.record.request.approval_id, %status, "approval resolved");
+        for waiter in pending.waiters.drain(..) {
+            let _ = waiter.send(status);
+        }
+    }
+
+    fn append(
+        &self,
+        record: &mut ApprovalRecord,
+        action: ApprovalAction,
+        actor: Option<String>,
+        comment: Option<String>,
+    ) {
+        let entry = ApprovalAuditEntry {
+            at: Utc::now(),
+            action,
+            actor,
+            comment,
+        };
+        if let Some(audit) = &self.audit {
+            let payload = json!({
+                "type": "approval_audit",
+                "approval_id": record.request.approval_id,
+                "agent_id": record.request.agent_id.to_string(),
+                "subject": record.request.subject,
+                "entry": entry,
+            });
+            audit.emit(Message::new(MessageType::Event, payload.to_string()));
+        }
+        record.trail.push(entry);
+    }
+
+    /// Locks the records, discarding resolved ones past their retention.
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, Pending>> {
+        let mut records = self.records.lock().unwrap_or_else(PoisonError::into_inner);
+        let retention = self.config.retention;
+        records.retain(|_, pending| {
+            pending
+                .resolved_at
+                .is_none_or(|resolved_at| resolved_at.elapsed() < retention)
+        });
+        records
+    }
+}
+
+struct CancelOnDrop<'a> {
+    store: &'a ApprovalStore,
+    approval_id: Uuid,
+    armed: bool,
+}
+
+impl Drop for CancelOnDrop<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.store
+                .resolve(self.approval_id, ApprovalStatus::Cancelled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use agent_policy::PolicyAction;
+
+    #[derive(Default)]
+    struct Collecting {
+        requests: Mutex<Vec<ApprovalRequest>>,
+    }
+
+    impl ApprovalNotifier for Collecting {
+        fn notify(&self, request: &ApprovalRequest) {
+            self.requests.lock().unwrap().push(request.clone());
+        }
+    }
+
+    fn escalation(approvers: &[&str]) -> (PolicyRequest, PolicyDecision) {
+        let request = PolicyRequest::new(
+            AgentId::random(),
+            PolicyAction::InvokeTool {
+                name: "transfer".into(),
+            },
+        );
+        let decision = PolicyDecision::escalate(
+            "large transfer",
+            approvers.iter().map(|name| (*name).to_owned()).collect(),
+        );
+        (request, decision)
+    }
+
+    async fn next_request(notifier: &Collecting) -> ApprovalRequest {
+        loop {
+            if let Some(request) = notifier.requests.lock().unwrap().pop() {
+                return request;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn quorum_of_all_approvers_resumes_call() {
+        let notifier = Arc::new(Collecting::default());
+        let store = ApprovalStore::new(ApprovalConfig::new().with_quorum(ApprovalQuorum::All))
+            .with_notifier(notifier.clone());
+        let (request, decision) = escalation(&["alice", "bob"]);
+
+        let waiting = tokio::spawn({
+            let store = store.clone();
+            async move { store.request(&request, &decision, Some(7)).await }
+        });
+        let approval = next_request(&notifier).await;
+        assert_eq!(approval.required(), 2);
+        assert_eq!(approval.call_id(), Some(7));
+        let id = approval.approval_id();
+
+        assert_eq!(
+            store.decide(&ApprovalDecision::approve(id, "mallory")),
+            Err(ApprovalError::NotAnApprover {
+                approval_id: id,
+                approver: "mallory".into(),
+            })
+        );
+        assert_eq!(
+            store.decide(&ApprovalDecision::approve(id, "alice")),
+            Ok(ApprovalStatus::Pending)
+        );
+        assert_eq!(store.pending().len(), 1);
+        assert_eq!(
+            store.decide(&ApprovalDecision::approve(id, "bob").with_comment("ok")),
+            Ok(ApprovalStatus::Approved)
+        );
+        assert_eq!(waiting.await.unwrap(), ApprovalStatus::Approved);
+
+        let record = store.get(id).unwrap();
+        let actions = record
+            .trail()
+            .iter()
+            .map(ApprovalAuditEntry::action)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            [
+                ApprovalAction::Requested,
+                ApprovalAction::Approved,
+                ApprovalAction::Approved,
+                ApprovalAction::Resolved(ApprovalStatus::Approved),
+            ]
+        );
+        assert_eq!(record.trail()[2].comment(), Some("ok"));
+        assert!(matches!(
+            store.decide(&ApprovalDecision::deny(id, "alice")),
+            Err(ApprovalError::AlreadyResolved { .. })
+        ));
+        assert_eq!(store.take_resolved().len(), 1);
+        assert!(store.get(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn denial_rejects_and_silence_expires() {
+        let notifier = Arc::new(Collecting::default());
+        let store = ApprovalStore::new(ApprovalConfig::new().with_ttl(Duration::from_millis(50)))
+            .with_notifier(notifier.clone());
+
+        let (request, decision) = escalation(&[]);
+        let waiting = tokio::spawn({
+            let store = store.clone();
+            async move { store.request(&request, &decision, None).await }
+        });
+        let id = next_request(&notifier).await.approval_id();
+        assert_eq!(
+            store.decide(&ApprovalDecision::deny(id, "anyone")),
+            Ok(ApprovalStatus::Denied)
+        );
+        assert_eq!(waiting.await.unwrap(), ApprovalStatus::Denied);
+
+        let (request, decision) = escalation(&["alice"]);
+        let status = store.request(&request, &decision, None).await;
+        assert_eq!(status, ApprovalStatus::Expired);
+        assert!(store.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn network_votes_are_bound_to_the_sending_agent() {
+        let notifier = Arc::new(Collecting::default());
+        let (alice, mallory) = (AgentId::random(), AgentId::random());
+        let quorum = ApprovalQuorum::AtLeast(NonZeroUsize::new(5).unwrap());
+        let store = ApprovalStore::new(
+            ApprovalConfig::new()
+                .with_quorum(quorum)
+                .with_ttl(Duration::MAX)
+                .with_retention(Duration::ZERO),
+        )
+        .with_notifier(notifier.clone())
+        .with_approver("alice", alice);
+        let (request, decision) = escalation(&["alice", "bob"]);
+
+        let waiting = tokio::spawn({
+            let store = store.clone();
+            async move { store.request(&request, &decision, None).await }
+        });
+        let approval = next_request(&notifier).await;
+        assert_eq!(approval.required(), 2);
+        assert_eq!(approval.expires_at(), DateTime::<Utc>::MAX_UTC);
+        let id = approval.approval_id();
+
+        for (principal, name) in [(mallory, "alice"), (alice, "bob"), (mallory, "bob")] {
+            assert!(matches!(
+                store.decide_as(principal, &ApprovalDecision::approve(id, name)),
+                Err(ApprovalError::NotAnApprover { .. })
+            ));
+        }
+        let vote = ApprovalDecision::approve(id, "alice");
+        assert_eq!(store.decide_as(alice, &vote), Ok(ApprovalStatus::Pending));
+        assert_eq!(store.decide_as(alice, &vote), Ok(ApprovalStatus::Pending));
+        assert_eq!(
+            store.decide_as(alice, &ApprovalDecision::deny(id, "alice")),
+            Ok(ApprovalStatus::Denied)
+        );
+        assert_eq!(waiting.await.unwrap(), ApprovalStatus::Denied);
+        // Zero retention discards the resolved record right away.
+        assert!(store.get(id).is_none());
+    }
+
+    #[tokio::test]
+    async fn decisions_sent_while_notifying_find_the_request() {
+        struct Eager(Mutex<Option<ApprovalStore>>);
+
+        impl ApprovalNotifier for Eager {
+            fn notify(&self, request: &ApprovalRequest) {
+                let store = self.0.lock().unwrap().clone().unwrap();
+                let decision = ApprovalDecision::approve(request.approval_id(), "alice");
+                assert_eq!(store.decide(&decision), Ok(ApprovalStatus::Approved));
+            }
+        }
+
+        let notifier = Arc::new(Eager(Mutex::new(None)));
+        let store = ApprovalStore::default().with_notifier(notifier.clone());
+        *notifier.0.lock().unwrap() = Some(store.clone());
+        let (request, decision) = escalation(&["alice"]);
+        assert_eq!(
+            store.request(&request, &decision, None).await,
+            ApprovalStatus::Approved
+        );
+    }
+}
@@ -1,17 +1,20 @@
 //! Call message execution pipeline.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 use agent_adapters::traits::{
-    AdapterError, InferenceRequest, MessageRole, ModelAdapter, PromptMessage,
+    AdapterError, CancellationToken, InferenceRequest, MessageRole, ModelAdapter, PromptMessage,
+    TokenUsage, ToolCall,
 };
 use agent_memory::{MemoryBus, MemoryChannel, MemoryError, MemoryRecord};
 use agent_policy::{
     DecisionKind, PolicyAction, PolicyDecision, PolicyEngine, PolicyError, PolicyRequest,
 };
-use agent_primitives::AgentId;
+use agent_primitives::{AgentId, Scope};
 use agent_tools::registry::{ToolBinding, ToolError, ToolRegistry, descriptor_from_type_name};
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -22,6 +25,19 @@ use serde_json::{Value, json};
 use tokio::task;
 use tracing::{debug, info, warn};
 
+use crate::approval::{ApprovalDecision, ApprovalError, ApprovalStatus, ApprovalStore};
+use crate::cancellation::{CallCancellations, CancelRequest};
+use crate::checkpoint::{
+    Checkpoint, CheckpointStore, CompletedStep, InterruptedCall, RecoveryReport, ResumedSteps,
+};
+use crate::dedup::{CallKey, DedupSlot, DuplicateCall, in_progress_ack};
+use crate::delivery::{DeliveryAck, ReliableSender, send_ack};
+use crate::fragment::Fragmenter;
+use crate::jobs::{JobError, JobManager, JobRequest, JobResult, JobTicket, is_job_call};
+use crate::retrieval::{RetrievalStage, RetrievedMemory, context_message};
+use crate::scopes::{Caller, ToolScopes};
+use crate::session::{SessionOwner, SessionStore};
+use crate::signing::{AgentIdentity, sign_outbound};
 use crate::{HandlerContext, HandlerError, HandlerResult};
 
 /// Emits MXP audit events when policy decisions deny or escalate requests.
@@ -79,29 +95,83 @@ impl AuditEmitter for TracingAuditEmitter {
 /// Sends audit events to a remote governance agent using MXP transport.
 #[derive(Clone)]
 pub struct GovernanceAuditEmitter {
-    transport: TransportHandle,
+    transport: GovernanceTransport,
     target: SocketAddr,
+    fragmenter: Fragmenter,
+    identity: Option<AgentIdentity>,
+}
+
+#[derive(Clone)]
+enum GovernanceTransport {
+    BestEffort(TransportHandle),
+    Reliable(Box<ReliableSender>),
 }
 
 impl GovernanceAuditEmitter {
     /// Creates a new governance emitter.
     #[must_use]
     pub fn new(transport: TransportHandle, target: SocketAddr) -> Self {
-        Self { transport, target }
+        Self {
+            transport: GovernanceTransport::BestEffort(transport),
+            target,
+            fragmenter: Fragmenter::default(),
+            identity: None,
+        }
+    }
+
+    /// Creates an emitter that retransmits each audit event until the
+    /// governance agent acknowledges it.
+    #[must_use]
+    pub fn reliable(sender: ReliableSender, target: SocketAddr) -> Self {
+        Self {
+            transport: GovernanceTransport::Reliable(Box::new(sender)),
+            target,
+            fragmenter: Fragmenter::default(),
+            identity: None,
+        }
+    }
+
+    /// Sets how oversized audit events are fragmented. Reliable emitters use
+    /// the fragmenter of their [`ReliableSender`] instead.
+    #[must_use]
+    pub const fn with_fragmenter(mut self, fragmenter: Fragmenter) -> Self {
+        self.fragmenter = fragmenter;
+        self
+    }
+
+    /// Signs audit events with `identity`. Reliable emitters use the identity
+    /// of their [`ReliableSender`] instead.
+    #[must_use]
+    pub fn with_identity(mut self, identity: AgentIdentity) -> Self {
+        self.identity = Some(identity);
+        self
     }
 }
 
 impl AuditEmitter for GovernanceAuditEmitter {
     fn emit(&self, message: Message) {
-        let transport = self.transport.clone();
         let target = self.target;
-        let msg_type = message.message_type();
-        let message_id = message.message_id();
-        let trace_id = message.trace_id();
-        let encoded = message.encode();
+        let transport = match &self.transport {
+            GovernanceTransport::Reliable(sender) => {
+                sender.send(message, target);
+                return;
+            }
+            GovernanceTransport::BestEffort(transport) => transport.clone(),
+        };
+        let fragmenter = self.fragmenter;
+        let message = match sign_outbound(self.identity.as_ref(), &message) {
+            Ok(signed) => signed.into_owned(),
+            Err(err) => {
+                warn!(%err, %target, "failed to sign governance audit event");
+                return;
+            }
+        };
 
         task::spawn(async move {
-            if let Err(err) = transport.send(&encoded, target) {
+            if let Err(err) = fragmenter.send(&transport, &message, target) {
+                let msg_type = message.message_type();
+                let message_id = message.message_id();
+                let trace_id = message.trace_id();
                 warn!(
                     ?err,
                     %target,
@@ -224,6 +294,11 @@ pub struct CallExecutor {
     tools: Arc<ToolRegistry>,
     policy: Option<Arc<dyn PolicyEngine>>,
     policy_observer: Option<Arc<dyn PolicyObserver>>,
+    sessions: Option<SessionStore>,
+    retrieval: Option<RetrievalStage>,
+    approvals: Option<ApprovalStore>,
+    checkpoints: Option<CheckpointStore>,
+    tool_scopes: Option<ToolScopes>,
 }
 
 impl fmt::Debug for CallExecutor {
@@ -234,6 +309,11 @@ impl fmt::Debug for CallExecutor {
             .field("model", &metadata.model())
             .field("policy_configured", &self.policy.is_some())
             .field("observer_configured", &self.policy_observer.is_some())
+            .field("sessions_configured", &self.sessions.is_some())
+            .field("retrieval", &self.retrieval)
+            .field("approvals", &self.approvals)
+            .field("checkpoints", &self.checkpoints)
+            .field("tool_scopes", &self.tool_scopes)
             .finish_non_exhaustive()
     }
 }
@@ -247,6 +327,11 @@ impl CallExecutor {
             tools,
             policy: None,
             policy_observer: None,
+            sessions: None,
+            retrieval: None,
+            approvals: None,
+            checkpoints: None,
+            tool_scopes: None,
         }
     }
 
@@ -286,12 +371,243 @@ impl CallExecutor {
         self.policy_observer.as_ref()
     }
 
+    /// Installs the session store used for calls that carry a `session_id`.
+    pub fn set_sessions(&mut self, sessions: SessionStore) {
+        self.sessions = Some(sessions);
+    }
+
+    /// Configures the session store, returning the updated executor for chaining.
+    #[must_use]
+    pub fn with_sessions(mut self, sessions: SessionStore) -> Self {
+        self.set_sessions(sessions);
+        self
+    }
+
+    /// Returns the session store if configured.
+    #[must_use]
+    pub fn sessions(&self) -> Option<&SessionStore> {
+        self.sessions.as_ref()
+    }
+
+    /// Enables retrieval: memories matching the latest user message are
+    /// injected as a cited context message before it.
+    pub fn set_retrieval(&mut self, retrieval: RetrievalStage) {
+        self.retrieval = Some(retrieval);
+    }
+
+    /// Enables retrieval, returning the updated executor for chaining.
+    #[must_use]
+    pub fn with_retrieval(mut self, retrieval: RetrievalStage) -> Self {
+        self.set_retrieval(retrieval);
+        self
+    }
+
+    /// Returns the retrieval stage if configured.
+    #[must_use]
+    pub fn retrieval(&self) -> Option<&RetrievalStage> {
+        self.retrieval.as_ref()
+    }
+
+    /// Parks escalated actions in `approvals` until approvers decide, instead
+    /// of rejecting them.
+    pub fn set_approvals(&mut self, approvals: ApprovalStore) {
+        self.approvals = Some(approvals);
+    }
+
+    /// Enables human approval of escalated actions, returning the updated
+    /// executor for chaining.
+    #[must_use]
+    pub fn with_approvals(mut self, approvals: ApprovalStore) -> Self {
+        self.set_approvals(approvals);
+        self
+    }
+
+    /// Returns the approval store if configured.
+    #[must_use]
+    pub fn approvals(&self) -> Option<&ApprovalStore> {
+        self.approvals.as_ref()
+    }
+
+    /// Writes call checkpoints to `checkpoints` so interrupted calls can be
+    /// resumed after a restart.
+    pub fn set_checkpoints(&mut self, checkpoints: CheckpointStore) {
+        self.checkpoints = Some(checkpoints);
+    }
+
+    /// Enables call checkpoints, returning the updated executor for chaining.
+    #[must_use]
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointStore) -> Self {
+        self.set_checkpoints(checkpoints);
+        self
+    }
+
+    /// Returns the checkpoint store if configured.
+    #[must_use]
+    pub fn checkpoints(&self) -> Option<&CheckpointStore> {
+        self.checkpoints.as_ref()
+    }
+
+    /// Refuses tools whose capabilities require scopes the call's
+    /// [`Caller`] was not granted; calls without a caller hold no scopes.
+    pub fn set_tool_scopes(&mut self, scopes: ToolScopes) {
+        self.tool_scopes = Some(scopes);
+    }
+
+    /// Enables caller scope enforcement, returning the updated executor for
+    /// chaining.
+    #[must_use]
+    pub fn with_tool_scopes(mut self, scopes: ToolScopes) -> Self {
+        self.set_tool_scopes(scopes);
+        self
+    }
+
+    /// Returns the tool scope catalog if configured.
+    #[must_use]
+    pub fn tool_scopes(&self) -> Option<&ToolScopes> {
+        self.tool_scopes.as_ref()
+    }
+
+    async fn checkpoint(&self, checkpoint: Checkpoint) -> HandlerResult<()> {
+        match &self.checkpoints {
+            Some(store) => store
+                .record(&checkpoint)
+                .await
+                .map_err(|err| map_memory_error(&err)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns why an interrupted call cannot be resumed, if it cannot.
+    fn resume_blocker(&self, call: &InterruptedCall) -> Option<String> {
+        if self
+            .checkpoints
+            .as_ref()
+            .is_some_and(|store| !store.config().resume())
+        {
+            return Some("call interrupted and resumption is disabled".into());
+        }
+        if let Some((step, tool)) = call.interrupted_step() {
+            let idempotent = self
+                .tools
+                .get(tool)
+                .is_some_and(|handle| handle.metadata().is_idempotent());
+            if !idempotent {
+                return Some(format!(
+                    "tool `{tool}` (step {step}) was interrupted and is not idempotent"
+                ));
+            }
+        }
+        let timeout = serde_json::from_slice::<CallPayload>(call.message().payload())
+            .ok()
+            .and_then(|payload| payload.timeout_ms);
+        if let Some(timeout) = timeout
+            && call
+                .started_at()
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed > Duration::from_millis(timeout))
+        {
+            return Some("call deadline exceeded before recovery".into());
+        }
+        None
+    }
+
     fn notify_policy(&self, request: &PolicyRequest, decision: &PolicyDecision, subject: &str) {
         if let Some(observer) = &self.policy_observer {
             observer.on_decision(request, decision, subject);
         }
     }
 
+    /// Applies `decision`. When it escalates and an approval store is
+    /// configured, an action approvers already granted to the call proceeds,
+    /// and otherwise the call is parked on a new approval request. Outside a
+    /// call, approvers are awaited inline.
+    async fn settle_decision(
+        &self,
+        ctx: Option<&HandlerContext>,
+        request: &PolicyRequest,
+        decision: &PolicyDecision,
+    ) -> HandlerResult<()> {
+        let subject = request.action().label();
+        let Some(approvals) = self
+            .approvals
+            .as_ref()
+            .filter(|_| decision.kind() == DecisionKind::Escalate)
+        else {
+            return enforce_decision(decision, &subject);
+        };
+        let Some(ctx) = ctx else {
+            return match approvals.request(request, decision, None).await {
+                ApprovalStatus::Approved => Ok(()),
+                status => Err(HandlerError::custom(format!(
+                    "approval {status} for {subject}"
+                ))),
+            };
+        };
+        if ctx
+            .extension::<ApprovedActions>()
+            .is_some_and(|granted| granted.0.contains(&subject))
+        {
+            return Ok(());
+        }
+        let approval_id = approvals.open(request, decision, Some(ctx.message().message_id()));
+        Err(HandlerError::AwaitingApproval(approval_id))
+    }
+
+    /// Denies, and reports to the policy observer, tool invocations the
+    /// caller lacks a required scope for.
+    fn enforce_tool_scopes(
+        &self,
+        ctx: &HandlerContext,
+        invocation: &ToolInvocation,
+    ) -> HandlerResult<()> {
+        let (Some(catalog), Some(handle)) =
+            (self.tool_scopes.as_ref(), self.tools.get(&invocation.name))
+        else {
+            return Ok(());
+        };
+        let caller = ctx.extension::<Caller>();
+        let missing: Vec<Scope> = catalog
+            .required_for(handle.metadata().capabilities())
+            .into_iter()
+            .filter(|scope| !caller.is_some_and(|caller| caller.grants(scope)))
+            .collect();
+        let Some(scope) = missing.first().cloned() else {
+            return Ok(());
+        };
+
+        let mut request = PolicyRequest::new(
+            ctx.agent_id(),
+            PolicyAction::InvokeTool {
+                name: invocation.name.clone(),
+            },
+        );
+        let principal = caller
+            .and_then(Caller::principal)
+            .map(|agent_id| agent_id.to_string());
+        let granted: Vec<String> = caller
+            .map(|caller| caller.scopes().iter().map(ToString::to_string).collect())
+            .unwrap_or_default();
+        let context = request.context_mut();
+        context.insert_metadata("caller", Value::from(principal));
+        context.insert_metadata("granted_scopes", Value::from(granted));
+        context.insert_metadata(
+            "missing_scopes",
+            Value::from(missing.iter().map(ToString::to_string).collect::<Vec<_>>()),
+        );
+        let decision = PolicyDecision::deny(format!("caller lacks scope `{scope}`"));
+        warn!(
+            tool = %invocation.name,
+            caller = ?caller.and_then(Caller::principal),
+            %scope,
+            "denying tool invocation for missing scope"
+        );
+        self.notify_policy(&request, &decision, &request.action().label());
+        Err(HandlerError::Forbidden {
+            tool: invocation.name.clone(),
+            scope,
+        })
+    }
+
     async fn enforce_tool_policy(
         &self,
         ctx: &HandlerContext,
@@ -344,7 +660,26 @@ impl CallExecutor {
             .map_err(|err| map_policy_error(&err))?;
 
         self.notify_policy(&request, &decision, &request.action().label());
-        enforce_decision(&decision, &request.action().label())
+        self.settle_decision(Some(ctx), &request, &decision).await
+    }
+
+    async fn enforce_memory_policy(
+        &self,
+        agent_id: AgentId,
+        record: &MemoryRecord,
+    ) -> HandlerResult<()> {
+        let Some(policy) = self.policy.as_ref() else {
+            return Ok(());
+        };
+
+        let request = PolicyRequest::from_memory_record(agent_id, record);
+        let decision = policy
+            .evaluate(&request)
+            .await
+            .map_err(|err| map_policy_error(&err))?;
+
+        self.notify_policy(&request, &decision, &request.action().label());
+        self.settle_decision(None, &request, &decision).await
     }
 
     async fn enforce_inference_policy(
@@ -385,7 +720,7 @@ impl CallExecutor {
             .map_err(|err| map_policy_error(&err))?;
 
         self.notify_policy(&request, &decision, &request.action().label());
-        enforce_decision(&decision, &request.action().label())
+        self.settle_decision(Some(ctx), &request, &decision).await
     }
 
     /// Executes the call pipeline using data extracted from the handler context.
@@ -393,31 +728,104 @@ impl CallExecutor {
     /// # Errors
     ///
     /// Returns [`HandlerError`] when payload decoding, tool execution, or model
-    /// inference fails.
+    /// inference fails, or when the caller's deadline passes.
     pub async fn execute(&self, ctx: &HandlerContext) -> HandlerResult<CallOutcome> {
+        self.execute_with_cancellation(ctx, CancellationToken::new())
+            .await
+    }
+
+    /// Executes the call pipeline, aborting tool execution and in-flight
+    /// inference when `token` is cancelled or the caller's deadline passes.
+    ///
+    /// The deadline is taken from the payload's `timeout_ms`, measured from the
+    /// time the message was received.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HandlerError`] when payload decoding, tool execution, or model
+    /// inference fails, or when the call is cancelled or times out.
+    /// [`HandlerError::AwaitingApproval`] means an escalated action parked
+    /// the call; [`KernelMessageHandler`] re-dispatches such calls once
+    /// approvers decide.
+    pub async fn execute_with_cancellation(
+        &self,
+        ctx: &HandlerContext,
+        token: CancellationToken,
+    ) -> HandlerResult<CallOutcome> {
         let payload = parse_payload(ctx)?;
+        let deadline = payload
+            .timeout_ms
+            .map(|ms| ctx.received_at() + Duration::from_millis(ms));
+        let key = call_key(ctx);
+        if ctx.extension::<ResumedSteps>().is_none() {
+            let scopes = ctx.extension::<Caller>().map(Caller::scopes);
+            self.checkpoint(Checkpoint::Started {
+                key,
+                message: ctx.message().clone(),
+                caller: Caller::new(ctx.principal(), scopes.unwrap_or_default().to_vec()),
+            })
+            .await?;
+        }
 
-        let mut messages = payload.messages;
-        let mut tool_names = Vec::new();
-        let mut tool_results = Vec::new();
+        let expired = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        };
 
-        for invocation in payload.tools {
-            self.enforce_tool_policy(ctx, &invocation).await?;
+        let result = tokio::select! {
+            biased;
+            () = token.cancelled() => Err(HandlerError::custom("call cancelled")),
+            () = expired => Err(HandlerError::custom("call deadline exceeded")),
+            result = self.run(ctx, payload, deadline, token.clone()) => result,
+        };
+        let finished = match &result {
+            Ok(_) => Checkpoint::Completed { key },
+            // A parked call stays open in the journal, so it is resumed, and
+            // escalated again, after a restart.
+            Err(HandlerError::AwaitingApproval(_)) => return result,
+            Err(err) => Checkpoint::Failed {
+                key,
+                reason: err.to_string(),
+            },
+        };
+        if let Err(err) = self.checkpoint(finished).await {
+            warn!(
+                ?err,
+                call_id = key.message_id(),
+                "failed to checkpoint call result"
+            );
+        }
+        result
+    }
 
-            let tool_output = self
-                .tools
-                .invoke(&invocation.name, invocation.input.clone())
-                .await
-                .map_err(|err| map_tool_error(&invocation.name, &err))?;
+    async fn run(
+        &self,
+        ctx: &HandlerContext,
+        payload: CallPayload,
+        deadline: Option<Instant>,
+        token: CancellationToken,
+    ) -> HandlerResult<CallOutcome> {
+        let session = self.load_session(ctx, payload.session_id).await?;
+
+        let mut new_turns = payload.messages;
+        let (tool_names, tool_results) = self
+            .invoke_tools(ctx, payload.tools, &mut new_turns)
+            .await?;
 
-            let message_content =
-                serde_json::to_string(&tool_output).unwrap_or_else(|_| String::new());
-            messages.push(PromptMessage::new(MessageRole::Tool, message_content));
-            tool_names.push(invocation.name.clone());
-            tool_results.push(ToolInvocationResult {
-                name: invocation.name,
-                output: tool_output,
-            });
+        let mut messages = match &session {
+            Some((store, _, history)) => store.merge(history.clone(), new_turns.clone()),
+            None => new_turns.clone(),
+        };
+
+        let retrieved = self.recall(&new_turns).await?;
+        if !retrieved.is_empty() {
+            let at = messages
+                .iter()
+                .rposition(|message| message.role() == MessageRole::User)
+                .unwrap_or(messages.len());
+            messages.insert(at, context_message(&retrieved));
         }
 
         self.enforce_inference_policy(ctx, messages.len(), &tool_names)
@@ -438,6 +846,15 @@ impl CallExecutor {
             request = request.with_tools(tool_names);
         }
 
+        if let Some(budget) = payload.thinking_budget {
+            request = request.with_thinking_budget(budget);
+        }
+
+        if let Some(deadline) = deadline {
+            request = request.with_deadline(deadline);
+        }
+        request = request.with_cancellation(token);
+
         let mut stream = self
             .adapter
             .infer(request)
@@ -445,19 +862,232 @@ impl CallExecutor {
             .map_err(|err| map_adapter_error(&err, self.adapter.metadata()))?;
 
         let mut response = String::new();
+        let mut reasoning = String::new();
+        let mut usage = None;
+        let mut chunks = 0_usize;
+        let mut checkpointed = (0, 0);
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.map_err(|err| map_adapter_error(&err, self.adapter.metadata()))?;
-            response.push_str(&chunk.delta);
+            if chunk.usage.is_some() {
+                usage = chunk.usage;
+            }
+            if chunk.is_reasoning() {
+                reasoning.push_str(&chunk.delta);
+            } else {
+                response.push_str(&chunk.delta);
+                if let Some(job) = ctx.extension::<JobTicket>() {
+                    job.output(&chunk.delta);
+                }
+            }
             if chunk.done {
                 break;
             }
+            chunks += 1;
+            if let Some(store) = &self.checkpoints
+                && chunks % store.config().progress_interval() == 0
+            {
+                let progress = Checkpoint::Progress {
+                    key: call_key(ctx),
+                    response_offset: checkpointed.0,
+                    response: response[checkpointed.0..].to_owned(),
+                    reasoning_offset: checkpointed.1,
+                    reasoning: reasoning[checkpointed.1..].to_owned(),
+                };
+                match store.record(&progress).await {
+                    Ok(()) => checkpointed = (response.len(), reasoning.len()),
+                    Err(err) => warn!(?err, "failed to checkpoint call progress"),
+                }
+            }
         }
 
+        let session_id = match session {
+            Some((store, id, _)) => {
+                new_turns.push(PromptMessage::new(MessageRole::Assistant, response.clone()));
+                self.append_session_turns(ctx.agent_id(), store, &id, &new_turns)
+                    .await?;
+                Some(id)
+            }
+            None => None,
+        };
+
         Ok(CallOutcome {
             response,
+            reasoning,
+            usage,
+            session_id,
+            retrieved,
             tool_results,
         })
     }
+
+    /// Runs the requested tools, appending the assistant tool-call turn and the
+    /// correlated tool results to `turns`.
+    async fn invoke_tools(
+        &self,
+        ctx: &HandlerContext,
+        invocations: Vec<ToolInvocation>,
+        turns: &mut Vec<PromptMessage>,
+    ) -> HandlerResult<(Vec<String>, Vec<ToolInvocationResult>)> {
+        let mut tool_names = Vec::new();
+        let mut tool_results = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut tool_messages = Vec::new();
+        let key = call_key(ctx);
+        let resumed = ctx.extension::<ResumedSteps>();
+
+        for (idx, invocation) in invocations.into_iter().enumerate() {
+            let replayed = resumed
+                .and_then(|steps| steps.0.get(&idx))
+                .filter(|step| step.tool() == invocation.name && *step.input() == invocation.input)
+                .map(CompletedStep::output);
+            let tool_output = if let Some(output) = replayed {
+                debug!(tool = %invocation.name, step = idx, "replaying checkpointed tool output");
+                output.clone()
+            } else {
+                self.enforce_tool_scopes(ctx, &invocation)?;
+                self.enforce_tool_policy(ctx, &invocation).await?;
+                self.checkpoint(Checkpoint::ToolStarted {
+                    key,
+                    step: idx,
+                    tool: invocation.name.clone(),
+                })
+                .await?;
+                let output = self
+                    .tools
+                    .invoke(&invocation.name, invocation.input.clone())
+                    .await
+                    .map_err(|err| map_tool_error(&invocation.name, &err))?;
+                self.checkpoint(Checkpoint::ToolCompleted {
+                    key,
+                    step: idx,
+                    tool: invocation.name.clone(),
+                    input: invocation.input.clone(),
+                    output: output.clone(),
+                })
+                .await?;
+                if let Some(log) = ctx.extension::<StepLog>() {
+                    log.record(
+                        idx,
+                        CompletedStep::new(
+                            invocation.name.clone(),
+                            invocation.input.clone(),
+                            output.clone(),
+                        ),
+                    );
+                }
+                output
+            };
+
+            let call_id = invocation.id.unwrap_or_else(|| format!("call_{idx}"));
+            let message_content =
+                serde_json::to_string(&tool_output).unwrap_or_else(|_| String::new());
+            tool_messages.push(PromptMessage::tool_result(call_id.clone(), message_content));
+            tool_calls.push(ToolCall::new(
+                call_id,
+                invocation.name.clone(),
+                invocation.input,
+            ));
+            if let Some(job) = ctx.extension::<JobTicket>() {
+                job.tool_completed(&invocation.name);
+            }
+            tool_names.push(invocation.name.clone());
+            tool_results.push(ToolInvocationResult {
+                name: invocation.name,
+                output: tool_output,
+            });
+        }
+
+        // Providers require tool results to follow the assistant turn that requested them.
+        if !tool_calls.is_empty() {
+            turns.push(PromptMessage::assistant_tool_calls("", tool_calls));
+            turns.extend(tool_messages);
+        }
+
+        Ok((tool_names, tool_results))
+    }
+
+    async fn recall(&self, turns: &[PromptMessage]) -> HandlerResult<Vec<RetrievedMemory>> {
+        let Some(retrieval) = &self.retrieval else {
+            return Ok(Vec::new());
+        };
+        let Some(query) = turns
+            .iter()
+            .rev()
+            .find(|message| message.role() == MessageRole::User)
+        else {
+            return Ok(Vec::new());
+        };
+        retrieval
+            .retrieve(query.content())
+            .await
+            .map_err(|err| map_memory_error(&err))
+    }
+
+    async fn load_session(
+        &self,
+        ctx: &HandlerContext,
+        session_id: Option<String>,
+    ) -> HandlerResult<Option<(&SessionStore, String, Vec<PromptMessage>)>> {
+        let Some(id) = session_id else {
+            return Ok(None);
+        };
+        let store = self.sessions.as_ref().ok_or_else(|| {
+            HandlerError::custom("call carries a session_id but no session store is configured")
+        })?;
+        let history = store
+            .load(&id)
+            .await
+            .map_err(|err| map_memory_error(&err))?;
+        if !store.claim(&id, SessionOwner::of(ctx)) {
+            return Err(HandlerError::SessionForbidden(id));
+        }
+        Ok(Some((store, id, history)))
+    }
+
+    async fn append_session_turns(
+        &self,
+        agent_id: AgentId,
+        store: &SessionStore,
+        session_id: &str,
+        turns: &[PromptMessage],
+    ) -> HandlerResult<()> {
+        let records = store
+            .prepare(session_id, turns)
+            .await
+            .map_err(|err| map_memory_error(&err))?;
+        for record in &records {
+            self.enforce_memory_policy(agent_id, record).await?;
+        }
+        store
+            .commit(records)
+            .await
+            .map_err(|err| map_memory_error(&err))
+    }
+}
+
+fn call_key(ctx: &HandlerContext) -> CallKey {
+    CallKey::new(ctx.sender(), ctx.message().message_id())
+}
+
+/// Escalated actions approvers granted to a re-dispatched call.
+#[derive(Debug, Clone, Default)]
+struct ApprovedActions(BTreeSet<String>);
+
+/// Tool steps a call completed, replayed if it is parked and re-dispatched.
+#[derive(Debug, Clone, Default)]
+struct StepLog(Arc<Mutex<BTreeMap<usize, CompletedStep>>>);
+
+impl StepLog {
+    fn record(&self, step: usize, completed: CompletedStep) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(step, completed);
+    }
+
+    fn take(&self) -> BTreeMap<usize, CompletedStep> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
 }
 
 fn parse_payload(ctx: &HandlerContext) -> HandlerResult<CallPayload> {
@@ -518,33 +1148,81 @@ fn enforce_decision(decision: &PolicyDecision, subject: &str) -> HandlerResult<(
 }
 
 /// Outcome of processing a call message.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CallOutcome {
     response: String,
+    reasoning: String,
+    usage: Option<TokenUsage>,
+    session_id: Option<String>,
+    retrieved: Vec<RetrievedMemory>,
     tool_results: Vec<ToolInvocationResult>,
 }
 
 impl CallOutcome {
-    /// Returns the aggregated model response text.
+    /// Rebuilds an outcome carrying only the response text of a call restored
+    /// from a deduplication store.
+    pub(crate) fn replayed(response: String) -> Self {
+        Self {
+            response,
+            reasoning: String::new(),
+            usage: None,
+            session_id: None,
+            retrieved: Vec::new(),
+            tool_results: Vec::new(),
+        }
+    }
+
+    /// Returns the aggregated model response text, excluding any reasoning.
     #[must_use]
     pub fn response(&self) -> &str {
         &self.response
     }
 
-    /// Returns the tool invocation results that were executed as part of this call.
+    /// Returns the reasoning emitted by the model, or an empty string when the
+    /// model produced none.
     #[must_use]
-    pub fn tool_results(&self) -> &[ToolInvocationResult] {
-        &self.tool_results
+    pub fn reasoning(&self) -> &str {
+        &self.reasoning
     }
-}
 
-/// Result describing an executed tool invocation.
-#[derive(Debug, Clone)]
-pub struct ToolInvocationResult {
-    /// Name of the tool that was invoked.
-    pub name: String,
-    /// Output produced by the tool.
-    pub output: Value,
+    /// Returns token usage and prompt cache statistics reported by the provider.
+    #[must_use]
+    pub const fn usage(&self) -> Option<&TokenUsage> {
+        self.usage.as_ref()
+    }
+
+    /// Returns the session the call belonged to, if any.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Returns the memories injected into the prompt by the retrieval stage.
+    #[must_use]
+    pub fn retrieved(&self) -> &[RetrievedMemory] {
+        &self.retrieved
+    }
+
+    /// Returns the ids of the memories that grounded the response.
+    #[must_use]
+    pub fn memory_ids(&self) -> Vec<uuid::Uuid> {
+        self.retrieved.iter().map(RetrievedMemory::id).collect()
+    }
+
+    /// Returns the tool invocation results that were executed as part of this call.
+    #[must_use]
+    pub fn tool_results(&self) -> &[ToolInvocationResult] {
+        &self.tool_results
+    }
+}
+
+/// Result describing an executed tool invocation.
+#[derive(Debug, Clone)]
+pub struct ToolInvocationResult {
+    /// Name of the tool that was invoked.
+    pub name: String,
+    /// Output produced by the tool.
+    pub output: Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -555,21 +1233,35 @@ struct CallPayload {
     #[serde(default)]
     max_output_tokens: Option<u32>,
     #[serde(default)]
+    thinking_budget: Option<u32>,
+    /// Caller deadline in milliseconds, measured from receipt of the call.
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    /// Conversation whose stored turns precede `messages`.
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
     tools: Vec<ToolInvocation>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ToolInvocation {
+    #[serde(default)]
+    id: Option<String>,
     name: String,
     #[serde(default)]
     input: Value,
 }
 
 /// Handler implementation that wires the call executor into the MXP handler trait.
+#[derive(Clone)]
 pub struct KernelMessageHandler {
     executor: Arc<CallExecutor>,
     sink: Arc<dyn CallOutcomeSink>,
     memory: Option<Arc<MemoryBus>>,
+    cancellations: CallCancellations,
+    delivery: Option<ReliableSender>,
+    jobs: Option<JobManager>,
 }
 
 impl KernelMessageHandler {
@@ -585,6 +1277,9 @@ impl KernelMessageHandler {
             executor,
             sink,
             memory: None,
+            cancellations: CallCancellations::new(),
+            delivery: None,
+            jobs: None,
         }
     }
 
@@ -639,12 +1334,252 @@ impl KernelMessageHandler {
         self.executor.policy_observer()
     }
 
+    /// Configures the session store used for calls that carry a `session_id`.
+    #[must_use]
+    pub fn with_sessions(mut self, sessions: SessionStore) -> Self {
+        self.set_sessions(sessions);
+        self
+    }
+
+    /// Installs or replaces the session store after construction.
+    pub fn set_sessions(&mut self, sessions: SessionStore) {
+        Arc::make_mut(&mut self.executor).set_sessions(sessions);
+    }
+
+    /// Returns the configured session store, if any.
+    #[must_use]
+    pub fn sessions(&self) -> Option<&SessionStore> {
+        self.executor.sessions()
+    }
+
+    /// Enables the retrieval stage for call execution.
+    #[must_use]
+    pub fn with_retrieval(mut self, retrieval: RetrievalStage) -> Self {
+        self.set_retrieval(retrieval);
+        self
+    }
+
+    /// Installs or replaces the retrieval stage after construction.
+    pub fn set_retrieval(&mut self, retrieval: RetrievalStage) {
+        Arc::make_mut(&mut self.executor).set_retrieval(retrieval);
+    }
+
+    /// Parks escalated calls in `approvals` until approvers decide. Approval
+    /// decisions arrive as `approval_decision` events from authenticated
+    /// agents.
+    #[must_use]
+    pub fn with_approvals(mut self, approvals: ApprovalStore) -> Self {
+        self.set_approvals(approvals);
+        self
+    }
+
+    /// Installs or replaces the approval store after construction.
+    pub fn set_approvals(&mut self, approvals: ApprovalStore) {
+        Arc::make_mut(&mut self.executor).set_approvals(approvals);
+    }
+
+    /// Returns the configured approval store, if any.
+    #[must_use]
+    pub fn approvals(&self) -> Option<&ApprovalStore> {
+        self.executor.approvals()
+    }
+
+    /// Writes call checkpoints to `checkpoints`; call
+    /// [`recover`](Self::recover) after a restart to finish interrupted calls.
+    #[must_use]
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointStore) -> Self {
+        self.set_checkpoints(checkpoints);
+        self
+    }
+
+    /// Installs or replaces the checkpoint store after construction.
+    pub fn set_checkpoints(&mut self, checkpoints: CheckpointStore) {
+        Arc::make_mut(&mut self.executor).set_checkpoints(checkpoints);
+    }
+
+    /// Returns the configured checkpoint store, if any.
+    #[must_use]
+    pub fn checkpoints(&self) -> Option<&CheckpointStore> {
+        self.executor.checkpoints()
+    }
+
+    /// Refuses tools whose capabilities require scopes the caller lacks;
+    /// install [`ScopeGrants`](crate::ScopeGrants) to attach callers.
+    #[must_use]
+    pub fn with_tool_scopes(mut self, scopes: ToolScopes) -> Self {
+        self.set_tool_scopes(scopes);
+        self
+    }
+
+    /// Installs or replaces the tool scope catalog after construction.
+    pub fn set_tool_scopes(&mut self, scopes: ToolScopes) {
+        Arc::make_mut(&mut self.executor).set_tool_scopes(scopes);
+    }
+
+    /// Returns the configured tool scope catalog, if any.
+    #[must_use]
+    pub fn tool_scopes(&self) -> Option<&ToolScopes> {
+        self.executor.tool_scopes()
+    }
+
+    /// Resumes or fails the calls a previous run left unfinished.
+    ///
+    /// A call is resumed unless resumption is disabled, its deadline has
+    /// passed, or it was interrupted inside a tool that is not marked
+    /// idempotent; those calls are failed with a checkpoint explaining why.
+    /// Resumed calls replay completed tool steps from their checkpoints
+    /// instead of running them again, re-run the model, and deliver the
+    /// outcome to the sink like a fresh call. Their deadline restarts at
+    /// recovery.
+    ///
+    /// With [`with_jobs`](Self::with_jobs), a resumed job call continues
+    /// under the job it was accepted as; if that job is no longer stored as
+    /// running, the call is failed instead. Stored jobs still running after
+    /// recovery are then failed and expired jobs are purged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HandlerError`] when the checkpoint journal or the job store
+    /// cannot be read or written.
+    pub async fn recover(&self, agent_id: AgentId) -> HandlerResult<RecoveryReport> {
+        let mut report = RecoveryReport::default();
+        let job_error =
+            |err: JobError| HandlerError::custom(format!("failed to recover jobs: {err}"));
+        if let Some(store) = self.checkpoints() {
+            let interrupted = store
+                .interrupted()
+                .await
+                .map_err(|err| map_memory_error(&err))?;
+            for call in interrupted {
+                let key = call.key();
+                let mut ctx = HandlerContext::from_message(agent_id, call.message().clone());
+                if let Some(sender) = key.sender() {
+                    ctx = ctx.with_sender(sender);
+                }
+                ctx.insert_extension(call.caller().clone());
+
+                let mut blocker = self.executor.resume_blocker(&call);
+                if blocker.is_none()
+                    && let Some(jobs) = &self.jobs
+                    && is_job_call(&ctx)
+                {
+                    match jobs.resume(&ctx).await.map_err(job_error)? {
+                        Some(job) => ctx.insert_extension(job),
+                        None => blocker = Some("job is no longer running".to_owned()),
+                    }
+                }
+                if let Some(reason) = blocker {
+                    warn!(call_id = key.message_id(), %reason, "failing interrupted call");
+                    store
+                        .record(&Checkpoint::Failed {
+                            key,
+                            reason: reason.clone(),
+                        })
+                        .await
+                        .map_err(|err| map_memory_error(&err))?;
+                    report.failed.push((key, reason));
+                    continue;
+                }
+
+                info!(call_id = key.message_id(), "resuming interrupted call");
+                ctx.insert_extension(ResumedSteps(call.completed_steps().clone()));
+                match crate::AgentMessageHandler::handle_call(self, ctx).await {
+                    Ok(()) => report.resumed.push(key),
+                    Err(err) => report.failed.push((key, err.to_string())),
+                }
+            }
+        }
+        if let Some(jobs) = &self.jobs {
+            report.interrupted_jobs = jobs.fail_interrupted().await.map_err(job_error)?;
+            jobs.purge_expired().await.map_err(job_error)?;
+        }
+        Ok(report)
+    }
+
+    /// Routes inbound delivery acknowledgements to `sender` and acknowledges
+    /// inbound calls through its transport.
+    #[must_use]
+    pub fn with_reliable_sender(mut self, sender: ReliableSender) -> Self {
+        self.set_reliable_sender(sender);
+        self
+    }
+
+    /// Installs or replaces the reliable sender after construction.
+    pub fn set_reliable_sender(&mut self, sender: ReliableSender) {
+        self.delivery = Some(sender);
+    }
+
+    /// Returns the reliable sender acknowledged by inbound `Ack` messages, if any.
+    #[must_use]
+    pub fn reliable_sender(&self) -> Option<&ReliableSender> {
+        self.delivery.as_ref()
+    }
+
+    /// Runs calls that set `"job": true` as jobs tracked by `jobs`, answering
+    /// `job_status` and `job_cancel` events.
+    #[must_use]
+    pub fn with_jobs(mut self, jobs: JobManager) -> Self {
+        self.set_jobs(jobs);
+        self
+    }
+
+    /// Installs or replaces the job manager after construction.
+    pub fn set_jobs(&mut self, jobs: JobManager) {
+        self.jobs = Some(jobs);
+    }
+
+    /// Returns the configured job manager, if any.
+    #[must_use]
+    pub fn jobs(&self) -> Option<&JobManager> {
+        self.jobs.as_ref()
+    }
+
+    /// Cancels a running job.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::Finished`] when the job already finished and
+    /// [`JobError::Unknown`] when it does not exist or jobs are not enabled.
+    pub async fn cancel_job(&self, job_id: uuid::Uuid) -> JobResult<()> {
+        self.cancel_job_for(job_id, None).await
+    }
+
+    /// Cancels a job on behalf of `requester`, or unconditionally when it is
+    /// `None`.
+    async fn cancel_job_for(
+        &self,
+        job_id: uuid::Uuid,
+        requester: Option<&HandlerContext>,
+    ) -> JobResult<()> {
+        let Some(jobs) = &self.jobs else {
+            return Err(JobError::Unknown(job_id));
+        };
+        if let Some(key) = jobs.request_cancel(job_id, requester) {
+            // The job settles as cancelled even if its call has not
+            // registered a token yet.
+            let _ = self.cancellations.cancel(key?);
+            return Ok(());
+        }
+        let record = jobs.status(job_id).await?;
+        Err(JobError::Finished {
+            job_id,
+            status: record.status(),
+        })
+    }
+
     /// Returns the configured memory bus, if any.
     #[must_use]
     pub fn memory(&self) -> Option<&Arc<MemoryBus>> {
         self.memory.as_ref()
     }
 
+    /// Returns the registry of in-flight calls, which can be used to cancel
+    /// calls outside of MXP cancel events.
+    #[must_use]
+    pub fn cancellations(&self) -> &CallCancellations {
+        &self.cancellations
+    }
+
     async fn record_inbound(&self, ctx: &HandlerContext) -> HandlerResult<()> {
         let Some(memory) = &self.memory else {
             return Ok(());
@@ -659,7 +1594,9 @@ impl KernelMessageHandler {
             .build()
             .map_err(|err| map_memory_error(&err))?;
 
-        self.enforce_memory_policy(ctx.agent_id(), &record).await?;
+        self.executor
+            .enforce_memory_policy(ctx.agent_id(), &record)
+            .await?;
         memory
             .record(record)
             .await
@@ -685,7 +1622,31 @@ impl KernelMessageHandler {
                 .metadata("tool_name", Value::from(tool.name.clone()))
                 .build()
                 .map_err(|err| map_memory_error(&err))?;
-            self.enforce_memory_policy(agent_id, &record).await?;
+            self.executor
+                .enforce_memory_policy(agent_id, &record)
+                .await?;
+            memory
+                .record(record)
+                .await
+                .map_err(|err| map_memory_error(&err))?;
+        }
+
+        if !outcome.reasoning().is_empty() {
+            let record = MemoryRecord::builder(
+                MemoryChannel::Output,
+                Bytes::from(outcome.reasoning().to_owned()),
+            )
+            .tag("mxp.call")
+            .map_err(|err| map_memory_error(&err))?
+            .tag("reasoning")
+            .map_err(|err| map_memory_error(&err))?
+            .metadata("direction", Value::from("reasoning"))
+            .metadata("message_type", Value::from("call"))
+            .build()
+            .map_err(|err| map_memory_error(&err))?;
+            self.executor
+                .enforce_memory_policy(agent_id, &record)
+                .await?;
             memory
                 .record(record)
                 .await
@@ -699,11 +1660,22 @@ impl KernelMessageHandler {
         .tag("mxp.call")
         .map_err(|err| map_memory_error(&err))?
         .metadata("direction", Value::from("outbound"))
-        .metadata("message_type", Value::from("call"))
+        .metadata("message_type", Value::from("call"));
+        let response_record = if outcome.retrieved().is_empty() {
+            response_record
+        } else {
+            let ids: Vec<Value> = outcome
+                .memory_ids()
+                .iter()
+                .map(|id| Value::from(id.to_string()))
+                .collect();
+            response_record.metadata("memory_ids", Value::from(ids))
+        }
         .build()
         .map_err(|err| map_memory_error(&err))?;
 
-        self.enforce_memory_policy(agent_id, &response_record)
+        self.executor
+            .enforce_memory_policy(agent_id, &response_record)
             .await?;
         memory
             .record(response_record)
@@ -712,24 +1684,138 @@ impl KernelMessageHandler {
         Ok(())
     }
 
-    async fn enforce_memory_policy(
-        &self,
-        agent_id: AgentId,
-        record: &MemoryRecord,
-    ) -> HandlerResult<()> {
-        let Some(policy) = self.executor.policy() else {
-            return Ok(());
+    /// Acknowledges a call to its sender through the reliable sender.
+    fn acknowledge(&self, ctx: &HandlerContext) {
+        if let (Some(delivery), Some(sender)) = (&self.delivery, ctx.sender()) {
+            send_ack(delivery.transport(), ctx.message().message_id(), sender);
+        }
+    }
+
+    /// Runs an admitted call and delivers its outcome, parking it instead
+    /// when policy escalates one of its actions.
+    async fn process(&self, mut ctx: HandlerContext) -> HandlerResult {
+        if ctx.extension::<ResumedSteps>().is_none() {
+            self.record_inbound(&ctx).await?;
+        }
+
+        // A re-dispatched call keeps the job it was accepted under.
+        let job = match (ctx.extension::<JobTicket>(), &self.jobs) {
+            (Some(job), _) => Some(job.clone()),
+            (None, Some(jobs)) => jobs.accept(&ctx).await?,
+            (None, None) => None,
         };
+        if let Some(job) = &job {
+            ctx.insert_extension(job.clone());
+        }
+        ctx.insert_extension(StepLog::default());
+
+        let guard = self.cancellations.register(call_key(&ctx), ctx.principal());
+        let result = self
+            .executor
+            .execute_with_cancellation(&ctx, guard.token())
+            .await;
+        drop(guard);
+        if let Err(HandlerError::AwaitingApproval(approval_id)) = result {
+            let dedup = ctx.extension::<DedupSlot>().and_then(DedupSlot::detach);
+            self.park(ctx, approval_id, dedup, job);
+            return Ok(());
+        }
+        if let Some(job) = &job {
+            job.finish(result.as_ref()).await;
+        }
+        let outcome = result?;
 
-        let request = PolicyRequest::from_memory_record(agent_id, record);
-        let decision = policy
-            .evaluate(&request)
-            .await
-            .map_err(|err| map_policy_error(&err))?;
+        self.record_outbound(ctx.agent_id(), &outcome).await?;
 
-        self.executor
-            .notify_policy(&request, &decision, &request.action().label());
-        enforce_decision(&decision, &request.action().label())
+        if let Some(dedup) = ctx.extension::<DedupSlot>() {
+            dedup.record_outcome(&outcome);
+        }
+        self.sink.record(outcome);
+        Ok(())
+    }
+
+    /// Waits off the scheduler for approvers to decide `approval_id`, then
+    /// re-dispatches the call with the action granted and its completed tool
+    /// steps replayed, or fails it when the request is denied, expires, or
+    /// the call is cancelled.
+    fn park(
+        &self,
+        mut ctx: HandlerContext,
+        approval_id: uuid::Uuid,
+        dedup: Option<DedupSlot>,
+        job: Option<JobTicket>,
+    ) {
+        let Some(approvals) = self.approvals().cloned() else {
+            return;
+        };
+        let subject = approvals
+            .get(approval_id)
+            .map(|record| record.request().subject().to_owned())
+            .unwrap_or_default();
+        let key = call_key(&ctx);
+        info!(call_id = key.message_id(), %approval_id, "call parked awaiting approval");
+        let handler = self.clone();
+        tokio::spawn(async move {
+            let cancel = handler.cancellations.register(key, ctx.principal());
+            let token = cancel.token();
+            let status = tokio::select! {
+                status = approvals.wait(approval_id) => status,
+                () = token.cancelled() => approvals.cancel(approval_id),
+            };
+            drop(cancel);
+            if status != ApprovalStatus::Approved {
+                let reason = format!("approval {status} for {subject}");
+                warn!(call_id = key.message_id(), %approval_id, %reason, "failing parked call");
+                if let Err(err) = handler
+                    .executor
+                    .checkpoint(Checkpoint::Failed {
+                        key,
+                        reason: reason.clone(),
+                    })
+                    .await
+                {
+                    warn!(
+                        ?err,
+                        call_id = key.message_id(),
+                        "failed to checkpoint call result"
+                    );
+                }
+                let err = HandlerError::custom(reason);
+                if let Some(job) = job {
+                    job.finish(Err(&err)).await;
+                }
+                if let Some(dedup) = dedup {
+                    dedup.settle(&Err(err)).await;
+                }
+                return;
+            }
+
+            let mut steps = ctx
+                .extension::<ResumedSteps>()
+                .map(|resumed| resumed.0.clone())
+                .unwrap_or_default();
+            if let Some(log) = ctx.extension::<StepLog>() {
+                steps.extend(log.take());
+            }
+            ctx.insert_extension(ResumedSteps(steps));
+            let mut granted = ctx
+                .extension::<ApprovedActions>()
+                .cloned()
+                .unwrap_or_default();
+            granted.0.insert(subject);
+            ctx.insert_extension(granted);
+            if let Some(dedup) = &dedup {
+                ctx.insert_extension(dedup.clone());
+            }
+            info!(call_id = key.message_id(), %approval_id, "re-dispatching approved call");
+            let result = handler.process(ctx).await;
+            if let Err(err) = &result {
+                warn!(call_id = key.message_id(), %err, "approved call failed");
+            }
+            if let Some(dedup) = dedup {
+                dedup.settle(&result).await;
+            }
+        });
     }
 
     /// Returns the underlying executor for advanced scenarios.
@@ -745,6 +1831,13 @@ pub struct KernelMessageHandlerBuilder {
     sink: Arc<dyn CallOutcomeSink>,
     tools: Vec<ToolBinding>,
     memory: Option<Arc<MemoryBus>>,
+    sessions: Option<SessionStore>,
+    retrieval: Option<RetrievalStage>,
+    delivery: Option<ReliableSender>,
+    approvals: Option<ApprovalStore>,
+    checkpoints: Option<CheckpointStore>,
+    jobs: Option<JobManager>,
+    tool_scopes: Option<ToolScopes>,
     policy: Option<Arc<dyn PolicyEngine>>,
     policy_observer: Option<Arc<dyn PolicyObserver>>,
 }
@@ -756,6 +1849,13 @@ impl KernelMessageHandlerBuilder {
             sink,
             tools: Vec::new(),
             memory: None,
+            sessions: None,
+            retrieval: None,
+            delivery: None,
+            approvals: None,
+            checkpoints: None,
+            jobs: None,
+            tool_scopes: None,
             policy: None,
             policy_observer: None,
         }
@@ -787,6 +1887,56 @@ impl KernelMessageHandlerBuilder {
         self
     }
 
+    /// Configures the session store used for calls that carry a `session_id`.
+    #[must_use]
+    pub fn with_sessions(mut self, sessions: SessionStore) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Enables the retrieval stage for call execution.
+    #[must_use]
+    pub fn with_retrieval(mut self, retrieval: RetrievalStage) -> Self {
+        self.retrieval = Some(retrieval);
+        self
+    }
+
+    /// Routes inbound delivery acknowledgements to `sender` and acknowledges
+    /// inbound calls through its transport.
+    #[must_use]
+    pub fn with_reliable_sender(mut self, sender: ReliableSender) -> Self {
+        self.delivery = Some(sender);
+        self
+    }
+
+    /// Parks escalated calls in `approvals` until approvers decide.
+    #[must_use]
+    pub fn with_approvals(mut self, approvals: ApprovalStore) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Writes call checkpoints to `checkpoints`.
+    #[must_use]
+    pub fn with_checkpoints(mut self, checkpoints: CheckpointStore) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Runs calls that set `"job": true` as jobs tracked by `jobs`.
+    #[must_use]
+    pub fn with_jobs(mut self, jobs: JobManager) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Refuses tools whose capabilities require scopes the caller lacks.
+    #[must_use]
+    pub fn with_tool_scopes(mut self, scopes: ToolScopes) -> Self {
+        self.tool_scopes = Some(scopes);
+        self
+    }
+
     /// Installs or replaces the policy engine.
     #[must_use]
     pub fn with_policy(mut self, policy: Arc<dyn PolicyEngine>) -> Self {
@@ -817,6 +1967,27 @@ impl KernelMessageHandlerBuilder {
         if let Some(memory) = self.memory {
             handler.set_memory(memory);
         }
+        if let Some(sessions) = self.sessions {
+            handler.set_sessions(sessions);
+        }
+        if let Some(retrieval) = self.retrieval {
+            handler.set_retrieval(retrieval);
+        }
+        if let Some(sender) = self.delivery {
+            handler.set_reliable_sender(sender);
+        }
+        if let Some(approvals) = self.approvals {
+            handler.set_approvals(approvals);
+        }
+        if let Some(checkpoints) = self.checkpoints {
+            handler.set_checkpoints(checkpoints);
+        }
+        if let Some(jobs) = self.jobs {
+            handler.set_jobs(jobs);
+        }
+        if let Some(scopes) = self.tool_scopes {
+            handler.set_tool_scopes(scopes);
+        }
         if let Some(policy) = self.policy {
             handler.set_policy(policy);
         }
@@ -831,13 +2002,89 @@ impl KernelMessageHandlerBuilder {
 #[async_trait]
 impl crate::AgentMessageHandler for KernelMessageHandler {
     async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
-        self.record_inbound(&ctx).await?;
+        if ctx.extension::<ResumedSteps>().is_none() {
+            self.acknowledge(&ctx);
+        }
+        self.process(ctx).await
+    }
+
+    async fn handle_duplicate(&self, ctx: HandlerContext) -> HandlerResult {
+        // Retransmissions are acknowledged too, in case the first ack was lost.
+        self.acknowledge(&ctx);
+        match ctx.extension::<DuplicateCall>() {
+            Some(DuplicateCall::InProgress) => self
+                .sink
+                .record_in_progress(in_progress_ack(ctx.message().message_id())),
+            Some(DuplicateCall::Completed(call)) => {
+                if let Some(outcome) = call.outcome() {
+                    self.sink.record_duplicate(outcome.clone());
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_event(&self, ctx: HandlerContext) -> HandlerResult {
+        let payload = ctx.message().payload();
+        if let (Some(jobs), Some(request)) = (&self.jobs, JobRequest::from_payload(payload)) {
+            if let JobRequest::JobCancel { job_id } = request
+                && let Err(err) = self.cancel_job_for(job_id, Some(&ctx)).await
+            {
+                debug!(%job_id, %err, "job cancel request not applied");
+            }
+            jobs.reply(&ctx, request.job_id()).await;
+            return Ok(());
+        }
+        if let (Some(approvals), Some(decision)) =
+            (self.approvals(), ApprovalDecision::from_payload(payload))
+        {
+            // Votes from the network count only when an authentication
+            // layer identified the sending agent.
+            let status = ctx
+                .principal()
+                .ok_or(ApprovalError::Unauthenticated(decision.approval_id()))
+                .and_then(|principal| approvals.decide_as(principal, &decision))
+                .map_err(|err| HandlerError::custom(err.to_string()))?;
+            debug!(
+                approval_id = %decision.approval_id(),
+                approver = decision.approver(),
+                %status,
+                "approval decision applied"
+            );
+            return Ok(());
+        }
+        let Some(request) = CancelRequest::from_payload(payload) else {
+            return Err(HandlerError::Unsupported(MessageType::Event));
+        };
 
-        let outcome = self.executor.execute(&ctx).await?;
+        if !self.cancellations.cancel_from(&ctx, request.call_id()) {
+            debug!(
+                call_id = request.call_id(),
+                sender = ?ctx.sender(),
+                "cancel requested for call that is not in flight for this sender"
+            );
+        }
+        Ok(())
+    }
 
-        self.record_outbound(ctx.agent_id(), &outcome).await?;
+    async fn handle_ack(&self, ctx: HandlerContext) -> HandlerResult {
+        let (Some(sender), Some(ack)) = (
+            &self.delivery,
+            DeliveryAck::from_payload(ctx.message().payload()),
+        ) else {
+            return Err(HandlerError::Unsupported(MessageType::Ack));
+        };
 
-        self.sink.record(outcome);
+        let acked = ctx
+            .sender()
+            .is_some_and(|source| sender.acknowledge(source, ack.message_id()));
+        if !acked {
+            debug!(
+                message_id = ack.message_id(),
+                "ack received for message that is not awaiting delivery"
+            );
+        }
         Ok(())
     }
 }
@@ -846,6 +2093,18 @@ impl crate::AgentMessageHandler for KernelMessageHandler {
 pub trait CallOutcomeSink: Send + Sync {
     /// Records the outcome of a call invocation.
     fn record(&self, outcome: CallOutcome);
+
+    /// Records the cached outcome replayed for a retransmitted call. Defaults
+    /// to [`record`](Self::record) so responders answer the retransmission.
+    fn record_duplicate(&self, outcome: CallOutcome) {
+        self.record(outcome);
+    }
+
+    /// Receives the MXP `Ack` for a retransmitted call whose original
+    /// delivery is still executing. Ignored by default.
+    fn record_in_progress(&self, ack: Message) {
+        let _ = ack;
+    }
 }
 
 /// Sink implementation that logs to tracing.
@@ -907,10 +2166,14 @@ impl CallOutcomeSink for CollectingSink {
 mod tests {
     use super::*;
 
-    use agent_adapters::traits::{AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk};
-    use agent_memory::{FileJournal, MemoryBusBuilder, MemoryChannel, VolatileConfig};
+    use agent_adapters::traits::{
+        AdapterMetadata, AdapterResult, AdapterStream, InferenceChunk, MessageRole,
+    };
+    use agent_memory::{
+        EmbeddingVector, FileJournal, MemoryBusBuilder, MemoryChannel, VolatileConfig,
+    };
     use agent_policy::{PolicyAction, PolicyDecision, PolicyEngine, PolicyRequest, PolicyResult};
-    use agent_primitives::AgentId;
+    use agent_primitives::{AgentId, Scope};
     use agent_tools::registry::{ToolMetadata, ToolRegistry};
     use futures::stream;
     use mxp::Message;
@@ -918,11 +2181,14 @@ mod tests {
     use std::io::ErrorKind;
     use std::net::SocketAddr;
     use std::num::NonZeroUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::{Arc, Mutex};
     use std::time::Duration;
     use tokio::sync::oneshot;
 
-    use crate::{AgentMessageHandler, HandlerContext, HandlerError};
+    use crate::{
+        AgentMessageHandler, ApprovalDecision, CallDeduplicator, HandlerContext, HandlerError,
+    };
 
     struct StaticAdapter {
         metadata: AdapterMetadata,
@@ -998,22 +2264,433 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn policy_denies_tool_invocation() {
-        let adapter = Arc::new(StaticAdapter {
-            metadata: AdapterMetadata::new("test", "static"),
-            response: "static-response".to_owned(),
-        });
+    async fn recover_resumes_interrupted_calls_without_rerunning_tools() {
+        let path = temp_path();
+        let store = CheckpointStore::new(Arc::new(FileJournal::open(&path).await.unwrap()));
+        let charges = Arc::new(AtomicUsize::new(0));
+        let notices = Arc::new(AtomicUsize::new(0));
         let tools = Arc::new(ToolRegistry::new());
+        let counter = Arc::clone(&charges);
+        tools
+            .register_tool(ToolMetadata::new("charge", "1.0.0").unwrap(), move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Ok(json!({"receipt": "new"})) }
+            })
+            .unwrap();
+        let counter = Arc::clone(&notices);
+        let capability = agent_primitives::CapabilityId::new("billing.notify").unwrap();
         tools
             .register_tool(
-                ToolMetadata::new("echo", "1.0.0").unwrap(),
-                |input: Value| async move { Ok(input) },
+                ToolMetadata::new("notify", "1.0.0")
+                    .unwrap()
+                    .with_idempotent(true)
+                    .with_capabilities(vec![capability.clone()]),
+                move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async { Ok(json!({"sent": true})) }
+                },
             )
             .unwrap();
-
         let sink = CollectingSink::new();
-        let handler = KernelMessageHandler::new(adapter, tools, sink.clone())
-            .with_policy(Arc::new(DenyPolicy));
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "charged".to_owned(),
+        });
+        let mut catalog = ToolScopes::new();
+        catalog.require(capability, vec![Scope::new("billing:notify").unwrap()]);
+        let handler = KernelMessageHandler::new(adapter, tools, sink.clone())
+            .with_checkpoints(store.clone())
+            .with_tool_scopes(catalog);
+
+        // A previous run crashed while notifying, after charging.
+        let payload = json!({
+            "messages": [{"role": "user", "content": "Charge and notify"}],
+            "tools": [{"name": "charge"}, {"name": "notify"}]
+        });
+        let resumable = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let resumable_key = CallKey::new(None, resumable.message_id());
+        // Another crashed inside the non-idempotent charge.
+        let stuck = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let stuck_key = CallKey::new(None, stuck.message_id());
+        for checkpoint in [
+            Checkpoint::Started {
+                key: resumable_key,
+                message: resumable,
+                // The scope-protected notify step only runs if the caller is restored.
+                caller: Caller::new(
+                    Some(AgentId::random()),
+                    [Scope::new("billing:notify").unwrap()],
+                ),
+            },
+            Checkpoint::ToolStarted {
+                key: resumable_key,
+                step: 0,
+                tool: "charge".into(),
+            },
+            Checkpoint::ToolCompleted {
+                key: resumable_key,
+                step: 0,
+                tool: "charge".into(),
+                input: Value::Null,
+                output: json!({"receipt": "old"}),
+            },
+            Checkpoint::ToolStarted {
+                key: resumable_key,
+                step: 1,
+                tool: "notify".into(),
+            },
+            Checkpoint::Started {
+                key: stuck_key,
+                message: stuck,
+                caller: Caller::new(None, []),
+            },
+            Checkpoint::ToolStarted {
+                key: stuck_key,
+                step: 0,
+                tool: "charge".into(),
+            },
+        ] {
+            store.record(&checkpoint).await.unwrap();
+        }
+
+        let report = handler.recover(AgentId::random()).await.unwrap();
+        assert_eq!(report.resumed(), [resumable_key]);
+        assert_eq!(report.failed().len(), 1);
+        assert_eq!(report.failed()[0].0, stuck_key);
+        assert!(report.failed()[0].1.contains("not idempotent"));
+
+        assert_eq!(charges.load(Ordering::SeqCst), 0);
+        assert_eq!(notices.load(Ordering::SeqCst), 1);
+        let outcomes = sink.drain();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].response(), "charged");
+        assert_eq!(
+            outcomes[0].tool_results()[0].output,
+            json!({"receipt": "old"})
+        );
+        assert!(store.interrupted().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn recover_reruns_steps_whose_input_changed() {
+        let path = temp_path();
+        let store = CheckpointStore::new(Arc::new(FileJournal::open(&path).await.unwrap()));
+        let tools = Arc::new(ToolRegistry::new());
+        tools
+            .register_tool(
+                ToolMetadata::new("charge", "1.0.0").unwrap(),
+                |input: Value| async move { Ok(json!({"charged": input["amount"]})) },
+            )
+            .unwrap();
+        let sink = CollectingSink::new();
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "charged".to_owned(),
+        });
+        let handler =
+            KernelMessageHandler::new(adapter, tools, sink.clone()).with_checkpoints(store.clone());
+
+        let payload = json!({
+            "messages": [{"role": "user", "content": "Charge"}],
+            "tools": [{"name": "charge", "input": {"amount": 2}}]
+        });
+        let message = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let key = CallKey::new(None, message.message_id());
+        for checkpoint in [
+            Checkpoint::Started {
+                key,
+                message,
+                caller: Caller::new(None, []),
+            },
+            Checkpoint::ToolCompleted {
+                key,
+                step: 0,
+                tool: "charge".into(),
+                input: json!({"amount": 1}),
+                output: json!({"charged": 1}),
+            },
+        ] {
+            store.record(&checkpoint).await.unwrap();
+        }
+
+        let report = handler.recover(AgentId::random()).await.unwrap();
+        assert_eq!(report.resumed(), [key]);
+        let outcomes = sink.drain();
+        assert_eq!(outcomes[0].tool_results()[0].output, json!({"charged": 2}));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    struct CapturingAdapter {
+        metadata: AdapterMetadata,
+        requests: Arc<Mutex<Vec<InferenceRequest>>>,
+    }
+
+    #[async_trait]
+    impl ModelAdapter for CapturingAdapter {
+        fn metadata(&self) -> &AdapterMetadata {
+            &self.metadata
+        }
+
+        async fn infer(&self, request: InferenceRequest) -> AdapterResult<AdapterStream> {
+            self.requests.lock().unwrap().push(request);
+            let chunk = InferenceChunk::new("done", true);
+            Ok(Box::pin(stream::once(async move { Ok(chunk) })))
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_calls_replay_cached_outcome() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let adapter = Arc::new(CapturingAdapter {
+            metadata: AdapterMetadata::new("test", "capture"),
+            requests: Arc::clone(&requests),
+        });
+        let sink = CollectingSink::new();
+        let handler = KernelMessageHandler::builder(adapter, sink.clone())
+            .build()
+            .unwrap();
+        let dedup = CallDeduplicator::new(crate::DedupConfig::new());
+        let stack = crate::MiddlewareStack::new().layer(dedup.clone());
+
+        let payload = json!({"messages": [{"role": "user", "content": "charge card"}]});
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let sender: SocketAddr = "127.0.0.1:7000".parse().unwrap();
+        let ctx = HandlerContext::from_message(AgentId::random(), message).with_sender(sender);
+
+        stack.dispatch(&handler, ctx.clone()).await.unwrap();
+        stack.dispatch(&handler, ctx).await.unwrap();
+
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        let outcomes = sink.drain();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[1].response(), outcomes[0].response());
+        assert_eq!(dedup.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn routed_calls_are_deduplicated_before_the_router() {
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "unused".to_owned(),
+        });
+        let fallback = KernelMessageHandler::new(
+            adapter,
+            Arc::new(ToolRegistry::new()),
+            CollectingSink::new(),
+        );
+        let responses = Arc::new(CollectingResponses::default());
+        let charges = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&charges);
+        let router = crate::CallRouter::new(Arc::clone(&responses) as Arc<dyn crate::ResponseSink>)
+            .with_fallback(Arc::new(fallback))
+            .with_method("billing.charge", move |_ctx, _params: Value| {
+                let charge = counter.fetch_add(1, Ordering::SeqCst);
+                async move { Ok(json!({"charge": charge})) }
+            });
+        let stack =
+            crate::MiddlewareStack::new().layer(CallDeduplicator::new(crate::DedupConfig::new()));
+
+        let payload = json!({"method": "billing.charge", "params": {}});
+        let message = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let ctx = HandlerContext::from_message(AgentId::random(), message)
+            .with_sender("127.0.0.1:7000".parse().unwrap());
+        stack.dispatch(&router, ctx.clone()).await.unwrap();
+        stack.dispatch(&router, ctx).await.unwrap();
+
+        // The retransmission gets the original reply without charging again.
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        let replies = std::mem::take(&mut *responses.0.lock().unwrap());
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0], replies[1]);
+        assert_eq!(replies[0]["result"], json!({"charge": 0}));
+    }
+
+    #[tokio::test]
+    async fn tool_results_follow_assistant_tool_call_turn() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let adapter = Arc::new(CapturingAdapter {
+            metadata: AdapterMetadata::new("test", "capture"),
+            requests: Arc::clone(&requests),
+        });
+        let tools = Arc::new(ToolRegistry::new());
+        tools
+            .register_tool(
+                ToolMetadata::new("echo", "1.0.0").unwrap(),
+                |input: Value| async move { Ok(input) },
+            )
+            .unwrap();
+
+        let executor = CallExecutor::new(adapter, tools);
+        let payload = json!({
+            "messages": [{"role": "user", "content": "Ping"}],
+            "tools": [
+                {"id": "toolu_1", "name": "echo", "input": {"value": 1}},
+                {"name": "echo", "input": {"value": 2}}
+            ]
+        });
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let ctx = HandlerContext::from_message(AgentId::random(), message);
+
+        executor.execute(&ctx).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let messages = requests[0].messages();
+        assert_eq!(messages.len(), 4);
+
+        let calls = messages[1].tool_calls();
+        assert_eq!(messages[1].role(), MessageRole::Assistant);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id(), "toolu_1");
+        assert_eq!(calls[1].id(), "call_1");
+        assert_eq!(calls[1].arguments(), &json!({"value": 2}));
+
+        assert_eq!(messages[2].role(), MessageRole::Tool);
+        assert_eq!(messages[2].tool_call_id(), Some("toolu_1"));
+        assert_eq!(messages[3].tool_call_id(), Some("call_1"));
+        assert_eq!(messages[3].resolve_tool_name(messages), Some("echo"));
+    }
+
+    struct ConstantEmbedder;
+
+    #[async_trait]
+    impl agent_memory::Embedder for ConstantEmbedder {
+        async fn embed(&self, _text: &str) -> agent_memory::MemoryResult<EmbeddingVector> {
+            EmbeddingVector::new(vec![1.0, 0.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn retrieval_injects_cited_context_before_user_turn() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let adapter = Arc::new(CapturingAdapter {
+            metadata: AdapterMetadata::new("test", "capture"),
+            requests: Arc::clone(&requests),
+        });
+        let path = temp_path();
+        let journal: Arc<dyn agent_memory::Journal> =
+            Arc::new(FileJournal::open(&path).await.unwrap());
+        let bus = Arc::new(
+            MemoryBusBuilder::new(VolatileConfig::default())
+                .with_journal(journal)
+                .with_vector_store(Arc::new(agent_memory::LocalVectorStore::new()))
+                .build()
+                .unwrap(),
+        );
+        let fact = MemoryRecord::builder(
+            MemoryChannel::Input,
+            Bytes::from_static(b"The office opens at 9."),
+        )
+        .embedding(EmbeddingVector::new(vec![1.0, 0.0]).unwrap())
+        .build()
+        .unwrap();
+        bus.record(fact.clone()).await.unwrap();
+
+        let executor = CallExecutor::new(adapter, Arc::new(ToolRegistry::new()))
+            .with_retrieval(RetrievalStage::new(bus, Arc::new(ConstantEmbedder)));
+        let payload = json!({"messages": [{"role": "user", "content": "When do you open?"}]});
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let outcome = executor
+            .execute(&HandlerContext::from_message(AgentId::random(), message))
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.memory_ids(), vec![fact.id()]);
+        let requests = requests.lock().unwrap();
+        let messages = requests[0].messages();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].content().contains("[1]"));
+        assert!(messages[0].content().contains("The office opens at 9."));
+        assert_eq!(messages[1].content(), "When do you open?");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn session_calls_replay_prior_turns() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let adapter = Arc::new(CapturingAdapter {
+            metadata: AdapterMetadata::new("test", "capture"),
+            requests: Arc::clone(&requests),
+        });
+        let path = temp_path();
+        let journal: Arc<dyn agent_memory::Journal> =
+            Arc::new(FileJournal::open(&path).await.unwrap());
+        let bus = Arc::new(
+            MemoryBusBuilder::new(VolatileConfig::default())
+                .with_journal(journal)
+                .build()
+                .unwrap(),
+        );
+        let sessions = SessionStore::new(bus, crate::SessionConfig::new());
+        let executor = CallExecutor::new(adapter, Arc::new(ToolRegistry::new()))
+            .with_sessions(sessions.clone());
+
+        for content in ["first", "second"] {
+            let payload = json!({
+                "session_id": "conv-1",
+                "messages": [{"role": "user", "content": content}]
+            });
+            let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+            let outcome = executor
+                .execute(&HandlerContext::from_message(AgentId::random(), message))
+                .await
+                .unwrap();
+            assert_eq!(outcome.session_id(), Some("conv-1"));
+        }
+
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests[0].messages().len(), 1);
+            let replayed: Vec<&str> = requests[1]
+                .messages()
+                .iter()
+                .map(PromptMessage::content)
+                .collect();
+            assert_eq!(replayed, ["first", "done", "second"]);
+        }
+        assert_eq!(sessions.sessions()[0].turns(), 4);
+
+        let stateless = CallExecutor::new(
+            Arc::new(StaticAdapter {
+                metadata: AdapterMetadata::new("test", "static"),
+                response: "static".to_owned(),
+            }),
+            Arc::new(ToolRegistry::new()),
+        );
+        let payload = json!({
+            "session_id": "conv-1",
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let err = stateless
+            .execute(&HandlerContext::from_message(AgentId::random(), message))
+            .await
+            .expect_err("sessions not configured");
+        assert!(err.to_string().contains("session"));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn policy_denies_tool_invocation() {
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "static-response".to_owned(),
+        });
+        let tools = Arc::new(ToolRegistry::new());
+        tools
+            .register_tool(
+                ToolMetadata::new("echo", "1.0.0").unwrap(),
+                |input: Value| async move { Ok(input) },
+            )
+            .unwrap();
+
+        let sink = CollectingSink::new();
+        let handler = KernelMessageHandler::new(adapter, tools, sink.clone())
+            .with_policy(Arc::new(DenyPolicy));
 
         let payload = json!({
             "messages": [
@@ -1093,6 +2770,177 @@ mod tests {
         }
     }
 
+    struct ReasoningAdapter {
+        metadata: AdapterMetadata,
+        budgets: Mutex<Vec<Option<u32>>>,
+    }
+
+    #[async_trait]
+    impl ModelAdapter for ReasoningAdapter {
+        fn metadata(&self) -> &AdapterMetadata {
+            &self.metadata
+        }
+
+        async fn infer(&self, request: InferenceRequest) -> AdapterResult<AdapterStream> {
+            self.budgets.lock().unwrap().push(request.thinking_budget());
+            let chunks = vec![
+                Ok(InferenceChunk::reasoning("step one. ")),
+                Ok(InferenceChunk::reasoning("step two.")),
+                Ok(InferenceChunk::new("answer", true).with_usage(TokenUsage {
+                    input_tokens: 10,
+                    output_tokens: 3,
+                    cache_read_tokens: 512,
+                    cache_write_tokens: 0,
+                })),
+            ];
+            Ok(Box::pin(stream::iter(chunks)))
+        }
+    }
+
+    #[tokio::test]
+    async fn reasoning_is_kept_out_of_response_but_recorded() {
+        let adapter = Arc::new(ReasoningAdapter {
+            metadata: AdapterMetadata::new("test", "reasoning"),
+            budgets: Mutex::new(Vec::new()),
+        });
+        let path = temp_path();
+        let journal: Arc<dyn agent_memory::Journal> =
+            Arc::new(FileJournal::open(&path).await.unwrap());
+        let bus = Arc::new(
+            MemoryBusBuilder::new(VolatileConfig::default())
+                .with_journal(journal)
+                .build()
+                .unwrap(),
+        );
+        let sink = CollectingSink::new();
+        let handler =
+            KernelMessageHandler::new(adapter.clone(), Arc::new(ToolRegistry::new()), sink.clone())
+                .with_memory(bus.clone());
+
+        let payload = json!({
+            "messages": [{"role": "user", "content": "think"}],
+            "thinking_budget": 2048
+        });
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        handler
+            .handle_call(HandlerContext::from_message(AgentId::random(), message))
+            .await
+            .unwrap();
+
+        assert_eq!(*adapter.budgets.lock().unwrap(), vec![Some(2048)]);
+
+        let outcome = sink.drain().pop().unwrap();
+        assert_eq!(outcome.response(), "answer");
+        assert_eq!(outcome.reasoning(), "step one. step two.");
+        assert!(outcome.usage().is_some_and(TokenUsage::cache_hit));
+
+        let records = bus.recent(5).await;
+        assert_eq!(records.len(), 3);
+        assert!(records[1].tags().iter().any(|tag| tag == "reasoning"));
+        assert_eq!(records[1].payload().as_ref(), b"step one. step two.");
+        assert_eq!(records[2].payload().as_ref(), b"answer");
+
+        if path.exists() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    struct PendingAdapter {
+        metadata: AdapterMetadata,
+        cancelled: Arc<Mutex<Option<CancellationToken>>>,
+    }
+
+    #[async_trait]
+    impl ModelAdapter for PendingAdapter {
+        fn metadata(&self) -> &AdapterMetadata {
+            &self.metadata
+        }
+
+        async fn infer(&self, request: InferenceRequest) -> AdapterResult<AdapterStream> {
+            *self.cancelled.lock().unwrap() = request.cancellation().cloned();
+            std::future::pending().await
+        }
+    }
+
+    fn pending_handler() -> (
+        Arc<KernelMessageHandler>,
+        Arc<Mutex<Option<CancellationToken>>>,
+    ) {
+        let cancelled = Arc::new(Mutex::new(None));
+        let adapter = Arc::new(PendingAdapter {
+            metadata: AdapterMetadata::new("test", "pending"),
+            cancelled: Arc::clone(&cancelled),
+        });
+        let handler = KernelMessageHandler::new(
+            adapter,
+            Arc::new(ToolRegistry::new()),
+            CollectingSink::new(),
+        );
+        (Arc::new(handler), cancelled)
+    }
+
+    #[tokio::test]
+    async fn cancel_event_aborts_in_flight_call() {
+        let (handler, token) = pending_handler();
+        let payload = json!({"messages": [{"role": "user", "content": "wait"}]});
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let call_id = message.message_id();
+
+        let call = tokio::spawn({
+            let handler = Arc::clone(&handler);
+            async move {
+                handler
+                    .handle_call(HandlerContext::from_message(AgentId::random(), message))
+                    .await
+            }
+        });
+
+        while handler.cancellations().in_flight() == 0 || token.lock().unwrap().is_none() {
+            tokio::task::yield_now().await;
+        }
+
+        let cancel = Message::new(
+            mxp::MessageType::Event,
+            CancelRequest::new(call_id).to_payload(),
+        );
+        handler
+            .handle_event(HandlerContext::from_message(AgentId::random(), cancel))
+            .await
+            .unwrap();
+
+        let err = call.await.unwrap().unwrap_err();
+        assert_eq!(err, HandlerError::custom("call cancelled"));
+        assert!(token.lock().unwrap().as_ref().unwrap().is_cancelled());
+        assert_eq!(handler.cancellations().in_flight(), 0);
+
+        let other = Message::new(mxp::MessageType::Event, b"{}");
+        let err = handler
+            .handle_event(HandlerContext::from_message(AgentId::random(), other))
+            .await
+            .unwrap_err();
+        assert_eq!(err, HandlerError::Unsupported(mxp::MessageType::Event));
+    }
+
+    #[tokio::test]
+    async fn caller_deadline_aborts_call() {
+        let (handler, _) = pending_handler();
+        let payload = json!({
+            "messages": [{"role": "user", "content": "wait"}],
+            "timeout_ms": 20
+        });
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+
+        let err = tokio::time::timeout(
+            Duration::from_secs(5),
+            handler.handle_call(HandlerContext::from_message(AgentId::random(), message)),
+        )
+        .await
+        .expect("deadline should abort the call")
+        .unwrap_err();
+        assert_eq!(err, HandlerError::custom("call deadline exceeded"));
+        assert_eq!(handler.cancellations().in_flight(), 0);
+    }
+
     struct RecordingObserver {
         decisions: Mutex<Vec<(String, DecisionKind)>>,
     }
@@ -1161,6 +3009,61 @@ mod tests {
         assert_eq!(records[0].1, DecisionKind::Deny);
     }
 
+    #[tokio::test]
+    async fn tools_require_caller_scopes() {
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "ok".to_owned(),
+        });
+        let capability = agent_primitives::CapabilityId::new("inventory.adjust").unwrap();
+        let tools = Arc::new(ToolRegistry::new());
+        tools
+            .register_tool(
+                ToolMetadata::new("adjust_stock", "1.0.0")
+                    .unwrap()
+                    .with_capabilities(vec![capability.clone()]),
+                |input: Value| async move { Ok(input) },
+            )
+            .unwrap();
+        let mut catalog = ToolScopes::new();
+        catalog.require(capability, vec![Scope::new("inventory:write").unwrap()]);
+
+        let sink = CollectingSink::new();
+        let observer = RecordingObserver::new();
+        let handler = KernelMessageHandler::new(adapter, tools, sink.clone())
+            .with_tool_scopes(catalog)
+            .with_policy_observer(observer.clone());
+
+        let payload = json!({
+            "messages": [{"role": "user", "content": "restock"}],
+            "tools": [{"name": "adjust_stock", "input": {"sku": "A1"}}]
+        });
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let peer = AgentId::random();
+        let grants =
+            crate::ScopeGrants::new().with_anonymous([Scope::new("catalog:read").unwrap()]);
+
+        let mut ctx = HandlerContext::from_message(AgentId::random(), message.clone());
+        ctx.insert_extension(grants.caller(Some(peer)));
+        let err = handler
+            .handle_call(ctx)
+            .await
+            .expect_err("caller lacks inventory:write");
+        assert!(
+            matches!(&err, HandlerError::Forbidden { tool, scope } if tool == "adjust_stock" && scope.as_str() == "inventory:write")
+        );
+        assert_eq!(err.code(), "forbidden");
+        let decisions = observer.decisions.lock().unwrap().clone();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].1, DecisionKind::Deny);
+
+        grants.grant(peer, [Scope::new("inventory:*").unwrap()]);
+        let mut ctx = HandlerContext::from_message(AgentId::random(), message);
+        ctx.insert_extension(grants.caller(Some(peer)));
+        handler.handle_call(ctx).await.expect("wildcard grant");
+        assert_eq!(sink.drain().len(), 1);
+    }
+
     struct MemoryDenyPolicy;
 
     #[async_trait]
@@ -1390,4 +3293,416 @@ mod tests {
         assert!(payload.contains("needs approval"));
         assert!(payload.contains("secops"));
     }
+
+    /// Escalates model inference and allows everything else.
+    struct EscalateInference;
+
+    #[async_trait]
+    impl PolicyEngine for EscalateInference {
+        async fn evaluate(&self, request: &PolicyRequest) -> PolicyResult<PolicyDecision> {
+            Ok(match request.action() {
+                PolicyAction::ModelInference { .. } => {
+                    PolicyDecision::escalate("needs approval", vec!["secops".into()])
+                }
+                _ => PolicyDecision::allow(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn escalated_calls_are_parked_until_approvers_decide() {
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "approved-response".to_owned(),
+        });
+        let charges = Arc::new(AtomicUsize::new(0));
+        let tools = Arc::new(ToolRegistry::new());
+        let counter = Arc::clone(&charges);
+        tools
+            .register_tool(ToolMetadata::new("charge", "1.0.0").unwrap(), move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Ok(json!({"receipt": "r-1"})) }
+            })
+            .unwrap();
+        let sink = CollectingSink::new();
+        let emitter = RecordingAuditEmitter::new();
+        let (secops, intern) = (AgentId::random(), AgentId::random());
+        let approvals = ApprovalStore::default()
+            .with_audit(emitter.clone())
+            .with_approver("secops", secops)
+            .with_approver("intern", intern);
+        let handler = KernelMessageHandler::new(adapter, tools, sink.clone())
+            .with_policy(Arc::new(EscalateInference))
+            .with_approvals(approvals.clone());
+
+        let call = || {
+            let payload = json!({
+                "messages": [{"role": "user", "content": "ping"}],
+                "tools": [{"name": "charge", "input": {}}]
+            });
+            let message = mxp::Message::new(mxp::MessageType::Call, payload.to_string());
+            HandlerContext::from_message(AgentId::random(), message)
+        };
+        let decide_as = |principal: Option<AgentId>, decision: ApprovalDecision| {
+            let mut ctx = HandlerContext::from_message(AgentId::random(), decision.to_message());
+            ctx.insert_extension(crate::Caller::new(principal, []));
+            handler.handle_event(ctx)
+        };
+        let parked = || {
+            let pending = approvals.pending();
+            assert_eq!(pending.len(), 1);
+            pending[0].request().approval_id()
+        };
+
+        // The call returns as soon as it is parked.
+        handler.handle_call(call()).await.unwrap();
+        let id = parked();
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        let err = decide_as(None, ApprovalDecision::approve(id, "secops"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not from an authenticated agent"));
+        decide_as(Some(secops), ApprovalDecision::approve(id, "secops"))
+            .await
+            .unwrap();
+        let outcome = loop {
+            if let Some(outcome) = sink.drain().pop() {
+                break outcome;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(outcome.response(), "approved-response");
+        // The re-dispatched call replays the tool step instead of running it again.
+        assert_eq!(outcome.tool_results()[0].output, json!({"receipt": "r-1"}));
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        assert_eq!(emitter.events.lock().unwrap().len(), 3);
+
+        handler.handle_call(call()).await.unwrap();
+        let id = parked();
+        for (principal, name) in [(intern, "intern"), (intern, "secops")] {
+            let err = decide_as(Some(principal), ApprovalDecision::approve(id, name))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("not an approver"));
+        }
+        decide_as(Some(secops), ApprovalDecision::deny(id, "secops"))
+            .await
+            .unwrap();
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(approvals.get(id).unwrap().status(), ApprovalStatus::Denied);
+        assert!(approvals.pending().is_empty());
+
+        // Cancelling a parked call withdraws its approval request.
+        let ctx = call();
+        let key = CallKey::new(None, ctx.message().message_id());
+        handler.handle_call(ctx).await.unwrap();
+        let id = parked();
+        while !handler.cancellations().cancel(key) {
+            tokio::task::yield_now().await;
+        }
+        while approvals.get(id).unwrap().status() == ApprovalStatus::Pending {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            approvals.get(id).unwrap().status(),
+            ApprovalStatus::Cancelled
+        );
+        assert!(sink.drain().is_empty());
+    }
+
+    #[derive(Default)]
+    struct CollectingResponses(Mutex<Vec<Value>>);
+
+    impl crate::ResponseSink for CollectingResponses {
+        fn respond(&self, _ctx: &HandlerContext, response: Message) {
+            let body = serde_json::from_slice(response.payload()).unwrap();
+            self.0.lock().unwrap().push(body);
+        }
+    }
+
+    #[tokio::test]
+    async fn recover_fails_jobs_left_running() {
+        use crate::JobStore;
+
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "ok".to_owned(),
+        });
+        let store = Arc::new(crate::MemoryJobStore::new());
+        let running: crate::JobRecord = serde_json::from_value(json!({
+            "job_id": uuid::Uuid::new_v4(),
+            "call_id": 7,
+            "caller": null,
+            "status": "running",
+            "submitted_at": chrono::Utc::now(),
+        }))
+        .unwrap();
+        store.save(&running).await.unwrap();
+        let jobs = JobManager::new(store.clone(), Arc::new(CollectingResponses::default()));
+        let handler = KernelMessageHandler::new(
+            adapter,
+            Arc::new(ToolRegistry::new()),
+            CollectingSink::new(),
+        )
+        .with_jobs(jobs);
+
+        let report = handler.recover(AgentId::random()).await.unwrap();
+        assert_eq!(report.interrupted_jobs(), 1);
+        let failed = store.load(running.job_id()).await.unwrap().unwrap();
+        assert_eq!(failed.status(), crate::JobStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn job_calls_are_acknowledged_and_queryable() {
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "report ready".to_owned(),
+        });
+        let tools = Arc::new(ToolRegistry::new());
+        tools
+            .register_tool(
+                ToolMetadata::new("echo", "1.0.0").unwrap(),
+                |input: Value| async move { Ok(input) },
+            )
+            .unwrap();
+        let responses = Arc::new(CollectingResponses::default());
+        let jobs = JobManager::new(
+            Arc::new(crate::MemoryJobStore::new()),
+            Arc::clone(&responses) as Arc<dyn crate::ResponseSink>,
+        );
+        let handler =
+            KernelMessageHandler::new(adapter, tools, CollectingSink::new()).with_jobs(jobs);
+
+        let payload = json!({
+            "job": true,
+            "stream": true,
+            "messages": [{"role": "user", "content": "Build the report"}],
+            "tools": [{"name": "echo", "input": {"value": 1}}]
+        });
+        let message = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let ctx = HandlerContext::from_message(AgentId::random(), message);
+        handler.handle_call(ctx).await.unwrap();
+
+        let sent = std::mem::take(&mut *responses.0.lock().unwrap());
+        assert_eq!(sent[0]["type"], "job_accepted");
+        let job_id: uuid::Uuid = serde_json::from_value(sent[0]["job_id"].clone()).unwrap();
+        let (last, progress) = sent[1..].split_last().unwrap();
+        assert!(progress.iter().all(|body| body["type"] == "job_progress"));
+        assert!(progress.iter().any(|body| body["event"]["name"] == "echo"));
+        // Streaming callers are also pushed the final record.
+        assert_eq!(last["type"], "job");
+
+        let query = JobRequest::JobStatus { job_id }.to_message();
+        let ctx = HandlerContext::from_message(AgentId::random(), query);
+        handler.handle_event(ctx).await.unwrap();
+        let reply = responses.0.lock().unwrap().pop().unwrap();
+        assert_eq!(reply["type"], "job");
+        assert_eq!(reply["status"], "succeeded");
+        assert_eq!(reply["output"]["response"], "report ready");
+
+        // Other callers cannot read the job's output.
+        let query = JobRequest::JobStatus { job_id }.to_message();
+        let ctx = HandlerContext::from_message(AgentId::random(), query)
+            .with_sender("127.0.0.1:9000".parse().unwrap());
+        handler.handle_event(ctx).await.unwrap();
+        let reply = responses.0.lock().unwrap().pop().unwrap();
+        assert_eq!(reply["type"], "job_error");
+        assert!(reply.get("output").is_none());
+
+        assert!(matches!(
+            handler.cancel_job(job_id).await,
+            Err(JobError::Finished { .. })
+        ));
+        assert!(matches!(
+            handler.cancel_job(uuid::Uuid::new_v4()).await,
+            Err(JobError::Unknown(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn admitted_calls_are_acknowledged_to_the_caller() {
+        let transport = mxp::Transport::default();
+        let bind = || match transport.bind("127.0.0.1:0".parse::<SocketAddr>().unwrap()) {
+            Ok(handle) => Some(handle),
+            Err(mxp::transport::SocketError::Io(err))
+                if err.kind() == ErrorKind::PermissionDenied =>
+            {
+                None
+            }
+            Err(err) => panic!("bind: {err:?}"),
+        };
+        let (Some(agent), Some(caller)) = (bind(), bind()) else {
+            eprintln!("skipping call ack test: bind requires elevated privileges");
+            return;
+        };
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "done".into(),
+        });
+        let handler = KernelMessageHandler::builder(adapter, CollectingSink::new())
+            .with_reliable_sender(ReliableSender::new(agent, crate::DeliveryConfig::new()))
+            .build()
+            .unwrap();
+        let stack =
+            crate::MiddlewareStack::new().layer(CallDeduplicator::new(crate::DedupConfig::new()));
+
+        let payload = json!({"messages": [{"role": "user", "content": "hi"}]});
+        let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+        let call_id = message.message_id();
+        let ctx = HandlerContext::from_message(AgentId::random(), message)
+            .with_sender(caller.local_addr().unwrap());
+
+        // The retransmission is acknowledged as well, in case the first ack was lost.
+        stack.dispatch(&handler, ctx.clone()).await.unwrap();
+        stack.dispatch(&handler, ctx).await.unwrap();
+
+        let acks = tokio::task::spawn_blocking(move || {
+            (0..2)
+                .map(|_| {
+                    let mut buffer = caller.acquire_buffer();
+                    let (len, _) = caller.receive(&mut buffer).unwrap();
+                    let ack = Message::decode(buffer.as_slice()[..len].to_vec()).unwrap();
+                    DeliveryAck::from_payload(ack.payload())
+                        .unwrap()
+                        .message_id()
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        assert_eq!(acks, [call_id, call_id]);
+    }
+
+    #[tokio::test]
+    async fn sessions_are_bound_to_their_creator() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let adapter = Arc::new(CapturingAdapter {
+            metadata: AdapterMetadata::new("test", "capture"),
+            requests: Arc::clone(&requests),
+        });
+        let path = temp_path();
+        let journal: Arc<dyn agent_memory::Journal> =
+            Arc::new(FileJournal::open(&path).await.unwrap());
+        let bus = Arc::new(
+            MemoryBusBuilder::new(VolatileConfig::default())
+                .with_journal(journal)
+                .build()
+                .unwrap(),
+        );
+        let executor =
+            CallExecutor::new(adapter.clone(), Arc::new(ToolRegistry::new())).with_sessions(
+                SessionStore::new(Arc::clone(&bus), crate::SessionConfig::new()),
+            );
+        let owner = AgentId::random();
+        let call = |principal: AgentId, sender: &str| {
+            let payload = json!({
+                "session_id": "conv-1",
+                "messages": [{"role": "user", "content": "hi"}]
+            });
+            let message = Message::new(mxp::MessageType::Call, payload.to_string().as_bytes());
+            let mut ctx = HandlerContext::from_message(AgentId::random(), message)
+                .with_sender(sender.parse().unwrap());
+            ctx.insert_extension(crate::Caller::new(Some(principal), []));
+            ctx
+        };
+
+        executor
+            .execute(&call(owner, "127.0.0.1:7000"))
+            .await
+            .unwrap();
+        // The principal owns the session, whichever address it calls from.
+        executor
+            .execute(&call(owner, "127.0.0.1:7001"))
+            .await
+            .unwrap();
+        let err = executor
+            .execute(&call(AgentId::random(), "127.0.0.1:7000"))
+            .await
+            .expect_err("another principal");
+        assert_eq!(err, HandlerError::SessionForbidden("conv-1".into()));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        // The binding is restored from the journal after a restart.
+        let restored = SessionStore::new(bus, crate::SessionConfig::new());
+        assert_eq!(restored.restore_all().await.unwrap(), 1);
+        assert_eq!(
+            restored.sessions()[0].owner(),
+            Some(SessionOwner::Agent(owner))
+        );
+        let executor =
+            CallExecutor::new(adapter, Arc::new(ToolRegistry::new())).with_sessions(restored);
+        let err = executor
+            .execute(&call(AgentId::random(), "127.0.0.1:7000"))
+            .await
+            .expect_err("another principal after restart");
+        assert_eq!(err.code(), "session_forbidden");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn recover_resumes_job_calls_under_their_job() {
+        use crate::JobStore;
+
+        let path = temp_path();
+        let checkpoints = CheckpointStore::new(Arc::new(FileJournal::open(&path).await.unwrap()));
+        let jobs_store = Arc::new(crate::MemoryJobStore::new());
+        let responses = Arc::new(CollectingResponses::default());
+        let jobs = JobManager::new(
+            jobs_store.clone(),
+            Arc::clone(&responses) as Arc<dyn crate::ResponseSink>,
+        );
+        let adapter = Arc::new(StaticAdapter {
+            metadata: AdapterMetadata::new("test", "static"),
+            response: "report ready".to_owned(),
+        });
+        let handler = KernelMessageHandler::new(
+            adapter,
+            Arc::new(ToolRegistry::new()),
+            CollectingSink::new(),
+        )
+        .with_checkpoints(checkpoints.clone())
+        .with_jobs(jobs);
+
+        let payload = json!({"job": true, "messages": [{"role": "user", "content": "Report"}]});
+        let tracked = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let tracked_key = CallKey::new(None, tracked.message_id());
+        // This call's job record was lost, so it cannot resume under it.
+        let orphaned = Message::new(MessageType::Call, payload.to_string().as_bytes());
+        let orphaned_key = CallKey::new(None, orphaned.message_id());
+        let running: crate::JobRecord = serde_json::from_value(json!({
+            "job_id": uuid::Uuid::new_v4(),
+            "call_id": tracked.message_id(),
+            "status": "running",
+            "submitted_at": chrono::Utc::now(),
+        }))
+        .unwrap();
+        jobs_store.save(&running).await.unwrap();
+        for (key, message) in [(tracked_key, tracked), (orphaned_key, orphaned)] {
+            checkpoints
+                .record(&Checkpoint::Started {
+                    key,
+                    message,
+                    caller: Caller::new(None, []),
+                })
+                .await
+                .unwrap();
+        }
+
+        let report = handler.recover(AgentId::random()).await.unwrap();
+        assert_eq!(report.resumed(), [tracked_key]);
+        assert_eq!(report.failed().len(), 1);
+        assert_eq!(report.failed()[0].0, orphaned_key);
+        assert_eq!(report.interrupted_jobs(), 0);
+
+        // The caller is not sent a second job id.
+        assert!(responses.0.lock().unwrap().is_empty());
+        let finished = jobs_store.load(running.job_id()).await.unwrap().unwrap();
+        assert_eq!(finished.status(), crate::JobStatus::Succeeded);
+        assert_eq!(jobs_store.list().await.unwrap().len(), 1);
+
+        let _ = std::fs::remove_file(path);
+    }
 }
@@ -0,0 +1,223 @@
+//! Tracking of in-flight calls so they can be cancelled over MXP.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use agent_adapters::traits::CancellationToken;
+use agent_primitives::AgentId;
+use serde::{Deserialize, Serialize};
+
+use crate::HandlerContext;
+use crate::dedup::CallKey;
+
+/// Payload of an MXP `Event` message requesting cancellation of an in-flight call.
+///
+/// Encoded as `{"type": "cancel", "call_id": <message id of the call>}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "cancel")]
+pub struct CancelRequest {
+    call_id: u64,
+}
+
+impl CancelRequest {
+    /// Creates a cancellation request targeting the call with the supplied MXP message id.
+    #[must_use]
+    pub const fn new(call_id: u64) -> Self {
+        Self { call_id }
+    }
+
+    /// Returns the MXP message id of the call to cancel.
+    #[must_use]
+    pub const fn call_id(&self) -> u64 {
+        self.call_id
+    }
+
+    /// Decodes a cancellation request from an event payload, returning `None`
+    /// when the payload is not a cancellation request.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Encodes the request as an event payload.
+    #[must_use]
+    pub fn to_payload(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    generation: u64,
+    principal: Option<AgentId>,
+    token: CancellationToken,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_generation: u64,
+    calls: HashMap<CallKey, InFlight>,
+}
+
+/// Registry of in-flight calls keyed by the sender and MXP message id of the
+/// originating call.
+#[derive(Debug, Clone, Default)]
+pub struct CallCancellations {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl CallCancellations {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an in-flight call made by `principal`. The call is removed
+    /// when the guard drops.
+    pub(crate) fn register(&self, key: CallKey, principal: Option<AgentId>) -> CallGuard {
+        let token = CancellationToken::new();
+        let mut inner = self.lock();
+        inner.next_generation += 1;
+        let generation = inner.next_generation;
+        inner.calls.insert(
+            key,
+            InFlight {
+                generation,
+                principal,
+                token: token.clone(),
+            },
+        );
+        CallGuard {
+            registry: self.clone(),
+            key,
+            generation,
+            token,
+        }
+    }
+
+    /// Cancels the in-flight call with the supplied key, returning `false`
+    /// when no such call is running.
+    #[must_use]
+    pub fn cancel(&self, key: CallKey) -> bool {
+        self.cancel_if(key, |_| true)
+    }
+
+    /// Cancels call `call_id` on behalf of the sender of `ctx`. Only the
+    /// address that sent the call may cancel it, and calls made by an
+    /// authenticated agent must be cancelled by that same agent.
+    pub(crate) fn cancel_from(&self, ctx: &HandlerContext, call_id: u64) -> bool {
+        let principal = ctx.principal();
+        self.cancel_if(CallKey::new(ctx.sender(), call_id), |call| {
+            call.principal.is_none_or(|owner| principal == Some(owner))
+        })
+    }
+
+    fn cancel_if(&self, key: CallKey, allowed: impl FnOnce(&InFlight) -> bool) -> bool {
+        match self.lock().calls.get(&key) {
+            Some(call) if allowed(call) => {
+                call.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the number of calls currently in flight.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.lock().calls.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Keeps a call registered while it executes.
+#[derive(Debug)]
+pub(crate) struct CallGuard {
+    registry: CallCancellations,
+    key: CallKey,
+    generation: u64,
+    token: CancellationToken,
+}
+
+impl CallGuard {
+    pub(crate) fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        let mut inner = self.registry.lock();
+        // A newer call may have reused the id; only remove our own entry.
+        if inner
+            .calls
+            .get(&self.key)
+            .is_some_and(|call| call.generation == self.generation)
+        {
+            inner.calls.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_request_roundtrip() {
+        let request = CancelRequest::new(42);
+        let payload = request.to_payload();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&payload).unwrap(),
+            serde_json::json!({"type": "cancel", "call_id": 42})
+        );
+        assert_eq!(CancelRequest::from_payload(&payload), Some(request));
+        assert_eq!(CancelRequest::from_payload(br#"{"type":"other"}"#), None);
+    }
+
+    #[test]
+    fn guards_track_in_flight_calls() {
+        let registry = CallCancellations::new();
+        let key = CallKey::new(None, 7);
+        let guard = registry.register(key, None);
+        let token = guard.token();
+        assert_eq!(registry.in_flight(), 1);
+
+        assert!(registry.cancel(key));
+        assert!(token.is_cancelled());
+        assert!(!registry.cancel(CallKey::new(None, 8)));
+
+        let replacement = registry.register(key, None);
+        drop(guard);
+        assert_eq!(registry.in_flight(), 1);
+        drop(replacement);
+        assert_eq!(registry.in_flight(), 0);
+    }
+
+    #[test]
+    fn only_the_original_sender_and_principal_may_cancel() {
+        let registry = CallCancellations::new();
+        let caller: std::net::SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let owner = AgentId::random();
+        let guard = registry.register(CallKey::new(Some(caller), 7), Some(owner));
+        let cancel = |sender: &str| {
+            let message =
+                mxp::Message::new(mxp::MessageType::Event, CancelRequest::new(7).to_payload());
+            HandlerContext::from_message(AgentId::random(), message)
+                .with_sender(sender.parse().unwrap())
+        };
+
+        assert!(!registry.cancel_from(&cancel("127.0.0.1:4001"), 7));
+        assert!(!registry.cancel_from(&cancel("127.0.0.1:4000"), 7));
+        let mut ctx = cancel("127.0.0.1:4000");
+        ctx.insert_extension(crate::Caller::new(Some(owner), []));
+        assert!(registry.cancel_from(&ctx, 7));
+        assert!(guard.token().is_cancelled());
+    }
+}
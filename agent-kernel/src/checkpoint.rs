@@ -0,0 +1,700 @@
+//! Durable checkpoints that let interrupted calls be resumed after a restart.
+//!
+//! While a call runs, the executor appends checkpoints to a [`Journal`]: the
+//! call message and its [`Caller`] when it starts, each tool step as it
+//! starts and completes, the model output streamed since the previous
+//! checkpoint, and the final status. After a crash,
+//! [`CheckpointStore::interrupted`] rebuilds every call that started but
+//! never finished so it can be resumed or failed explicitly.
+//!
+//! Checkpoints are [`MemoryChannel::System`] records tagged `checkpoint`, so
+//! they can share a journal with the rest of the agent's memory.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use agent_memory::{Journal, MemoryChannel, MemoryRecord, MemoryResult};
+use agent_primitives::{AgentId, Scope};
+use bytes::Bytes;
+use mxp::Message;
+use serde_json::{Value, json};
+
+use crate::dedup::CallKey;
+use crate::scopes::Caller;
+
+/// Tag applied to every checkpoint record.
+pub const CHECKPOINT_TAG: &str = "checkpoint";
+
+const DEFAULT_PROGRESS_INTERVAL: NonZeroUsize = NonZeroUsize::new(32).unwrap();
+const DEFAULT_SCAN_LIMIT: usize = 10_000;
+
+/// A single step in the life of a call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Checkpoint {
+    /// The call was received; carries the original message.
+    Started {
+        /// Call identity.
+        key: CallKey,
+        /// Original call message.
+        message: Message,
+        /// Who sent the call and the scopes they held.
+        caller: Caller,
+    },
+    /// A tool step is about to run.
+    ToolStarted {
+        /// Call identity.
+        key: CallKey,
+        /// Position of the step in the call's tool list.
+        step: usize,
+        /// Tool name.
+        tool: String,
+    },
+    /// A tool step finished and produced `output`.
+    ToolCompleted {
+        /// Call identity.
+        key: CallKey,
+        /// Position of the step in the call's tool list.
+        step: usize,
+        /// Tool name.
+        tool: String,
+        /// Tool input.
+        input: Value,
+        /// Tool output.
+        output: Value,
+    },
+    /// Model output streamed since the previous progress checkpoint.
+    Progress {
+        /// Call identity.
+        key: CallKey,
+        /// Length in bytes of the response text before `response`.
+        response_offset: usize,
+        /// Response text appended at `response_offset`.
+        response: String,
+        /// Length in bytes of the reasoning text before `reasoning`.
+        reasoning_offset: usize,
+        /// Reasoning text appended at `reasoning_offset`.
+        reasoning: String,
+    },
+    /// The call finished successfully.
+    Completed {
+        /// Call identity.
+        key: CallKey,
+    },
+    /// The call failed and will not be resumed.
+    Failed {
+        /// Call identity.
+        key: CallKey,
+        /// Why the call failed.
+        reason: String,
+    },
+}
+
+impl Checkpoint {
+    /// Returns the identity of the call the checkpoint belongs to.
+    #[must_use]
+    pub const fn key(&self) -> CallKey {
+        match self {
+            Self::Started { key, .. }
+            | Self::ToolStarted { key, .. }
+            | Self::ToolCompleted { key, .. }
+            | Self::Progress { key, .. }
+            | Self::Completed { key }
+            | Self::Failed { key, .. } => *key,
+        }
+    }
+
+    /// Returns a stable name for the checkpoint kind.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Started { .. } => "started",
+            Self::ToolStarted { .. } => "tool_started",
+            Self::ToolCompleted { .. } => "tool_completed",
+            Self::Progress { .. } => "progress",
+            Self::Completed { .. } => "completed",
+            Self::Failed { .. } => "failed",
+        }
+    }
+
+    /// Encodes the checkpoint as a journal record.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`](agent_memory::MemoryError) when the record
+    /// cannot be built.
+    pub fn to_record(&self) -> MemoryResult<MemoryRecord> {
+        let key = self.key();
+        let (payload, extra) = match self {
+            Self::Started {
+                message, caller, ..
+            } => (
+                Bytes::from(message.encode()),
+                json!({
+                    "principal": caller.principal().map(|principal| principal.to_string()),
+                    "scopes": caller.scopes().iter().map(Scope::as_str).collect::<Vec<_>>(),
+                }),
+            ),
+            Self::ToolStarted { step, tool, .. } => {
+                (Bytes::new(), json!({"step": step, "tool": tool}))
+            }
+            Self::ToolCompleted {
+                step,
+                tool,
+                input,
+                output,
+                ..
+            } => (
+                Bytes::from(output.to_string()),
+                json!({"step": step, "tool": tool, "input": input}),
+            ),
+            Self::Progress {
+                response_offset,
+                response,
+                reasoning_offset,
+                reasoning,
+                ..
+            } => (
+                Bytes::from(
+                    json!({
+                        "response_offset": response_offset,
+                        "response": response,
+                        "reasoning_offset": reasoning_offset,
+                        "reasoning": reasoning,
+                    })
+                    .to_string(),
+                ),
+                json!({}),
+            ),
+            Self::Completed { .. } => (Bytes::new(), json!({})),
+            Self::Failed { reason, .. } => (Bytes::new(), json!({"reason": reason})),
+        };
+        let mut builder = MemoryRecord::builder(MemoryChannel::System, payload)
+            .tag(CHECKPOINT_TAG)?
+            .metadata("checkpoint", Value::from(self.kind()))
+            .metadata("call_id", Value::from(key.message_id()))
+            .metadata(
+                "sender",
+                key.sender()
+                    .map_or(Value::Null, |sender| Value::from(sender.to_string())),
+            );
+        if let Value::Object(extra) = extra {
+            builder = builder.merge_metadata(extra);
+        }
+        builder.build()
+    }
+
+    /// Decodes a journal record, returning `None` for records that are not
+    /// well-formed checkpoints.
+    #[must_use]
+    pub fn from_record(record: &MemoryRecord) -> Option<Self> {
+        if *record.channel() != MemoryChannel::System
+            || !record.tags().iter().any(|tag| tag == CHECKPOINT_TAG)
+        {
+            return None;
+        }
+        let metadata = record.metadata();
+        let sender = match metadata.get("sender") {
+            Some(Value::String(sender)) => Some(sender.parse::<SocketAddr>().ok()?),
+            _ => None,
+        };
+        let key = CallKey::new(sender, metadata.get("call_id")?.as_u64()?);
+        let step = || {
+            metadata
+                .get("step")
+                .and_then(Value::as_u64)
+                .and_then(|step| usize::try_from(step).ok())
+        };
+        let tool = || {
+            metadata
+                .get("tool")
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+        };
+        let checkpoint = match metadata.get("checkpoint")?.as_str()? {
+            "started" => {
+                let principal = match metadata.get("principal") {
+                    Some(Value::String(principal)) => Some(principal.parse::<AgentId>().ok()?),
+                    _ => None,
+                };
+                let scopes = match metadata.get("scopes") {
+                    Some(Value::Array(scopes)) => scopes
+                        .iter()
+                        .map(|scope| Scope::new(scope.as_str()?).ok())
+                        .collect::<Option<Vec<_>>>()?,
+                    _ => Vec::new(),
+                };
+                Self::Started {
+                    key,
+                    message: Message::decode(record.payload().to_vec()).ok()?,
+                    caller: Caller::new(principal, scopes),
+                }
+            }
+            "tool_started" => Self::ToolStarted {
+                key,
+                step: step()?,
+                tool: tool()?,
+            },
+            "tool_completed" => Self::ToolCompleted {
+                key,
+                step: step()?,
+                tool: tool()?,
+                input: metadata.get("input").cloned().unwrap_or_default(),
+                output: serde_json::from_slice(record.payload()).ok()?,
+            },
+            "progress" => {
+                let progress: Value = serde_json::from_slice(record.payload()).ok()?;
+                let text = |field: &str| progress.get(field)?.as_str().map(str::to_owned);
+                // Records without an offset hold the full text so far.
+                let offset = |field: &str| {
+                    progress
+                        .get(field)
+                        .and_then(Value::as_u64)
+                        .map_or(Some(0), |offset| usize::try_from(offset).ok())
+                };
+                Self::Progress {
+                    key,
+                    response_offset: offset("response_offset")?,
+                    response: text("response")?,
+                    reasoning_offset: offset("reasoning_offset")?,
+                    reasoning: text("reasoning")?,
+                }
+            }
+            "completed" => Self::Completed { key },
+            "failed" => Self::Failed {
+                key,
+                reason: metadata.get("reason")?.as_str()?.to_owned(),
+            },
+            _ => return None,
+        };
+        Some(checkpoint)
+    }
+}
+
+/// Tool step recorded as completed before an interruption.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletedStep {
+    tool: String,
+    input: Value,
+    output: Value,
+}
+
+impl CompletedStep {
+    pub(crate) const fn new(tool: String, input: Value, output: Value) -> Self {
+        Self {
+            tool,
+            input,
+            output,
+        }
+    }
+
+    /// Returns the tool name.
+    #[must_use]
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    /// Returns the input the tool ran with.
+    #[must_use]
+    pub const fn input(&self) -> &Value {
+        &self.input
+    }
+
+    /// Returns the recorded tool output.
+    #[must_use]
+    pub const fn output(&self) -> &Value {
+        &self.output
+    }
+}
+
+/// A call that started but never completed or failed.
+#[derive(Debug, Clone)]
+pub struct InterruptedCall {
+    key: CallKey,
+    message: Message,
+    caller: Caller,
+    started_at: SystemTime,
+    completed: BTreeMap<usize, CompletedStep>,
+    in_flight: Option<(usize, String)>,
+    response: String,
+    reasoning: String,
+}
+
+impl InterruptedCall {
+    /// Returns the call identity.
+    #[must_use]
+    pub const fn key(&self) -> CallKey {
+        self.key
+    }
+
+    /// Returns the original call message.
+    #[must_use]
+    pub const fn message(&self) -> &Message {
+        &self.message
+    }
+
+    /// Returns who sent the call and the scopes they held.
+    #[must_use]
+    pub const fn caller(&self) -> &Caller {
+        &self.caller
+    }
+
+    /// Returns when the call started.
+    #[must_use]
+    pub const fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    /// Returns the tool steps that completed, keyed by position.
+    #[must_use]
+    pub const fn completed_steps(&self) -> &BTreeMap<usize, CompletedStep> {
+        &self.completed
+    }
+
+    /// Returns the position and tool name of a step that started but did not
+    /// complete, if any.
+    #[must_use]
+    pub fn interrupted_step(&self) -> Option<(usize, &str)> {
+        self.in_flight
+            .as_ref()
+            .map(|(step, tool)| (*step, tool.as_str()))
+    }
+
+    /// Returns the response text streamed before the interruption.
+    #[must_use]
+    pub fn partial_response(&self) -> &str {
+        &self.response
+    }
+
+    /// Returns the reasoning text streamed before the interruption.
+    #[must_use]
+    pub fn partial_reasoning(&self) -> &str {
+        &self.reasoning
+    }
+}
+
+/// Tool outputs replayed into a resumed call instead of running the tools.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResumedSteps(pub(crate) BTreeMap<usize, CompletedStep>);
+
+/// Outcome of [`KernelMessageHandler::recover`](crate::KernelMessageHandler::recover).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub(crate) resumed: Vec<CallKey>,
+    pub(crate) failed: Vec<(CallKey, String)>,
+    pub(crate) interrupted_jobs: usize,
+}
+
+impl RecoveryReport {
+    /// Returns the calls that were resumed and completed.
+    #[must_use]
+    pub fn resumed(&self) -> &[CallKey] {
+        &self.resumed
+    }
+
+    /// Returns the calls that were failed, with the reason.
+    #[must_use]
+    pub fn failed(&self) -> &[(CallKey, String)] {
+        &self.failed
+    }
+
+    /// Returns how many stored jobs were failed because they were running
+    /// when the previous run stopped.
+    #[must_use]
+    pub const fn interrupted_jobs(&self) -> usize {
+        self.interrupted_jobs
+    }
+}
+
+/// Tuning for [`CheckpointStore`].
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointConfig {
+    progress_interval: NonZeroUsize,
+    scan_limit: usize,
+    resume: bool,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            scan_limit: DEFAULT_SCAN_LIMIT,
+            resume: true,
+        }
+    }
+}
+
+impl CheckpointConfig {
+    /// Creates the default configuration: progress every 32 streamed chunks,
+    /// the last 10 000 journal records scanned, and resumption enabled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many streamed model chunks pass between progress checkpoints.
+    #[must_use]
+    pub const fn with_progress_interval(mut self, chunks: NonZeroUsize) -> Self {
+        self.progress_interval = chunks;
+        self
+    }
+
+    /// Sets how many of the most recent journal records recovery scans.
+    #[must_use]
+    pub const fn with_scan_limit(mut self, records: usize) -> Self {
+        self.scan_limit = records;
+        self
+    }
+
+    /// Sets whether interrupted calls are resumed; when `false` they are all
+    /// failed on recovery.
+    #[must_use]
+    pub const fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Returns how many streamed chunks pass between progress checkpoints.
+    #[must_use]
+    pub const fn progress_interval(&self) -> NonZeroUsize {
+        self.progress_interval
+    }
+
+    /// Returns how many journal records recovery scans.
+    #[must_use]
+    pub const fn scan_limit(&self) -> usize {
+        self.scan_limit
+    }
+
+    /// Returns whether interrupted calls are resumed.
+    #[must_use]
+    pub const fn resume(&self) -> bool {
+        self.resume
+    }
+}
+
+/// Writes call checkpoints to a [`Journal`] and finds interrupted calls.
+#[derive(Clone)]
+pub struct CheckpointStore {
+    journal: Arc<dyn Journal>,
+    config: CheckpointConfig,
+}
+
+impl fmt::Debug for CheckpointStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckpointStore")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CheckpointStore {
+    /// Creates a store that appends to `journal`.
+    #[must_use]
+    pub fn new(journal: Arc<dyn Journal>) -> Self {
+        Self {
+            journal,
+            config: CheckpointConfig::default(),
+        }
+    }
+
+    /// Replaces the configuration.
+    #[must_use]
+    pub const fn with_config(mut self, config: CheckpointConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &CheckpointConfig {
+        &self.config
+    }
+
+    /// Appends a checkpoint to the journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`](agent_memory::MemoryError) when the journal
+    /// write fails.
+    pub async fn record(&self, checkpoint: &Checkpoint) -> MemoryResult<()> {
+        self.journal.append(&checkpoint.to_record()?).await
+    }
+
+    /// Returns the calls that started but never completed or failed, oldest
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`](agent_memory::MemoryError) when the journal
+    /// cannot be read.
+    pub async fn interrupted(&self) -> MemoryResult<Vec<InterruptedCall>> {
+        let records = self.journal.tail(self.config.scan_limit).await?;
+        let mut calls: HashMap<CallKey, InterruptedCall> = HashMap::new();
+        let mut order = Vec::new();
+        for record in &records {
+            let Some(checkpoint) = Checkpoint::from_record(record) else {
+                continue;
+            };
+            let key = checkpoint.key();
+            match checkpoint {
+                Checkpoint::Started {
+                    message, caller, ..
+                } => {
+                    order.push(key);
+                    calls.insert(
+                        key,
+                        InterruptedCall {
+                            key,
+                            message,
+                            caller,
+                            started_at: record.timestamp(),
+                            completed: BTreeMap::new(),
+                            in_flight: None,
+                            response: String::new(),
+                            reasoning: String::new(),
+                        },
+                    );
+                }
+                Checkpoint::Completed { .. } | Checkpoint::Failed { .. } => {
+                    calls.remove(&key);
+                }
+                Checkpoint::ToolStarted { step, tool, .. } => {
+                    if let Some(call) = calls.get_mut(&key) {
+                        call.in_flight = Some((step, tool));
+                    }
+                }
+                Checkpoint::ToolCompleted {
+                    step,
+                    tool,
+                    input,
+                    output,
+                    ..
+                } => {
+                    if let Some(call) = calls.get_mut(&key) {
+                        call.in_flight.take_if(|(started, _)| *started == step);
+                        call.completed.insert(
+                            step,
+                            CompletedStep {
+                                tool,
+                                input,
+                                output,
+                            },
+                        );
+                    }
+                }
+                Checkpoint::Progress {
+                    response_offset,
+                    response,
+                    reasoning_offset,
+                    reasoning,
+                    ..
+                } => {
+                    if let Some(call) = calls.get_mut(&key) {
+                        splice(&mut call.response, response_offset, &response);
+                        splice(&mut call.reasoning, reasoning_offset, &reasoning);
+                    }
+                }
+            }
+        }
+        Ok(order
+            .into_iter()
+            .filter_map(|key| calls.remove(&key))
+            .collect())
+    }
+}
+
+/// Appends `delta` to `text` at `offset`, dropping anything after it so a
+/// resumed call that streams from the start replaces the earlier output.
+fn splice(text: &mut String, offset: usize, delta: &str) {
+    if text.is_char_boundary(offset) {
+        text.truncate(offset);
+    }
+    text.push_str(delta);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_memory::FileJournal;
+    use mxp::MessageType;
+
+    #[tokio::test]
+    async fn interrupted_calls_are_rebuilt_from_the_journal() {
+        let path = std::env::temp_dir().join(format!("checkpoints-{}.log", uuid::Uuid::new_v4()));
+        let store = CheckpointStore::new(Arc::new(FileJournal::open(&path).await.unwrap()));
+        let sender: SocketAddr = "127.0.0.1:7000".parse().unwrap();
+
+        let finished = Message::new(MessageType::Call, br#"{"messages":[]}"#);
+        let crashed = Message::new(MessageType::Call, br#"{"messages":[],"tools":[]}"#);
+        let done = CallKey::new(Some(sender), finished.message_id());
+        let key = CallKey::new(None, crashed.message_id());
+        let caller = Caller::new(
+            Some(AgentId::random()),
+            [Scope::new("billing:charge").unwrap()],
+        );
+        for checkpoint in [
+            Checkpoint::Started {
+                key: done,
+                message: finished,
+                caller: Caller::new(None, []),
+            },
+            Checkpoint::Started {
+                key,
+                message: crashed.clone(),
+                caller: caller.clone(),
+            },
+            Checkpoint::ToolStarted {
+                key,
+                step: 0,
+                tool: "charge".into(),
+            },
+            Checkpoint::ToolCompleted {
+                key,
+                step: 0,
+                tool: "charge".into(),
+                input: json!({"amount": 5}),
+                output: json!({"receipt": 7}),
+            },
+            Checkpoint::ToolStarted {
+                key,
+                step: 1,
+                tool: "email".into(),
+            },
+            Checkpoint::Progress {
+                key,
+                response_offset: 0,
+                response: "Charg".into(),
+                reasoning_offset: 0,
+                reasoning: String::new(),
+            },
+            Checkpoint::Progress {
+                key,
+                response_offset: 5,
+                response: "ed and".into(),
+                reasoning_offset: 0,
+                reasoning: "ok".into(),
+            },
+            Checkpoint::Completed { key: done },
+        ] {
+            store.record(&checkpoint).await.unwrap();
+        }
+
+        let interrupted = store.interrupted().await.unwrap();
+        assert_eq!(interrupted.len(), 1);
+        let call = &interrupted[0];
+        assert_eq!(call.key(), key);
+        assert_eq!(call.message(), &crashed);
+        assert_eq!(call.caller(), &caller);
+        assert_eq!(call.completed_steps()[&0].input(), &json!({"amount": 5}));
+        assert_eq!(call.completed_steps()[&0].output(), &json!({"receipt": 7}));
+        assert_eq!(call.interrupted_step(), Some((1, "email")));
+        assert_eq!(call.partial_response(), "Charged and");
+        assert_eq!(call.partial_reasoning(), "ok");
+
+        let _ = std::fs::remove_file(path);
+    }
+}
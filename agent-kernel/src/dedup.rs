@@ -0,0 +1,841 @@
+//! Deduplication of retransmitted MXP calls.
+//!
+//! [`CallDeduplicator`] is a [`Middleware`]: it admits each `Call` once per
+//! sender and message id, caches what the call produced, including errors,
+//! and hands retransmissions to
+//! [`AgentMessageHandler::handle_duplicate`](crate::AgentMessageHandler::handle_duplicate)
+//! instead of executing them again.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use mxp::{Message, MessageType};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{debug, warn};
+
+use crate::middleware::{Middleware, Next};
+use crate::{CallOutcome, HandlerContext, HandlerError, HandlerResult};
+
+const DEFAULT_TTL: Duration = Duration::from_mins(5);
+const DEFAULT_CAPACITY: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// Errors produced while persisting the deduplication window.
+#[derive(Debug, Error)]
+pub enum DedupError {
+    /// Reading or writing the backing store failed.
+    #[error("dedup store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A stored entry could not be encoded or decoded.
+    #[error("dedup store serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Result alias for deduplication operations.
+pub type DedupResult<T> = Result<T, DedupError>;
+
+/// Identifies a call by the address it came from and its MXP message id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CallKey {
+    sender: Option<SocketAddr>,
+    message_id: u64,
+}
+
+impl CallKey {
+    /// Creates a key; calls without a known sender are keyed by message id alone.
+    #[must_use]
+    pub const fn new(sender: Option<SocketAddr>, message_id: u64) -> Self {
+        Self { sender, message_id }
+    }
+
+    /// Returns the sender address, if known.
+    #[must_use]
+    pub const fn sender(&self) -> Option<SocketAddr> {
+        self.sender
+    }
+
+    /// Returns the MXP message id of the call.
+    #[must_use]
+    pub const fn message_id(&self) -> u64 {
+        self.message_id
+    }
+}
+
+/// A completed call as persisted by a [`DedupStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedCall {
+    key: CallKey,
+    completed_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    replies: Vec<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl CompletedCall {
+    /// Returns the key of the call.
+    #[must_use]
+    pub const fn key(&self) -> CallKey {
+        self.key
+    }
+
+    /// Returns when the call completed.
+    #[must_use]
+    pub fn completed_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.completed_at_ms)
+    }
+
+    /// Returns the response text of the call's outcome, if it produced one.
+    #[must_use]
+    pub fn response(&self) -> Option<&str> {
+        self.response.as_deref()
+    }
+
+    /// Returns the encoded MXP replies sent to the caller.
+    #[must_use]
+    pub fn replies(&self) -> &[Vec<u8>] {
+        &self.replies
+    }
+
+    /// Returns the error the call failed with, if it failed.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Rebuilds the cached result; errors come back as
+    /// [`HandlerError::Custom`].
+    fn result(&self) -> Result<CachedCall, HandlerError> {
+        if let Some(error) = &self.error {
+            return Err(HandlerError::custom(error.clone()));
+        }
+        Ok(CachedCall {
+            outcome: self.response.clone().map(CallOutcome::replayed),
+            replies: self
+                .replies
+                .iter()
+                .filter_map(|reply| Message::decode(reply.clone()).ok())
+                .collect(),
+        })
+    }
+}
+
+/// Durable backing for the deduplication window.
+#[async_trait]
+pub trait DedupStore: Send + Sync {
+    /// Returns the calls that completed at or after `since`, discarding older ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DedupError`] when the store cannot be read.
+    async fn load(&self, since: SystemTime) -> DedupResult<Vec<CompletedCall>>;
+
+    /// Persists a completed call.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DedupError`] when the store cannot be written.
+    async fn save(&self, call: &CompletedCall) -> DedupResult<()>;
+}
+
+/// [`DedupStore`] that appends completed calls to a JSON lines file.
+///
+/// Expired entries are dropped on [`load`](DedupStore::load). Once the file
+/// holds twice [`max_entries`](Self::max_entries) lines, [`save`](DedupStore::save)
+/// also rewrites it with only the newest `max_entries`.
+#[derive(Debug)]
+pub struct FileDedupStore {
+    path: PathBuf,
+    max_entries: NonZeroUsize,
+    /// Lines written since the file was last rewritten.
+    lines: AsyncMutex<usize>,
+}
+
+impl FileDedupStore {
+    /// Uses the file at `path`, which is created on first write, keeping up
+    /// to 10,000 entries.
+    #[must_use]
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            max_entries: DEFAULT_CAPACITY,
+            lines: AsyncMutex::new(0),
+        }
+    }
+
+    /// Sets how many entries survive a compaction; use at least the
+    /// deduplicator's [`capacity`](DedupConfig::capacity).
+    #[must_use]
+    pub const fn with_max_entries(mut self, max_entries: NonZeroUsize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Returns how many entries survive a compaction.
+    #[must_use]
+    pub const fn max_entries(&self) -> NonZeroUsize {
+        self.max_entries
+    }
+
+    /// Rewrites the file with `lines`, returning how many were written.
+    async fn rewrite<'a>(&self, lines: impl Iterator<Item = &'a str>) -> DedupResult<usize> {
+        let mut compacted = String::new();
+        let mut written = 0;
+        for line in lines {
+            compacted.push_str(line);
+            compacted.push('\n');
+            written += 1;
+        }
+        fs::write(&self.path, compacted).await?;
+        Ok(written)
+    }
+}
+
+#[async_trait]
+impl DedupStore for FileDedupStore {
+    async fn load(&self, since: SystemTime) -> DedupResult<Vec<CompletedCall>> {
+        let mut lines = self.lines.lock().await;
+        let contents = match fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut live = Vec::new();
+        let mut kept = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let call: CompletedCall = serde_json::from_str(line)?;
+            if call.completed_at() >= since {
+                live.push(call);
+                kept.push(line);
+            }
+        }
+
+        // Rewrite the file without the expired entries.
+        *lines = self.rewrite(kept.into_iter()).await?;
+        Ok(live)
+    }
+
+    async fn save(&self, call: &CompletedCall) -> DedupResult<()> {
+        let mut line = serde_json::to_vec(call)?;
+        line.push(b'\n');
+        let mut lines = self.lines.lock().await;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        *lines += 1;
+
+        if *lines >= self.max_entries.get().saturating_mul(2) {
+            let contents = fs::read_to_string(&self.path).await?;
+            let stored: Vec<&str> = contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect();
+            let newest = stored.len().saturating_sub(self.max_entries.get());
+            *lines = self.rewrite(stored[newest..].iter().copied()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Window size and retention for [`CallDeduplicator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DedupConfig {
+    ttl: Duration,
+    capacity: NonZeroUsize,
+}
+
+impl DedupConfig {
+    /// Creates the default configuration: completed calls are remembered for
+    /// five minutes, up to 10,000 of them.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Sets how long completed calls are remembered.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the maximum number of completed calls remembered.
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Returns how long completed calls are remembered.
+    #[must_use]
+    pub const fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the maximum number of completed calls remembered.
+    #[must_use]
+    pub const fn capacity(&self) -> NonZeroUsize {
+        self.capacity
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a successful call sent its caller, replayed to retransmissions.
+#[derive(Debug, Clone, Default)]
+pub struct CachedCall {
+    outcome: Option<CallOutcome>,
+    replies: Vec<Message>,
+}
+
+impl CachedCall {
+    /// Returns the outcome a [`KernelMessageHandler`](crate::KernelMessageHandler)
+    /// recorded, if any.
+    #[must_use]
+    pub const fn outcome(&self) -> Option<&CallOutcome> {
+        self.outcome.as_ref()
+    }
+
+    /// Returns the replies sent to the caller, such as
+    /// [`CallRouter`](crate::CallRouter) responses.
+    #[must_use]
+    pub fn replies(&self) -> &[Message] {
+        &self.replies
+    }
+}
+
+/// A retransmitted call recognised by [`CallDeduplicator`].
+///
+/// Attached as a context extension to calls dispatched to
+/// [`AgentMessageHandler::handle_duplicate`](crate::AgentMessageHandler::handle_duplicate).
+#[derive(Debug, Clone)]
+pub enum DuplicateCall {
+    /// The original delivery is still executing.
+    InProgress,
+    /// The original delivery succeeded.
+    Completed(CachedCall),
+}
+
+enum Entry {
+    InProgress,
+    Completed {
+        at: Instant,
+        result: Result<CachedCall, HandlerError>,
+    },
+}
+
+#[derive(Default)]
+struct Window {
+    entries: HashMap<CallKey, Entry>,
+    completed: VecDeque<(CallKey, Instant)>,
+}
+
+impl Window {
+    fn evict(&mut self, ttl: Duration, capacity: usize, now: Instant) {
+        while let Some(&(key, at)) = self.completed.front() {
+            let expired = now.saturating_duration_since(at) > ttl;
+            if !expired && self.completed.len() <= capacity {
+                break;
+            }
+            self.completed.pop_front();
+            if matches!(self.entries.get(&key), Some(Entry::Completed { at: stored, .. }) if *stored == at)
+            {
+                self.entries.remove(&key);
+            }
+        }
+    }
+
+    fn complete(&mut self, key: CallKey, at: Instant, result: Result<CachedCall, HandlerError>) {
+        self.entries.insert(key, Entry::Completed { at, result });
+        self.completed.push_back((key, at));
+    }
+}
+
+/// Result of registering an inbound call with the deduplicator.
+pub(crate) enum Admission {
+    /// First delivery; the guard must be completed once the call finishes.
+    New(DedupGuard),
+    /// The original delivery is still executing.
+    InProgress,
+    /// The call already finished with this result.
+    Completed(Result<CachedCall, HandlerError>),
+}
+
+/// Remembers recent calls so retransmissions are answered without re-executing.
+#[derive(Clone)]
+pub struct CallDeduplicator {
+    config: DedupConfig,
+    window: Arc<Mutex<Window>>,
+    store: Option<Arc<dyn DedupStore>>,
+}
+
+impl std::fmt::Debug for CallDeduplicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallDeduplicator")
+            .field("config", &self.config)
+            .field("entries", &self.len())
+            .field("store_configured", &self.store.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CallDeduplicator {
+    /// Creates an in-memory deduplicator.
+    #[must_use]
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            window: Arc::new(Mutex::new(Window::default())),
+            store: None,
+        }
+    }
+
+    /// Persists completed calls to `store` so the window survives restarts.
+    #[must_use]
+    pub fn with_store(mut self, store: Arc<dyn DedupStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> DedupConfig {
+        self.config
+    }
+
+    /// Returns the number of calls currently remembered, including in-flight ones.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns `true` when no calls are remembered.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Loads unexpired calls from the configured store, returning how many
+    /// were restored. Restored calls replay their response text and replies,
+    /// and restored errors replay as [`HandlerError::Custom`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DedupError`] when the store cannot be read.
+    pub async fn restore(&self) -> DedupResult<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let now = SystemTime::now();
+        let since = now.checked_sub(self.config.ttl).unwrap_or(UNIX_EPOCH);
+        let calls = store.load(since).await?;
+
+        let instant_now = Instant::now();
+        let mut window = self.lock();
+        for call in &calls {
+            let age = now.duration_since(call.completed_at()).unwrap_or_default();
+            let at = instant_now.checked_sub(age).unwrap_or(instant_now);
+            window.complete(call.key, at, call.result());
+        }
+        window.evict(self.config.ttl, self.config.capacity.get(), instant_now);
+        Ok(calls.len())
+    }
+
+    pub(crate) fn admit(&self, key: CallKey) -> Admission {
+        let mut window = self.lock();
+        window.evict(self.config.ttl, self.config.capacity.get(), Instant::now());
+        match window.entries.get(&key) {
+            Some(Entry::InProgress) => Admission::InProgress,
+            Some(Entry::Completed { result, .. }) => Admission::Completed(result.clone()),
+            None => {
+                window.entries.insert(key, Entry::InProgress);
+                Admission::New(DedupGuard {
+                    dedup: self.clone(),
+                    key,
+                    completed: false,
+                })
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Window> {
+        self.window.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl Middleware for CallDeduplicator {
+    async fn handle(&self, mut ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        if ctx.message().message_type() != Some(MessageType::Call) {
+            return next.run(ctx).await;
+        }
+        let message_id = ctx.message().message_id();
+        match self.admit(CallKey::new(ctx.sender(), message_id)) {
+            Admission::New(guard) => {
+                let slot = DedupSlot::new(guard);
+                ctx.insert_extension(slot.clone());
+                let result = next.run(ctx).await;
+                slot.settle(&result).await;
+                result
+            }
+            Admission::InProgress => {
+                debug!(call_id = message_id, "duplicate call still in progress");
+                ctx.insert_extension(DuplicateCall::InProgress);
+                next.run(ctx).await
+            }
+            Admission::Completed(Ok(call)) => {
+                debug!(
+                    call_id = message_id,
+                    "replaying cached result for duplicate call"
+                );
+                ctx.insert_extension(DuplicateCall::Completed(call));
+                next.run(ctx).await
+            }
+            Admission::Completed(Err(err)) => {
+                debug!(call_id = message_id, %err, "replaying cached error for duplicate call");
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Returns whether `err` refused the call before it could run, so the call
+/// is forgotten rather than cached and a retry can still go through.
+const fn is_rejection(err: &HandlerError) -> bool {
+    matches!(
+        err,
+        HandlerError::MissingMessageType
+            | HandlerError::Unsupported(_)
+            | HandlerError::NotAccepting { .. }
+            | HandlerError::Reassembly(_)
+            | HandlerError::PayloadTooLarge { .. }
+            | HandlerError::Unauthenticated(_)
+            | HandlerError::Encryption(_)
+    )
+}
+
+/// Marks a call in progress; forgets it on drop unless completed, so a
+/// rejected or abandoned call can be retried.
+pub(crate) struct DedupGuard {
+    dedup: CallDeduplicator,
+    key: CallKey,
+    completed: bool,
+}
+
+impl DedupGuard {
+    /// Caches the result for later duplicates and persists it when a store
+    /// is configured.
+    pub(crate) async fn complete(
+        mut self,
+        result: Result<CachedCall, HandlerError>,
+    ) -> DedupResult<()> {
+        self.completed = true;
+        let completed_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        let stored = CompletedCall {
+            key: self.key,
+            completed_at_ms,
+            response: result
+                .as_ref()
+                .ok()
+                .and_then(CachedCall::outcome)
+                .map(|outcome| outcome.response().to_owned()),
+            replies: result
+                .as_ref()
+                .map(|call| call.replies.iter().map(Message::encode).collect())
+                .unwrap_or_default(),
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+        self.dedup.lock().complete(self.key, Instant::now(), result);
+        let Some(store) = &self.dedup.store else {
+            return Ok(());
+        };
+        store.save(&stored).await
+    }
+}
+
+impl Drop for DedupGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let mut window = self.dedup.lock();
+        if matches!(window.entries.get(&self.key), Some(Entry::InProgress)) {
+            window.entries.remove(&self.key);
+        }
+    }
+}
+
+struct Recording {
+    guard: DedupGuard,
+    call: CachedCall,
+}
+
+/// First delivery of a call, attached to its context by [`CallDeduplicator`]
+/// so handlers can record what they send the caller.
+#[derive(Clone)]
+pub(crate) struct DedupSlot(Arc<Mutex<Option<Recording>>>);
+
+impl DedupSlot {
+    fn new(guard: DedupGuard) -> Self {
+        Self(Arc::new(Mutex::new(Some(Recording {
+            guard,
+            call: CachedCall::default(),
+        }))))
+    }
+
+    /// Records the outcome replayed through
+    /// [`CallOutcomeSink::record_duplicate`](crate::CallOutcomeSink::record_duplicate).
+    pub(crate) fn record_outcome(&self, outcome: &CallOutcome) {
+        if let Some(recording) = self.lock().as_mut() {
+            recording.call.outcome = Some(outcome.clone());
+        }
+    }
+
+    /// Records a reply sent to the caller.
+    pub(crate) fn record_reply(&self, reply: &Message) {
+        if let Some(recording) = self.lock().as_mut() {
+            recording.call.replies.push(reply.clone());
+        }
+    }
+
+    /// Moves the call to a new slot for a handler that keeps running it after
+    /// returning, such as a call parked for approval. This slot is left
+    /// empty, so returning from the handler does not settle the call.
+    pub(crate) fn detach(&self) -> Option<Self> {
+        self.lock()
+            .take()
+            .map(|recording| Self(Arc::new(Mutex::new(Some(recording)))))
+    }
+
+    /// Caches the call's result for later duplicates, unless the call was
+    /// detached or rejected before it ran.
+    pub(crate) async fn settle(&self, result: &HandlerResult) {
+        let Some(Recording { guard, call }) = self.lock().take() else {
+            return;
+        };
+        let key = guard.key;
+        let result = match result {
+            Ok(()) => Ok(call),
+            Err(err) if is_rejection(err) => return,
+            Err(err) => Err(err.clone()),
+        };
+        if let Err(err) = guard.complete(result).await {
+            warn!(
+                ?err,
+                call_id = key.message_id(),
+                "failed to persist call deduplication entry"
+            );
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Recording>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Builds the MXP `Ack` sent for a retransmitted call that is still executing.
+///
+/// Encoded as `{"call_id": <message id>, "status": "in_progress"}`.
+#[must_use]
+pub fn in_progress_ack(call_id: u64) -> Message {
+    let payload = json!({ "call_id": call_id, "status": "in_progress" });
+    Message::new(MessageType::Ack, payload.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: u64) -> CallKey {
+        CallKey::new(Some("127.0.0.1:9000".parse().unwrap()), id)
+    }
+
+    fn done(response: impl Into<String>) -> CachedCall {
+        CachedCall {
+            outcome: Some(CallOutcome::replayed(response.into())),
+            replies: Vec::new(),
+        }
+    }
+
+    fn response(admission: Admission) -> String {
+        let Admission::Completed(Ok(call)) = admission else {
+            panic!("completed call should be cached");
+        };
+        call.outcome().unwrap().response().to_owned()
+    }
+
+    #[tokio::test]
+    async fn tracks_in_progress_and_completed_calls() {
+        let dedup = CallDeduplicator::new(DedupConfig::new());
+        let Admission::New(guard) = dedup.admit(key(1)) else {
+            panic!("first delivery should be new");
+        };
+        assert!(matches!(dedup.admit(key(1)), Admission::InProgress));
+        assert!(matches!(
+            dedup.admit(CallKey::new(None, 1)),
+            Admission::New(_)
+        ));
+
+        guard.complete(Ok(done("done"))).await.unwrap();
+        assert_eq!(response(dedup.admit(key(1))), "done");
+
+        let Admission::New(abandoned) = dedup.admit(key(2)) else {
+            panic!("first delivery should be new");
+        };
+        drop(abandoned);
+        assert!(matches!(dedup.admit(key(2)), Admission::New(_)));
+    }
+
+    #[tokio::test]
+    async fn expires_entries_and_restores_from_store() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("dedup-test-{}.jsonl", uuid::Uuid::new_v4()));
+        let store: Arc<dyn DedupStore> = Arc::new(FileDedupStore::new(&path));
+
+        let config = DedupConfig::new().with_capacity(NonZeroUsize::new(1).unwrap());
+        let dedup = CallDeduplicator::new(config).with_store(Arc::clone(&store));
+        for id in [1, 2, 3] {
+            let Admission::New(guard) = dedup.admit(key(id)) else {
+                panic!("first delivery should be new");
+            };
+            let result = if id == 3 {
+                Err(HandlerError::custom("card declined"))
+            } else {
+                Ok(done(format!("response {id}")))
+            };
+            guard.complete(result).await.unwrap();
+        }
+        // Capacity one: the older calls have been evicted.
+        assert!(matches!(dedup.admit(key(1)), Admission::New(_)));
+
+        let restarted = CallDeduplicator::new(DedupConfig::new()).with_store(store);
+        assert_eq!(restarted.restore().await.unwrap(), 3);
+        assert_eq!(response(restarted.admit(key(2))), "response 2");
+        let Admission::Completed(Err(err)) = restarted.admit(key(3)) else {
+            panic!("restored failure should be cached");
+        };
+        assert!(err.to_string().contains("card declined"));
+
+        let expired = CallDeduplicator::new(DedupConfig::new().with_ttl(Duration::ZERO))
+            .with_store(Arc::new(FileDedupStore::new(&path)));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(expired.restore().await.unwrap(), 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn file_store_compacts_past_its_size_threshold() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("dedup-test-{}.jsonl", uuid::Uuid::new_v4()));
+        let store = FileDedupStore::new(&path).with_max_entries(NonZeroUsize::new(2).unwrap());
+
+        for id in 1..=4 {
+            let call = CompletedCall {
+                key: key(id),
+                completed_at_ms: 0,
+                response: Some(format!("response {id}")),
+                replies: Vec::new(),
+                error: None,
+            };
+            store.save(&call).await.unwrap();
+        }
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(stored.lines().count(), 2);
+
+        let loaded = store.load(SystemTime::UNIX_EPOCH).await.unwrap();
+        let ids: Vec<u64> = loaded.iter().map(|call| call.key().message_id()).collect();
+        assert_eq!(ids, [3, 4]);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[derive(Default)]
+    struct Flaky {
+        calls: std::sync::atomic::AtomicUsize,
+        duplicates: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl crate::AgentMessageHandler for Flaky {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            match ctx.message().payload().as_ref() {
+                b"charge" => Err(HandlerError::custom("card declined")),
+                b"busy" => Err(HandlerError::not_accepting(
+                    crate::AgentState::Suspended,
+                    "maintenance",
+                )),
+                _ => Ok(()),
+            }
+        }
+
+        async fn handle_duplicate(&self, _ctx: HandlerContext) -> HandlerResult {
+            self.duplicates
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn middleware_caches_failures_and_routes_duplicates() {
+        use std::sync::atomic::Ordering;
+
+        let handler = Flaky::default();
+        let stack = crate::MiddlewareStack::new().layer(CallDeduplicator::new(DedupConfig::new()));
+        let sender = "127.0.0.1:9000".parse().unwrap();
+        let call = |payload: &[u8]| {
+            HandlerContext::from_message(
+                agent_primitives::AgentId::random(),
+                Message::new(MessageType::Call, payload),
+            )
+            .with_sender(sender)
+        };
+
+        // A failed call is not run again; its retransmission gets the error.
+        let charge = call(b"charge");
+        let first = stack.dispatch(&handler, charge.clone()).await;
+        let retry = stack.dispatch(&handler, charge).await;
+        assert_eq!(first, retry);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+
+        let lookup = call(b"lookup");
+        stack.dispatch(&handler, lookup.clone()).await.unwrap();
+        stack.dispatch(&handler, lookup).await.unwrap();
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(handler.duplicates.load(Ordering::SeqCst), 1);
+
+        // Rejections are forgotten, so the retry runs.
+        let busy = call(b"busy");
+        assert!(stack.dispatch(&handler, busy.clone()).await.is_err());
+        assert!(stack.dispatch(&handler, busy).await.is_err());
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 4);
+    }
+}
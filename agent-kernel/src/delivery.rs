@@ -0,0 +1,580 @@
+//! Acknowledged outbound delivery with retransmission over MXP.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use mxp::{Message, MessageType, TransportHandle};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::encryption::{EncryptionError, SecureChannels};
+use crate::fragment::{FragmentError, Fragmenter, send_datagrams};
+use crate::signing::{AgentIdentity, SigningError, sign_outbound};
+
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_ATTEMPTS: NonZeroU32 = NonZeroU32::new(5).unwrap();
+
+/// Payload of an MXP `Ack` message confirming receipt of a reliable message.
+///
+/// Encoded as `{"type": "ack", "message_id": <id of the acknowledged message>}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "ack")]
+pub struct DeliveryAck {
+    message_id: u64,
+}
+
+impl DeliveryAck {
+    /// Creates an acknowledgement for the message with the supplied id.
+    #[must_use]
+    pub const fn new(message_id: u64) -> Self {
+        Self { message_id }
+    }
+
+    /// Returns the id of the acknowledged message.
+    #[must_use]
+    pub const fn message_id(&self) -> u64 {
+        self.message_id
+    }
+
+    /// Decodes an acknowledgement from an `Ack` payload, returning `None` when
+    /// the payload is not a delivery acknowledgement.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Encodes the acknowledgement as an `Ack` payload.
+    #[must_use]
+    pub fn to_payload(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    /// Builds the MXP `Ack` message to send back to the original sender.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(MessageType::Ack, self.to_payload())
+    }
+}
+
+/// Acknowledges `message_id` to `target`, logging rather than failing when
+/// the ack cannot be sent; the sender's retransmission covers the loss.
+pub(crate) fn send_ack(transport: &TransportHandle, message_id: u64, target: SocketAddr) {
+    let ack = DeliveryAck::new(message_id).to_message();
+    if let Err(err) = transport.send(&ack.encode(), target) {
+        debug!(?err, %target, message_id, "failed to send delivery ack");
+    }
+}
+
+/// Retransmission schedule for [`ReliableSender`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryConfig {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: u32,
+    max_attempts: NonZeroU32,
+}
+
+impl DeliveryConfig {
+    /// Creates the default schedule: five attempts, waiting 200ms for the
+    /// first acknowledgement and doubling up to five seconds.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: 2,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Sets how long to wait for an acknowledgement after the first attempt.
+    #[must_use]
+    pub const fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Caps the wait between attempts.
+    #[must_use]
+    pub const fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the factor the wait grows by after each unacknowledged attempt.
+    #[must_use]
+    pub const fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the number of transmissions before giving up.
+    #[must_use]
+    pub const fn with_max_attempts(mut self, attempts: NonZeroU32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Returns the wait after the first attempt.
+    #[must_use]
+    pub const fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// Returns the maximum wait between attempts.
+    #[must_use]
+    pub const fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// Returns the backoff growth factor.
+    #[must_use]
+    pub const fn multiplier(&self) -> u32 {
+        self.multiplier
+    }
+
+    /// Returns the number of transmissions before giving up.
+    #[must_use]
+    pub const fn max_attempts(&self) -> NonZeroU32 {
+        self.max_attempts
+    }
+
+    /// Returns how long to wait for an acknowledgement after `attempt` (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1)
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Details of a message that was never acknowledged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryFailure {
+    message_id: u64,
+    message_type: Option<MessageType>,
+    target: SocketAddr,
+    attempts: u32,
+    last_error: Option<String>,
+}
+
+impl DeliveryFailure {
+    /// Returns the id of the undelivered message.
+    #[must_use]
+    pub const fn message_id(&self) -> u64 {
+        self.message_id
+    }
+
+    /// Returns the type of the undelivered message.
+    #[must_use]
+    pub const fn message_type(&self) -> Option<MessageType> {
+        self.message_type
+    }
+
+    /// Returns the destination address.
+    #[must_use]
+    pub const fn target(&self) -> SocketAddr {
+        self.target
+    }
+
+    /// Returns how many times the message was transmitted.
+    #[must_use]
+    pub const fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the last transport error, if sends failed rather than went
+    /// unacknowledged.
+    #[must_use]
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+/// Errors produced by [`ReliableSender`].
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum DeliveryError {
+    /// Every attempt went unacknowledged.
+    #[error("message {} to {} not acknowledged after {} attempts", .0.message_id, .0.target, .0.attempts)]
+    Exhausted(DeliveryFailure),
+    /// The message could not be split into datagrams.
+    #[error(transparent)]
+    Fragment(#[from] FragmentError),
+    /// The message could not be signed.
+    #[error(transparent)]
+    Signing(#[from] SigningError),
+    /// The message could not be encrypted.
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+}
+
+/// Result alias for delivery operations.
+pub type DeliveryResult<T> = Result<T, DeliveryError>;
+
+/// Callback invoked when a reliable message is given up on.
+pub trait DeliveryObserver: Send + Sync {
+    /// Called once retransmission for a message is exhausted.
+    fn delivery_failed(&self, failure: &DeliveryFailure);
+}
+
+/// Observer that logs delivery failures via `tracing`.
+#[derive(Debug, Default)]
+pub struct TracingDeliveryObserver;
+
+impl DeliveryObserver for TracingDeliveryObserver {
+    fn delivery_failed(&self, failure: &DeliveryFailure) {
+        warn!(
+            message_id = failure.message_id,
+            message_type = ?failure.message_type,
+            target = %failure.target,
+            attempts = failure.attempts,
+            last_error = ?failure.last_error,
+            "reliable delivery failed"
+        );
+    }
+}
+
+/// Ack waiters keyed by target and message id. Sending the same message to
+/// one target twice adds a second waiter that the single ack resolves too.
+type AckWaiters = HashMap<(SocketAddr, u64), Vec<oneshot::Sender<()>>>;
+type PendingAcks = Arc<Mutex<AckWaiters>>;
+
+/// Sends MXP messages that must be acknowledged, retransmitting with
+/// exponential backoff until an `Ack` arrives or attempts run out.
+#[derive(Clone)]
+pub struct ReliableSender {
+    transport: TransportHandle,
+    config: DeliveryConfig,
+    pending: PendingAcks,
+    observer: Arc<dyn DeliveryObserver>,
+    fragmenter: Fragmenter,
+    identity: Option<AgentIdentity>,
+    channels: Option<SecureChannels>,
+}
+
+impl fmt::Debug for ReliableSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReliableSender")
+            .field("config", &self.config)
+            .field("in_flight", &self.in_flight())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReliableSender {
+    /// Creates a sender that logs delivery failures.
+    #[must_use]
+    pub fn new(transport: TransportHandle, config: DeliveryConfig) -> Self {
+        Self {
+            transport,
+            config,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            observer: Arc::new(TracingDeliveryObserver),
+            fragmenter: Fragmenter::default(),
+            identity: None,
+            channels: None,
+        }
+    }
+
+    /// Replaces the callback invoked when delivery is given up on.
+    #[must_use]
+    pub fn with_observer(mut self, observer: Arc<dyn DeliveryObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Sets the datagram size and message limit used to fragment oversized
+    /// messages.
+    #[must_use]
+    pub const fn with_fragmenter(mut self, fragmenter: Fragmenter) -> Self {
+        self.fragmenter = fragmenter;
+        self
+    }
+
+    /// Signs every attempt with `identity`. Acknowledgements still refer to
+    /// the id of the unsigned message.
+    #[must_use]
+    pub fn with_identity(mut self, identity: AgentIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Encrypts every attempt to targets that have a session in `channels`;
+    /// other targets still receive plaintext.
+    #[must_use]
+    pub fn with_channels(mut self, channels: SecureChannels) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Returns the retransmission schedule.
+    #[must_use]
+    pub const fn config(&self) -> DeliveryConfig {
+        self.config
+    }
+
+    /// Returns the number of messages awaiting acknowledgement.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.lock().values().map(Vec::len).sum()
+    }
+
+    /// Transmits `message` to `target` until acknowledged. The returned task
+    /// resolves to the number of attempts used.
+    ///
+    /// Retransmissions reuse the message id, so receivers can deduplicate.
+    /// Oversized messages are fragmented, and every fragment is resent on
+    /// each attempt.
+    pub fn send(&self, message: Message, target: SocketAddr) -> JoinHandle<DeliveryResult<u32>> {
+        let key = (target, message.message_id());
+        let (acked, mut ack) = oneshot::channel();
+        self.lock().entry(key).or_default().push(acked);
+
+        let sender = self.clone();
+        tokio::spawn(async move {
+            let result = sender.transmit(&message, target, &mut ack).await;
+            drop(ack);
+            // Only this task's waiter is closed now; others keep waiting.
+            let mut pending = sender.lock();
+            if let Some(waiters) = pending.get_mut(&key) {
+                waiters.retain(|waiter| !waiter.is_closed());
+                if waiters.is_empty() {
+                    pending.remove(&key);
+                }
+            }
+            drop(pending);
+            if let Err(DeliveryError::Exhausted(failure)) = &result {
+                sender.observer.delivery_failed(failure);
+            }
+            result
+        })
+    }
+
+    /// Marks the message with the supplied id as delivered to `source`,
+    /// returning `false` when it was not awaiting acknowledgement from that
+    /// address. Acks from any other address are ignored.
+    #[must_use]
+    pub fn acknowledge(&self, source: SocketAddr, message_id: u64) -> bool {
+        let waiters = self
+            .lock()
+            .remove(&(source, message_id))
+            .unwrap_or_default();
+        let mut delivered = false;
+        for acked in waiters {
+            delivered |= acked.send(()).is_ok();
+        }
+        delivered
+    }
+
+    async fn transmit(
+        &self,
+        message: &Message,
+        target: SocketAddr,
+        ack: &mut oneshot::Receiver<()>,
+    ) -> DeliveryResult<u32> {
+        let mut datagrams = self.prepare(message, target)?;
+        let mut last_error = None;
+        let mut attempts = 0;
+        for attempt in 1..=self.config.max_attempts.get() {
+            if attempt > 1 && (self.identity.is_some() || self.channels.is_some()) {
+                // A fresh nonce keeps retransmissions past replay protection.
+                datagrams = self.prepare(message, target)?;
+            }
+            match send_datagrams(&self.transport, &datagrams, target) {
+                Ok(()) => last_error = None,
+                Err(err) => {
+                    debug!(?err, %target, attempt, "reliable send attempt failed");
+                    last_error = Some(format!("{err:?}"));
+                }
+            }
+            attempts = attempt;
+            match tokio::time::timeout(self.config.backoff(attempt), &mut *ack).await {
+                Ok(Ok(())) => return Ok(attempt),
+                // The waiter was dropped, so no ack can arrive any more.
+                Ok(Err(_)) => break,
+                Err(_) => {}
+            }
+        }
+
+        Err(DeliveryError::Exhausted(DeliveryFailure {
+            message_id: message.message_id(),
+            message_type: message.message_type(),
+            target,
+            attempts,
+            last_error,
+        }))
+    }
+
+    /// Signs and encrypts `message` as configured and splits it into
+    /// datagrams.
+    fn prepare(&self, message: &Message, target: SocketAddr) -> DeliveryResult<Vec<Vec<u8>>> {
+        let mut outbound = sign_outbound(self.identity.as_ref(), message)?;
+        if let Some(channels) = &self.channels
+            && let Some(peer) = channels.peer_at(target)
+        {
+            outbound = Cow::Owned(channels.encrypt(peer, &outbound)?);
+        }
+        Ok(self.fragmenter.split(&outbound)?)
+    }
+
+    pub(crate) const fn transport(&self) -> &TransportHandle {
+        &self.transport
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AckWaiters> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    use mxp::transport::{SocketError, Transport, TransportConfig};
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        failures: Mutex<Vec<DeliveryFailure>>,
+    }
+
+    impl DeliveryObserver for RecordingObserver {
+        fn delivery_failed(&self, failure: &DeliveryFailure) {
+            self.failures.lock().unwrap().push(failure.clone());
+        }
+    }
+
+    fn bind_pair() -> Option<(TransportHandle, TransportHandle)> {
+        let transport = Transport::new(TransportConfig {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..TransportConfig::default()
+        });
+        let bind = || match transport.bind("127.0.0.1:0".parse().unwrap()) {
+            Ok(handle) => Some(handle),
+            Err(SocketError::Io(err)) if err.kind() == ErrorKind::PermissionDenied => None,
+            Err(err) => panic!("bind: {err:?}"),
+        };
+        Some((bind()?, bind()?))
+    }
+
+    fn config() -> DeliveryConfig {
+        DeliveryConfig::new()
+            .with_initial_backoff(Duration::from_millis(20))
+            .with_max_backoff(Duration::from_millis(40))
+            .with_max_attempts(NonZeroU32::new(3).unwrap())
+    }
+
+    #[test]
+    fn backoff_grows_to_cap() {
+        let config = config();
+        assert_eq!(config.backoff(1), Duration::from_millis(20));
+        assert_eq!(config.backoff(2), Duration::from_millis(40));
+        assert_eq!(config.backoff(30), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn retransmits_until_acknowledged() {
+        let Some((local, remote)) = bind_pair() else {
+            eprintln!("skipping reliable delivery test: bind requires elevated privileges");
+            return;
+        };
+        let target = remote.local_addr().unwrap();
+        let local_addr = local.local_addr().unwrap();
+        let sender = ReliableSender::new(local, config());
+        let message = Message::new(MessageType::Event, b"{\"audit\":true}");
+        let message_id = message.message_id();
+
+        let delivery = sender.send(message, target);
+
+        // Ignore the first transmission and acknowledge the retransmission.
+        let received = tokio::task::spawn_blocking(move || {
+            let mut ids = Vec::new();
+            for _ in 0..2 {
+                let mut buffer = remote.acquire_buffer();
+                let (len, _) = remote.receive(&mut buffer).unwrap();
+                let decoded = Message::decode(buffer.as_slice()[..len].to_vec()).unwrap();
+                ids.push(decoded.message_id());
+            }
+            ids
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, [message_id, message_id]);
+
+        let ack =
+            DeliveryAck::from_payload(DeliveryAck::new(message_id).to_message().payload()).unwrap();
+        assert!(!sender.acknowledge(local_addr, ack.message_id()));
+        assert!(sender.acknowledge(target, ack.message_id()));
+        assert_eq!(delivery.await.unwrap().unwrap(), 2);
+        assert_eq!(sender.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn tracks_clones_sent_to_different_targets_separately() {
+        let (Some((local, first)), Some((_, second))) = (bind_pair(), bind_pair()) else {
+            eprintln!("skipping reliable delivery test: bind requires elevated privileges");
+            return;
+        };
+        let (first, second) = (first.local_addr().unwrap(), second.local_addr().unwrap());
+        let sender = ReliableSender::new(local, config());
+        let message = Message::new(MessageType::Event, b"{}");
+        let message_id = message.message_id();
+
+        let to_first = sender.send(message.clone(), first);
+        let to_second = sender.send(message, second);
+        assert_eq!(sender.in_flight(), 2);
+
+        assert!(sender.acknowledge(second, message_id));
+        assert_eq!(to_second.await.unwrap().unwrap(), 1);
+        assert!(matches!(
+            to_first.await.unwrap(),
+            Err(DeliveryError::Exhausted(_))
+        ));
+        assert_eq!(sender.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn reports_failure_after_last_attempt() {
+        let Some((local, remote)) = bind_pair() else {
+            eprintln!("skipping reliable delivery test: bind requires elevated privileges");
+            return;
+        };
+        let observer = Arc::new(RecordingObserver::default());
+        let sender = ReliableSender::new(local, config()).with_observer(observer.clone());
+        let message = Message::new(MessageType::Response, b"{}");
+        let message_id = message.message_id();
+
+        let err = sender
+            .send(message, remote.local_addr().unwrap())
+            .await
+            .unwrap()
+            .unwrap_err();
+        let DeliveryError::Exhausted(failure) = err else {
+            panic!("unexpected delivery error: {err:?}");
+        };
+        assert_eq!(failure.message_id(), message_id);
+        assert_eq!(failure.attempts(), 3);
+        assert_eq!(failure.message_type(), Some(MessageType::Response));
+        assert_eq!(*observer.failures.lock().unwrap(), [failure]);
+        assert!(!sender.acknowledge(remote.local_addr().unwrap(), message_id));
+    }
+}
@@ -0,0 +1,1311 @@
+//! End-to-end payload encryption between agents.
+//!
+//! Two agents agree on a session key with a handshake of two `Event`
+//! messages. Each carries an ephemeral X25519 public key signed with the
+//! sender's [`AgentIdentity`], so only peers in the [`TrustStore`] can open a
+//! session:
+//!
+//! | bytes | field                                        |
+//! |-------|----------------------------------------------|
+//! | 4     | magic `MXPH`                                 |
+//! | 1     | stage: 1 offer, 2 accept                     |
+//! | 16    | handshake id                                 |
+//! | 16    | sender agent id                              |
+//! | 16    | recipient agent id                           |
+//! | 8     | signing time, Unix milliseconds (LE)         |
+//! | 32    | ephemeral X25519 public key                  |
+//! | 64    | Ed25519 signature                            |
+//!
+//! The signature covers the preceding 93 bytes; an accept also covers the
+//! offer's public key. Both sides derive a ChaCha20-Poly1305 key from the
+//! shared secret with HKDF-SHA256, salted with the handshake id.
+//!
+//! An encrypted message has the same [`MessageType`] as the original and
+//! carries the sealed, encoded original message after a header:
+//!
+//! | bytes | field                                     |
+//! |-------|-------------------------------------------|
+//! | 4     | magic `MXPE`                              |
+//! | 16    | sender agent id                           |
+//! | 16    | recipient agent id                        |
+//! | 16    | session key id (the handshake id)         |
+//! | 12    | nonce                                     |
+//!
+//! The header is authenticated as associated data. Both directions share the
+//! session key, so the nonce starts with the sender's handshake stage and
+//! ends with a per-key message counter (big-endian u64). The receiver
+//! remembers the last 64 counters of each key and rejects repeats.
+//!
+//! The recipient id stays readable so an [`AgentHost`] can route sealed
+//! messages and handshakes; agents reject those addressed to someone else.
+//!
+//! [`AgentHost`]: crate::AgentHost
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use agent_primitives::AgentId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mxp::{Message, MessageType, TransportHandle};
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::agreement::{self, EphemeralPrivateKey, X25519};
+use ring::hkdf::{HKDF_SHA256, Salt};
+use ring::rand::SystemRandom;
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::middleware::{Middleware, Next};
+use crate::registry_wire::AgentRecord;
+use crate::signing::{AgentIdentity, SigningError, TrustStore, field, verify_bytes, within_skew};
+use crate::timer::is_trigger_run;
+use crate::{HandlerContext, HandlerError, HandlerResult};
+
+const HANDSHAKE_MAGIC: &[u8; 4] = b"MXPH";
+const HANDSHAKE_SIGNED_LEN: usize = 93;
+const HANDSHAKE_LEN: usize = HANDSHAKE_SIGNED_LEN + 64;
+const OFFER: u8 = 1;
+const ACCEPT: u8 = 2;
+const SEALED_MAGIC: &[u8; 4] = b"MXPE";
+const SEALED_HEADER_LEN: usize = 52 + NONCE_LEN;
+const KDF_INFO: &[u8] = b"mxp-agents x25519 chacha20-poly1305 v1";
+const DEFAULT_ROTATE_AFTER: Duration = Duration::from_hours(1);
+const DEFAULT_ROTATE_AFTER_MESSAGES: u64 = 1 << 24;
+const DEFAULT_PREVIOUS_KEY_GRACE: Duration = Duration::from_mins(1);
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_mins(1);
+/// How many counters below the highest opened one are still accepted.
+const REPLAY_WINDOW: u64 = 64;
+
+/// Errors produced by payload encryption and key agreement.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// A plaintext message arrived while encryption is required.
+    #[error("unencrypted message rejected")]
+    PlaintextRejected,
+    /// No session key is established with the peer.
+    #[error("no encrypted session with agent {0}")]
+    NoSession(AgentId),
+    /// The message was sealed with a key that is unknown or has expired.
+    #[error("unknown session key from agent {0}")]
+    UnknownKey(AgentId),
+    /// The message failed authentication.
+    #[error("failed to decrypt message from agent {0}")]
+    Decrypt(AgentId),
+    /// The message was already opened, or is too old to tell.
+    #[error("replayed sealed message from agent {0}")]
+    Replayed(AgentId),
+    /// A sealed message or handshake is addressed to another agent.
+    #[error("message is addressed to agent {0}")]
+    Misdirected(AgentId),
+    /// A sealed message or handshake could not be parsed.
+    #[error("malformed encrypted message: {reason}")]
+    Malformed {
+        /// What was wrong with the message.
+        reason: &'static str,
+    },
+    /// A handshake was rejected.
+    #[error("handshake rejected: {0}")]
+    Handshake(SigningError),
+    /// An accept answered no pending offer.
+    #[error("unexpected handshake accept {0}")]
+    UnexpectedAccept(Uuid),
+    /// The peer did not accept the handshake in time.
+    #[error("handshake with agent {0} timed out")]
+    Timeout(AgentId),
+    /// Key generation, agreement, or sealing failed.
+    #[error("cryptographic operation failed")]
+    Crypto,
+    /// A handshake message could not be sent.
+    #[error("transport error: {0}")]
+    Transport(String),
+}
+
+/// Result alias for encryption operations.
+pub type EncryptionResult<T> = Result<T, EncryptionError>;
+
+/// Key rotation, handshake, and plaintext settings for [`SecureChannels`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionConfig {
+    require_encryption: bool,
+    plaintext_types: Vec<MessageType>,
+    rotate_after: Duration,
+    rotate_after_messages: u64,
+    previous_key_grace: Duration,
+    handshake_timeout: Duration,
+    max_clock_skew: Duration,
+}
+
+impl EncryptionConfig {
+    /// Creates the default configuration: plaintext accepted, keys rotated
+    /// hourly or after 2^24 messages, a one minute grace period for the
+    /// previous key, and a five second handshake timeout.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            require_encryption: false,
+            plaintext_types: vec![MessageType::Ack],
+            rotate_after: DEFAULT_ROTATE_AFTER,
+            rotate_after_messages: DEFAULT_ROTATE_AFTER_MESSAGES,
+            previous_key_grace: DEFAULT_PREVIOUS_KEY_GRACE,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+        }
+    }
+
+    /// Rejects plaintext messages, except handshakes and the types allowed
+    /// with [`allow_plaintext`](Self::allow_plaintext) (`Ack` by default).
+    #[must_use]
+    pub const fn with_required(mut self, required: bool) -> Self {
+        self.require_encryption = required;
+        self
+    }
+
+    /// Accepts plaintext messages of `message_type` even when encryption is
+    /// required.
+    #[must_use]
+    pub fn allow_plaintext(mut self, message_type: MessageType) -> Self {
+        if !self.plaintext_types.contains(&message_type) {
+            self.plaintext_types.push(message_type);
+        }
+        self
+    }
+
+    /// Sets how long a session key is used before it is rotated.
+    #[must_use]
+    pub const fn with_rotate_after(mut self, rotate_after: Duration) -> Self {
+        self.rotate_after = rotate_after;
+        self
+    }
+
+    /// Sets how many messages are sealed with a key before it is rotated.
+    #[must_use]
+    pub const fn with_rotate_after_messages(mut self, messages: u64) -> Self {
+        self.rotate_after_messages = messages;
+        self
+    }
+
+    /// Sets how long the previous key still opens messages after rotation.
+    #[must_use]
+    pub const fn with_previous_key_grace(mut self, grace: Duration) -> Self {
+        self.previous_key_grace = grace;
+        self
+    }
+
+    /// Sets how long to wait for a peer to accept a handshake.
+    #[must_use]
+    pub const fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Sets how far a handshake's signing time may differ from the local
+    /// clock.
+    #[must_use]
+    pub const fn with_max_clock_skew(mut self, skew: Duration) -> Self {
+        self.max_clock_skew = skew;
+        self
+    }
+
+    /// Returns `true` when plaintext messages are rejected.
+    #[must_use]
+    pub const fn is_required(&self) -> bool {
+        self.require_encryption
+    }
+
+    /// Returns the message types accepted in plaintext.
+    #[must_use]
+    pub fn plaintext_types(&self) -> &[MessageType] {
+        &self.plaintext_types
+    }
+
+    /// Returns how long a session key is used.
+    #[must_use]
+    pub const fn rotate_after(&self) -> Duration {
+        self.rotate_after
+    }
+
+    /// Returns how many messages a session key seals.
+    #[must_use]
+    pub const fn rotate_after_messages(&self) -> u64 {
+        self.rotate_after_messages
+    }
+
+    /// Returns how long the previous key stays usable after rotation.
+    #[must_use]
+    pub const fn previous_key_grace(&self) -> Duration {
+        self.previous_key_grace
+    }
+
+    /// Returns the handshake timeout.
+    #[must_use]
+    pub const fn handshake_timeout(&self) -> Duration {
+        self.handshake_timeout
+    }
+
+    /// Returns the allowed handshake clock skew.
+    #[must_use]
+    pub const fn max_clock_skew(&self) -> Duration {
+        self.max_clock_skew
+    }
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Context extension naming the peer whose encrypted message was opened.
+///
+/// [`MxpResponseSink`](crate::MxpResponseSink) encrypts replies to contexts
+/// that carry it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptedPeer {
+    agent_id: AgentId,
+    key_id: Uuid,
+}
+
+impl EncryptedPeer {
+    /// Returns the agent that sent the message.
+    #[must_use]
+    pub const fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Returns the session key the message was sealed with.
+    #[must_use]
+    pub const fn key_id(&self) -> Uuid {
+        self.key_id
+    }
+}
+
+struct SessionKey {
+    key_id: Uuid,
+    key: Arc<LessSafeKey>,
+    /// The handshake stage this side played, which prefixes its nonces.
+    stage: u8,
+    established_at: Instant,
+    sealed: u64,
+    opened: ReplayWindow,
+}
+
+/// The highest counter opened with a key and a bitmap of the
+/// [`REPLAY_WINDOW`] counters below it.
+#[derive(Default)]
+struct ReplayWindow {
+    highest: Option<u64>,
+    bitmap: u64,
+}
+
+impl ReplayWindow {
+    /// Records `counter`, returning `false` when it was already opened or
+    /// falls below the window.
+    fn accept(&mut self, counter: u64) -> bool {
+        let Some(highest) = self.highest.filter(|highest| counter <= *highest) else {
+            let shift = self
+                .highest
+                .map_or(REPLAY_WINDOW, |highest| counter - highest);
+            self.bitmap = u32::try_from(shift)
+                .ok()
+                .and_then(|shift| self.bitmap.checked_shl(shift))
+                .unwrap_or(0)
+                | 1;
+            self.highest = Some(counter);
+            return true;
+        };
+        let offset = highest - counter;
+        if offset >= REPLAY_WINDOW || self.bitmap & (1 << offset) != 0 {
+            return false;
+        }
+        self.bitmap |= 1 << offset;
+        true
+    }
+}
+
+struct PeerSession {
+    addr: Option<SocketAddr>,
+    current: SessionKey,
+    previous: Option<(SessionKey, Instant)>,
+    rotating: bool,
+}
+
+struct PendingHandshake {
+    peer: AgentId,
+    addr: SocketAddr,
+    private_key: EphemeralPrivateKey,
+    public_key: [u8; 32],
+    started: Instant,
+    done: Option<oneshot::Sender<EncryptionResult<()>>>,
+}
+
+#[derive(Default)]
+struct ChannelState {
+    sessions: HashMap<AgentId, PeerSession>,
+    pending: HashMap<Uuid, PendingHandshake>,
+    seen_offers: HashMap<Uuid, DateTime<Utc>>,
+}
+
+struct Handshake {
+    stage: u8,
+    id: Uuid,
+    sender: AgentId,
+    signed_at: DateTime<Utc>,
+    public_key: [u8; 32],
+}
+
+/// Encrypted sessions with peer agents, and the middleware that opens
+/// their messages.
+///
+/// As middleware, it answers handshakes, opens sealed messages and passes
+/// them on with an [`EncryptedPeer`] extension, and rejects plaintext when
+/// encryption is required. Add it outside [`SignatureVerifier`] so signed
+/// messages are verified after decryption.
+///
+/// [`SignatureVerifier`]: crate::SignatureVerifier
+#[derive(Clone)]
+pub struct SecureChannels {
+    identity: AgentIdentity,
+    trust: TrustStore,
+    transport: TransportHandle,
+    config: EncryptionConfig,
+    rng: SystemRandom,
+    state: Arc<Mutex<ChannelState>>,
+}
+
+impl fmt::Debug for SecureChannels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("SecureChannels")
+            .field("agent_id", &self.identity.agent_id())
+            .field("config", &self.config)
+            .field("sessions", &state.sessions.len())
+            .field("pending", &state.pending.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecureChannels {
+    /// Creates channels that authenticate handshakes with `identity`, accept
+    /// peers in `trust`, and send handshakes through `transport`.
+    #[must_use]
+    pub fn new(identity: AgentIdentity, trust: TrustStore, transport: TransportHandle) -> Self {
+        Self {
+            identity,
+            trust,
+            transport,
+            config: EncryptionConfig::default(),
+            rng: SystemRandom::new(),
+            state: Arc::default(),
+        }
+    }
+
+    /// Replaces the configuration.
+    #[must_use]
+    pub fn with_config(mut self, config: EncryptionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &EncryptionConfig {
+        &self.config
+    }
+
+    /// Returns the trust store handshakes are checked against.
+    #[must_use]
+    pub const fn trust(&self) -> &TrustStore {
+        &self.trust
+    }
+
+    /// Opens a session with an agent found through registry discovery, at
+    /// its advertised address.
+    ///
+    /// Discovery replies are not authenticated, so the agent's key must
+    /// already be in the trust store, for example from
+    /// [`TrustStore::trust_manifest`]. A record advertising a different key
+    /// is rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Handshake`] when the agent is not trusted
+    /// or the record advertises a key other than the trusted one, and
+    /// otherwise the errors of [`connect`](Self::connect).
+    pub async fn connect_record(&self, record: &AgentRecord) -> EncryptionResult<()> {
+        let peer = record
+            .id
+            .parse::<Uuid>()
+            .map(AgentId::from_uuid)
+            .map_err(|_| EncryptionError::Malformed {
+                reason: "registry record id is not an agent id",
+            })?;
+        let trusted = self.trust.get(peer).ok_or(EncryptionError::Handshake(
+            SigningError::UntrustedSigner(peer),
+        ))?;
+        if record.public_key.is_some_and(|key| key != trusted) {
+            return Err(EncryptionError::Handshake(SigningError::KeyMismatch(peer)));
+        }
+        self.connect(peer, record.address).await
+    }
+
+    /// Opens a session with `peer` at `addr`, or rotates the key of an
+    /// existing session, and waits for the peer to accept.
+    ///
+    /// The peer's accept arrives as an inbound message, so the kernel must
+    /// be dispatching messages through this middleware.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Timeout`] when the peer does not accept in
+    /// time, [`EncryptionError::Transport`] when the offer cannot be sent,
+    /// and [`EncryptionError::Handshake`] when the accept is rejected.
+    pub async fn connect(&self, peer: AgentId, addr: SocketAddr) -> EncryptionResult<()> {
+        let (done, accepted) = oneshot::channel();
+        let handshake_id = self.offer(peer, addr, Some(done))?;
+        if let Ok(Ok(result)) = tokio::time::timeout(self.config.handshake_timeout, accepted).await
+        {
+            return result;
+        }
+        self.lock().pending.remove(&handshake_id);
+        Err(EncryptionError::Timeout(peer))
+    }
+
+    /// Returns `true` when a session key is established with `peer`.
+    #[must_use]
+    pub fn is_established(&self, peer: AgentId) -> bool {
+        self.lock().sessions.contains_key(&peer)
+    }
+
+    /// Returns the peer whose session was opened with or by `addr`.
+    #[must_use]
+    pub fn peer_at(&self, addr: SocketAddr) -> Option<AgentId> {
+        self.lock()
+            .sessions
+            .iter()
+            .find(|(_, session)| session.addr == Some(addr))
+            .map(|(peer, _)| *peer)
+    }
+
+    /// Returns the id of the current session key with `peer`.
+    #[must_use]
+    pub fn key_id(&self, peer: AgentId) -> Option<Uuid> {
+        self.lock()
+            .sessions
+            .get(&peer)
+            .map(|session| session.current.key_id)
+    }
+
+    /// Discards the session with `peer`, returning `false` when there was
+    /// none.
+    #[must_use]
+    pub fn close(&self, peer: AgentId) -> bool {
+        self.lock().sessions.remove(&peer).is_some()
+    }
+
+    /// Seals `message` for `peer` with the current session key.
+    ///
+    /// Starts a key rotation in the background once the key is older than
+    /// the configured age or has sealed the configured number of messages;
+    /// the current key stays in use until the peer accepts.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::NoSession`] without a session and
+    /// [`EncryptionError::Crypto`] when sealing fails.
+    pub fn encrypt(&self, peer: AgentId, message: &Message) -> EncryptionResult<Message> {
+        let message_type = message.message_type().ok_or(EncryptionError::Malformed {
+            reason: "message missing type information",
+        })?;
+        let (key_id, key, nonce, rotate_to) = {
+            let mut state = self.lock();
+            let session = state
+                .sessions
+                .get_mut(&peer)
+                .ok_or(EncryptionError::NoSession(peer))?;
+            let mut nonce = [0; NONCE_LEN];
+            nonce[0] = session.current.stage;
+            nonce[4..].copy_from_slice(&session.current.sealed.to_be_bytes());
+            session.current.sealed += 1;
+            let due = session.current.established_at.elapsed() >= self.config.rotate_after
+                || session.current.sealed >= self.config.rotate_after_messages;
+            let rotate_to = session.addr.filter(|_| due && !session.rotating);
+            if rotate_to.is_some() {
+                session.rotating = true;
+            }
+            (
+                session.current.key_id,
+                Arc::clone(&session.current.key),
+                nonce,
+                rotate_to,
+            )
+        };
+        if let Some(addr) = rotate_to
+            && let Err(err) = self.offer(peer, addr, None)
+        {
+            warn!(%peer, %err, "failed to start session key rotation");
+            if let Some(session) = self.lock().sessions.get_mut(&peer) {
+                session.rotating = false;
+            }
+        }
+
+        let mut payload = Vec::with_capacity(SEALED_HEADER_LEN);
+        payload.extend_from_slice(SEALED_MAGIC);
+        payload.extend_from_slice(self.identity.agent_id().as_uuid().as_bytes());
+        payload.extend_from_slice(peer.as_uuid().as_bytes());
+        payload.extend_from_slice(key_id.as_bytes());
+        payload.extend_from_slice(&nonce);
+        let mut sealed = message.encode();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(&payload[..]),
+            &mut sealed,
+        )
+        .map_err(|_| EncryptionError::Crypto)?;
+        payload.extend_from_slice(&sealed);
+        Ok(Message::new(message_type, payload))
+    }
+
+    /// Opens a sealed message, returning its sender and the original message.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::Malformed`] when the message is not sealed,
+    /// [`EncryptionError::Misdirected`] when it is sealed for another agent,
+    /// [`EncryptionError::UnknownKey`] when the key is unknown or expired,
+    /// [`EncryptionError::Decrypt`] when authentication fails, and
+    /// [`EncryptionError::Replayed`] when the message was already opened.
+    pub fn decrypt(&self, message: &Message) -> EncryptionResult<(EncryptedPeer, Message)> {
+        let payload = message.payload();
+        if !is_encrypted(payload) || payload.len() < SEALED_HEADER_LEN {
+            return Err(EncryptionError::Malformed {
+                reason: "not a sealed message",
+            });
+        }
+        let (header, sealed) = payload.split_at(SEALED_HEADER_LEN);
+        let peer = AgentId::from_uuid(Uuid::from_bytes(field(header, 4)));
+        self.check_recipient(header, 20)?;
+        let key_id = Uuid::from_bytes(field(header, 36));
+        let nonce: [u8; NONCE_LEN] = field(header, 52);
+        let key = self.opening_key(peer, key_id)?;
+
+        let mut in_out = sealed.to_vec();
+        let opened = key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(header),
+                &mut in_out,
+            )
+            .map_err(|_| EncryptionError::Decrypt(peer))?;
+        self.check_replay(peer, key_id, nonce)?;
+        let inner = Message::decode(opened.to_vec()).map_err(|_| EncryptionError::Malformed {
+            reason: "invalid sealed message body",
+        })?;
+        if inner.message_type() != message.message_type() {
+            return Err(EncryptionError::Malformed {
+                reason: "message type mismatch",
+            });
+        }
+        Ok((
+            EncryptedPeer {
+                agent_id: peer,
+                key_id,
+            },
+            inner,
+        ))
+    }
+
+    fn opening_key(&self, peer: AgentId, key_id: Uuid) -> EncryptionResult<Arc<LessSafeKey>> {
+        let mut state = self.lock();
+        let session = state
+            .sessions
+            .get_mut(&peer)
+            .ok_or(EncryptionError::NoSession(peer))?;
+        if session.current.key_id == key_id {
+            return Ok(Arc::clone(&session.current.key));
+        }
+        if session
+            .previous
+            .as_ref()
+            .is_some_and(|(_, expires)| *expires <= Instant::now())
+        {
+            session.previous = None;
+        }
+        session
+            .previous
+            .as_ref()
+            .filter(|(previous, _)| previous.key_id == key_id)
+            .map(|(previous, _)| Arc::clone(&previous.key))
+            .ok_or(EncryptionError::UnknownKey(peer))
+    }
+
+    /// Records an authenticated nonce, rejecting one sealed by this side or
+    /// already opened with the key.
+    fn check_replay(
+        &self,
+        peer: AgentId,
+        key_id: Uuid,
+        nonce: [u8; NONCE_LEN],
+    ) -> EncryptionResult<()> {
+        let mut state = self.lock();
+        let session = state
+            .sessions
+            .get_mut(&peer)
+            .ok_or(EncryptionError::NoSession(peer))?;
+        let key = if session.current.key_id == key_id {
+            &mut session.current
+        } else {
+            session
+                .previous
+                .as_mut()
+                .map(|(previous, _)| previous)
+                .filter(|previous| previous.key_id == key_id)
+                .ok_or(EncryptionError::UnknownKey(peer))?
+        };
+        let counter = u64::from_be_bytes(field(&nonce, 4));
+        if nonce[0] == key.stage || !key.opened.accept(counter) {
+            return Err(EncryptionError::Replayed(peer));
+        }
+        Ok(())
+    }
+
+    /// Sends a handshake offer to `peer`, returning the handshake id.
+    fn offer(
+        &self,
+        peer: AgentId,
+        addr: SocketAddr,
+        done: Option<oneshot::Sender<EncryptionResult<()>>>,
+    ) -> EncryptionResult<Uuid> {
+        let private_key = EphemeralPrivateKey::generate(&X25519, &self.rng)
+            .map_err(|_| EncryptionError::Crypto)?;
+        let public_key = public_key_bytes(&private_key)?;
+        let handshake_id = Uuid::new_v4();
+        let message = self.handshake_message(OFFER, handshake_id, peer, public_key, None);
+        self.send(&message, addr)?;
+
+        let mut state = self.lock();
+        let timeout = self.config.handshake_timeout;
+        state
+            .pending
+            .retain(|_, pending| pending.started.elapsed() < timeout);
+        state.pending.insert(
+            handshake_id,
+            PendingHandshake {
+                peer,
+                addr,
+                private_key,
+                public_key,
+                started: Instant::now(),
+                done,
+            },
+        );
+        debug!(%peer, %addr, %handshake_id, "sent session handshake offer");
+        Ok(handshake_id)
+    }
+
+    /// Answers an offer and installs the new session key.
+    fn accept_offer(&self, offer: &Handshake, addr: SocketAddr) -> EncryptionResult<()> {
+        {
+            let mut state = self.lock();
+            let window = self.config.max_clock_skew * 2;
+            let now = Utc::now();
+            state
+                .seen_offers
+                .retain(|_, signed| within_skew(*signed, now, window));
+            if state
+                .seen_offers
+                .insert(offer.id, offer.signed_at)
+                .is_some()
+            {
+                return Err(EncryptionError::Handshake(SigningError::Replayed(
+                    offer.sender,
+                )));
+            }
+        }
+        let private_key = EphemeralPrivateKey::generate(&X25519, &self.rng)
+            .map_err(|_| EncryptionError::Crypto)?;
+        let public_key = public_key_bytes(&private_key)?;
+        let message = self.handshake_message(
+            ACCEPT,
+            offer.id,
+            offer.sender,
+            public_key,
+            Some(&offer.public_key),
+        );
+        let key = derive_key(
+            private_key,
+            &offer.public_key,
+            offer.id,
+            &offer.public_key,
+            &public_key,
+        )?;
+        self.send(&message, addr)?;
+        self.install(offer.sender, Some(addr), offer.id, ACCEPT, key);
+        debug!(peer = %offer.sender, %addr, handshake_id = %offer.id, "accepted session handshake");
+        Ok(())
+    }
+
+    /// Completes a handshake this agent offered.
+    fn complete(&self, accept: &Handshake) -> EncryptionResult<()> {
+        let pending = self
+            .lock()
+            .pending
+            .remove(&accept.id)
+            .ok_or(EncryptionError::UnexpectedAccept(accept.id))?;
+        if pending.peer != accept.sender {
+            return Err(EncryptionError::UnexpectedAccept(accept.id));
+        }
+        let result = derive_key(
+            pending.private_key,
+            &accept.public_key,
+            accept.id,
+            &pending.public_key,
+            &accept.public_key,
+        )
+        .map(|key| {
+            self.install(pending.peer, Some(pending.addr), accept.id, OFFER, key);
+            debug!(peer = %pending.peer, handshake_id = %accept.id, "session established");
+        });
+        if let Some(done) = pending.done {
+            let _ = done.send(result.clone());
+        }
+        result
+    }
+
+    fn install(
+        &self,
+        peer: AgentId,
+        addr: Option<SocketAddr>,
+        key_id: Uuid,
+        local_stage: u8,
+        key: LessSafeKey,
+    ) {
+        let current = SessionKey {
+            key_id,
+            key: Arc::new(key),
+            stage: local_stage,
+            established_at: Instant::now(),
+            sealed: 0,
+            opened: ReplayWindow::default(),
+        };
+        let grace = Instant::now() + self.config.previous_key_grace;
+        let mut state = self.lock();
+        match state.sessions.remove(&peer) {
+            Some(session) => {
+                state.sessions.insert(
+                    peer,
+                    PeerSession {
+                        addr: addr.or(session.addr),
+                        current,
+                        previous: Some((session.current, grace)),
+                        rotating: false,
+                    },
+                );
+            }
+            None => {
+                state.sessions.insert(
+                    peer,
+                    PeerSession {
+                        addr,
+                        current,
+                        previous: None,
+                        rotating: false,
+                    },
+                );
+            }
+        }
+    }
+
+    fn handshake_message(
+        &self,
+        stage: u8,
+        handshake_id: Uuid,
+        recipient: AgentId,
+        public_key: [u8; 32],
+        offered: Option<&[u8; 32]>,
+    ) -> Message {
+        let mut payload = Vec::with_capacity(HANDSHAKE_LEN);
+        payload.extend_from_slice(HANDSHAKE_MAGIC);
+        payload.push(stage);
+        payload.extend_from_slice(handshake_id.as_bytes());
+        payload.extend_from_slice(self.identity.agent_id().as_uuid().as_bytes());
+        payload.extend_from_slice(recipient.as_uuid().as_bytes());
+        payload.extend_from_slice(&Utc::now().timestamp_millis().to_le_bytes());
+        payload.extend_from_slice(&public_key);
+        let signed = [&payload[..], offered.map_or(&[][..], |key| &key[..])].concat();
+        payload.extend_from_slice(&self.identity.sign_bytes(&signed));
+        Message::new(MessageType::Event, payload)
+    }
+
+    /// Parses and authenticates a handshake message.
+    fn parse_handshake(&self, payload: &[u8]) -> EncryptionResult<Handshake> {
+        if payload.len() != HANDSHAKE_LEN || !matches!(payload[4], OFFER | ACCEPT) {
+            return Err(EncryptionError::Malformed {
+                reason: "invalid handshake",
+            });
+        }
+        let handshake_id = Uuid::from_bytes(field(payload, 5));
+        let sender = AgentId::from_uuid(Uuid::from_bytes(field(payload, 21)));
+        self.check_recipient(payload, 37)?;
+        let millis = i64::from_le_bytes(field(payload, 53));
+        let public_key: [u8; 32] = field(payload, 61);
+        let stage = payload[4];
+
+        let key = self.trust.get(sender).ok_or(EncryptionError::Handshake(
+            SigningError::UntrustedSigner(sender),
+        ))?;
+        let offered = if stage == ACCEPT {
+            self.lock()
+                .pending
+                .get(&handshake_id)
+                .map(|pending| pending.public_key)
+                .ok_or(EncryptionError::UnexpectedAccept(handshake_id))?
+                .to_vec()
+        } else {
+            Vec::new()
+        };
+        let signed = [&payload[..HANDSHAKE_SIGNED_LEN], &offered].concat();
+        if !verify_bytes(key, &signed, &payload[HANDSHAKE_SIGNED_LEN..]) {
+            return Err(EncryptionError::Handshake(SigningError::BadSignature(
+                sender,
+            )));
+        }
+        let signed_at = DateTime::from_timestamp_millis(millis).unwrap_or(DateTime::UNIX_EPOCH);
+        if !within_skew(signed_at, Utc::now(), self.config.max_clock_skew) {
+            return Err(EncryptionError::Handshake(SigningError::ClockSkew {
+                agent_id: sender,
+                signed_at,
+            }));
+        }
+        Ok(Handshake {
+            stage,
+            id: handshake_id,
+            sender,
+            signed_at,
+            public_key,
+        })
+    }
+
+    /// Rejects a header whose recipient, at `offset`, is not this agent.
+    fn check_recipient(&self, header: &[u8], offset: usize) -> EncryptionResult<()> {
+        let recipient = AgentId::from_uuid(Uuid::from_bytes(field(header, offset)));
+        if recipient == self.identity.agent_id() {
+            Ok(())
+        } else {
+            Err(EncryptionError::Misdirected(recipient))
+        }
+    }
+
+    fn handle_handshake(&self, ctx: &HandlerContext) -> EncryptionResult<()> {
+        let handshake = self.parse_handshake(ctx.message().payload())?;
+        if handshake.stage == ACCEPT {
+            return self.complete(&handshake);
+        }
+        let addr = ctx.sender().ok_or_else(|| {
+            EncryptionError::Transport("handshake offer without a sender address".into())
+        })?;
+        self.accept_offer(&handshake, addr)
+    }
+
+    fn send(&self, message: &Message, addr: SocketAddr) -> EncryptionResult<()> {
+        self.transport
+            .send(&message.encode(), addr)
+            .map(|_| ())
+            .map_err(|err| EncryptionError::Transport(format!("{err:?}")))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ChannelState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl Middleware for SecureChannels {
+    async fn handle(&self, mut ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        let message = ctx.message();
+        let payload = message.payload();
+        let result = if is_handshake(payload) {
+            self.handle_handshake(&ctx).map(|()| None)
+        } else if is_encrypted(payload) {
+            self.decrypt(message).map(Some)
+        } else {
+            let allowed = !self.config.require_encryption
+                || is_trigger_run(&ctx)
+                || message.message_type().is_some_and(|message_type| {
+                    self.config.plaintext_types.contains(&message_type)
+                });
+            if allowed {
+                return next.run(ctx).await;
+            }
+            Err(EncryptionError::PlaintextRejected)
+        };
+        match result {
+            Ok(Some((peer, inner))) => {
+                ctx.insert_extension(peer);
+                ctx.set_message(inner);
+                next.run(ctx).await
+            }
+            Ok(None) => Ok(()),
+            Err(err) => {
+                warn!(
+                    agent_id = %ctx.agent_id(),
+                    sender = ?ctx.sender(),
+                    message_id = ctx.message().message_id(),
+                    %err,
+                    "rejecting encrypted message"
+                );
+                Err(HandlerError::Encryption(err))
+            }
+        }
+    }
+}
+
+/// Returns `true` when `payload` starts like a sealed message.
+#[must_use]
+pub fn is_encrypted(payload: &[u8]) -> bool {
+    payload.starts_with(SEALED_MAGIC)
+}
+
+pub(crate) fn is_handshake(payload: &[u8]) -> bool {
+    payload.starts_with(HANDSHAKE_MAGIC)
+}
+
+/// Returns the agent a sealed message or handshake is addressed to, without
+/// authenticating it.
+pub(crate) fn recipient(payload: &[u8]) -> Option<AgentId> {
+    let offset = if is_encrypted(payload) && payload.len() >= SEALED_HEADER_LEN {
+        20
+    } else if is_handshake(payload) && payload.len() == HANDSHAKE_LEN {
+        37
+    } else {
+        return None;
+    };
+    Some(AgentId::from_uuid(Uuid::from_bytes(field(payload, offset))))
+}
+
+fn public_key_bytes(private_key: &EphemeralPrivateKey) -> EncryptionResult<[u8; 32]> {
+    let public_key = private_key
+        .compute_public_key()
+        .map_err(|_| EncryptionError::Crypto)?;
+    Ok(field(public_key.as_ref(), 0))
+}
+
+/// Derives the session key shared by the offer and accept keys.
+fn derive_key(
+    private_key: EphemeralPrivateKey,
+    peer_public_key: &[u8; 32],
+    handshake_id: Uuid,
+    offer_key: &[u8; 32],
+    accept_key: &[u8; 32],
+) -> EncryptionResult<LessSafeKey> {
+    let peer_public_key = agreement::UnparsedPublicKey::new(&X25519, peer_public_key);
+    agreement::agree_ephemeral(private_key, &peer_public_key, |shared| {
+        let info = [KDF_INFO, &offer_key[..], &accept_key[..]];
+        Salt::new(HKDF_SHA256, handshake_id.as_bytes())
+            .extract(shared)
+            .expand(&info, &CHACHA20_POLY1305)
+            .map(|okm| LessSafeKey::new(UnboundKey::from(okm)))
+    })
+    .map_err(|_| EncryptionError::Crypto)?
+    .map_err(|_| EncryptionError::Crypto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use mxp::transport::{SocketError, Transport, TransportConfig};
+
+    use crate::{AgentMessageHandler, MiddlewareStack};
+
+    struct Recording(Mutex<Vec<(Vec<u8>, Option<EncryptedPeer>)>>);
+
+    #[async_trait]
+    impl AgentMessageHandler for Recording {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            let peer = ctx.extension::<EncryptedPeer>().copied();
+            self.0
+                .lock()
+                .unwrap()
+                .push((ctx.message().payload().to_vec(), peer));
+            Ok(())
+        }
+    }
+
+    struct Node {
+        channels: SecureChannels,
+        stack: MiddlewareStack,
+        transport: TransportHandle,
+        handler: Recording,
+    }
+
+    impl Node {
+        /// Receives one datagram and dispatches it through the stack.
+        async fn pump(&self) -> HandlerResult {
+            let mut buffer = self.transport.acquire_buffer();
+            let (len, sender) = self.transport.receive(&mut buffer).unwrap();
+            let message = Message::decode(buffer.as_slice()[..len].to_vec()).unwrap();
+            let ctx = HandlerContext::from_message(AgentId::random(), message).with_sender(sender);
+            self.stack.dispatch(&self.handler, ctx).await
+        }
+
+        fn addr(&self) -> SocketAddr {
+            self.transport.local_addr().unwrap()
+        }
+    }
+
+    fn nodes(config: &EncryptionConfig) -> Option<(Node, Node)> {
+        let transport = Transport::new(TransportConfig {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..TransportConfig::default()
+        });
+        let trust = TrustStore::new();
+        let mut nodes = Vec::new();
+        for _ in 0..2 {
+            let handle = match transport.bind("127.0.0.1:0".parse().unwrap()) {
+                Ok(handle) => handle,
+                Err(SocketError::Io(err)) if err.kind() == ErrorKind::PermissionDenied => {
+                    return None;
+                }
+                Err(err) => panic!("bind: {err:?}"),
+            };
+            let identity = AgentIdentity::generate(AgentId::random()).unwrap();
+            trust.trust(identity.agent_id(), identity.public_key());
+            let channels = SecureChannels::new(identity, trust.clone(), handle.clone())
+                .with_config(config.clone());
+            nodes.push(Node {
+                stack: MiddlewareStack::new().layer(channels.clone()),
+                channels,
+                transport: handle,
+                handler: Recording(Mutex::new(Vec::new())),
+            });
+        }
+        let b = nodes.pop()?;
+        let a = nodes.pop()?;
+        Some((a, b))
+    }
+
+    async fn connect(a: &Node, b: &Node) {
+        let b_id = b.channels.identity.agent_id();
+        let (connected, accepted, completed) =
+            tokio::join!(a.channels.connect(b_id, b.addr()), b.pump(), async {
+                // Yield until B has answered, then deliver the accept to A.
+                tokio::task::yield_now().await;
+                a.pump().await
+            },);
+        accepted.unwrap();
+        completed.unwrap();
+        connected.unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshake_encrypts_and_rejects_plaintext() {
+        let config = EncryptionConfig::new().with_required(true);
+        let Some((a, b)) = nodes(&config) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        connect(&a, &b).await;
+        let a_id = a.channels.identity.agent_id();
+        let b_id = b.channels.identity.agent_id();
+        assert_eq!(a.channels.key_id(b_id), b.channels.key_id(a_id));
+
+        let call = Message::new(MessageType::Call, b"{\"secret\":42}");
+        let sealed = a.channels.encrypt(b_id, &call).unwrap();
+        assert!(is_encrypted(sealed.payload()));
+        assert!(!sealed.payload().windows(6).any(|w| w == b"secret"));
+        a.transport.send(&sealed.encode(), b.addr()).unwrap();
+        b.pump().await.unwrap();
+        let (payload, peer) = b.handler.0.lock().unwrap().pop().unwrap();
+        assert_eq!(payload, b"{\"secret\":42}");
+        assert_eq!(peer.unwrap().agent_id(), a_id);
+
+        let mut tampered = sealed.payload().to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            b.channels
+                .decrypt(&Message::new(MessageType::Call, tampered)),
+            Err(EncryptionError::Decrypt(a_id))
+        );
+        assert_eq!(recipient(sealed.payload()), Some(b_id));
+        assert_eq!(
+            a.channels.decrypt(&sealed),
+            Err(EncryptionError::Misdirected(b_id))
+        );
+
+        a.transport.send(&call.encode(), b.addr()).unwrap();
+        assert_eq!(
+            b.pump().await,
+            Err(HandlerError::Encryption(EncryptionError::PlaintextRejected))
+        );
+        assert!(b.handler.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rotation_keeps_previous_key_for_in_flight_messages() {
+        let config = EncryptionConfig::new().with_rotate_after_messages(2);
+        let Some((a, b)) = nodes(&config) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        connect(&a, &b).await;
+        let b_id = b.channels.identity.agent_id();
+        let first_key = a.channels.key_id(b_id).unwrap();
+
+        let call = Message::new(MessageType::Call, b"ping");
+        let early = a.channels.encrypt(b_id, &call).unwrap();
+        // The second message reaches the limit and starts a rotation.
+        let late = a.channels.encrypt(b_id, &call).unwrap();
+        b.pump().await.unwrap();
+        a.pump().await.unwrap();
+        let second_key = a.channels.key_id(b_id).unwrap();
+        assert_ne!(first_key, second_key);
+        assert_eq!(
+            b.channels.key_id(a.channels.identity.agent_id()),
+            Some(second_key)
+        );
+
+        for sealed in [early, late, a.channels.encrypt(b_id, &call).unwrap()] {
+            b.channels.decrypt(&sealed).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn reliable_sender_encrypts_to_peers_with_a_session() {
+        let Some((a, b)) = nodes(&EncryptionConfig::new().with_required(true)) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        connect(&a, &b).await;
+        let a_id = a.channels.identity.agent_id();
+        assert_eq!(b.channels.peer_at(a.addr()), Some(a_id));
+
+        let sender = crate::ReliableSender::new(a.transport.clone(), crate::DeliveryConfig::new())
+            .with_channels(a.channels.clone());
+        let delivery = sender.send(Message::new(MessageType::Call, b"notify"), b.addr());
+        // Let the delivery task transmit before B blocks on the socket.
+        tokio::task::yield_now().await;
+        b.pump().await.unwrap();
+        delivery.abort();
+        let (payload, peer) = b.handler.0.lock().unwrap().pop().unwrap();
+        assert_eq!(payload, b"notify");
+        assert_eq!(peer.unwrap().agent_id(), a_id);
+    }
+
+    #[tokio::test]
+    async fn connect_record_requires_a_pinned_key() {
+        let Some((a, b)) = nodes(&EncryptionConfig::new()) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        let b_id = b.channels.identity.agent_id();
+        let forged = AgentIdentity::generate(b_id).unwrap().public_key();
+        let mut record = AgentRecord {
+            id: b_id.to_string(),
+            name: "b".into(),
+            version: "1.0.0".into(),
+            description: None,
+            capabilities: Vec::new(),
+            tags: Vec::new(),
+            address: b.addr(),
+            status: crate::registry_wire::AgentStatus::Online,
+            last_heartbeat: Utc::now(),
+            registered_at: Utc::now(),
+            public_key: Some(forged),
+        };
+        assert_eq!(
+            a.channels.connect_record(&record).await,
+            Err(EncryptionError::Handshake(SigningError::KeyMismatch(b_id)))
+        );
+        assert_eq!(
+            a.channels.trust().get(b_id),
+            Some(b.channels.identity.public_key())
+        );
+
+        let stranger = AgentId::random();
+        record.id = stranger.to_string();
+        assert_eq!(
+            a.channels.connect_record(&record).await,
+            Err(EncryptionError::Handshake(SigningError::UntrustedSigner(
+                stranger
+            )))
+        );
+        assert!(a.channels.trust().get(stranger).is_none());
+    }
+
+    #[tokio::test]
+    async fn remembers_future_dated_offers() {
+        let Some((a, b)) = nodes(&EncryptionConfig::new()) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        let offer = Handshake {
+            stage: OFFER,
+            id: Uuid::new_v4(),
+            sender: a.channels.identity.agent_id(),
+            signed_at: Utc::now() + chrono::TimeDelta::seconds(30),
+            public_key: [9; 32],
+        };
+        b.channels.accept_offer(&offer, a.addr()).unwrap();
+        assert_eq!(
+            b.channels.accept_offer(&offer, a.addr()),
+            Err(EncryptionError::Handshake(SigningError::Replayed(
+                offer.sender
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_replayed_and_reflected_messages() {
+        let Some((a, b)) = nodes(&EncryptionConfig::new()) else {
+            eprintln!("skipping encryption test: bind requires elevated privileges");
+            return;
+        };
+        connect(&a, &b).await;
+        let a_id = a.channels.identity.agent_id();
+        let b_id = b.channels.identity.agent_id();
+
+        let call = Message::new(MessageType::Call, b"ping");
+        let first = a.channels.encrypt(b_id, &call).unwrap();
+        let second = a.channels.encrypt(b_id, &call).unwrap();
+        // Out-of-order delivery within the window is fine; repeats are not.
+        b.channels.decrypt(&second).unwrap();
+        b.channels.decrypt(&first).unwrap();
+        for sealed in [&first, &second] {
+            assert_eq!(
+                b.channels.decrypt(sealed),
+                Err(EncryptionError::Replayed(a_id))
+            );
+        }
+
+        // A reply from B reflected back to B carries B's own stage.
+        let reply = b.channels.encrypt(a_id, &call).unwrap();
+        let mut reflected = reply.payload().to_vec();
+        reflected[4..20].copy_from_slice(a_id.as_uuid().as_bytes());
+        assert!(
+            b.channels
+                .decrypt(&Message::new(MessageType::Call, reflected))
+                .is_err()
+        );
+        a.channels.decrypt(&reply).unwrap();
+    }
+
+    #[test]
+    fn replay_window_slides_past_old_counters() {
+        let mut window = ReplayWindow::default();
+        assert!(window.accept(5));
+        assert!(window.accept(3));
+        assert!(!window.accept(3));
+        assert!(window.accept(5 + REPLAY_WINDOW));
+        assert!(!window.accept(5));
+        assert!(window.accept(6));
+        assert!(!window.accept(5 + REPLAY_WINDOW));
+    }
+}
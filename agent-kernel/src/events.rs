@@ -0,0 +1,1064 @@
+//! Topic-based event publishing and subscription over MXP.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use agent_policy::{DecisionKind, PolicyAction, PolicyEngine, PolicyRequest};
+use agent_primitives::AgentId;
+use async_trait::async_trait;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use mxp::{Message, MessageType, TransportHandle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::call::PolicyObserver;
+use crate::delivery::{DeliveryAck, DeliveryResult, ReliableSender, send_ack};
+use crate::registry::MxpRegistryClient;
+use crate::registry_wire::AgentStatus;
+use crate::{AgentMessageHandler, HandlerContext, HandlerError, HandlerResult};
+
+/// Prefix of the registry capability advertised by subscribers of a topic.
+pub const TOPIC_CAPABILITY_PREFIX: &str = "events.";
+
+const RECENT_EVENTS: usize = 4096;
+
+/// Returns the registry capability that subscribers of `topic` advertise,
+/// e.g. `events.orders.created`.
+#[must_use]
+pub fn topic_capability(topic: &str) -> String {
+    format!("{TOPIC_CAPABILITY_PREFIX}{topic}")
+}
+
+/// Errors raised while publishing or receiving events.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EventError {
+    /// Topic or topic filter is malformed.
+    #[error("invalid topic `{topic}`: {reason}")]
+    InvalidTopic {
+        /// Offending topic or filter.
+        topic: String,
+        /// Why it was rejected.
+        reason: &'static str,
+    },
+    /// Policy denied publishing to the topic.
+    #[error("policy denied event `{topic}`: {reason}")]
+    Denied {
+        /// Topic that was denied.
+        topic: String,
+        /// Reason reported by the policy engine.
+        reason: String,
+    },
+    /// Policy requires approval before publishing to the topic.
+    #[error("policy escalation required for event `{topic}`: {reason}")]
+    Escalated {
+        /// Topic awaiting approval.
+        topic: String,
+        /// Reason reported by the policy engine.
+        reason: String,
+        /// Approvers named by the policy engine.
+        approvers: Vec<String>,
+    },
+    /// Policy engine failed to evaluate the request.
+    #[error("policy engine error: {0}")]
+    Policy(String),
+    /// Event data could not be serialized.
+    #[error("failed to encode event `{topic}`: {reason}")]
+    Encode {
+        /// Topic being published.
+        topic: String,
+        /// Serializer error.
+        reason: String,
+    },
+    /// Event data did not match the subscriber's type.
+    #[error("failed to decode event `{topic}`: {reason}")]
+    Decode {
+        /// Topic of the event.
+        topic: String,
+        /// Deserializer error.
+        reason: String,
+    },
+    /// Subscribers could not be looked up.
+    #[error("subscriber lookup failed: {0}")]
+    Directory(String),
+}
+
+/// Result alias for event operations.
+pub type EventResult<T> = Result<T, EventError>;
+
+fn validate_topic(topic: &str, allow_wildcard: bool) -> EventResult<()> {
+    let invalid = |reason| EventError::InvalidTopic {
+        topic: topic.to_owned(),
+        reason,
+    };
+    if topic.is_empty() {
+        return Err(invalid("topic cannot be empty"));
+    }
+    let segments = topic.split('.').collect::<Vec<_>>();
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.is_empty() {
+            return Err(invalid("topic segments cannot be empty"));
+        }
+        if segment.contains('*') {
+            if !allow_wildcard {
+                return Err(invalid("published topics cannot contain `*`"));
+            }
+            if *segment != "*" || index + 1 != segments.len() {
+                return Err(invalid("`*` must be the whole last segment"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Topic pattern a subscriber listens to.
+///
+/// `orders.created` matches only that topic, `orders.*` matches every topic
+/// below `orders`, and `*` matches all topics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TopicFilter(String);
+
+impl TopicFilter {
+    /// Parses a topic filter.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventError::InvalidTopic`] when a segment is empty or `*` is
+    /// used anywhere but as the whole last segment.
+    pub fn new(pattern: impl Into<String>) -> EventResult<Self> {
+        let pattern = pattern.into();
+        validate_topic(&pattern, true)?;
+        Ok(Self(pattern))
+    }
+
+    /// Returns the filter as written.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` when `topic` falls under this filter.
+    #[must_use]
+    pub fn matches(&self, topic: &str) -> bool {
+        match self.0.strip_suffix('*') {
+            Some("") => true,
+            Some(prefix) => topic.len() > prefix.len() && topic.starts_with(prefix),
+            None => self.0 == topic,
+        }
+    }
+}
+
+impl fmt::Display for TopicFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for TopicFilter {
+    type Error = EventError;
+
+    fn try_from(value: String) -> EventResult<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<TopicFilter> for String {
+    fn from(value: TopicFilter) -> Self {
+        value.0
+    }
+}
+
+/// Application event published under a topic.
+///
+/// Encoded as an MXP `Event` with payload `{"type": "topic_event",
+/// "event_id": ..., "topic": ..., "publisher": ..., "data": ...}`. The
+/// `event_id` stays the same across retransmissions and broker hops, so
+/// subscribers can discard duplicates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "topic_event")]
+pub struct TopicEvent {
+    event_id: Uuid,
+    topic: String,
+    publisher: AgentId,
+    #[serde(default)]
+    data: Value,
+}
+
+impl TopicEvent {
+    /// Returns the identifier shared by every copy of the event.
+    #[must_use]
+    pub const fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+
+    /// Returns the topic the event was published under.
+    #[must_use]
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the publishing agent.
+    #[must_use]
+    pub const fn publisher(&self) -> AgentId {
+        self.publisher
+    }
+
+    /// Returns the raw event data.
+    #[must_use]
+    pub const fn data(&self) -> &Value {
+        &self.data
+    }
+
+    /// Deserializes the event data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventError::Decode`] when the data does not match `T`.
+    pub fn decode<T: DeserializeOwned>(&self) -> EventResult<T> {
+        T::deserialize(&self.data).map_err(|err| EventError::Decode {
+            topic: self.topic.clone(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Decodes an event from an `Event` payload, returning `None` for other
+    /// event payloads.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Builds a fresh MXP `Event` message carrying the event.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(
+            MessageType::Event,
+            serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+}
+
+/// Request sent to an [`EventBroker`] to change the sender's subscriptions.
+///
+/// Encoded as `{"type": "subscribe", "topics": [...]}` or
+/// `{"type": "unsubscribe", "topics": [...]}`; unsubscribing from no topics
+/// removes every subscription of the sender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubscriptionRequest {
+    /// Start receiving events matching the filters.
+    Subscribe {
+        /// Filters to add.
+        topics: Vec<TopicFilter>,
+    },
+    /// Stop receiving events matching the filters.
+    Unsubscribe {
+        /// Filters to remove.
+        #[serde(default)]
+        topics: Vec<TopicFilter>,
+    },
+}
+
+impl SubscriptionRequest {
+    /// Decodes a request from an `Event` payload.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Builds the MXP `Event` message carrying the request. Send it with a
+    /// [`ReliableSender`]; the broker acknowledges it.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(
+            MessageType::Event,
+            serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+}
+
+/// Resolves the addresses an event on a topic is sent to.
+#[async_trait]
+pub trait SubscriberDirectory: Send + Sync {
+    /// Returns the targets for an event published under `topic`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventError::Directory`] when the lookup fails.
+    async fn subscribers(&self, topic: &str) -> EventResult<Vec<SocketAddr>>;
+}
+
+/// Sends every event to one broker agent, which fans it out.
+#[derive(Debug, Clone, Copy)]
+pub struct BrokerDirectory {
+    broker: SocketAddr,
+}
+
+impl BrokerDirectory {
+    /// Publishes through the broker at `broker`.
+    #[must_use]
+    pub const fn new(broker: SocketAddr) -> Self {
+        Self { broker }
+    }
+}
+
+#[async_trait]
+impl SubscriberDirectory for BrokerDirectory {
+    async fn subscribers(&self, _topic: &str) -> EventResult<Vec<SocketAddr>> {
+        Ok(vec![self.broker])
+    }
+}
+
+/// Finds subscribers through the registry: agents subscribe to a topic by
+/// advertising the capability returned by [`topic_capability`].
+///
+/// Registry lookups match exact topics only; use an [`EventBroker`] for
+/// wildcard subscriptions.
+#[derive(Debug, Clone)]
+pub struct RegistryDirectory {
+    registry: Arc<MxpRegistryClient>,
+}
+
+impl RegistryDirectory {
+    /// Looks subscribers up through `registry`.
+    #[must_use]
+    pub const fn new(registry: Arc<MxpRegistryClient>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl SubscriberDirectory for RegistryDirectory {
+    async fn subscribers(&self, topic: &str) -> EventResult<Vec<SocketAddr>> {
+        let agents = self
+            .registry
+            .discover(&topic_capability(topic))
+            .await
+            .map_err(|err| EventError::Directory(err.to_string()))?;
+        Ok(agents
+            .into_iter()
+            .filter(|agent| agent.status != AgentStatus::Offline)
+            .map(|agent| agent.address)
+            .collect())
+    }
+}
+
+/// In-memory topic subscriptions keyed by subscriber address.
+#[derive(Debug, Default)]
+pub struct SubscriptionTable {
+    subscriptions: Mutex<HashMap<SocketAddr, BTreeSet<TopicFilter>>>,
+}
+
+impl SubscriptionTable {
+    /// Creates an empty table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `topics` to the subscriptions of `subscriber`.
+    pub fn subscribe<I>(&self, subscriber: SocketAddr, topics: I)
+    where
+        I: IntoIterator<Item = TopicFilter>,
+    {
+        self.lock().entry(subscriber).or_default().extend(topics);
+    }
+
+    /// Removes `topics` from `subscriber`, or every subscription when
+    /// `topics` is empty.
+    pub fn unsubscribe<I>(&self, subscriber: SocketAddr, topics: I)
+    where
+        I: IntoIterator<Item = TopicFilter>,
+    {
+        let mut subscriptions = self.lock();
+        let mut topics = topics.into_iter().peekable();
+        if topics.peek().is_none() {
+            subscriptions.remove(&subscriber);
+            return;
+        }
+        if let Some(filters) = subscriptions.get_mut(&subscriber) {
+            for topic in topics {
+                filters.remove(&topic);
+            }
+            if filters.is_empty() {
+                subscriptions.remove(&subscriber);
+            }
+        }
+    }
+
+    /// Returns the subscribers with a filter matching `topic`, sorted.
+    #[must_use]
+    pub fn subscribers_of(&self, topic: &str) -> Vec<SocketAddr> {
+        let mut subscribers = self
+            .lock()
+            .iter()
+            .filter(|(_, filters)| filters.iter().any(|filter| filter.matches(topic)))
+            .map(|(subscriber, _)| *subscriber)
+            .collect::<Vec<_>>();
+        subscribers.sort_unstable();
+        subscribers
+    }
+
+    /// Returns the number of subscribers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` when nobody is subscribed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<SocketAddr, BTreeSet<TopicFilter>>> {
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl SubscriberDirectory for SubscriptionTable {
+    async fn subscribers(&self, topic: &str) -> EventResult<Vec<SocketAddr>> {
+        Ok(self.subscribers_of(topic))
+    }
+}
+
+/// Bounded memory of recently handled event ids.
+#[derive(Debug, Default)]
+struct RecentEvents {
+    inner: Mutex<(VecDeque<Uuid>, HashSet<Uuid>)>,
+}
+
+impl RecentEvents {
+    fn contains(&self, event_id: Uuid) -> bool {
+        self.lock().1.contains(&event_id)
+    }
+
+    /// Records `event_id`, returning `false` when it was already recorded.
+    fn insert(&self, event_id: Uuid) -> bool {
+        let mut guard = self.lock();
+        let (order, ids) = &mut *guard;
+        if !ids.insert(event_id) {
+            return false;
+        }
+        order.push_back(event_id);
+        if order.len() > RECENT_EVENTS
+            && let Some(oldest) = order.pop_front()
+        {
+            ids.remove(&oldest);
+        }
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, (VecDeque<Uuid>, HashSet<Uuid>)> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Reliable sends started by [`EventPublisher::publish`].
+#[derive(Debug)]
+pub struct PublishReceipt {
+    event_id: Uuid,
+    deliveries: Vec<(SocketAddr, JoinHandle<DeliveryResult<u32>>)>,
+}
+
+impl PublishReceipt {
+    /// Returns the id of the published event.
+    #[must_use]
+    pub const fn event_id(&self) -> Uuid {
+        self.event_id
+    }
+
+    /// Returns the addresses the event was sent to.
+    #[must_use]
+    pub fn targets(&self) -> Vec<SocketAddr> {
+        self.deliveries.iter().map(|(target, _)| *target).collect()
+    }
+
+    /// Waits for every delivery and returns how many were acknowledged.
+    /// Failed deliveries are reported to the sender's delivery observer.
+    pub async fn delivered(self) -> usize {
+        let mut delivered = 0;
+        for (target, delivery) in self.deliveries {
+            match delivery.await {
+                Ok(Ok(_)) => delivered += 1,
+                Ok(Err(err)) => debug!(%target, %err, "event delivery failed"),
+                Err(err) => warn!(%target, ?err, "event delivery task failed"),
+            }
+        }
+        delivered
+    }
+}
+
+/// Publishes typed events under a topic after checking policy.
+///
+/// Each publish is evaluated as [`PolicyAction::EmitEvent`] with the topic as
+/// the event type, then sent with a [`ReliableSender`] to every target the
+/// [`SubscriberDirectory`] returns, so delivery is at-least-once.
+#[derive(Clone)]
+pub struct EventPublisher {
+    agent_id: AgentId,
+    sender: ReliableSender,
+    directory: Arc<dyn SubscriberDirectory>,
+    policy: Option<Arc<dyn PolicyEngine>>,
+    policy_observer: Option<Arc<dyn PolicyObserver>>,
+}
+
+impl fmt::Debug for EventPublisher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventPublisher")
+            .field("agent_id", &self.agent_id)
+            .field("sender", &self.sender)
+            .field("policy_configured", &self.policy.is_some())
+            .field("observer_configured", &self.policy_observer.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventPublisher {
+    /// Creates a publisher for `agent_id` without policy checks.
+    #[must_use]
+    pub fn new(
+        agent_id: AgentId,
+        sender: ReliableSender,
+        directory: Arc<dyn SubscriberDirectory>,
+    ) -> Self {
+        Self {
+            agent_id,
+            sender,
+            directory,
+            policy: None,
+            policy_observer: None,
+        }
+    }
+
+    /// Checks every publish against `policy`.
+    #[must_use]
+    pub fn with_policy(mut self, policy: Arc<dyn PolicyEngine>) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Reports publish decisions to `observer`, e.g. an
+    /// [`MxpAuditObserver`](crate::MxpAuditObserver).
+    #[must_use]
+    pub fn with_policy_observer(mut self, observer: Arc<dyn PolicyObserver>) -> Self {
+        self.policy_observer = Some(observer);
+        self
+    }
+
+    /// Publishes `data` under `topic`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventError::InvalidTopic`] for malformed topics,
+    /// [`EventError::Denied`] or [`EventError::Escalated`] when policy
+    /// blocks the event, [`EventError::Encode`] when `data` cannot be
+    /// serialized, and [`EventError::Directory`] when subscribers cannot be
+    /// looked up.
+    pub async fn publish<T>(&self, topic: &str, data: &T) -> EventResult<PublishReceipt>
+    where
+        T: Serialize + ?Sized,
+    {
+        validate_topic(topic, false)?;
+        self.enforce_policy(topic).await?;
+
+        let event = TopicEvent {
+            event_id: Uuid::new_v4(),
+            topic: topic.to_owned(),
+            publisher: self.agent_id,
+            data: serde_json::to_value(data).map_err(|err| EventError::Encode {
+                topic: topic.to_owned(),
+                reason: err.to_string(),
+            })?,
+        };
+        let targets = self.directory.subscribers(topic).await?;
+        debug!(
+            topic,
+            event_id = %event.event_id,
+            targets = targets.len(),
+            "publishing event"
+        );
+
+        let deliveries = targets
+            .into_iter()
+            .map(|target| (target, self.sender.send(event.to_message(), target)))
+            .collect();
+        Ok(PublishReceipt {
+            event_id: event.event_id,
+            deliveries,
+        })
+    }
+
+    async fn enforce_policy(&self, topic: &str) -> EventResult<()> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+
+        let request = PolicyRequest::new(
+            self.agent_id,
+            PolicyAction::EmitEvent {
+                event_type: topic.to_owned(),
+            },
+        )
+        .with_metadata("topic", Value::from(topic))
+        .with_tag(format!("topic:{topic}"));
+        let decision = policy
+            .evaluate(&request)
+            .await
+            .map_err(|err| EventError::Policy(err.to_string()))?;
+        if let Some(observer) = &self.policy_observer {
+            observer.on_decision(&request, &decision, &request.action().label());
+        }
+
+        match decision.kind() {
+            DecisionKind::Allow => Ok(()),
+            DecisionKind::Deny => Err(EventError::Denied {
+                topic: topic.to_owned(),
+                reason: decision
+                    .reason()
+                    .unwrap_or("policy denied the request")
+                    .to_owned(),
+            }),
+            DecisionKind::Escalate => Err(EventError::Escalated {
+                topic: topic.to_owned(),
+                reason: decision
+                    .reason()
+                    .unwrap_or("policy escalation required")
+                    .to_owned(),
+                approvers: decision.required_approvals().to_vec(),
+            }),
+        }
+    }
+}
+
+/// Message handler for a broker agent that fans events out to subscribers.
+///
+/// Subscribers register with [`SubscriptionRequest`] messages. The broker
+/// acknowledges each request and event, drops events it has already
+/// forwarded, and sends new ones reliably to every matching subscriber other
+/// than the publisher.
+#[derive(Debug)]
+pub struct EventBroker {
+    subscriptions: Arc<SubscriptionTable>,
+    sender: ReliableSender,
+    seen: RecentEvents,
+}
+
+impl EventBroker {
+    /// Creates a broker that forwards events through `sender`.
+    #[must_use]
+    pub fn new(sender: ReliableSender) -> Self {
+        Self {
+            subscriptions: Arc::new(SubscriptionTable::new()),
+            sender,
+            seen: RecentEvents::default(),
+        }
+    }
+
+    /// Returns the broker's subscription table.
+    #[must_use]
+    pub fn subscriptions(&self) -> &Arc<SubscriptionTable> {
+        &self.subscriptions
+    }
+
+    fn forward(&self, event: &TopicEvent, publisher: SocketAddr) {
+        if !self.seen.insert(event.event_id) {
+            debug!(event_id = %event.event_id, "dropping duplicate event");
+            return;
+        }
+        for subscriber in self.subscriptions.subscribers_of(&event.topic) {
+            if subscriber != publisher {
+                drop(self.sender.send(event.to_message(), subscriber));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AgentMessageHandler for EventBroker {
+    async fn handle_event(&self, ctx: HandlerContext) -> HandlerResult {
+        let Some(peer) = ctx.sender() else {
+            return Err(HandlerError::custom(
+                "event broker requires the sender address",
+            ));
+        };
+        let payload = ctx.message().payload();
+
+        if let Some(event) = TopicEvent::from_payload(payload) {
+            self.forward(&event, peer);
+        } else if let Some(request) = SubscriptionRequest::from_payload(payload) {
+            match request {
+                SubscriptionRequest::Subscribe { topics } => {
+                    self.subscriptions.subscribe(peer, topics);
+                }
+                SubscriptionRequest::Unsubscribe { topics } => {
+                    self.subscriptions.unsubscribe(peer, topics);
+                }
+            }
+        } else {
+            return Err(HandlerError::Unsupported(MessageType::Event));
+        }
+
+        send_ack(self.sender.transport(), ctx.message().message_id(), peer);
+        Ok(())
+    }
+
+    async fn handle_ack(&self, ctx: HandlerContext) -> HandlerResult {
+        let Some(ack) = DeliveryAck::from_payload(ctx.message().payload()) else {
+            return Err(HandlerError::Unsupported(MessageType::Ack));
+        };
+        let acked = ctx
+            .sender()
+            .is_some_and(|source| self.sender.acknowledge(source, ack.message_id()));
+        if !acked {
+            debug!(
+                message_id = ack.message_id(),
+                "ack received for event that is not awaiting delivery"
+            );
+        }
+        Ok(())
+    }
+}
+
+type TopicHandler =
+    Arc<dyn Fn(HandlerContext, TopicEvent) -> BoxFuture<'static, HandlerResult> + Send + Sync>;
+
+/// Delivers inbound topic events to typed handlers by topic filter.
+///
+/// Every handler whose filter matches runs in registration order. The event
+/// is acknowledged to its sender only after all of them succeed, so a failed
+/// handler causes the sender to retransmit; handlers should therefore be
+/// idempotent. Events already handled are acknowledged without running the
+/// handlers again. Other `Event` payloads and every other message type go to
+/// the fallback handler.
+pub struct EventRouter {
+    routes: Vec<(TopicFilter, TopicHandler)>,
+    fallback: Option<Arc<dyn AgentMessageHandler>>,
+    transport: TransportHandle,
+    seen: RecentEvents,
+}
+
+impl fmt::Debug for EventRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventRouter")
+            .field("topics", &self.topics())
+            .field("fallback", &self.fallback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventRouter {
+    /// Creates a router that acknowledges events through `transport`.
+    #[must_use]
+    pub fn new(transport: TransportHandle) -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback: None,
+            transport,
+            seen: RecentEvents::default(),
+        }
+    }
+
+    /// Sends non-topic events and all other messages to `fallback`.
+    #[must_use]
+    pub fn with_fallback<H>(mut self, fallback: Arc<H>) -> Self
+    where
+        H: AgentMessageHandler + 'static,
+    {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Runs `handler` for events matching `filter`, with the data
+    /// deserialized into `T`. The [`TopicEvent`] itself is available through
+    /// `ctx.extension::<TopicEvent>()`.
+    #[must_use]
+    pub fn with_topic<T, F, Fut>(mut self, filter: TopicFilter, handler: F) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Fn(HandlerContext, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        let erased: TopicHandler = Arc::new(move |mut ctx, event| match event.decode::<T>() {
+            Ok(data) => {
+                ctx.insert_extension(event);
+                handler(ctx, data).boxed()
+            }
+            Err(err) => futures::future::ready(Err(HandlerError::custom(err.to_string()))).boxed(),
+        });
+        self.routes.push((filter, erased));
+        self
+    }
+
+    /// Returns the filters with a registered handler.
+    #[must_use]
+    pub fn topics(&self) -> Vec<TopicFilter> {
+        let topics = self
+            .routes
+            .iter()
+            .map(|(filter, _)| filter.clone())
+            .collect::<BTreeSet<_>>();
+        topics.into_iter().collect()
+    }
+
+    /// Builds the request subscribing a broker to every routed topic.
+    #[must_use]
+    pub fn subscription(&self) -> SubscriptionRequest {
+        SubscriptionRequest::Subscribe {
+            topics: self.topics(),
+        }
+    }
+
+    fn acknowledge(&self, ctx: &HandlerContext) {
+        if let Some(sender) = ctx.sender() {
+            send_ack(&self.transport, ctx.message().message_id(), sender);
+        }
+    }
+}
+
+#[async_trait]
+impl AgentMessageHandler for EventRouter {
+    async fn handle_event(&self, ctx: HandlerContext) -> HandlerResult {
+        let Some(event) = TopicEvent::from_payload(ctx.message().payload()) else {
+            return match &self.fallback {
+                Some(fallback) => fallback.handle_event(ctx).await,
+                None => Err(HandlerError::Unsupported(MessageType::Event)),
+            };
+        };
+
+        if self.seen.contains(event.event_id) {
+            debug!(event_id = %event.event_id, "acknowledging duplicate event");
+            self.acknowledge(&ctx);
+            return Ok(());
+        }
+
+        let handlers = self
+            .routes
+            .iter()
+            .filter(|(filter, _)| filter.matches(&event.topic))
+            .map(|(_, handler)| Arc::clone(handler))
+            .collect::<Vec<_>>();
+        if handlers.is_empty() {
+            debug!(topic = event.topic, "no handler for event topic");
+        }
+        for handler in handlers {
+            handler(ctx.clone(), event.clone()).await?;
+        }
+
+        self.seen.insert(event.event_id);
+        self.acknowledge(&ctx);
+        Ok(())
+    }
+
+    async fn handle_unhandled(
+        &self,
+        ctx: HandlerContext,
+        message_type: MessageType,
+    ) -> HandlerResult {
+        match &self.fallback {
+            Some(fallback) => crate::mxp_handlers::dispatch_message(fallback.as_ref(), ctx).await,
+            None => Err(HandlerError::Unsupported(message_type)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use agent_policy::{PolicyDecision, PolicyRule, RuleBasedEngine, RuleMatcher};
+    use mxp::transport::{SocketError, Transport, TransportConfig};
+
+    use crate::DeliveryConfig;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct OrderCreated {
+        id: u32,
+    }
+
+    fn bind() -> Option<TransportHandle> {
+        let transport = Transport::new(TransportConfig {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..TransportConfig::default()
+        });
+        match transport.bind("127.0.0.1:0".parse().unwrap()) {
+            Ok(handle) => Some(handle),
+            Err(SocketError::Io(err)) if err.kind() == ErrorKind::PermissionDenied => None,
+            Err(err) => panic!("bind: {err:?}"),
+        }
+    }
+
+    // Long enough that nothing is retransmitted while the test runs.
+    fn sender(transport: &TransportHandle) -> ReliableSender {
+        ReliableSender::new(
+            transport.clone(),
+            DeliveryConfig::new().with_initial_backoff(Duration::from_secs(5)),
+        )
+    }
+
+    async fn receive(transport: &TransportHandle) -> (Message, SocketAddr) {
+        let transport = transport.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut buffer = transport.acquire_buffer();
+            let (len, from) = transport.receive(&mut buffer).unwrap();
+            let message = Message::decode(buffer.as_slice()[..len].to_vec()).unwrap();
+            (message, from)
+        })
+        .await
+        .unwrap()
+    }
+
+    fn ctx(message: Message, sender: SocketAddr) -> HandlerContext {
+        HandlerContext::from_message(AgentId::random(), message).with_sender(sender)
+    }
+
+    #[test]
+    fn topic_filters_match_by_segment() {
+        let exact = TopicFilter::new("orders.created").unwrap();
+        let below = TopicFilter::new("orders.*").unwrap();
+        let all = TopicFilter::new("*").unwrap();
+
+        assert!(exact.matches("orders.created"));
+        assert!(!exact.matches("orders.created.eu"));
+        assert!(below.matches("orders.created"));
+        assert!(below.matches("orders.eu.created"));
+        assert!(!below.matches("orders"));
+        assert!(!below.matches("ordersx.created"));
+        assert!(all.matches("anything"));
+        for invalid in ["", "orders.", "or*ders", "*.created", "orders..created"] {
+            assert!(TopicFilter::new(invalid).is_err(), "{invalid}");
+        }
+        assert!(validate_topic("orders.*", false).is_err());
+    }
+
+    #[tokio::test]
+    async fn publish_checks_emit_event_policy() {
+        let Some(local) = bind() else {
+            eprintln!("skipping event test: bind requires elevated privileges");
+            return;
+        };
+        let engine = RuleBasedEngine::new(PolicyDecision::allow());
+        engine.add_rule(
+            PolicyRule::new(
+                "no-payroll",
+                RuleMatcher::for_event("payroll.updated"),
+                PolicyDecision::deny("payroll events are restricted"),
+            )
+            .unwrap(),
+        );
+        let publisher = EventPublisher::new(
+            AgentId::random(),
+            sender(&local),
+            Arc::new(SubscriptionTable::new()),
+        )
+        .with_policy(Arc::new(engine));
+
+        let err = publisher
+            .publish("payroll.updated", &serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            EventError::Denied {
+                topic: "payroll.updated".into(),
+                reason: "payroll events are restricted".into(),
+            }
+        );
+        let receipt = publisher
+            .publish("orders.created", &serde_json::json!({"id": 1}))
+            .await
+            .unwrap();
+        assert!(receipt.targets().is_empty());
+    }
+
+    #[tokio::test]
+    async fn broker_fans_out_with_acks_and_dedup() {
+        let (Some(publisher_socket), Some(broker_socket), Some(subscriber_socket)) =
+            (bind(), bind(), bind())
+        else {
+            eprintln!("skipping event test: bind requires elevated privileges");
+            return;
+        };
+        let publisher_addr = publisher_socket.local_addr().unwrap();
+        let broker_addr = broker_socket.local_addr().unwrap();
+        let subscriber_addr = subscriber_socket.local_addr().unwrap();
+
+        let broker = EventBroker::new(sender(&broker_socket));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let router = {
+            let received = Arc::clone(&received);
+            EventRouter::new(subscriber_socket.clone()).with_topic(
+                TopicFilter::new("orders.*").unwrap(),
+                move |ctx: HandlerContext, order: OrderCreated| {
+                    let received = Arc::clone(&received);
+                    async move {
+                        let topic = ctx.extension::<TopicEvent>().unwrap().topic().to_owned();
+                        received.lock().unwrap().push((topic, order));
+                        Ok(())
+                    }
+                },
+            )
+        };
+
+        broker
+            .handle_event(ctx(router.subscription().to_message(), subscriber_addr))
+            .await
+            .unwrap();
+        assert_eq!(
+            broker.subscriptions().subscribers_of("orders.created"),
+            [subscriber_addr]
+        );
+        let (ack, _) = receive(&subscriber_socket).await;
+        assert_eq!(ack.message_type(), Some(MessageType::Ack));
+
+        let publisher_sender = sender(&publisher_socket);
+        let publisher = EventPublisher::new(
+            AgentId::random(),
+            publisher_sender.clone(),
+            Arc::new(BrokerDirectory::new(broker_addr)),
+        );
+        let receipt = publisher
+            .publish("orders.created", &serde_json::json!({"id": 7}))
+            .await
+            .unwrap();
+
+        // Broker receives the event, acks the publisher, and forwards it.
+        let (inbound, from) = receive(&broker_socket).await;
+        assert_eq!(from, publisher_addr);
+        broker.handle_event(ctx(inbound, from)).await.unwrap();
+        let (ack, ack_from) = receive(&publisher_socket).await;
+        let ack = DeliveryAck::from_payload(ack.payload()).unwrap();
+        assert!(publisher_sender.acknowledge(ack_from, ack.message_id()));
+        assert_eq!(receipt.delivered().await, 1);
+
+        // Subscriber handles the event once, even when it is redelivered.
+        let (forwarded, from) = receive(&subscriber_socket).await;
+        assert_eq!(from, broker_addr);
+        for _ in 0..2 {
+            router
+                .handle_event(ctx(forwarded.clone(), broker_addr))
+                .await
+                .unwrap();
+            let (ack, _) = receive(&broker_socket).await;
+            broker.handle_ack(ctx(ack, subscriber_addr)).await.unwrap();
+        }
+        assert_eq!(
+            *received.lock().unwrap(),
+            [("orders.created".to_owned(), OrderCreated { id: 7 })]
+        );
+    }
+}
@@ -0,0 +1,649 @@
+//! Fragmentation and reassembly of MXP messages that exceed one datagram.
+//!
+//! An oversized message is encoded once and the encoded bytes are split across
+//! fragment messages of the same [`MessageType`]. Each fragment payload starts
+//! with a fixed header:
+//!
+//! | bytes | field                                     |
+//! |-------|-------------------------------------------|
+//! | 4     | magic `MXPF`                              |
+//! | 8     | original message id (little endian)       |
+//! | 2     | fragment index                            |
+//! | 2     | fragment count                            |
+//! | 4     | encoded length of the original message    |
+//! | 4     | CRC-32 of the encoded original message    |
+//!
+//! The receiver concatenates the fragments, verifies length and checksum, and
+//! decodes the original message with its id, trace id, and flags intact.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use mxp::{Message, TransportHandle};
+use thiserror::Error;
+use tracing::debug;
+
+const MAGIC: &[u8; 4] = b"MXPF";
+const HEADER_LEN: usize = 24;
+const DEFAULT_MAX_DATAGRAM_SIZE: NonZeroUsize = NonZeroUsize::new(16 * 1024).unwrap();
+const DEFAULT_MAX_MESSAGE_SIZE: NonZeroUsize = NonZeroUsize::new(4 * 1024 * 1024).unwrap();
+const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_PENDING: NonZeroUsize = NonZeroUsize::new(64).unwrap();
+
+/// Errors produced while fragmenting or reassembling messages.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum FragmentError {
+    /// The message exceeds the configured per-message size limit.
+    #[error("message of {size} bytes exceeds the {limit} byte limit")]
+    TooLarge {
+        /// Encoded size of the message.
+        size: usize,
+        /// Configured limit.
+        limit: usize,
+    },
+    /// Splitting the message would need more fragments than the header can number.
+    #[error("message would need {count} fragments")]
+    TooManyFragments {
+        /// Number of fragments required.
+        count: usize,
+    },
+    /// The datagram size leaves no room for fragment data.
+    #[error("datagram size {size} is too small to carry fragments")]
+    DatagramTooSmall {
+        /// Configured datagram size.
+        size: usize,
+    },
+    /// A fragment could not be parsed.
+    #[error("malformed fragment: {reason}")]
+    Malformed {
+        /// What was wrong with the fragment.
+        reason: &'static str,
+    },
+    /// Fragments of one message disagree with each other.
+    #[error("fragments of message {message_id} are inconsistent")]
+    Inconsistent {
+        /// Id of the original message.
+        message_id: u64,
+    },
+    /// The reassembled bytes failed the integrity check.
+    #[error("checksum mismatch reassembling message {message_id}")]
+    ChecksumMismatch {
+        /// Id of the original message.
+        message_id: u64,
+    },
+    /// The reassembled bytes are not a valid MXP message.
+    #[error("failed to decode reassembled message {message_id}: {reason}")]
+    Decode {
+        /// Id of the original message.
+        message_id: u64,
+        /// Decoder error.
+        reason: String,
+    },
+    /// Too many messages are partially received.
+    #[error("too many incomplete messages (limit {limit})")]
+    PendingLimit {
+        /// Configured limit.
+        limit: usize,
+    },
+    /// A datagram could not be sent.
+    #[error("transport error: {0}")]
+    Transport(String),
+}
+
+/// Result alias for fragmentation operations.
+pub type FragmentResult<T> = Result<T, FragmentError>;
+
+/// Size limits and timeouts for fragmentation and reassembly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentConfig {
+    max_datagram_size: NonZeroUsize,
+    max_message_size: NonZeroUsize,
+    reassembly_timeout: Duration,
+    max_pending: NonZeroUsize,
+}
+
+impl FragmentConfig {
+    /// Creates the default configuration: 16 KiB datagrams, 4 MiB messages,
+    /// a 30 second reassembly timeout, and at most 64 incomplete messages.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            reassembly_timeout: DEFAULT_REASSEMBLY_TIMEOUT,
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+
+    /// Sets the largest datagram to send, which should match the peer's
+    /// transport buffer size.
+    #[must_use]
+    pub const fn with_max_datagram_size(mut self, size: NonZeroUsize) -> Self {
+        self.max_datagram_size = size;
+        self
+    }
+
+    /// Sets the largest encoded message that may be sent or reassembled.
+    #[must_use]
+    pub const fn with_max_message_size(mut self, size: NonZeroUsize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Sets how long an incomplete message is kept before it is discarded.
+    #[must_use]
+    pub const fn with_reassembly_timeout(mut self, timeout: Duration) -> Self {
+        self.reassembly_timeout = timeout;
+        self
+    }
+
+    /// Sets how many incomplete messages may be held at once.
+    #[must_use]
+    pub const fn with_max_pending(mut self, max_pending: NonZeroUsize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Returns the largest datagram to send.
+    #[must_use]
+    pub const fn max_datagram_size(&self) -> NonZeroUsize {
+        self.max_datagram_size
+    }
+
+    /// Returns the largest encoded message size.
+    #[must_use]
+    pub const fn max_message_size(&self) -> NonZeroUsize {
+        self.max_message_size
+    }
+
+    /// Returns how long incomplete messages are kept.
+    #[must_use]
+    pub const fn reassembly_timeout(&self) -> Duration {
+        self.reassembly_timeout
+    }
+
+    /// Returns how many incomplete messages may be held at once.
+    #[must_use]
+    pub const fn max_pending(&self) -> NonZeroUsize {
+        self.max_pending
+    }
+}
+
+impl Default for FragmentConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits oversized messages into datagram-sized fragments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fragmenter {
+    config: FragmentConfig,
+}
+
+impl Fragmenter {
+    /// Creates a fragmenter with the supplied limits.
+    #[must_use]
+    pub const fn new(config: FragmentConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns the configured limits.
+    #[must_use]
+    pub const fn config(&self) -> FragmentConfig {
+        self.config
+    }
+
+    /// Encodes `message` into one datagram, or into several fragments when it
+    /// does not fit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FragmentError`] when the message exceeds the size limit or
+    /// cannot be split with the configured datagram size.
+    pub fn split(&self, message: &Message) -> FragmentResult<Vec<Vec<u8>>> {
+        let encoded = message.encode();
+        let max_datagram = self.config.max_datagram_size.get();
+        if encoded.len() <= max_datagram {
+            return Ok(vec![encoded]);
+        }
+
+        let limit = self.config.max_message_size.get();
+        if encoded.len() > limit {
+            return Err(FragmentError::TooLarge {
+                size: encoded.len(),
+                limit,
+            });
+        }
+        let kind = message.message_type().ok_or(FragmentError::Malformed {
+            reason: "message has no type",
+        })?;
+
+        let overhead = Message::new(kind, [0; HEADER_LEN]).encode().len();
+        let chunk_size = max_datagram
+            .checked_sub(overhead)
+            .filter(|size| *size > 0)
+            .ok_or(FragmentError::DatagramTooSmall { size: max_datagram })?;
+        let count = encoded.len().div_ceil(chunk_size);
+        let too_many = FragmentError::TooManyFragments { count };
+        let total = u16::try_from(count).map_err(|_| too_many.clone())?;
+        let total_len = u32::try_from(encoded.len()).map_err(|_| too_many)?;
+
+        let header = FragmentHeader {
+            message_id: message.message_id(),
+            index: 0,
+            count: total,
+            total_len,
+            checksum: crc32(&encoded),
+        };
+        Ok(encoded
+            .chunks(chunk_size)
+            .zip(0..)
+            .map(|(chunk, index)| {
+                let mut payload = Vec::with_capacity(HEADER_LEN + chunk.len());
+                FragmentHeader { index, ..header }.write(&mut payload);
+                payload.extend_from_slice(chunk);
+                Message::new(kind, payload).encode()
+            })
+            .collect())
+    }
+
+    /// Sends `message` to `target`, fragmenting it when necessary. Returns the
+    /// number of datagrams sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FragmentError`] when the message cannot be split or a
+    /// datagram cannot be sent.
+    pub fn send(
+        &self,
+        transport: &TransportHandle,
+        message: &Message,
+        target: SocketAddr,
+    ) -> FragmentResult<usize> {
+        let datagrams = self.split(message)?;
+        send_datagrams(transport, &datagrams, target)?;
+        Ok(datagrams.len())
+    }
+}
+
+/// Sends pre-split datagrams to `target` in order.
+pub(crate) fn send_datagrams(
+    transport: &TransportHandle,
+    datagrams: &[Vec<u8>],
+    target: SocketAddr,
+) -> FragmentResult<()> {
+    for datagram in datagrams {
+        transport
+            .send(datagram, target)
+            .map_err(|err| FragmentError::Transport(format!("{err:?}")))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FragmentHeader {
+    message_id: u64,
+    index: u16,
+    count: u16,
+    total_len: u32,
+    checksum: u32,
+}
+
+impl FragmentHeader {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.message_id.to_le_bytes());
+        out.extend_from_slice(&self.index.to_le_bytes());
+        out.extend_from_slice(&self.count.to_le_bytes());
+        out.extend_from_slice(&self.total_len.to_le_bytes());
+        out.extend_from_slice(&self.checksum.to_le_bytes());
+    }
+
+    /// Parses a fragment payload, returning `None` for ordinary messages.
+    fn parse(payload: &[u8]) -> Option<FragmentResult<(Self, &[u8])>> {
+        let rest = payload.strip_prefix(MAGIC)?;
+        let Some((header, data)) = rest.split_first_chunk::<{ HEADER_LEN - 4 }>() else {
+            return Some(Err(FragmentError::Malformed {
+                reason: "truncated header",
+            }));
+        };
+        let (id, header) = header.split_at(8);
+        let (index, header) = header.split_at(2);
+        let (count, header) = header.split_at(2);
+        let (total_len, checksum) = header.split_at(4);
+        let header = Self {
+            message_id: u64::from_le_bytes(id.try_into().ok()?),
+            index: u16::from_le_bytes(index.try_into().ok()?),
+            count: u16::from_le_bytes(count.try_into().ok()?),
+            total_len: u32::from_le_bytes(total_len.try_into().ok()?),
+            checksum: u32::from_le_bytes(checksum.try_into().ok()?),
+        };
+        if header.index >= header.count {
+            return Some(Err(FragmentError::Malformed {
+                reason: "fragment index out of range",
+            }));
+        }
+        Some(Ok((header, data)))
+    }
+
+    fn same_message(&self, other: &Self) -> bool {
+        self.count == other.count
+            && self.total_len == other.total_len
+            && self.checksum == other.checksum
+    }
+}
+
+struct Partial {
+    header: FragmentHeader,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    bytes: usize,
+    started: Instant,
+}
+
+type PartialKey = (Option<SocketAddr>, u64);
+
+/// Collects fragments and yields the original messages once complete.
+#[derive(Default)]
+pub struct Reassembler {
+    config: FragmentConfig,
+    partial: Mutex<HashMap<PartialKey, Partial>>,
+}
+
+impl fmt::Debug for Reassembler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reassembler")
+            .field("config", &self.config)
+            .field("pending", &self.pending())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reassembler {
+    /// Creates a reassembler with the supplied limits.
+    #[must_use]
+    pub fn new(config: FragmentConfig) -> Self {
+        Self {
+            config,
+            partial: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the configured limits.
+    #[must_use]
+    pub const fn config(&self) -> FragmentConfig {
+        self.config
+    }
+
+    /// Returns the number of partially received messages.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Offers a received message. Ordinary messages are returned unchanged;
+    /// fragments are held until the last one arrives, at which point the
+    /// original message is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FragmentError`] when a fragment is malformed, disagrees with
+    /// earlier fragments, exceeds the size limit, or the reassembled message
+    /// fails its integrity check. The partial message is discarded.
+    pub fn accept(
+        &self,
+        sender: Option<SocketAddr>,
+        message: Message,
+    ) -> FragmentResult<Option<Message>> {
+        let Some(parsed) = FragmentHeader::parse(message.payload()) else {
+            return Ok(Some(message));
+        };
+        let (header, data) = parsed?;
+        let limit = self.config.max_message_size.get();
+        let size = header.total_len as usize;
+        if size > limit {
+            return Err(FragmentError::TooLarge { size, limit });
+        }
+
+        let mut partial = self.lock();
+        self.expire_locked(&mut partial);
+        let key = (sender, header.message_id);
+        if !partial.contains_key(&key) && partial.len() >= self.config.max_pending.get() {
+            return Err(FragmentError::PendingLimit {
+                limit: self.config.max_pending.get(),
+            });
+        }
+        let entry = partial.entry(key).or_insert_with(|| Partial {
+            header,
+            chunks: vec![None; usize::from(header.count)],
+            received: 0,
+            bytes: 0,
+            started: Instant::now(),
+        });
+
+        // Check the header against the first fragment before indexing, since
+        // a conflicting `count` can put `index` past the end of `chunks`.
+        let slot = usize::from(header.index);
+        let duplicate = entry.chunks.get(slot).is_some_and(Option::is_some);
+        let consistent = entry.header.same_message(&header)
+            && slot < entry.chunks.len()
+            && (duplicate || entry.bytes + data.len() <= size);
+        if !consistent {
+            partial.remove(&key);
+            return Err(FragmentError::Inconsistent {
+                message_id: header.message_id,
+            });
+        }
+        if !duplicate {
+            entry.chunks[slot] = Some(data.to_vec());
+            entry.received += 1;
+            entry.bytes += data.len();
+        }
+        if entry.received < entry.chunks.len() {
+            return Ok(None);
+        }
+
+        let chunks = partial
+            .remove(&key)
+            .map(|entry| entry.chunks)
+            .unwrap_or_default();
+        drop(partial);
+        let encoded: Vec<u8> = chunks.into_iter().flatten().flatten().collect();
+        if encoded.len() != size || crc32(&encoded) != header.checksum {
+            return Err(FragmentError::ChecksumMismatch {
+                message_id: header.message_id,
+            });
+        }
+        Message::decode(encoded)
+            .map(Some)
+            .map_err(|err| FragmentError::Decode {
+                message_id: header.message_id,
+                reason: format!("{err:?}"),
+            })
+    }
+
+    /// Discards incomplete messages older than the reassembly timeout and
+    /// returns how many were dropped.
+    pub fn expire(&self) -> usize {
+        let mut partial = self.lock();
+        self.expire_locked(&mut partial)
+    }
+
+    fn expire_locked(&self, partial: &mut HashMap<PartialKey, Partial>) -> usize {
+        let before = partial.len();
+        let timeout = self.config.reassembly_timeout;
+        partial.retain(|(sender, message_id), entry| {
+            let keep = entry.started.elapsed() < timeout;
+            if !keep {
+                debug!(
+                    ?sender,
+                    message_id,
+                    received = entry.received,
+                    count = entry.chunks.len(),
+                    "discarding incomplete fragmented message"
+                );
+            }
+            keep
+        });
+        before - partial.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PartialKey, Partial>> {
+        self.partial.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+    let mut i = 0_u32;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3) of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(u32::MAX, |crc, byte| {
+        CRC_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mxp::MessageType;
+
+    fn config() -> FragmentConfig {
+        FragmentConfig::new().with_max_datagram_size(NonZeroUsize::new(256).unwrap())
+    }
+
+    fn large_call() -> Message {
+        let payload = (0..2_000_u32)
+            .map(|i| char::from(b'a' + u8::try_from(i % 26).unwrap()))
+            .collect::<String>();
+        Message::new(MessageType::Call, payload)
+    }
+
+    fn fragments(message: &Message) -> Vec<Message> {
+        Fragmenter::new(config())
+            .split(message)
+            .unwrap()
+            .into_iter()
+            .map(|datagram| {
+                assert!(datagram.len() <= 256);
+                Message::decode(datagram).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn small_messages_pass_through() {
+        let message = Message::new(MessageType::Call, b"{}");
+        let datagrams = Fragmenter::new(config()).split(&message).unwrap();
+        assert_eq!(datagrams, [message.encode()]);
+
+        let reassembler = Reassembler::new(config());
+        assert_eq!(
+            reassembler.accept(None, message.clone()).unwrap(),
+            Some(message)
+        );
+    }
+
+    #[test]
+    fn reassembles_out_of_order_and_duplicate_fragments() {
+        let message = large_call();
+        let mut parts = fragments(&message);
+        assert!(parts.len() > 2);
+        parts.reverse();
+        parts.insert(1, parts[0].clone());
+
+        let reassembler = Reassembler::new(config());
+        let last = parts.pop().unwrap();
+        for part in parts {
+            assert_eq!(reassembler.accept(None, part).unwrap(), None);
+        }
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.accept(None, last).unwrap(), Some(message));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn rejects_corrupted_and_oversized_messages() {
+        let message = large_call();
+        let mut parts = fragments(&message);
+        let mut corrupted = parts[0].payload().to_vec();
+        *corrupted.last_mut().unwrap() ^= 0xFF;
+        parts[0] = Message::new(MessageType::Call, corrupted);
+
+        let reassembler = Reassembler::new(config());
+        let results = parts
+            .into_iter()
+            .map(|part| reassembler.accept(None, part))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            results.last(),
+            Some(Err(FragmentError::ChecksumMismatch { .. }))
+        ));
+
+        let small = config().with_max_message_size(NonZeroUsize::new(512).unwrap());
+        assert!(matches!(
+            Fragmenter::new(small).split(&message),
+            Err(FragmentError::TooLarge { limit: 512, .. })
+        ));
+        let strict = Reassembler::new(small);
+        assert!(matches!(
+            strict.accept(None, fragments(&message).remove(0)),
+            Err(FragmentError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_fragment_with_conflicting_count() {
+        let first = fragments(&large_call()).remove(0);
+        let (mut header, data) = FragmentHeader::parse(first.payload()).unwrap().unwrap();
+        let reassembler = Reassembler::new(config());
+        assert_eq!(reassembler.accept(None, first.clone()).unwrap(), None);
+
+        header.count += 10;
+        header.index = header.count - 1;
+        let mut forged = Vec::new();
+        header.write(&mut forged);
+        forged.extend_from_slice(data);
+        assert!(matches!(
+            reassembler.accept(None, Message::new(first.message_type().unwrap(), forged)),
+            Err(FragmentError::Inconsistent { .. })
+        ));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn expires_incomplete_messages() {
+        let reassembler = Reassembler::new(config().with_reassembly_timeout(Duration::ZERO));
+        let first = fragments(&large_call()).remove(0);
+        assert_eq!(reassembler.accept(None, first).unwrap(), None);
+        assert_eq!(reassembler.expire(), 1);
+        assert_eq!(reassembler.pending(), 0);
+    }
+}
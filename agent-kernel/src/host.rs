@@ -0,0 +1,690 @@
+//! Hosting several agent kernels behind one MXP endpoint.
+
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use agent_primitives::{AgentId, AgentManifest, CapabilityId};
+use async_trait::async_trait;
+use mxp::transport::{SocketError, TransportHandle};
+use mxp::{Message, MessageType};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::{RwLock as AsyncRwLock, watch};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::encryption::recipient;
+use crate::fragment::{FragmentConfig, Reassembler};
+use crate::registry_wire::ErrorResponse;
+use crate::signing::{is_signed, signed_body};
+use crate::{
+    AgentKernel, AgentMessageHandler, AgentState, HandlerError, HandlerResult, KernelError,
+    KernelResult, LifecycleEvent,
+};
+
+/// Payload field naming the agent a message is addressed to.
+pub const TARGET_AGENT_FIELD: &str = "target_agent";
+/// Payload field naming the capability a message requires.
+pub const TARGET_CAPABILITY_FIELD: &str = "target_capability";
+
+/// Destination of an inbound message within an [`AgentHost`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// Deliver to the agent with this id.
+    Agent(AgentId),
+    /// Deliver to an active agent advertising this capability.
+    Capability(CapabilityId),
+}
+
+impl Route {
+    /// Reads the route from the message's JSON payload: [`TARGET_AGENT_FIELD`]
+    /// takes precedence over [`TARGET_CAPABILITY_FIELD`].
+    ///
+    /// Encrypted messages and handshakes are routed to the agent named in
+    /// their header, and signed messages by the message they wrap. Neither is
+    /// authenticated here; the receiving agent's middleware checks them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError::InvalidTarget`] when a target field is present but
+    /// not a valid agent or capability id.
+    pub fn from_message(message: &Message) -> HostResult<Option<Self>> {
+        let payload = message.payload();
+        if let Some(recipient) = recipient(payload) {
+            return Ok(Some(Self::Agent(recipient)));
+        }
+        if is_signed(payload) {
+            return signed_body(payload).map_or(Ok(None), |inner| Self::from_message(&inner));
+        }
+        let Ok(Value::Object(payload)) = serde_json::from_slice::<Value>(message.payload()) else {
+            return Ok(None);
+        };
+        if let Some(target) = payload.get(TARGET_AGENT_FIELD) {
+            let id = target
+                .as_str()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| HostError::InvalidTarget(target.to_string()))?;
+            return Ok(Some(Self::Agent(id)));
+        }
+        if let Some(target) = payload.get(TARGET_CAPABILITY_FIELD) {
+            let id = target
+                .as_str()
+                .and_then(|id| CapabilityId::new(id).ok())
+                .ok_or_else(|| HostError::InvalidTarget(target.to_string()))?;
+            return Ok(Some(Self::Capability(id)));
+        }
+        Ok(None)
+    }
+}
+
+/// Errors produced by [`AgentHost`].
+#[derive(Debug, Error)]
+pub enum HostError {
+    /// An agent with the same id is already hosted.
+    #[error("agent {0} is already hosted")]
+    AlreadyHosted(AgentId),
+    /// The manifest and kernel describe different agents.
+    #[error("manifest for agent {manifest} does not match kernel {kernel}")]
+    ManifestMismatch {
+        /// Id in the manifest.
+        manifest: AgentId,
+        /// Id of the kernel.
+        kernel: AgentId,
+    },
+    /// No hosted agent has the requested id.
+    #[error("agent {0} is not hosted")]
+    UnknownAgent(AgentId),
+    /// No active hosted agent advertises the requested capability.
+    #[error("no active agent offers capability {}", .0.as_str())]
+    NoCapability(CapabilityId),
+    /// The message names no target and the host has no default agent.
+    #[error("message does not name a target agent or capability")]
+    NoRoute,
+    /// A target field could not be parsed.
+    #[error("invalid message target {0}")]
+    InvalidTarget(String),
+    /// A lifecycle transition of a hosted kernel failed.
+    #[error(transparent)]
+    Kernel(#[from] KernelError),
+    /// The hosted agent failed to handle the message.
+    #[error(transparent)]
+    Handler(#[from] HandlerError),
+}
+
+impl HostError {
+    /// Returns a machine-readable code for the error.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::AlreadyHosted(_) => "already_hosted",
+            Self::ManifestMismatch { .. } => "manifest_mismatch",
+            Self::UnknownAgent(_) => "unknown_agent",
+            Self::NoCapability(_) => "no_capability",
+            Self::NoRoute => "no_route",
+            Self::InvalidTarget(_) => "invalid_target",
+            Self::Kernel(_) => "kernel_error",
+            Self::Handler(err) => err.code(),
+        }
+    }
+
+    /// Builds an MXP `Error` message carrying an [`ErrorResponse`] payload,
+    /// suitable as a reply to the message that failed.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        if let Self::Handler(err) = self {
+            return err.to_message();
+        }
+        let response = ErrorResponse {
+            error: self.to_string(),
+            code: self.code().to_owned(),
+        };
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Message::new(MessageType::Error, payload)
+    }
+}
+
+/// Result alias for host operations.
+pub type HostResult<T> = Result<T, HostError>;
+
+/// Type-erased kernel so agents with different handlers share one host.
+#[async_trait]
+trait Hosted: Send + Sync {
+    async fn handle(&self, sender: Option<SocketAddr>, message: Message) -> HandlerResult;
+    async fn transition(&self, event: LifecycleEvent) -> KernelResult<AgentState>;
+}
+
+struct HostedKernel<H>
+where
+    H: AgentMessageHandler + 'static,
+{
+    kernel: AsyncRwLock<AgentKernel<H>>,
+}
+
+#[async_trait]
+impl<H> Hosted for HostedKernel<H>
+where
+    H: AgentMessageHandler + 'static,
+{
+    async fn handle(&self, sender: Option<SocketAddr>, message: Message) -> HandlerResult {
+        let kernel = self.kernel.read().await;
+        match sender {
+            Some(sender) => kernel.handle_message_from(sender, message).await,
+            None => kernel.handle_message(message).await,
+        }
+    }
+
+    async fn transition(&self, event: LifecycleEvent) -> KernelResult<AgentState> {
+        self.kernel.write().await.transition(event).await
+    }
+}
+
+struct HostedAgent {
+    manifest: AgentManifest,
+    state: watch::Receiver<AgentState>,
+    kernel: Arc<dyn Hosted>,
+}
+
+impl HostedAgent {
+    fn state(&self) -> AgentState {
+        *self.state.borrow()
+    }
+
+    fn offers(&self, capability: &CapabilityId) -> bool {
+        self.manifest
+            .capabilities()
+            .iter()
+            .any(|offered| offered.id() == capability)
+    }
+}
+
+/// Runs several [`AgentKernel`]s in one process and routes inbound messages
+/// to them by target agent id or capability.
+///
+/// Each kernel keeps its own scheduler, memory, and policy; the host only
+/// owns the routing table, so agents can be added and retired while it
+/// serves.
+#[derive(Default)]
+pub struct AgentHost {
+    agents: RwLock<Vec<HostedAgent>>,
+    default_agent: RwLock<Option<AgentId>>,
+    next: AtomicUsize,
+    reassembler: Reassembler,
+    stopped: AtomicBool,
+}
+
+impl fmt::Debug for AgentHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentHost")
+            .field("agents", &self.agents())
+            .field("default_agent", &self.default_agent())
+            .field("reassembler", &self.reassembler)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentHost {
+    /// Creates an empty host.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the limits used to reassemble fragmented messages before routing.
+    #[must_use]
+    pub fn with_fragment_config(mut self, config: FragmentConfig) -> Self {
+        self.reassembler = Reassembler::new(config);
+        self
+    }
+
+    /// Adds a kernel to the host, booting and activating it if it has not
+    /// been started yet. The manifest's capabilities are used for routing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError::AlreadyHosted`] or [`HostError::ManifestMismatch`]
+    /// when the agent cannot be added, and [`HostError::Kernel`] when it fails
+    /// to start.
+    pub async fn register<H>(
+        &self,
+        manifest: AgentManifest,
+        mut kernel: AgentKernel<H>,
+    ) -> HostResult<()>
+    where
+        H: AgentMessageHandler + 'static,
+    {
+        let agent_id = kernel.agent_id();
+        if manifest.id() != agent_id {
+            return Err(HostError::ManifestMismatch {
+                manifest: manifest.id(),
+                kernel: agent_id,
+            });
+        }
+        if self.contains(agent_id) {
+            return Err(HostError::AlreadyHosted(agent_id));
+        }
+
+        if kernel.state() == AgentState::Init {
+            kernel.transition(LifecycleEvent::Boot).await?;
+        }
+        if kernel.state() == AgentState::Ready {
+            kernel.transition(LifecycleEvent::Activate).await?;
+        }
+
+        let hosted = HostedAgent {
+            state: kernel.subscribe_state(),
+            kernel: Arc::new(HostedKernel {
+                kernel: AsyncRwLock::new(kernel),
+            }),
+            manifest,
+        };
+        let mut agents = self.agents.write().unwrap_or_else(PoisonError::into_inner);
+        if agents.iter().any(|agent| agent.manifest.id() == agent_id) {
+            return Err(HostError::AlreadyHosted(agent_id));
+        }
+        agents.push(hosted);
+        info!(%agent_id, "agent hosted");
+        Ok(())
+    }
+
+    /// Stops routing to the agent, then retires it and terminates it once its
+    /// scheduler has drained.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError::UnknownAgent`] when the agent is not hosted and
+    /// [`HostError::Kernel`] when a lifecycle transition fails; the agent is
+    /// removed from the host either way.
+    pub async fn retire(&self, agent_id: AgentId) -> HostResult<()> {
+        let hosted = {
+            let mut agents = self.agents.write().unwrap_or_else(PoisonError::into_inner);
+            let index = agents
+                .iter()
+                .position(|agent| agent.manifest.id() == agent_id)
+                .ok_or(HostError::UnknownAgent(agent_id))?;
+            agents.remove(index)
+        };
+        self.default_agent
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_if(|default| *default == agent_id);
+
+        if matches!(
+            hosted.state(),
+            AgentState::Ready | AgentState::Active | AgentState::Suspended
+        ) {
+            hosted.kernel.transition(LifecycleEvent::Retire).await?;
+        }
+        hosted.kernel.transition(LifecycleEvent::Terminate).await?;
+        info!(%agent_id, "agent retired from host");
+        Ok(())
+    }
+
+    /// Applies a lifecycle event to a hosted agent, for example to suspend or
+    /// resume it. Messages arriving while the agent is not active follow its
+    /// kernel's suspension policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError::UnknownAgent`] when the agent is not hosted and
+    /// [`HostError::Kernel`] when the transition fails.
+    pub async fn transition(
+        &self,
+        agent_id: AgentId,
+        event: LifecycleEvent,
+    ) -> HostResult<AgentState> {
+        let kernel = self
+            .read()
+            .iter()
+            .find(|agent| agent.manifest.id() == agent_id)
+            .map(|agent| Arc::clone(&agent.kernel))
+            .ok_or(HostError::UnknownAgent(agent_id))?;
+        Ok(kernel.transition(event).await?)
+    }
+
+    /// Retires every hosted agent, returning the first error encountered.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`retire`](Self::retire).
+    pub async fn retire_all(&self) -> HostResult<()> {
+        let mut result = Ok(());
+        for agent_id in self.agents() {
+            if let Err(err) = self.retire(agent_id).await {
+                warn!(%agent_id, %err, "failed to retire hosted agent");
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        result
+    }
+
+    /// Routes messages that name no target to `agent_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError::UnknownAgent`] when the agent is not hosted.
+    pub fn set_default_agent(&self, agent_id: AgentId) -> HostResult<()> {
+        if !self.contains(agent_id) {
+            return Err(HostError::UnknownAgent(agent_id));
+        }
+        *self
+            .default_agent
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(agent_id);
+        Ok(())
+    }
+
+    /// Returns the agent receiving untargeted messages, if any.
+    #[must_use]
+    pub fn default_agent(&self) -> Option<AgentId> {
+        *self
+            .default_agent
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the hosted agent ids in registration order.
+    #[must_use]
+    pub fn agents(&self) -> Vec<AgentId> {
+        self.read()
+            .iter()
+            .map(|agent| agent.manifest.id())
+            .collect()
+    }
+
+    /// Returns `true` when the agent is hosted.
+    #[must_use]
+    pub fn contains(&self, agent_id: AgentId) -> bool {
+        self.read()
+            .iter()
+            .any(|agent| agent.manifest.id() == agent_id)
+    }
+
+    /// Returns the lifecycle state of a hosted agent.
+    #[must_use]
+    pub fn state(&self, agent_id: AgentId) -> Option<AgentState> {
+        self.read()
+            .iter()
+            .find(|agent| agent.manifest.id() == agent_id)
+            .map(HostedAgent::state)
+    }
+
+    /// Resolves the agent a message should be delivered to. Capability
+    /// routes rotate between active agents offering the capability.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError`] when the target is invalid or no hosted agent
+    /// matches it.
+    pub fn route(&self, message: &Message) -> HostResult<AgentId> {
+        self.resolve(message).map(|(agent_id, _)| agent_id)
+    }
+
+    fn resolve(&self, message: &Message) -> HostResult<(AgentId, Arc<dyn Hosted>)> {
+        let route = Route::from_message(message)?;
+        let agents = self.read();
+        let found = match &route {
+            Some(Route::Agent(agent_id)) => agents
+                .iter()
+                .find(|agent| agent.manifest.id() == *agent_id)
+                .ok_or(HostError::UnknownAgent(*agent_id))?,
+            Some(Route::Capability(capability)) => {
+                let candidates = agents
+                    .iter()
+                    .filter(|agent| agent.state() == AgentState::Active && agent.offers(capability))
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    return Err(HostError::NoCapability(capability.clone()));
+                }
+                candidates[self.next.fetch_add(1, Ordering::Relaxed) % candidates.len()]
+            }
+            None => {
+                let agent_id = self.default_agent().ok_or(HostError::NoRoute)?;
+                agents
+                    .iter()
+                    .find(|agent| agent.manifest.id() == agent_id)
+                    .ok_or(HostError::UnknownAgent(agent_id))?
+            }
+        };
+        Ok((found.manifest.id(), Arc::clone(&found.kernel)))
+    }
+
+    /// Reassembles, routes, and handles an inbound message. Returns the id of
+    /// the agent that handled it, or `None` while fragments are outstanding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostError`] when the message cannot be routed or the agent's
+    /// handler fails.
+    pub async fn dispatch(
+        &self,
+        sender: Option<SocketAddr>,
+        message: Message,
+    ) -> HostResult<Option<AgentId>> {
+        let Some(message) = self
+            .reassembler
+            .accept(sender, message)
+            .map_err(HandlerError::Reassembly)?
+        else {
+            return Ok(None);
+        };
+        let (agent_id, kernel) = self.resolve(&message)?;
+        kernel.handle(sender, message).await?;
+        Ok(Some(agent_id))
+    }
+
+    /// Receives messages on `transport` and dispatches each on its own task,
+    /// replying to the sender with an MXP `Error` when dispatch fails. The
+    /// loop exits after [`shutdown`](Self::shutdown) once the next receive
+    /// returns or times out, so configure a transport read timeout.
+    pub fn serve(self: &Arc<Self>, transport: TransportHandle) -> JoinHandle<()> {
+        let host = Arc::clone(self);
+        let runtime = tokio::runtime::Handle::current();
+        self.stopped.store(false, Ordering::Release);
+        tokio::task::spawn_blocking(move || {
+            while !host.stopped.load(Ordering::Acquire) {
+                let mut buffer = transport.acquire_buffer();
+                let peer = match transport.receive(&mut buffer) {
+                    Ok((_len, peer)) => peer,
+                    Err(SocketError::Io(err))
+                        if matches!(
+                            err.kind(),
+                            ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!(?err, "agent host receive failed; stopping");
+                        break;
+                    }
+                };
+                let message = match Message::decode(buffer.as_slice().to_vec()) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        debug!(?err, %peer, "dropping undecodable datagram");
+                        continue;
+                    }
+                };
+
+                let host = Arc::clone(&host);
+                let transport = transport.clone();
+                runtime.spawn(async move {
+                    if let Err(err) = host.dispatch(Some(peer), message).await {
+                        debug!(%peer, %err, "hosted dispatch failed");
+                        if let Err(err) = transport.send(&err.to_message().encode(), peer) {
+                            warn!(?err, %peer, "failed to send error reply");
+                        }
+                    }
+                });
+            }
+        })
+    }
+
+    /// Stops the [`serve`](Self::serve) loop. Hosted agents keep running.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<HostedAgent>> {
+        self.agents.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    use agent_primitives::Capability;
+    use serde_json::json;
+
+    use crate::{AgentIdentity, HandlerContext, TaskScheduler};
+
+    #[derive(Default)]
+    struct CountingHandler {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl AgentMessageHandler for CountingHandler {
+        async fn handle_call(&self, _ctx: HandlerContext) -> HandlerResult {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn hosted(
+        capability: &str,
+    ) -> (
+        AgentManifest,
+        AgentKernel<CountingHandler>,
+        Arc<CountingHandler>,
+    ) {
+        let agent_id = AgentId::random();
+        let capability = Capability::builder(CapabilityId::new(capability).unwrap())
+            .name("Test")
+            .unwrap()
+            .version("1.0.0")
+            .unwrap()
+            .add_scope("read:test")
+            .unwrap()
+            .build()
+            .unwrap();
+        let manifest = AgentManifest::builder(agent_id)
+            .name("hosted-agent")
+            .unwrap()
+            .version("0.0.1")
+            .unwrap()
+            .capabilities(vec![capability])
+            .build()
+            .unwrap();
+        let handler = Arc::new(CountingHandler::default());
+        let kernel = AgentKernel::new(agent_id, Arc::clone(&handler), TaskScheduler::default());
+        (manifest, kernel, handler)
+    }
+
+    fn call(payload: &Value) -> Message {
+        Message::new(MessageType::Call, serde_json::to_vec(payload).unwrap())
+    }
+
+    #[tokio::test]
+    async fn routes_by_agent_id_and_capability() {
+        let host = AgentHost::new();
+        let (review_manifest, review_kernel, review) = hosted("code.review");
+        let (debug_manifest, debug_kernel, debug) = hosted("debug.assist");
+        let review_id = review_manifest.id();
+        let debug_id = debug_manifest.id();
+        host.register(review_manifest, review_kernel).await.unwrap();
+        host.register(debug_manifest, debug_kernel).await.unwrap();
+        assert_eq!(host.state(review_id), Some(AgentState::Active));
+
+        let by_id = call(&json!({ TARGET_AGENT_FIELD: debug_id.to_string() }));
+        assert_eq!(host.dispatch(None, by_id).await.unwrap(), Some(debug_id));
+        let by_capability = call(&json!({ TARGET_CAPABILITY_FIELD: "code.review" }));
+        assert_eq!(
+            host.dispatch(None, by_capability).await.unwrap(),
+            Some(review_id)
+        );
+        assert_eq!(review.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(debug.calls.load(Ordering::SeqCst), 1);
+
+        let untargeted = call(&json!({ "input": "hi" }));
+        assert!(matches!(host.route(&untargeted), Err(HostError::NoRoute)));
+        host.set_default_agent(review_id).unwrap();
+        assert_eq!(host.route(&untargeted).unwrap(), review_id);
+
+        let missing = call(&json!({ TARGET_CAPABILITY_FIELD: "data.export" }));
+        let err = host.dispatch(None, missing).await.unwrap_err();
+        assert_eq!(err.code(), "no_capability");
+        assert_eq!(err.to_message().message_type(), Some(MessageType::Error));
+    }
+
+    #[tokio::test]
+    async fn routes_signed_and_encrypted_messages() {
+        let host = AgentHost::new();
+        let (review_manifest, review_kernel, _) = hosted("code.review");
+        let (debug_manifest, debug_kernel, _) = hosted("debug.assist");
+        let review_id = review_manifest.id();
+        let debug_id = debug_manifest.id();
+        host.register(review_manifest, review_kernel).await.unwrap();
+        host.register(debug_manifest, debug_kernel).await.unwrap();
+        host.set_default_agent(review_id).unwrap();
+
+        let identity = AgentIdentity::generate(AgentId::random()).unwrap();
+        let signed = identity
+            .sign(&call(&json!({ TARGET_CAPABILITY_FIELD: "debug.assist" })))
+            .unwrap();
+        assert_eq!(host.route(&signed).unwrap(), debug_id);
+
+        let mut sealed = b"MXPE".to_vec();
+        sealed.extend_from_slice(identity.agent_id().as_uuid().as_bytes());
+        sealed.extend_from_slice(debug_id.as_uuid().as_bytes());
+        sealed.resize(80, 0);
+        let sealed = Message::new(MessageType::Call, sealed);
+        assert_eq!(host.route(&sealed).unwrap(), debug_id);
+        assert_eq!(
+            host.route(&identity.sign(&sealed).unwrap()).unwrap(),
+            debug_id
+        );
+    }
+
+    #[tokio::test]
+    async fn registers_and_retires_at_runtime() {
+        let host = AgentHost::new();
+        let (manifest, kernel, handler) = hosted("code.review");
+        let agent_id = manifest.id();
+        let duplicate = manifest.clone();
+        host.register(manifest, kernel).await.unwrap();
+        let (_, other_kernel, _) = hosted("code.review");
+        assert!(matches!(
+            host.register(duplicate, other_kernel).await,
+            Err(HostError::ManifestMismatch { .. })
+        ));
+
+        host.transition(agent_id, LifecycleEvent::Suspend)
+            .await
+            .unwrap();
+        let message = call(&json!({ TARGET_AGENT_FIELD: agent_id.to_string() }));
+        assert!(matches!(
+            host.dispatch(None, message.clone()).await,
+            Err(HostError::Handler(HandlerError::NotAccepting { .. }))
+        ));
+        host.transition(agent_id, LifecycleEvent::Resume)
+            .await
+            .unwrap();
+        host.dispatch(None, message.clone()).await.unwrap();
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+
+        host.retire(agent_id).await.unwrap();
+        assert!(host.agents().is_empty());
+        assert!(matches!(
+            host.dispatch(None, message).await,
+            Err(HostError::UnknownAgent(id)) if id == agent_id
+        ));
+    }
+}
@@ -0,0 +1,984 @@
+//! Asynchronous job mode for long-running calls.
+//!
+//! A `Call` whose payload sets `"job": true` is acknowledged straight away
+//! with a `job_accepted` response carrying a job id, and keeps executing on
+//! the scheduler. The caller then polls it with `job_status` events, cancels
+//! it with `job_cancel`, and, when the call also set `"stream": true`,
+//! receives `job_progress` events as tool steps complete and model output
+//! streams in. Finished jobs keep their result in a [`JobStore`] for the
+//! configured retention period.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use agent_primitives::AgentId;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use mxp::{Message, MessageType};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use thiserror::Error;
+use tokio::fs;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::call::CallOutcome;
+use crate::dedup::CallKey;
+use crate::router::ResponseSink;
+use crate::{HandlerContext, HandlerError, HandlerResult};
+
+const DEFAULT_RETENTION: Duration = Duration::from_hours(1);
+const DEFAULT_PURGE_INTERVAL: Duration = Duration::from_mins(5);
+
+/// Errors produced by job tracking.
+#[derive(Debug, Error)]
+pub enum JobError {
+    /// No job with this id exists, or its retention period has passed.
+    #[error("unknown job {0}")]
+    Unknown(Uuid),
+    /// The job was started by a different caller.
+    #[error("job {0} belongs to another caller")]
+    Forbidden(Uuid),
+    /// The job already finished.
+    #[error("job {job_id} already {status}")]
+    Finished {
+        /// Job id.
+        job_id: Uuid,
+        /// Final status.
+        status: JobStatus,
+    },
+    /// Reading or writing the backing store failed.
+    #[error("job store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A stored job could not be encoded or decoded.
+    #[error("job store serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Result alias for job operations.
+pub type JobResult<T> = Result<T, JobError>;
+
+/// Lifecycle of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// The call is executing.
+    Running,
+    /// The call finished and its result is available.
+    Succeeded,
+    /// The call failed.
+    Failed,
+    /// The call was cancelled.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Returns `true` once the job can no longer change.
+    #[must_use]
+    pub const fn is_finished(self) -> bool {
+        !matches!(self, Self::Running)
+    }
+
+    /// Returns a stable lowercase name.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Result of a successful job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobOutput {
+    response: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    reasoning: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_results: Vec<JobToolResult>,
+}
+
+impl JobOutput {
+    fn from_outcome(outcome: &CallOutcome) -> Self {
+        Self {
+            response: outcome.response().to_owned(),
+            reasoning: outcome.reasoning().to_owned(),
+            tool_results: outcome
+                .tool_results()
+                .iter()
+                .map(|result| JobToolResult {
+                    name: result.name.clone(),
+                    output: result.output.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the model response.
+    #[must_use]
+    pub fn response(&self) -> &str {
+        &self.response
+    }
+
+    /// Returns the model reasoning, empty when none was produced.
+    #[must_use]
+    pub fn reasoning(&self) -> &str {
+        &self.reasoning
+    }
+
+    /// Returns the tool outputs.
+    #[must_use]
+    pub fn tool_results(&self) -> &[JobToolResult] {
+        &self.tool_results
+    }
+}
+
+/// Output of one tool step of a job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobToolResult {
+    /// Tool name.
+    pub name: String,
+    /// Tool output.
+    pub output: Value,
+}
+
+/// Progress made by a running job.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobProgress {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    output: String,
+}
+
+impl JobProgress {
+    /// Returns the tools that have completed, in order.
+    #[must_use]
+    pub fn tools(&self) -> &[String] {
+        &self.tools
+    }
+
+    /// Returns the model output streamed so far.
+    #[must_use]
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+}
+
+/// Stored state of a job.
+///
+/// Sent to callers as `{"type": "job", "job_id": ..., "status": ..., ...}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    job_id: Uuid,
+    call_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    caller: Option<SocketAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    principal: Option<AgentId>,
+    status: JobStatus,
+    submitted_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    finished_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    progress: JobProgress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<JobOutput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl JobRecord {
+    /// Returns the job id.
+    #[must_use]
+    pub const fn job_id(&self) -> Uuid {
+        self.job_id
+    }
+
+    /// Returns the MXP message id of the call that started the job.
+    #[must_use]
+    pub const fn call_id(&self) -> u64 {
+        self.call_id
+    }
+
+    /// Returns the address of the caller, if known.
+    #[must_use]
+    pub const fn caller(&self) -> Option<SocketAddr> {
+        self.caller
+    }
+
+    /// Returns the authenticated agent that started the job, if any.
+    #[must_use]
+    pub const fn principal(&self) -> Option<AgentId> {
+        self.principal
+    }
+
+    /// Returns the current status.
+    #[must_use]
+    pub const fn status(&self) -> JobStatus {
+        self.status
+    }
+
+    /// Returns when the job was submitted.
+    #[must_use]
+    pub const fn submitted_at(&self) -> DateTime<Utc> {
+        self.submitted_at
+    }
+
+    /// Returns when the job finished, if it has.
+    #[must_use]
+    pub const fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.finished_at
+    }
+
+    /// Returns when the finished job will be discarded.
+    #[must_use]
+    pub const fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at
+    }
+
+    /// Returns the progress made so far.
+    #[must_use]
+    pub const fn progress(&self) -> &JobProgress {
+        &self.progress
+    }
+
+    /// Returns the result of a successful job.
+    #[must_use]
+    pub const fn output(&self) -> Option<&JobOutput> {
+        self.output.as_ref()
+    }
+
+    /// Returns why the job failed or was cancelled.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns whether `ctx` comes from the address and authenticated agent
+    /// that started the job.
+    fn is_owned_by(&self, ctx: &HandlerContext) -> bool {
+        ctx.sender() == self.caller
+            && self
+                .principal
+                .is_none_or(|owner| ctx.principal() == Some(owner))
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Encodes the record as a `Response` message.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        let mut payload = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(map) = &mut payload {
+            map.insert("type".into(), Value::from("job"));
+        }
+        Message::new(MessageType::Response, payload.to_string().as_bytes())
+    }
+}
+
+/// Payload of an MXP `Event` message that queries or cancels a job.
+///
+/// Encoded as `{"type": "job_status", "job_id": ...}` or
+/// `{"type": "job_cancel", "job_id": ...}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobRequest {
+    /// Asks for the job's status, progress, and result.
+    JobStatus {
+        /// Job id.
+        job_id: Uuid,
+    },
+    /// Cancels the job.
+    JobCancel {
+        /// Job id.
+        job_id: Uuid,
+    },
+}
+
+impl JobRequest {
+    /// Returns the job the request targets.
+    #[must_use]
+    pub const fn job_id(&self) -> Uuid {
+        match self {
+            Self::JobStatus { job_id } | Self::JobCancel { job_id } => *job_id,
+        }
+    }
+
+    /// Decodes a job request from an event payload, returning `None` when the
+    /// payload is not a job request.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Encodes the request as an `Event` message.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        Message::new(
+            MessageType::Event,
+            serde_json::to_vec(self).unwrap_or_default(),
+        )
+    }
+}
+
+/// Persistence for job records.
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Inserts or replaces a job.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be written.
+    async fn save(&self, job: &JobRecord) -> JobResult<()>;
+
+    /// Returns the job with `job_id`, if stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be read.
+    async fn load(&self, job_id: Uuid) -> JobResult<Option<JobRecord>>;
+
+    /// Returns every stored job.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be read.
+    async fn list(&self) -> JobResult<Vec<JobRecord>>;
+
+    /// Removes a job, returning whether it was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be written.
+    async fn remove(&self, job_id: Uuid) -> JobResult<bool>;
+}
+
+/// [`JobStore`] that keeps jobs in memory.
+#[derive(Debug, Default)]
+pub struct MemoryJobStore {
+    jobs: Mutex<HashMap<Uuid, JobRecord>>,
+}
+
+impl MemoryJobStore {
+    /// Creates an empty store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, JobRecord>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryJobStore {
+    async fn save(&self, job: &JobRecord) -> JobResult<()> {
+        self.lock().insert(job.job_id, job.clone());
+        Ok(())
+    }
+
+    async fn load(&self, job_id: Uuid) -> JobResult<Option<JobRecord>> {
+        Ok(self.lock().get(&job_id).cloned())
+    }
+
+    async fn list(&self) -> JobResult<Vec<JobRecord>> {
+        Ok(self.lock().values().cloned().collect())
+    }
+
+    async fn remove(&self, job_id: Uuid) -> JobResult<bool> {
+        Ok(self.lock().remove(&job_id).is_some())
+    }
+}
+
+/// [`JobStore`] that keeps one JSON file per job in a directory.
+#[derive(Debug)]
+pub struct FileJobStore {
+    dir: PathBuf,
+}
+
+impl FileJobStore {
+    /// Uses the directory at `dir`, which is created on first write.
+    #[must_use]
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, job_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{job_id}.json"))
+    }
+}
+
+#[async_trait]
+impl JobStore for FileJobStore {
+    async fn save(&self, job: &JobRecord) -> JobResult<()> {
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.path(job.job_id);
+        // Write then rename so readers never see a partial record.
+        let staging = path.with_extension("json.tmp");
+        fs::write(&staging, serde_json::to_vec(job)?).await?;
+        fs::rename(&staging, &path).await?;
+        Ok(())
+    }
+
+    async fn load(&self, job_id: Uuid) -> JobResult<Option<JobRecord>> {
+        match fs::read(self.path(job_id)).await {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn list(&self) -> JobResult<Vec<JobRecord>> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut jobs = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                jobs.push(serde_json::from_slice(&fs::read(path).await?)?);
+            }
+        }
+        Ok(jobs)
+    }
+
+    async fn remove(&self, job_id: Uuid) -> JobResult<bool> {
+        match fs::remove_file(self.path(job_id)).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Tuning for [`JobManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobConfig {
+    retention: Duration,
+    purge_interval: Duration,
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            retention: DEFAULT_RETENTION,
+            purge_interval: DEFAULT_PURGE_INTERVAL,
+        }
+    }
+}
+
+impl JobConfig {
+    /// Creates the default configuration: finished jobs are kept for an hour
+    /// and expired ones purged at most every five minutes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how long finished jobs are kept.
+    #[must_use]
+    pub const fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Sets how often starting a job also purges expired ones.
+    #[must_use]
+    pub const fn with_purge_interval(mut self, interval: Duration) -> Self {
+        self.purge_interval = interval;
+        self
+    }
+
+    /// Returns how long finished jobs are kept.
+    #[must_use]
+    pub const fn retention(&self) -> Duration {
+        self.retention
+    }
+
+    /// Returns how often expired jobs are purged.
+    #[must_use]
+    pub const fn purge_interval(&self) -> Duration {
+        self.purge_interval
+    }
+}
+
+/// Reads the job options of a call that asks to run as a job.
+fn job_options(ctx: &HandlerContext) -> Option<JobOptions> {
+    serde_json::from_slice::<JobOptions>(ctx.message().payload())
+        .ok()
+        .filter(|options| options.job)
+}
+
+/// Returns whether the call described by `ctx` asks to run as a job.
+pub(crate) fn is_job_call(ctx: &HandlerContext) -> bool {
+    job_options(ctx).is_some()
+}
+
+#[derive(Deserialize)]
+struct JobOptions {
+    #[serde(default)]
+    job: bool,
+    #[serde(default)]
+    stream: bool,
+}
+
+struct LiveJob {
+    record: JobRecord,
+    cancel_requested: bool,
+    stream_to: Option<HandlerContext>,
+    seq: u64,
+}
+
+/// Tracks jobs started by `"job": true` calls.
+#[derive(Clone)]
+pub struct JobManager {
+    store: Arc<dyn JobStore>,
+    responses: Arc<dyn ResponseSink>,
+    config: JobConfig,
+    live: Arc<Mutex<HashMap<Uuid, LiveJob>>>,
+    last_purge: Arc<Mutex<Instant>>,
+}
+
+impl fmt::Debug for JobManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobManager")
+            .field("config", &self.config)
+            .field("running", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl JobManager {
+    /// Creates a manager that keeps jobs in `store` and answers callers
+    /// through `responses`.
+    #[must_use]
+    pub fn new(store: Arc<dyn JobStore>, responses: Arc<dyn ResponseSink>) -> Self {
+        Self {
+            store,
+            responses,
+            config: JobConfig::default(),
+            live: Arc::default(),
+            last_purge: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Replaces the configuration.
+    #[must_use]
+    pub const fn with_config(mut self, config: JobConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &JobConfig {
+        &self.config
+    }
+
+    /// Returns the job with `job_id`, including live progress while it runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError::Unknown`] when the job does not exist or has
+    /// expired, and [`JobError`] when the store cannot be read.
+    pub async fn status(&self, job_id: Uuid) -> JobResult<JobRecord> {
+        if let Some(live) = self.lock().get(&job_id) {
+            return Ok(live.record.clone());
+        }
+        match self.store.load(job_id).await? {
+            Some(record) if !record.is_expired(Utc::now()) => Ok(record),
+            _ => Err(JobError::Unknown(job_id)),
+        }
+    }
+
+    /// Removes finished jobs whose retention period has passed, returning how
+    /// many were removed.
+    ///
+    /// Starting a job also runs this once the
+    /// [`purge_interval`](JobConfig::purge_interval) has passed since the
+    /// last purge.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be read or written.
+    pub async fn purge_expired(&self) -> JobResult<usize> {
+        let now = Utc::now();
+        let mut purged = 0;
+        for record in self.store.list().await? {
+            if record.is_expired(now) && self.store.remove(record.job_id).await? {
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Fails stored jobs that are marked running but are not running in this
+    /// process, such as jobs interrupted by a restart. Returns how many were
+    /// failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`JobError`] when the store cannot be read or written.
+    pub async fn fail_interrupted(&self) -> JobResult<usize> {
+        let mut failed = 0;
+        for mut record in self.store.list().await? {
+            if record.status.is_finished() || self.lock().contains_key(&record.job_id) {
+                continue;
+            }
+            self.settle(
+                &mut record,
+                JobStatus::Failed,
+                None,
+                Some("job interrupted".into()),
+            );
+            self.store.save(&record).await?;
+            failed += 1;
+        }
+        Ok(failed)
+    }
+
+    /// Starts a job for `ctx` when its payload asks for one, acknowledging
+    /// the caller with the job id.
+    pub(crate) async fn accept(&self, ctx: &HandlerContext) -> HandlerResult<Option<JobTicket>> {
+        let Some(JobOptions { stream, .. }) = job_options(ctx) else {
+            return Ok(None);
+        };
+        self.purge_if_due().await;
+        let record = JobRecord {
+            job_id: Uuid::new_v4(),
+            call_id: ctx.message().message_id(),
+            caller: ctx.sender(),
+            principal: ctx.principal(),
+            status: JobStatus::Running,
+            submitted_at: Utc::now(),
+            finished_at: None,
+            expires_at: None,
+            progress: JobProgress::default(),
+            output: None,
+            error: None,
+        };
+        self.store
+            .save(&record)
+            .await
+            .map_err(|err| HandlerError::custom(format!("failed to store job: {err}")))?;
+        let job_id = record.job_id;
+        let ticket = self.track(record, ctx, stream);
+
+        let accepted = json!({
+            "type": "job_accepted",
+            "job_id": job_id,
+            "call_id": ctx.message().message_id(),
+        });
+        self.responses.respond(
+            ctx,
+            Message::new(MessageType::Response, accepted.to_string().as_bytes()),
+        );
+        debug!(%job_id, call_id = ctx.message().message_id(), "job accepted");
+        Ok(Some(ticket))
+    }
+
+    /// Picks up the stored job of an interrupted call again, so that the
+    /// resumed call keeps its job id and the caller gets no second
+    /// `job_accepted`. Returns `None` when no running job is stored for the
+    /// call.
+    pub(crate) async fn resume(&self, ctx: &HandlerContext) -> JobResult<Option<JobTicket>> {
+        let stream = job_options(ctx).is_some_and(|options| options.stream);
+        let call_id = ctx.message().message_id();
+        let record = self.store.list().await?.into_iter().find(|record| {
+            record.status == JobStatus::Running
+                && record.call_id == call_id
+                && record.is_owned_by(ctx)
+                && !self.lock().contains_key(&record.job_id)
+        });
+        Ok(record.map(|record| {
+            debug!(job_id = %record.job_id, call_id, "job resumed");
+            self.track(record, ctx, stream)
+        }))
+    }
+
+    fn track(&self, record: JobRecord, ctx: &HandlerContext, stream: bool) -> JobTicket {
+        let job_id = record.job_id;
+        self.lock().insert(
+            job_id,
+            LiveJob {
+                record,
+                cancel_requested: false,
+                stream_to: stream.then(|| ctx.clone()),
+                seq: 0,
+            },
+        );
+        JobTicket {
+            manager: self.clone(),
+            job_id,
+        }
+    }
+
+    async fn purge_if_due(&self) {
+        {
+            let mut last_purge = self
+                .last_purge
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if last_purge.elapsed() < self.config.purge_interval {
+                return;
+            }
+            *last_purge = Instant::now();
+        }
+        match self.purge_expired().await {
+            Ok(0) => {}
+            Ok(purged) => debug!(purged, "purged expired jobs"),
+            Err(err) => warn!(%err, "failed to purge expired jobs"),
+        }
+    }
+
+    /// Marks a running job as cancelled, returning the key of its call.
+    ///
+    /// When `requester` is set, only the address and authenticated agent
+    /// that started the job may cancel it.
+    pub(crate) fn request_cancel(
+        &self,
+        job_id: Uuid,
+        requester: Option<&HandlerContext>,
+    ) -> Option<JobResult<CallKey>> {
+        let mut live = self.lock();
+        let job = live.get_mut(&job_id)?;
+        if let Some(ctx) = requester
+            && !job.record.is_owned_by(ctx)
+        {
+            return Some(Err(JobError::Forbidden(job_id)));
+        }
+        job.cancel_requested = true;
+        Some(Ok(CallKey::new(job.record.caller, job.record.call_id)))
+    }
+
+    /// Answers a `job_status` or `job_cancel` request with the job record.
+    ///
+    /// Only the address and authenticated agent that started the job see its
+    /// record; other requesters get [`JobError::Forbidden`].
+    pub(crate) async fn reply(&self, ctx: &HandlerContext, job_id: Uuid) {
+        let status = self.status(job_id).await.and_then(|record| {
+            if record.is_owned_by(ctx) {
+                Ok(record)
+            } else {
+                Err(JobError::Forbidden(job_id))
+            }
+        });
+        let reply = match status {
+            Ok(record) => record.to_message(),
+            Err(err) => {
+                let body = json!({"type": "job_error", "job_id": job_id, "error": err.to_string()});
+                Message::new(MessageType::Error, body.to_string().as_bytes())
+            }
+        };
+        self.responses.respond(ctx, reply);
+    }
+
+    fn progress(&self, job_id: Uuid, update: impl FnOnce(&mut JobProgress) -> Value) {
+        let mut live = self.lock();
+        let Some(job) = live.get_mut(&job_id) else {
+            return;
+        };
+        let event = update(&mut job.record.progress);
+        job.seq += 1;
+        if let Some(ctx) = &job.stream_to {
+            let body =
+                json!({"type": "job_progress", "job_id": job_id, "seq": job.seq, "event": event});
+            self.responses.respond(
+                ctx,
+                Message::new(MessageType::Event, body.to_string().as_bytes()),
+            );
+        }
+    }
+
+    async fn finish(&self, job_id: Uuid, result: Result<&CallOutcome, &HandlerError>) {
+        let Some(live) = self.lock().remove(&job_id) else {
+            return;
+        };
+        let mut record = live.record;
+        match result {
+            Ok(outcome) => self.settle(
+                &mut record,
+                JobStatus::Succeeded,
+                Some(JobOutput::from_outcome(outcome)),
+                None,
+            ),
+            Err(err) => {
+                let status = if live.cancel_requested {
+                    JobStatus::Cancelled
+                } else {
+                    JobStatus::Failed
+                };
+                self.settle(&mut record, status, None, Some(err.to_string()));
+            }
+        }
+        if let Err(err) = self.store.save(&record).await {
+            warn!(%job_id, %err, "failed to store finished job");
+        }
+        debug!(%job_id, status = %record.status, "job finished");
+        if let Some(ctx) = &live.stream_to {
+            self.responses.respond(ctx, record.to_message());
+        }
+    }
+
+    fn settle(
+        &self,
+        record: &mut JobRecord,
+        status: JobStatus,
+        output: Option<JobOutput>,
+        error: Option<String>,
+    ) {
+        let now = Utc::now();
+        record.status = status;
+        record.finished_at = Some(now);
+        record.expires_at = TimeDelta::from_std(self.config.retention)
+            .ok()
+            .and_then(|retention| now.checked_add_signed(retention));
+        record.output = output;
+        record.error = error;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, LiveJob>> {
+        self.live.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Handle on an accepted job, attached to the call's context so the executor
+/// can report progress.
+#[derive(Clone)]
+pub(crate) struct JobTicket {
+    manager: JobManager,
+    job_id: Uuid,
+}
+
+impl JobTicket {
+    pub(crate) fn tool_completed(&self, tool: &str) {
+        self.manager.progress(self.job_id, |progress| {
+            progress.tools.push(tool.to_owned());
+            json!({"kind": "tool", "name": tool})
+        });
+    }
+
+    pub(crate) fn output(&self, delta: &str) {
+        if delta.is_empty() {
+            return;
+        }
+        self.manager.progress(self.job_id, |progress| {
+            progress.output.push_str(delta);
+            json!({"kind": "output", "delta": delta})
+        });
+    }
+
+    pub(crate) async fn finish(&self, result: Result<&CallOutcome, &HandlerError>) {
+        self.manager.finish(self.job_id, result).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Discard;
+
+    impl ResponseSink for Discard {
+        fn respond(&self, _ctx: &HandlerContext, _response: Message) {}
+    }
+
+    #[tokio::test]
+    async fn file_store_round_trips_and_purges_expired_jobs() {
+        let dir = std::env::temp_dir().join(format!("jobs-{}", Uuid::new_v4()));
+        let store = Arc::new(FileJobStore::new(&dir));
+        let manager = JobManager::new(store.clone(), Arc::new(Discard))
+            .with_config(JobConfig::new().with_retention(Duration::ZERO));
+
+        let mut record = JobRecord {
+            job_id: Uuid::new_v4(),
+            call_id: 7,
+            caller: None,
+            principal: None,
+            status: JobStatus::Running,
+            submitted_at: Utc::now(),
+            finished_at: None,
+            expires_at: None,
+            progress: JobProgress::default(),
+            output: None,
+            error: None,
+        };
+        store.save(&record).await.unwrap();
+        assert_eq!(
+            store.load(record.job_id).await.unwrap(),
+            Some(record.clone())
+        );
+        assert_eq!(
+            manager.status(record.job_id).await.unwrap().status(),
+            JobStatus::Running
+        );
+
+        // Nothing is running in this process, so the stored job was interrupted.
+        assert_eq!(manager.fail_interrupted().await.unwrap(), 1);
+        record = store.load(record.job_id).await.unwrap().unwrap();
+        assert_eq!(record.status(), JobStatus::Failed);
+        assert_eq!(record.error(), Some("job interrupted"));
+        assert!(matches!(
+            manager.status(record.job_id).await,
+            Err(JobError::Unknown(_))
+        ));
+        assert_eq!(manager.purge_expired().await.unwrap(), 1);
+        assert!(store.list().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn starting_a_job_purges_expired_jobs_when_due() {
+        let store = Arc::new(MemoryJobStore::new());
+        let manager = JobManager::new(store.clone(), Arc::new(Discard)).with_config(
+            JobConfig::new()
+                .with_retention(Duration::ZERO)
+                .with_purge_interval(Duration::ZERO),
+        );
+        let expired = JobRecord {
+            job_id: Uuid::new_v4(),
+            call_id: 7,
+            caller: None,
+            principal: None,
+            status: JobStatus::Succeeded,
+            submitted_at: Utc::now(),
+            finished_at: Some(Utc::now()),
+            expires_at: Some(Utc::now()),
+            progress: JobProgress::default(),
+            output: None,
+            error: None,
+        };
+        store.save(&expired).await.unwrap();
+
+        let call = Message::new(MessageType::Call, br#"{"job":true}"#);
+        let ctx = HandlerContext::from_message(AgentId::random(), call);
+        let ticket = manager.accept(&ctx).await.unwrap().unwrap();
+        let stored = store.list().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].job_id(), ticket.job_id);
+    }
+}
@@ -5,29 +5,92 @@
 
 #![warn(missing_docs, clippy::pedantic)]
 
+mod approval;
 mod call;
+mod cancellation;
+mod checkpoint;
+mod dedup;
+mod delivery;
+mod encryption;
+mod events;
+mod fragment;
+mod host;
+mod jobs;
 mod lifecycle;
+mod middleware;
 mod mxp_handlers;
 mod registry;
 mod registry_wire;
+mod retrieval;
+mod router;
 mod scheduler;
+mod scopes;
+mod session;
+mod signing;
+mod suspension;
+mod timer;
 
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use agent_memory::{MemoryBus, MemoryChannel, MemoryRecord, MemoryResult};
 use agent_primitives::{AgentId, AgentManifest};
+use bytes::Bytes;
 use mxp::Message;
-use mxp_handlers::dispatch_message;
+use serde_json::{Value, json};
 use thiserror::Error;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
-use tracing::warn;
+use tracing::{info, warn};
 
+pub use approval::{
+    ApprovalAction, ApprovalAuditEntry, ApprovalConfig, ApprovalDecision, ApprovalError,
+    ApprovalNotifier, ApprovalQuorum, ApprovalRecord, ApprovalRequest, ApprovalStatus,
+    ApprovalStore, MxpApprovalNotifier, TracingApprovalNotifier,
+};
 pub use call::{
     AuditEmitter, CallExecutor, CallOutcome, CallOutcomeSink, CollectingSink,
     CompositeAuditEmitter, CompositePolicyObserver, GovernanceAuditEmitter, KernelMessageHandler,
     KernelMessageHandlerBuilder, MxpAuditObserver, PolicyObserver, ToolInvocationResult,
     TracingAuditEmitter, TracingCallSink, TracingPolicyObserver,
 };
-pub use lifecycle::{AgentState, Lifecycle, LifecycleError, LifecycleEvent, LifecycleResult};
+pub use cancellation::{CallCancellations, CancelRequest};
+pub use checkpoint::{
+    CHECKPOINT_TAG, Checkpoint, CheckpointConfig, CheckpointStore, CompletedStep, InterruptedCall,
+    RecoveryReport,
+};
+pub use dedup::{
+    CachedCall, CallDeduplicator, CallKey, CompletedCall, DedupConfig, DedupError, DedupResult,
+    DedupStore, DuplicateCall, FileDedupStore, in_progress_ack,
+};
+pub use delivery::{
+    DeliveryAck, DeliveryConfig, DeliveryError, DeliveryFailure, DeliveryObserver, DeliveryResult,
+    ReliableSender, TracingDeliveryObserver,
+};
+pub use encryption::{
+    EncryptedPeer, EncryptionConfig, EncryptionError, EncryptionResult, SecureChannels,
+    is_encrypted,
+};
+pub use events::{
+    BrokerDirectory, EventBroker, EventError, EventPublisher, EventResult, EventRouter,
+    PublishReceipt, RegistryDirectory, SubscriberDirectory, SubscriptionRequest, SubscriptionTable,
+    TOPIC_CAPABILITY_PREFIX, TopicEvent, TopicFilter, topic_capability,
+};
+pub use fragment::{FragmentConfig, FragmentError, FragmentResult, Fragmenter, Reassembler};
+pub use host::{
+    AgentHost, HostError, HostResult, Route, TARGET_AGENT_FIELD, TARGET_CAPABILITY_FIELD,
+};
+pub use jobs::{
+    FileJobStore, JobConfig, JobError, JobManager, JobOutput, JobProgress, JobRecord, JobRequest,
+    JobResult, JobStatus, JobStore, JobToolResult, MemoryJobStore,
+};
+pub use lifecycle::{
+    AgentState, AsyncLifecycleHook, Lifecycle, LifecycleError, LifecycleEvent, LifecycleHook,
+    LifecycleResult, LifecycleTransition, LifecycleVeto,
+};
+pub use middleware::{Middleware, MiddlewareStack, Next, PayloadLimit, TracingMiddleware};
 pub use mxp_handlers::{AgentMessageHandler, HandlerContext, HandlerError, HandlerResult};
 pub use registry::{
     AgentRegistry, MxpRegistryClient, RegistrationConfig, RegistryError, RegistryResult,
@@ -36,12 +99,35 @@ pub use registry_wire::{
     AgentRecord, AgentStatus as WireAgentStatus, DiscoverRequest, DiscoverResponse, ErrorResponse,
     HeartbeatRequest, HeartbeatResponse, RegisterRequest, RegisterResponse,
 };
-pub use scheduler::{SchedulerConfig, SchedulerError, SchedulerResult, TaskScheduler};
+pub use retrieval::{RetrievalStage, RetrievedMemory};
+pub use router::{CallRouter, MxpResponseSink, ResponseSink, RpcError, RpcResponse};
+pub use scheduler::{
+    DrainReport, SchedulerConfig, SchedulerError, SchedulerResult, SchedulerStats, TaskOptions,
+    TaskPriority, TaskScheduler,
+};
+pub use scopes::{Caller, ScopeGrants, ToolScopes};
+pub use session::{SessionConfig, SessionInfo, SessionOwner, SessionStore};
+pub use signing::{
+    AgentIdentity, SignatureVerifier, SigningError, SigningResult, TrustStore, VerifiedPeer,
+    VerifierConfig, is_signed,
+};
+pub use suspension::{BufferLimits, MessageForwarder, MxpForwarder, SuspensionPolicy};
+pub use timer::{
+    CronSchedule, MissedRunPolicy, TimerError, TimerResult, Trigger, TriggerFire, TriggerSchedule,
+};
 
+use lifecycle::LifecycleHooks;
 use registry::RegistrationController;
+use suspension::{Admission, Pending, SuspensionGate};
+use timer::{TriggerDispatch, TriggerSet};
+
+/// Tag applied to the System memory records written for lifecycle transitions.
+const LIFECYCLE_TAG: &str = "lifecycle";
+
+/// Default time the kernel waits for scheduled work to finish when retiring.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Core runtime that wires lifecycle, scheduler, and MXP handlers.
-#[derive(Debug)]
 pub struct AgentKernel<H>
 where
     H: AgentMessageHandler + 'static,
@@ -51,6 +137,37 @@ where
     handler: Arc<H>,
     scheduler: TaskScheduler,
     registry: Option<RegistrationController>,
+    drain_timeout: Duration,
+    last_drain: Option<DrainReport>,
+    hooks: LifecycleHooks,
+    state: watch::Sender<AgentState>,
+    memory: Option<Arc<MemoryBus>>,
+    suspension: SuspensionGate,
+    reassembler: Reassembler,
+    middleware: MiddlewareStack,
+    triggers: TriggerSet,
+}
+
+impl<H> fmt::Debug for AgentKernel<H>
+where
+    H: AgentMessageHandler + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentKernel")
+            .field("agent_id", &self.agent_id)
+            .field("lifecycle", &self.lifecycle)
+            .field("scheduler", &self.scheduler)
+            .field("registry", &self.registry)
+            .field("drain_timeout", &self.drain_timeout)
+            .field("last_drain", &self.last_drain)
+            .field("hooks", &self.hooks)
+            .field("memory_configured", &self.memory.is_some())
+            .field("suspension", &self.suspension)
+            .field("reassembler", &self.reassembler)
+            .field("middleware", &self.middleware)
+            .field("triggers", &self.triggers)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<H> AgentKernel<H>
@@ -60,15 +177,149 @@ where
     /// Creates a new agent kernel with the provided handler and scheduler.
     #[must_use]
     pub fn new(agent_id: AgentId, handler: Arc<H>, scheduler: TaskScheduler) -> Self {
+        let lifecycle = Lifecycle::new(agent_id);
         Self {
             agent_id,
-            lifecycle: Lifecycle::new(agent_id),
+            state: watch::Sender::new(lifecycle.state()),
+            lifecycle,
             handler,
             scheduler,
             registry: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            last_drain: None,
+            hooks: LifecycleHooks::default(),
+            memory: None,
+            suspension: SuspensionGate::default(),
+            reassembler: Reassembler::default(),
+            middleware: MiddlewareStack::default(),
+            triggers: TriggerSet::default(),
         }
     }
 
+    /// Sets how messages are treated while the agent is not active.
+    pub fn set_suspension_policy(&mut self, policy: SuspensionPolicy) {
+        self.suspension.set_policy(policy);
+    }
+
+    /// Returns the suspension policy.
+    #[must_use]
+    pub const fn suspension_policy(&self) -> &SuspensionPolicy {
+        self.suspension.policy()
+    }
+
+    /// Returns the number of messages buffered until the agent becomes active.
+    #[must_use]
+    pub fn buffered_messages(&self) -> usize {
+        self.suspension.buffered_len()
+    }
+
+    /// Sets the size limits and timeout used to reassemble fragmented inbound
+    /// messages. Partially received messages are discarded.
+    pub fn set_fragment_config(&mut self, config: FragmentConfig) {
+        self.reassembler = Reassembler::new(config);
+    }
+
+    /// Returns the number of inbound messages still missing fragments.
+    #[must_use]
+    pub fn pending_fragments(&self) -> usize {
+        self.reassembler.pending()
+    }
+
+    /// Replaces the middleware layers wrapped around message dispatch.
+    pub fn set_middleware(&mut self, middleware: MiddlewareStack) {
+        self.middleware = middleware;
+    }
+
+    /// Adds a middleware layer inside the layers registered so far.
+    pub fn add_middleware<M>(&mut self, layer: Arc<M>)
+    where
+        M: Middleware + 'static,
+    {
+        self.middleware.push(layer);
+    }
+
+    /// Returns the middleware layers wrapped around message dispatch.
+    #[must_use]
+    pub const fn middleware(&self) -> &MiddlewareStack {
+        &self.middleware
+    }
+
+    /// Registers a trigger that runs its call on a schedule.
+    ///
+    /// Triggers start when the agent becomes [`AgentState::Active`], or
+    /// immediately when it already is, pause while it is suspended, and stop
+    /// once it retires.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimerError::Duplicate`] when a trigger with the same name is
+    /// already registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called on an active agent outside a Tokio runtime.
+    pub fn add_trigger(&mut self, trigger: Trigger) -> TimerResult<()> {
+        self.triggers.insert(trigger)?;
+        if self.state() == AgentState::Active {
+            self.start_triggers();
+        }
+        Ok(())
+    }
+
+    /// Stops and removes the named trigger, returning whether it existed.
+    pub fn remove_trigger(&mut self, name: &str) -> bool {
+        self.triggers.remove(name)
+    }
+
+    /// Returns the named trigger, if registered.
+    #[must_use]
+    pub fn trigger(&self, name: &str) -> Option<&Trigger> {
+        self.triggers.get(name)
+    }
+
+    /// Returns the registered triggers in registration order.
+    pub fn triggers(&self) -> impl Iterator<Item = &Trigger> {
+        self.triggers.iter()
+    }
+
+    fn start_triggers(&mut self) {
+        let handler: Arc<dyn AgentMessageHandler> = Arc::<H>::clone(&self.handler);
+        let dispatch = TriggerDispatch {
+            agent_id: self.agent_id,
+            handler,
+            middleware: self.middleware.clone(),
+            scheduler: self.scheduler.clone(),
+        };
+        self.triggers.start(&dispatch, &self.state);
+    }
+
+    /// Registers a synchronous lifecycle hook. Hooks run in registration order.
+    pub fn add_lifecycle_hook<K>(&mut self, hook: Arc<K>)
+    where
+        K: LifecycleHook + 'static,
+    {
+        self.hooks.push_sync(hook);
+    }
+
+    /// Registers an asynchronous lifecycle hook. Hooks run in registration order.
+    pub fn add_async_lifecycle_hook<K>(&mut self, hook: Arc<K>)
+    where
+        K: AsyncLifecycleHook + 'static,
+    {
+        self.hooks.push_async(hook);
+    }
+
+    /// Records each lifecycle transition as a [`MemoryChannel::System`] record.
+    pub fn set_memory(&mut self, memory: Arc<MemoryBus>) {
+        self.memory = Some(memory);
+    }
+
+    /// Returns a receiver that observes the current lifecycle state.
+    #[must_use]
+    pub fn subscribe_state(&self) -> watch::Receiver<AgentState> {
+        self.state.subscribe()
+    }
+
     /// Provides registry integration for mesh discovery and heartbeat management.
     pub fn set_registry<R>(
         &mut self,
@@ -82,6 +333,24 @@ where
         self.registry = Some(RegistrationController::new(registry, manifest, config));
     }
 
+    /// Sets how long retiring waits for scheduled work before aborting it.
+    pub fn set_drain_timeout(&mut self, timeout: Duration) {
+        self.drain_timeout = timeout;
+    }
+
+    /// Returns how long retiring waits for scheduled work before aborting it.
+    #[must_use]
+    pub const fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    /// Returns the outcome of the scheduler drain performed when the agent
+    /// retired, if it has.
+    #[must_use]
+    pub const fn last_drain(&self) -> Option<DrainReport> {
+        self.last_drain
+    }
+
     /// Returns the identifier associated with this agent.
     #[must_use]
     pub const fn agent_id(&self) -> AgentId {
@@ -96,46 +365,243 @@ where
 
     /// Applies a lifecycle event, returning the new state on success.
     ///
+    /// Entering [`AgentState::Retiring`] or [`AgentState::Terminated`] closes
+    /// the scheduler and drains it: queued and running tasks get up to
+    /// [`drain_timeout`](Self::drain_timeout) to finish before being aborted
+    /// ([`LifecycleEvent::Abort`] aborts them immediately). The outcome is
+    /// available through [`last_drain`](Self::last_drain). Triggers start on
+    /// entering [`AgentState::Active`] and stop on retiring.
+    ///
+    /// Registered hooks may veto the transition before it is applied. Once
+    /// applied, the new state is published to
+    /// [`subscribe_state`](Self::subscribe_state) receivers, recorded to
+    /// memory when configured, and passed to the hooks' after-transition
+    /// callbacks.
+    ///
     /// # Errors
     ///
     /// Returns [`LifecycleError`](LifecycleError) when the transition is
-    /// not permitted from the current state.
-    pub fn transition(&mut self, event: LifecycleEvent) -> KernelResult<AgentState> {
+    /// not permitted from the current state or a hook vetoes it, and
+    /// [`RegistryError`] when the registry hook fails (the transition is
+    /// still applied).
+    pub async fn transition(&mut self, event: LifecycleEvent) -> KernelResult<AgentState> {
+        let transition = self.lifecycle.plan(event)?;
+        if let Err(err) = self.hooks.before(&transition).await {
+            warn!(%err, "lifecycle transition vetoed");
+            return Err(err.into());
+        }
+
         let state = self.lifecycle.transition(event)?;
-        if let Some(controller) = &mut self.registry
-            && let Err(err) = controller.on_state_change(state, &self.scheduler)
-        {
+        let registry_result = match &mut self.registry {
+            Some(controller) => controller.on_state_change(state, &self.scheduler),
+            None => Ok(()),
+        };
+        if let Err(err) = &registry_result {
             warn!(?err, "registry hook failed during state transition");
-            return Err(err.into());
         }
 
+        if state == AgentState::Active {
+            self.replay_buffered();
+            self.start_triggers();
+        }
+        if matches!(state, AgentState::Retiring | AgentState::Terminated) {
+            self.triggers.stop();
+            let discarded = self.suspension.take().len();
+            if discarded > 0 {
+                warn!(discarded, "discarding messages buffered while inactive");
+            }
+            if self.last_drain.is_none() {
+                self.drain(event).await;
+            }
+        }
+
+        self.state.send_if_modified(|current| {
+            let changed = *current != state;
+            *current = state;
+            changed
+        });
+        if let Some(memory) = &self.memory
+            && let Err(err) = record_transition(memory, &transition).await
+        {
+            warn!(?err, "failed to record lifecycle transition");
+        }
+        self.hooks.after(&transition).await;
+
+        registry_result?;
         Ok(state)
     }
 
+    fn replay_buffered(&self) {
+        for pending in self.suspension.take() {
+            if let Err(err) = self.spawn_dispatch(pending.message, pending.sender, &pending.options)
+            {
+                warn!(?err, "failed to replay buffered message");
+            }
+        }
+    }
+
+    async fn drain(&mut self, event: LifecycleEvent) {
+        let timeout = if event == LifecycleEvent::Abort {
+            Duration::ZERO
+        } else {
+            self.drain_timeout
+        };
+        let report = self.scheduler.drain(timeout).await;
+        info!(
+            agent_id = %self.agent_id,
+            completed = report.completed(),
+            aborted = report.aborted(),
+            "scheduler drained"
+        );
+        self.last_drain = Some(report);
+    }
+
     /// Handles an MXP message immediately on the current task.
     ///
+    /// Messages arriving while the agent is not [`AgentState::Active`] are
+    /// handled by the [`SuspensionPolicy`]; buffered and forwarded messages
+    /// return `Ok(())`. Fragments of an oversized message also return `Ok(())`
+    /// until the last one arrives and the reassembled message is dispatched.
+    ///
     /// # Errors
     ///
-    /// Propagates any error returned by the message handler implementation.
+    /// Propagates any error returned by the message handler implementation,
+    /// returns [`HandlerError::NotAccepting`] when the message is rejected
+    /// because of the lifecycle state, and [`HandlerError::Reassembly`] when a
+    /// fragment is invalid.
     pub async fn handle_message(&self, message: Message) -> HandlerResult {
-        let ctx = HandlerContext::from_message(self.agent_id, message);
-        dispatch_message(self.handler.as_ref(), ctx).await
+        self.handle(message, None).await
+    }
+
+    /// Handles an MXP message received from `sender`, which lets handlers
+    /// key per-sender state such as call deduplication.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`handle_message`](Self::handle_message).
+    pub async fn handle_message_from(&self, sender: SocketAddr, message: Message) -> HandlerResult {
+        self.handle(message, Some(sender)).await
+    }
+
+    async fn handle(&self, message: Message, sender: Option<SocketAddr>) -> HandlerResult {
+        let Some(message) = self.reassemble(sender, message)? else {
+            return Ok(());
+        };
+        let pending = Pending {
+            message,
+            sender,
+            options: TaskOptions::default(),
+        };
+        match self.suspension.admit(self.state(), pending) {
+            Admission::Process(message) => {
+                let ctx = context(self.agent_id, message, sender);
+                self.middleware.dispatch(self.handler.as_ref(), ctx).await
+            }
+            Admission::Settled(result) => result,
+        }
     }
 
     /// Enqueues an MXP message for asynchronous processing via the scheduler.
     ///
+    /// Messages arriving while the agent is not [`AgentState::Active`] are
+    /// handled by the [`SuspensionPolicy`], and the returned handle resolves
+    /// with that outcome.
+    ///
     /// # Errors
     ///
-    /// Returns [`SchedulerError`] when the scheduler has been closed.
+    /// Returns [`SchedulerError`] when the scheduler has been closed or its
+    /// queue is full.
     pub fn schedule_message(&self, message: Message) -> SchedulerResult<JoinHandle<HandlerResult>> {
+        self.schedule_message_with(message, &TaskOptions::default())
+    }
+
+    /// Enqueues an MXP message in the priority lane and fairness queue
+    /// selected by `options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchedulerError`] when the scheduler has been closed or its
+    /// queue is full.
+    pub fn schedule_message_with(
+        &self,
+        message: Message,
+        options: &TaskOptions,
+    ) -> SchedulerResult<JoinHandle<HandlerResult>> {
+        self.schedule(message, None, options)
+    }
+
+    /// Enqueues an MXP message received from `sender`, so fragments are
+    /// reassembled and calls deduplicated per sender.
+    ///
+    /// Same as [`schedule_message_with`](Self::schedule_message_with).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchedulerError`] when the scheduler has been closed or its
+    /// queue is full.
+    pub fn schedule_message_from(
+        &self,
+        sender: SocketAddr,
+        message: Message,
+        options: &TaskOptions,
+    ) -> SchedulerResult<JoinHandle<HandlerResult>> {
+        self.schedule(message, Some(sender), options)
+    }
+
+    fn schedule(
+        &self,
+        message: Message,
+        sender: Option<SocketAddr>,
+        options: &TaskOptions,
+    ) -> SchedulerResult<JoinHandle<HandlerResult>> {
+        let message = match self.reassemble(sender, message) {
+            Ok(Some(message)) => message,
+            Ok(None) => return self.scheduler.spawn_with(options, async { Ok(()) }),
+            Err(err) => return self.scheduler.spawn_with(options, async move { Err(err) }),
+        };
+        let pending = Pending {
+            message,
+            sender,
+            options: options.clone(),
+        };
+        match self.suspension.admit(self.state(), pending) {
+            Admission::Process(message) => self.spawn_dispatch(message, sender, options),
+            Admission::Settled(result) => self.scheduler.spawn_with(options, async move { result }),
+        }
+    }
+
+    fn reassemble(
+        &self,
+        sender: Option<SocketAddr>,
+        message: Message,
+    ) -> HandlerResult<Option<Message>> {
+        self.reassembler.accept(sender, message).map_err(|err| {
+            warn!(agent_id = %self.agent_id, ?sender, %err, "dropping fragmented message");
+            HandlerError::Reassembly(err)
+        })
+    }
+
+    fn spawn_dispatch(
+        &self,
+        message: Message,
+        sender: Option<SocketAddr>,
+        options: &TaskOptions,
+    ) -> SchedulerResult<JoinHandle<HandlerResult>> {
         let handler = Arc::clone(&self.handler);
+        let middleware = self.middleware.clone();
         let agent_id = self.agent_id;
-        self.scheduler.spawn(async move {
-            let ctx = HandlerContext::from_message(agent_id, message);
-            dispatch_message(handler.as_ref(), ctx).await
+        self.scheduler.spawn_with(options, async move {
+            let ctx = context(agent_id, message, sender);
+            middleware.dispatch(handler.as_ref(), ctx).await
         })
     }
 
+    /// Returns scheduler queue depth and wait-time statistics.
+    #[must_use]
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        self.scheduler.stats()
+    }
+
     /// Returns a reference to the underlying scheduler.
     #[must_use]
     pub fn scheduler(&self) -> &TaskScheduler {
@@ -143,6 +609,34 @@ where
     }
 }
 
+fn context(agent_id: AgentId, message: Message, sender: Option<SocketAddr>) -> HandlerContext {
+    let ctx = HandlerContext::from_message(agent_id, message);
+    match sender {
+        Some(sender) => ctx.with_sender(sender),
+        None => ctx,
+    }
+}
+
+async fn record_transition(
+    memory: &MemoryBus,
+    transition: &LifecycleTransition,
+) -> MemoryResult<()> {
+    let payload = json!({
+        "agent_id": transition.agent_id().to_string(),
+        "from": transition.from().as_str(),
+        "to": transition.to().as_str(),
+        "event": transition.event().as_str(),
+    });
+    let record = MemoryRecord::builder(MemoryChannel::System, Bytes::from(payload.to_string()))
+        .tag(LIFECYCLE_TAG)?
+        .metadata("agent_id", Value::from(transition.agent_id().to_string()))
+        .metadata("from", Value::from(transition.from().as_str()))
+        .metadata("to", Value::from(transition.to().as_str()))
+        .metadata("event", Value::from(transition.event().as_str()))
+        .build()?;
+    memory.record(record).await
+}
+
 /// Errors emitted by [`AgentKernel`] operations.
 #[derive(Debug, Error)]
 pub enum KernelError {
@@ -166,6 +660,7 @@ mod tests {
     use std::time::Duration;
 
     use agent_primitives::{Capability, CapabilityId};
+    use mxp::MessageType;
 
     struct NullHandler;
 
@@ -234,16 +729,448 @@ mod tests {
         );
         kernel.set_registry(registry.clone(), manifest(), config);
 
-        kernel.transition(LifecycleEvent::Boot).unwrap();
-        kernel.transition(LifecycleEvent::Activate).unwrap();
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
 
         tokio::time::sleep(Duration::from_millis(35)).await;
         assert!(registry.registers.load(Ordering::SeqCst) >= 1);
         assert!(registry.heartbeats.load(Ordering::SeqCst) >= 1);
 
-        kernel.transition(LifecycleEvent::Retire).unwrap();
-        kernel.transition(LifecycleEvent::Terminate).unwrap();
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        kernel.transition(LifecycleEvent::Terminate).await.unwrap();
         tokio::time::sleep(Duration::from_millis(20)).await;
         assert!(registry.deregisters.load(Ordering::SeqCst) >= 1);
     }
+
+    struct NoSuspend;
+
+    impl LifecycleHook for NoSuspend {
+        fn before_transition(&self, transition: &LifecycleTransition) -> Result<(), LifecycleVeto> {
+            if transition.event() == LifecycleEvent::Suspend {
+                return Err(LifecycleVeto::new("maintenance window closed"));
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingHook {
+        seen: std::sync::Mutex<Vec<(AgentState, AgentState)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncLifecycleHook for RecordingHook {
+        async fn after_transition(&self, transition: &LifecycleTransition) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((transition.from(), transition.to()));
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_observe_veto_and_publish_transitions() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("kernel-lifecycle-{}.log", AgentId::random()));
+        let journal: Arc<dyn agent_memory::Journal> =
+            Arc::new(agent_memory::FileJournal::open(&path).await.unwrap());
+        let memory = Arc::new(
+            agent_memory::MemoryBusBuilder::new(agent_memory::VolatileConfig::default())
+                .with_journal(journal)
+                .build()
+                .unwrap(),
+        );
+        let recorder = Arc::new(RecordingHook::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::new(NullHandler),
+            TaskScheduler::default(),
+        );
+        kernel.add_lifecycle_hook(Arc::new(NoSuspend));
+        kernel.add_async_lifecycle_hook(Arc::clone(&recorder));
+        kernel.set_memory(Arc::clone(&memory));
+        let mut states = kernel.subscribe_state();
+        assert_eq!(*states.borrow(), AgentState::Init);
+
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        assert!(states.has_changed().unwrap());
+        assert_eq!(*states.borrow_and_update(), AgentState::Active);
+
+        let err = kernel
+            .transition(LifecycleEvent::Suspend)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            KernelError::Lifecycle(LifecycleError::Vetoed { ref reason, .. })
+                if reason == "maintenance window closed"
+        ));
+        assert_eq!(kernel.state(), AgentState::Active);
+        assert!(!states.has_changed().unwrap());
+
+        assert_eq!(
+            *recorder.seen.lock().unwrap(),
+            [
+                (AgentState::Init, AgentState::Ready),
+                (AgentState::Ready, AgentState::Active)
+            ]
+        );
+
+        let records = memory.recent(10).await;
+        assert_eq!(records.len(), 2);
+        let last = &records[1];
+        assert_eq!(last.channel(), &MemoryChannel::System);
+        assert_eq!(last.tags(), [LIFECYCLE_TAG]);
+        assert_eq!(last.metadata().get("to"), Some(&Value::from("active")));
+        assert_eq!(last.metadata().get("event"), Some(&Value::from("activate")));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[derive(Default)]
+    struct CountingHandler {
+        calls: AtomicUsize,
+        senders: std::sync::Mutex<Vec<Option<SocketAddr>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentMessageHandler for CountingHandler {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.senders.lock().unwrap().push(ctx.sender());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct CollectingForwarder {
+        forwarded: std::sync::Mutex<Vec<Message>>,
+    }
+
+    impl MessageForwarder for CollectingForwarder {
+        fn forward(&self, message: &Message) -> HandlerResult {
+            self.forwarded.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn call_message(payload: &str) -> Message {
+        Message::new(mxp::MessageType::Call, payload.as_bytes())
+    }
+
+    #[tokio::test]
+    async fn rejects_messages_before_activation_by_default() {
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+
+        let err = kernel.handle_message(call_message("{}")).await.unwrap_err();
+        assert_eq!(
+            err,
+            HandlerError::not_accepting(AgentState::Init, "agent is not active")
+        );
+        let reply = err.to_message();
+        assert_eq!(reply.message_type(), Some(mxp::MessageType::Error));
+        let body: ErrorResponse = serde_json::from_slice(reply.payload()).unwrap();
+        assert_eq!(body.code, "not_accepting");
+
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        kernel.handle_message(call_message("{}")).await.unwrap();
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn buffers_while_suspended_and_replays_on_resume() {
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.set_suspension_policy(SuspensionPolicy::Buffer(
+            BufferLimits::new(NonZeroUsize::new(2).unwrap())
+                .with_max_bytes(NonZeroUsize::new(64).unwrap()),
+        ));
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        kernel.transition(LifecycleEvent::Suspend).await.unwrap();
+
+        kernel
+            .schedule_message(call_message("{}"))
+            .unwrap()
+            .await
+            .unwrap()
+            .unwrap();
+        let oversized = call_message(&"x".repeat(80));
+        assert!(matches!(
+            kernel.handle_message(oversized).await,
+            Err(HandlerError::NotAccepting { .. })
+        ));
+        kernel.handle_message(call_message("{}")).await.unwrap();
+        assert!(kernel.handle_message(call_message("{}")).await.is_err());
+        assert_eq!(kernel.buffered_messages(), 2);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 0);
+
+        kernel.transition(LifecycleEvent::Resume).await.unwrap();
+        assert_eq!(kernel.buffered_messages(), 0);
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn forwards_messages_while_inactive() {
+        let forwarder = Arc::new(CollectingForwarder::default());
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.set_suspension_policy(SuspensionPolicy::Forward(forwarder.clone()));
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+
+        kernel.handle_message(call_message("ready")).await.unwrap();
+        assert_eq!(forwarder.forwarded.lock().unwrap().len(), 1);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 0);
+
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        assert!(matches!(
+            kernel.handle_message(call_message("late")).await,
+            Err(HandlerError::NotAccepting {
+                state: AgentState::Retiring,
+                ..
+            })
+        ));
+        assert_eq!(forwarder.forwarded.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn control_traffic_bypasses_the_suspension_gate() {
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.set_suspension_policy(SuspensionPolicy::Buffer(BufferLimits::new(
+            NonZeroUsize::new(4).unwrap(),
+        )));
+        let identity = AgentIdentity::generate(AgentId::random()).unwrap();
+        let cancel = CancelRequest::new(7).to_payload();
+        let control = || {
+            [
+                DeliveryAck::new(7).to_message(),
+                Message::new(MessageType::Event, cancel.clone()),
+                identity
+                    .sign(&ApprovalDecision::approve(uuid::Uuid::new_v4(), "secops").to_message())
+                    .unwrap(),
+            ]
+        };
+
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        kernel.transition(LifecycleEvent::Suspend).await.unwrap();
+        for message in control() {
+            // The handler itself, not the gate, answers.
+            assert!(matches!(
+                kernel.handle_message(message).await,
+                Err(HandlerError::Unsupported(_))
+            ));
+        }
+        kernel.handle_message(call_message("{}")).await.unwrap();
+        assert_eq!(kernel.buffered_messages(), 1);
+
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        for message in control() {
+            assert!(matches!(
+                kernel.handle_message(message).await,
+                Err(HandlerError::Unsupported(_))
+            ));
+        }
+        assert!(matches!(
+            kernel.handle_message(call_message("{}")).await,
+            Err(HandlerError::NotAccepting { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn reassembles_fragmented_messages_before_dispatch() {
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+
+        let config = FragmentConfig::new().with_max_datagram_size(NonZeroUsize::new(128).unwrap());
+        let datagrams = Fragmenter::new(config)
+            .split(&call_message(&"x".repeat(1_000)))
+            .unwrap();
+        let sender: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let (last, rest) = datagrams.split_last().unwrap();
+        for datagram in rest {
+            let fragment = Message::decode(datagram.clone()).unwrap();
+            kernel.handle_message_from(sender, fragment).await.unwrap();
+        }
+        assert_eq!(kernel.pending_fragments(), 1);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 0);
+
+        let last = Message::decode(last.clone()).unwrap();
+        kernel.handle_message_from(sender, last).await.unwrap();
+        assert_eq!(kernel.pending_fragments(), 0);
+        assert_eq!(handler.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn scheduled_messages_keep_their_sender() {
+        let handler = Arc::new(CountingHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+
+        let sender: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let options = TaskOptions::default();
+        for scheduled in [
+            kernel.schedule_message_from(sender, call_message("{}"), &options),
+            kernel.schedule_message_with(call_message("{}"), &options),
+        ] {
+            scheduled.unwrap().await.unwrap().unwrap();
+        }
+        assert_eq!(*handler.senders.lock().unwrap(), [Some(sender), None]);
+    }
+
+    #[tokio::test]
+    async fn retire_drains_scheduled_work() {
+        let scheduler = TaskScheduler::new(SchedulerConfig::new(NonZeroUsize::new(1).unwrap()));
+        let mut kernel = AgentKernel::new(AgentId::random(), Arc::new(NullHandler), scheduler);
+        kernel.set_drain_timeout(Duration::from_millis(50));
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+
+        let finished = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let finished = Arc::clone(&finished);
+            kernel
+                .scheduler()
+                .spawn(async move {
+                    finished.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+        kernel
+            .scheduler()
+            .spawn(std::future::pending::<()>())
+            .unwrap();
+
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        let report = kernel.last_drain().unwrap();
+        assert_eq!(report.completed(), 2);
+        assert_eq!(report.aborted(), 1);
+        assert_eq!(finished.load(Ordering::SeqCst), 2);
+        assert!(kernel.scheduler().is_closed());
+
+        kernel.transition(LifecycleEvent::Terminate).await.unwrap();
+        assert_eq!(kernel.last_drain(), Some(report));
+    }
+
+    #[derive(Default)]
+    struct TriggerHandler {
+        skipped: std::sync::Mutex<Vec<u64>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentMessageHandler for TriggerHandler {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            let fire = ctx.extension::<TriggerFire>().expect("trigger extension");
+            assert_eq!(fire.trigger(), "tick");
+            assert_eq!(ctx.message().payload().as_ref(), br#"{"task":"poll"}"#);
+            self.skipped.lock().unwrap().push(fire.skipped());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn triggers_pause_while_suspended_and_stop_on_retire() {
+        let handler = Arc::new(TriggerHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        let trigger = Trigger::interval("tick", Duration::from_millis(20))
+            .unwrap()
+            .with_payload(json!({"task": "poll"}));
+        kernel.add_trigger(trigger.clone()).unwrap();
+        assert_eq!(
+            kernel.add_trigger(trigger),
+            Err(TimerError::Duplicate("tick".into()))
+        );
+        let fired = || handler.skipped.lock().unwrap().len();
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(fired(), 0);
+
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        assert!(fired() >= 2);
+
+        kernel.transition(LifecycleEvent::Suspend).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let paused = fired();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(fired(), paused);
+
+        kernel.transition(LifecycleEvent::Resume).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(fired(), paused + 1);
+        assert!(handler.skipped.lock().unwrap()[paused] >= 3);
+
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+        let retired = fired();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(fired(), retired);
+        assert!(kernel.remove_trigger("tick"));
+        assert_eq!(kernel.triggers().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn triggers_skip_remote_authentication() {
+        let handler = Arc::new(TriggerHandler::default());
+        let mut kernel = AgentKernel::new(
+            AgentId::random(),
+            Arc::clone(&handler),
+            TaskScheduler::default(),
+        );
+        kernel.add_middleware(Arc::new(SignatureVerifier::new(TrustStore::new())));
+        kernel
+            .add_trigger(
+                Trigger::interval("tick", Duration::from_millis(20))
+                    .unwrap()
+                    .with_payload(json!({"task": "poll"})),
+            )
+            .unwrap();
+
+        kernel.transition(LifecycleEvent::Boot).await.unwrap();
+        kernel.transition(LifecycleEvent::Activate).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(!handler.skipped.lock().unwrap().is_empty());
+
+        let forged = kernel
+            .handle_message(Message::new(MessageType::Call, br#"{"task":"poll"}"#))
+            .await;
+        assert!(matches!(forged, Err(HandlerError::Unauthenticated(_))));
+        kernel.transition(LifecycleEvent::Retire).await.unwrap();
+    }
 }
@@ -1,6 +1,10 @@
 //! Lifecycle state machine for MXP agents.
 
+use std::fmt;
+use std::sync::Arc;
+
 use agent_primitives::AgentId;
+use async_trait::async_trait;
 use thiserror::Error;
 use tracing::debug;
 
@@ -33,6 +37,19 @@ impl AgentState {
     pub const fn is_terminal(self) -> bool {
         matches!(self, Self::Terminated)
     }
+
+    /// Returns a stable lowercase name for logs and memory records.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Init => "init",
+            Self::Ready => "ready",
+            Self::Active => "active",
+            Self::Suspended => "suspended",
+            Self::Retiring => "retiring",
+            Self::Terminated => "terminated",
+        }
+    }
 }
 
 /// Events that trigger lifecycle transitions.
@@ -54,6 +71,164 @@ pub enum LifecycleEvent {
     Abort,
 }
 
+impl LifecycleEvent {
+    /// Returns a stable lowercase name for logs and memory records.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Boot => "boot",
+            Self::Activate => "activate",
+            Self::Suspend => "suspend",
+            Self::Resume => "resume",
+            Self::Retire => "retire",
+            Self::Terminate => "terminate",
+            Self::Abort => "abort",
+        }
+    }
+}
+
+/// A lifecycle transition observed by [`LifecycleHook`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleTransition {
+    agent_id: AgentId,
+    from: AgentState,
+    to: AgentState,
+    event: LifecycleEvent,
+}
+
+impl LifecycleTransition {
+    /// Returns the agent undergoing the transition.
+    #[must_use]
+    pub const fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Returns the state before the transition.
+    #[must_use]
+    pub const fn from(&self) -> AgentState {
+        self.from
+    }
+
+    /// Returns the state after the transition.
+    #[must_use]
+    pub const fn to(&self) -> AgentState {
+        self.to
+    }
+
+    /// Returns the event that triggered the transition.
+    #[must_use]
+    pub const fn event(&self) -> LifecycleEvent {
+        self.event
+    }
+}
+
+/// Reason a hook gave for refusing a transition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifecycleVeto {
+    reason: String,
+}
+
+impl LifecycleVeto {
+    /// Creates a veto with the supplied reason.
+    #[must_use]
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns the reason for the veto.
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+/// Synchronous observer of lifecycle transitions.
+pub trait LifecycleHook: Send + Sync {
+    /// Invoked before a transition is applied; returning an error vetoes it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LifecycleVeto`] to keep the agent in its current state.
+    fn before_transition(&self, _transition: &LifecycleTransition) -> Result<(), LifecycleVeto> {
+        Ok(())
+    }
+
+    /// Invoked after a transition has been applied.
+    fn after_transition(&self, _transition: &LifecycleTransition) {}
+}
+
+/// Asynchronous observer of lifecycle transitions.
+#[async_trait]
+pub trait AsyncLifecycleHook: Send + Sync {
+    /// Invoked before a transition is applied; returning an error vetoes it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LifecycleVeto`] to keep the agent in its current state.
+    async fn before_transition(
+        &self,
+        _transition: &LifecycleTransition,
+    ) -> Result<(), LifecycleVeto> {
+        Ok(())
+    }
+
+    /// Invoked after a transition has been applied.
+    async fn after_transition(&self, _transition: &LifecycleTransition) {}
+}
+
+#[derive(Clone)]
+enum RegisteredHook {
+    Sync(Arc<dyn LifecycleHook>),
+    Async(Arc<dyn AsyncLifecycleHook>),
+}
+
+/// Hooks registered on a kernel, invoked in registration order.
+#[derive(Clone, Default)]
+pub(crate) struct LifecycleHooks {
+    hooks: Vec<RegisteredHook>,
+}
+
+impl fmt::Debug for LifecycleHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LifecycleHooks")
+            .field("count", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl LifecycleHooks {
+    pub(crate) fn push_sync(&mut self, hook: Arc<dyn LifecycleHook>) {
+        self.hooks.push(RegisteredHook::Sync(hook));
+    }
+
+    pub(crate) fn push_async(&mut self, hook: Arc<dyn AsyncLifecycleHook>) {
+        self.hooks.push(RegisteredHook::Async(hook));
+    }
+
+    /// Runs every before-transition callback, stopping at the first veto.
+    pub(crate) async fn before(&self, transition: &LifecycleTransition) -> LifecycleResult<()> {
+        for hook in &self.hooks {
+            let verdict = match hook {
+                RegisteredHook::Sync(hook) => hook.before_transition(transition),
+                RegisteredHook::Async(hook) => hook.before_transition(transition).await,
+            };
+            verdict.map_err(|veto| LifecycleError::vetoed(transition, veto))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn after(&self, transition: &LifecycleTransition) {
+        for hook in &self.hooks {
+            match hook {
+                RegisteredHook::Sync(hook) => hook.after_transition(transition),
+                RegisteredHook::Async(hook) => hook.after_transition(transition).await,
+            }
+        }
+    }
+}
+
 /// Lifecycle state manager.
 #[derive(Debug, Clone, Copy)]
 pub struct Lifecycle {
@@ -83,13 +258,13 @@ impl Lifecycle {
         self.state
     }
 
-    /// Applies a lifecycle event, returning the resulting state.
+    /// Describes the transition `event` would cause without applying it.
     ///
     /// # Errors
     ///
     /// Returns [`LifecycleError::InvalidTransition`] when the supplied event is not
     /// allowed from the current state.
-    pub fn transition(&mut self, event: LifecycleEvent) -> LifecycleResult<AgentState> {
+    pub fn plan(&self, event: LifecycleEvent) -> LifecycleResult<LifecycleTransition> {
         let next = match (self.state, event) {
             (AgentState::Init, LifecycleEvent::Boot) => Some(AgentState::Ready),
             (AgentState::Ready, LifecycleEvent::Activate)
@@ -104,7 +279,7 @@ impl Lifecycle {
             _ => None,
         };
 
-        let Some(next_state) = next else {
+        let Some(to) = next else {
             return Err(LifecycleError::InvalidTransition {
                 agent_id: self.agent_id,
                 from: self.state,
@@ -112,6 +287,23 @@ impl Lifecycle {
             });
         };
 
+        Ok(LifecycleTransition {
+            agent_id: self.agent_id,
+            from: self.state,
+            to,
+            event,
+        })
+    }
+
+    /// Applies a lifecycle event, returning the resulting state.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LifecycleError::InvalidTransition`] when the supplied event is not
+    /// allowed from the current state.
+    pub fn transition(&mut self, event: LifecycleEvent) -> LifecycleResult<AgentState> {
+        let next_state = self.plan(event)?.to;
+
         if next_state != self.state {
             debug!(
                 agent_id = %self.agent_id,
@@ -140,6 +332,31 @@ pub enum LifecycleError {
         /// Event that triggered the failure.
         event: LifecycleEvent,
     },
+    /// A lifecycle hook refused the transition.
+    #[error(
+        "lifecycle transition from {from:?} via {event:?} for agent {agent_id} vetoed: {reason}"
+    )]
+    Vetoed {
+        /// Identifier of the agent whose transition was vetoed.
+        agent_id: AgentId,
+        /// State the agent remains in.
+        from: AgentState,
+        /// Event that was refused.
+        event: LifecycleEvent,
+        /// Reason supplied by the hook.
+        reason: String,
+    },
+}
+
+impl LifecycleError {
+    pub(crate) fn vetoed(transition: &LifecycleTransition, veto: LifecycleVeto) -> Self {
+        Self::Vetoed {
+            agent_id: transition.agent_id,
+            from: transition.from,
+            event: transition.event,
+            reason: veto.reason,
+        }
+    }
 }
 
 /// Result alias used for lifecycle operations.
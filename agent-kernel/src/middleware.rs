@@ -0,0 +1,312 @@
+//! Composable layers wrapped around message dispatch.
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tracing::{debug, warn};
+
+use crate::mxp_handlers::dispatch_message;
+use crate::{AgentMessageHandler, HandlerContext, HandlerError, HandlerResult};
+
+/// Layer that runs around every dispatched message.
+///
+/// A layer may inspect or mutate the context before calling
+/// [`Next::run`], return an error instead of calling it to short-circuit
+/// the message, and inspect the result and latency afterwards.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Handles `ctx`, usually by delegating to `next`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HandlerError`] when the layer rejects the message or the
+    /// inner layers fail.
+    async fn handle(&self, ctx: HandlerContext, next: Next<'_>) -> HandlerResult;
+}
+
+/// Remaining layers and the handler, passed to each [`Middleware`].
+pub struct Next<'a> {
+    layers: &'a [Arc<dyn Middleware>],
+    handler: &'a dyn AgentMessageHandler,
+}
+
+impl fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &self.layers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Next<'_> {
+    /// Runs the remaining layers and then the handler.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors from the inner layers and the handler.
+    pub async fn run(self, ctx: HandlerContext) -> HandlerResult {
+        match self.layers.split_first() {
+            Some((layer, layers)) => {
+                let next = Next {
+                    layers,
+                    handler: self.handler,
+                };
+                layer.handle(ctx, next).await
+            }
+            None => dispatch_message(self.handler, ctx).await,
+        }
+    }
+}
+
+/// Ordered middleware layers; the first layer added is the outermost.
+#[derive(Clone, Default)]
+pub struct MiddlewareStack {
+    layers: Arc<Vec<Arc<dyn Middleware>>>,
+}
+
+impl fmt::Debug for MiddlewareStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiddlewareStack")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl MiddlewareStack {
+    /// Creates an empty stack.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer inside the layers added so far.
+    #[must_use]
+    pub fn layer<M>(mut self, layer: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        self.push(Arc::new(layer));
+        self
+    }
+
+    /// Adds a shared layer inside the layers added so far.
+    pub fn push(&mut self, layer: Arc<dyn Middleware>) {
+        Arc::make_mut(&mut self.layers).push(layer);
+    }
+
+    /// Returns the number of layers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns `true` when no layers are configured.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Runs `ctx` through every layer and then dispatches it to `handler`.
+    ///
+    /// # Errors
+    ///
+    /// Propagates errors from the layers and the handler.
+    pub async fn dispatch(
+        &self,
+        handler: &dyn AgentMessageHandler,
+        ctx: HandlerContext,
+    ) -> HandlerResult {
+        Next {
+            layers: &self.layers,
+            handler,
+        }
+        .run(ctx)
+        .await
+    }
+}
+
+/// Logs each message's type, outcome, and handling latency.
+#[derive(Debug, Default)]
+pub struct TracingMiddleware;
+
+#[async_trait]
+impl Middleware for TracingMiddleware {
+    async fn handle(&self, ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        let agent_id = ctx.agent_id();
+        let message_id = ctx.message().message_id();
+        let message_type = ctx.message().message_type();
+        let started = Instant::now();
+        let result = next.run(ctx).await;
+        let latency = started.elapsed();
+        match &result {
+            Ok(()) => debug!(%agent_id, message_id, ?message_type, ?latency, "message handled"),
+            Err(err) => warn!(
+                %agent_id,
+                message_id,
+                ?message_type,
+                ?latency,
+                code = err.code(),
+                %err,
+                "message handling failed"
+            ),
+        }
+        result
+    }
+}
+
+/// Rejects messages whose payload exceeds a size limit with
+/// [`HandlerError::PayloadTooLarge`].
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimit {
+    max_bytes: NonZeroUsize,
+}
+
+impl PayloadLimit {
+    /// Rejects payloads larger than `max_bytes`.
+    #[must_use]
+    pub const fn new(max_bytes: NonZeroUsize) -> Self {
+        Self { max_bytes }
+    }
+
+    /// Returns the configured limit.
+    #[must_use]
+    pub const fn max_bytes(&self) -> NonZeroUsize {
+        self.max_bytes
+    }
+}
+
+#[async_trait]
+impl Middleware for PayloadLimit {
+    async fn handle(&self, ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        let size = ctx.message().payload().len();
+        let limit = self.max_bytes.get();
+        if size > limit {
+            return Err(HandlerError::PayloadTooLarge { size, limit });
+        }
+        next.run(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use agent_primitives::AgentId;
+    use mxp::{Message, MessageType};
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Principal(&'static str);
+
+    struct RecordingHandler {
+        seen: Mutex<Vec<(Vec<u8>, Option<&'static str>)>>,
+    }
+
+    #[async_trait]
+    impl AgentMessageHandler for RecordingHandler {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            let principal = ctx.extension::<Principal>().map(|principal| principal.0);
+            self.seen
+                .lock()
+                .unwrap()
+                .push((ctx.message().payload().to_vec(), principal));
+            Ok(())
+        }
+    }
+
+    struct Order {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Order {
+        async fn handle(&self, ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} before", self.name));
+            let result = next.run(ctx).await;
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{} after", self.name));
+            result
+        }
+    }
+
+    struct Authenticate;
+
+    #[async_trait]
+    impl Middleware for Authenticate {
+        async fn handle(&self, mut ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+            if ctx.message().payload().starts_with(b"anon") {
+                return Err(HandlerError::custom("unauthenticated"));
+            }
+            ctx.insert_extension(Principal("alice"));
+            let upper = ctx.message().payload().to_ascii_uppercase();
+            ctx.set_message(Message::new(MessageType::Call, upper));
+            next.run(ctx).await
+        }
+    }
+
+    fn ctx(payload: &[u8]) -> HandlerContext {
+        HandlerContext::from_message(AgentId::random(), Message::new(MessageType::Call, payload))
+    }
+
+    #[tokio::test]
+    async fn layers_wrap_in_order_and_mutate_context() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let stack = MiddlewareStack::new()
+            .layer(Order {
+                name: "outer",
+                log: Arc::clone(&log),
+            })
+            .layer(Authenticate)
+            .layer(Order {
+                name: "inner",
+                log: Arc::clone(&log),
+            });
+        let handler = RecordingHandler {
+            seen: Mutex::new(Vec::new()),
+        };
+
+        stack.dispatch(&handler, ctx(b"ping")).await.unwrap();
+        assert_eq!(
+            *handler.seen.lock().unwrap(),
+            [(b"PING".to_vec(), Some("alice"))]
+        );
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["outer before", "inner before", "inner after", "outer after"]
+        );
+
+        log.lock().unwrap().clear();
+        let err = stack.dispatch(&handler, ctx(b"anon")).await.unwrap_err();
+        assert_eq!(err, HandlerError::custom("unauthenticated"));
+        assert_eq!(*log.lock().unwrap(), ["outer before", "outer after"]);
+        assert_eq!(handler.seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn payload_limit_short_circuits() {
+        let stack = MiddlewareStack::new()
+            .layer(TracingMiddleware)
+            .layer(PayloadLimit::new(NonZeroUsize::new(4).unwrap()));
+        let handler = RecordingHandler {
+            seen: Mutex::new(Vec::new()),
+        };
+
+        let err = stack
+            .dispatch(&handler, ctx(b"too long"))
+            .await
+            .unwrap_err();
+        assert_eq!(err, HandlerError::PayloadTooLarge { size: 8, limit: 4 });
+        assert_eq!(err.code(), "payload_too_large");
+        stack.dispatch(&handler, ctx(b"ok")).await.unwrap();
+        assert_eq!(handler.seen.lock().unwrap().len(), 1);
+    }
+}
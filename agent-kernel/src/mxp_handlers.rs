@@ -1,12 +1,25 @@
 //! Routing utilities for MXP protocol messages.
 
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Instant;
 
-use agent_primitives::AgentId;
+use agent_primitives::{AgentId, Scope};
 use async_trait::async_trait;
 use mxp::{Message, MessageType};
 use thiserror::Error;
+use uuid::Uuid;
+
+use crate::AgentState;
+use crate::dedup::DuplicateCall;
+use crate::encryption::{EncryptedPeer, EncryptionError};
+use crate::fragment::FragmentError;
+use crate::registry_wire::ErrorResponse;
+use crate::scopes::Caller;
+use crate::signing::{SigningError, VerifiedPeer};
 
 /// Context provided to message handlers.
 #[derive(Debug, Clone)]
@@ -14,6 +27,21 @@ pub struct HandlerContext {
     agent_id: AgentId,
     received_at: Instant,
     message: Arc<Message>,
+    sender: Option<SocketAddr>,
+    extensions: Extensions,
+}
+
+/// Typed values attached to a [`HandlerContext`], such as an authenticated
+/// principal set by middleware.
+#[derive(Clone, Default)]
+struct Extensions(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.0.len())
+            .finish()
+    }
 }
 
 impl HandlerContext {
@@ -30,9 +58,38 @@ impl HandlerContext {
             agent_id,
             received_at: Instant::now(),
             message,
+            sender: None,
+            extensions: Extensions::default(),
         }
     }
 
+    /// Records the transport address the message arrived from.
+    #[must_use]
+    pub fn with_sender(mut self, sender: SocketAddr) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Returns the transport address the message arrived from, if known.
+    #[must_use]
+    pub const fn sender(&self) -> Option<SocketAddr> {
+        self.sender
+    }
+
+    /// Returns the authenticated agent that sent the message, taken from the
+    /// [`Caller`], [`VerifiedPeer`], or [`EncryptedPeer`] extension.
+    /// Unauthenticated messages have no principal.
+    #[must_use]
+    pub fn principal(&self) -> Option<AgentId> {
+        self.extension::<Caller>()
+            .and_then(Caller::principal)
+            .or_else(|| self.extension::<VerifiedPeer>().map(VerifiedPeer::agent_id))
+            .or_else(|| {
+                self.extension::<EncryptedPeer>()
+                    .map(EncryptedPeer::agent_id)
+            })
+    }
+
     /// Returns the agent identifier.
     #[must_use]
     pub const fn agent_id(&self) -> AgentId {
@@ -51,6 +108,32 @@ impl HandlerContext {
         &self.message
     }
 
+    /// Replaces the message, for example after a middleware decodes or
+    /// rewrites the payload.
+    pub fn set_message(&mut self, message: Message) {
+        self.message = Arc::new(message);
+    }
+
+    /// Attaches a typed value, replacing any previous value of the same type.
+    pub fn insert_extension<T>(&mut self, value: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Returns the attached value of type `T`, if any.
+    #[must_use]
+    pub fn extension<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions
+            .0
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
     /// Returns the MXP message type.
     ///
     /// # Errors
@@ -65,7 +148,7 @@ impl HandlerContext {
 }
 
 /// Errors that can occur during message handling.
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum HandlerError {
     /// The message header did not contain a valid message type.
     #[error("message missing type information")]
@@ -76,6 +159,47 @@ pub enum HandlerError {
     /// Custom handler error with human-readable context.
     #[error("handler error: {0}")]
     Custom(String),
+    /// The agent is not accepting messages in its current lifecycle state.
+    #[error("agent not accepting messages while {state:?}: {reason}")]
+    NotAccepting {
+        /// Lifecycle state the message arrived in.
+        state: AgentState,
+        /// Why the message was refused.
+        reason: String,
+    },
+    /// A fragmented message could not be reassembled.
+    #[error("failed to reassemble message: {0}")]
+    Reassembly(FragmentError),
+    /// The message payload exceeds the configured limit.
+    #[error("payload of {size} bytes exceeds the {limit} byte limit")]
+    PayloadTooLarge {
+        /// Payload size in bytes.
+        size: usize,
+        /// Configured limit in bytes.
+        limit: usize,
+    },
+    /// The message is unsigned, forged, stale, or replayed.
+    #[error("unauthenticated message: {0}")]
+    Unauthenticated(SigningError),
+    /// An encrypted message or handshake was rejected, or plaintext arrived
+    /// while encryption is required.
+    #[error("encryption error: {0}")]
+    Encryption(EncryptionError),
+    /// The caller lacks a scope required by the tool it tried to run.
+    #[error("caller lacks scope `{scope}` required by tool `{tool}`")]
+    Forbidden {
+        /// Tool the caller tried to run.
+        tool: String,
+        /// First required scope the caller was not granted.
+        scope: Scope,
+    },
+    /// Policy escalated an action and the call was parked until approvers
+    /// decide the approval request.
+    #[error("call parked until approval request {0} is decided")]
+    AwaitingApproval(Uuid),
+    /// The call named a session bound to another caller.
+    #[error("session {0} belongs to another caller")]
+    SessionForbidden(String),
 }
 
 impl HandlerError {
@@ -84,6 +208,45 @@ impl HandlerError {
     pub fn custom(reason: impl Into<String>) -> Self {
         Self::Custom(reason.into())
     }
+
+    /// Creates a [`HandlerError::NotAccepting`] error.
+    #[must_use]
+    pub fn not_accepting(state: AgentState, reason: impl Into<String>) -> Self {
+        Self::NotAccepting {
+            state,
+            reason: reason.into(),
+        }
+    }
+
+    /// Returns a machine-readable code for the error.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::MissingMessageType => "missing_message_type",
+            Self::Unsupported(_) => "unsupported",
+            Self::Custom(_) => "handler_error",
+            Self::NotAccepting { .. } => "not_accepting",
+            Self::Reassembly(_) => "reassembly_failed",
+            Self::PayloadTooLarge { .. } => "payload_too_large",
+            Self::Unauthenticated(_) => "unauthenticated",
+            Self::Encryption(_) => "encryption_failed",
+            Self::Forbidden { .. } => "forbidden",
+            Self::AwaitingApproval(_) => "awaiting_approval",
+            Self::SessionForbidden(_) => "session_forbidden",
+        }
+    }
+
+    /// Builds an MXP `Error` message carrying an [`ErrorResponse`] payload,
+    /// suitable as a reply to the message that failed.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        let response = ErrorResponse {
+            error: self.to_string(),
+            code: self.code().to_owned(),
+        };
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Message::new(MessageType::Error, payload)
+    }
 }
 
 /// Result alias for handler operations.
@@ -113,6 +276,16 @@ pub trait AgentMessageHandler: Send + Sync {
         self.handle_unhandled(ctx, MessageType::Call).await
     }
 
+    /// Called instead of [`handle_call`](Self::handle_call) for a `Call`
+    /// that a [`CallDeduplicator`](crate::CallDeduplicator) recognised as a
+    /// retransmission; the [`DuplicateCall`](crate::DuplicateCall) extension
+    /// says what the original delivery produced. Ignored by default, so the
+    /// call never runs twice.
+    async fn handle_duplicate(&self, ctx: HandlerContext) -> HandlerResult {
+        let _ = ctx;
+        Ok(())
+    }
+
     /// Called for `Response` messages.
     async fn handle_response(&self, ctx: HandlerContext) -> HandlerResult {
         self.handle_unhandled(ctx, MessageType::Response).await
@@ -174,6 +347,9 @@ where
         MessageType::AgentRegister => handler.handle_agent_register(ctx).await,
         MessageType::AgentDiscover => handler.handle_agent_discover(ctx).await,
         MessageType::AgentHeartbeat => handler.handle_agent_heartbeat(ctx).await,
+        MessageType::Call if ctx.extension::<DuplicateCall>().is_some() => {
+            handler.handle_duplicate(ctx).await
+        }
         MessageType::Call => handler.handle_call(ctx).await,
         MessageType::Response => handler.handle_response(ctx).await,
         MessageType::Event => handler.handle_event(ctx).await,
@@ -9,7 +9,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-use agent_primitives::AgentManifest;
+use agent_primitives::{AgentId, AgentManifest, PublicKey};
 use async_trait::async_trait;
 use mxp::protocol::Flags;
 use mxp::transport::{SocketError, Transport, TransportConfig, TransportHandle};
@@ -19,10 +19,13 @@ use tokio::task::JoinHandle;
 use tokio::time::{MissedTickBehavior, sleep};
 use tracing::{debug, info, warn};
 
+use crate::fragment::{FragmentConfig, Fragmenter, Reassembler};
 use crate::registry_wire::{
-    ErrorResponse, HeartbeatRequest, HeartbeatResponse, RegisterRequest, RegisterResponse,
+    AgentRecord, DiscoverRequest, DiscoverResponse, ErrorResponse, HeartbeatRequest,
+    HeartbeatResponse, RegisterRequest, RegisterResponse,
 };
-use crate::{AgentState, SchedulerError, TaskScheduler};
+use crate::signing::{AgentIdentity, SignatureVerifier, SigningError, TrustStore, sign_outbound};
+use crate::{AgentState, SchedulerError, TaskOptions, TaskPriority, TaskScheduler};
 
 /// Configuration for registration and heartbeat maintenance.
 #[derive(Debug, Clone, Copy)]
@@ -152,6 +155,9 @@ pub struct MxpRegistryClient {
     handle: TransportHandle,
     registry_addr: SocketAddr,
     agent_endpoint: SocketAddr,
+    fragments: FragmentConfig,
+    identity: Option<AgentIdentity>,
+    verifier: Option<Arc<SignatureVerifier>>,
 }
 
 impl MxpRegistryClient {
@@ -174,6 +180,10 @@ impl MxpRegistryClient {
             .ok_or_else(|| RegistryError::backend("registry endpoint resolved to no address"))?;
 
         let config = transport_config.unwrap_or_else(default_transport_config);
+        let fragments = NonZeroUsize::new(config.buffer_size)
+            .map_or_else(FragmentConfig::default, |size| {
+                FragmentConfig::default().with_max_datagram_size(size)
+            });
         let transport = Transport::new(config);
         let local_bind: SocketAddr = "0.0.0.0:0".parse().map_err(|err| {
             RegistryError::backend(format!("invalid bind address configuration: {err:?}"))
@@ -186,9 +196,37 @@ impl MxpRegistryClient {
             handle,
             registry_addr,
             agent_endpoint,
+            fragments,
+            identity: None,
+            verifier: None,
         })
     }
 
+    /// Signs every registry request with `identity`.
+    #[must_use]
+    pub fn with_identity(mut self, identity: AgentIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Accepts only replies signed by `registry` with `key`.
+    ///
+    /// Unsigned, forged, and replayed replies are dropped, so discovery
+    /// records can be trusted as far as the registry is.
+    #[must_use]
+    pub fn with_registry_key(mut self, registry: AgentId, key: PublicKey) -> Self {
+        let trust = TrustStore::new();
+        trust.trust(registry, key);
+        self.verifier = Some(Arc::new(SignatureVerifier::new(trust)));
+        self
+    }
+
+    /// Returns the identity registry requests are signed with.
+    #[must_use]
+    pub const fn identity(&self) -> Option<&AgentIdentity> {
+        self.identity.as_ref()
+    }
+
     fn agent_id(manifest: &AgentManifest) -> String {
         manifest.id().to_string()
     }
@@ -205,6 +243,9 @@ impl MxpRegistryClient {
         if let Some(description) = manifest.description() {
             metadata.insert("description".to_string(), description.to_string());
         }
+        if let Some(key) = manifest.public_key() {
+            metadata.insert("public_key".to_string(), key.to_string());
+        }
         if !manifest.tags().is_empty() {
             metadata.insert(
                 "tags".to_string(),
@@ -225,22 +266,52 @@ impl MxpRegistryClient {
         handle: &TransportHandle,
         registry_addr: SocketAddr,
         message: &Message,
+        fragments: FragmentConfig,
+        identity: Option<&AgentIdentity>,
+        verifier: Option<&SignatureVerifier>,
     ) -> RegistryResult<Message> {
-        let encoded = message.encode();
         let message_id = message.message_id();
+        let outbound = sign_outbound(identity, message)
+            .map_err(|err| RegistryError::backend(format!("sign request failed: {err}")))?;
+        // A registry that does not verify signatures answers the outer id.
+        let sent_id = outbound.message_id();
 
-        handle
-            .send(&encoded, registry_addr)
-            .map_err(|err| RegistryError::backend(format!("send failed: {err:?}")))?;
+        Fragmenter::new(fragments)
+            .send(handle, &outbound, registry_addr)
+            .map_err(|err| RegistryError::backend(format!("send failed: {err}")))?;
 
+        let reassembler = Reassembler::new(fragments);
         let mut buffer = handle.acquire_buffer();
+        let mut rejected: Option<SigningError> = None;
         let response = loop {
             match handle.receive(&mut buffer) {
-                Ok((_len, _addr)) => {
+                Ok((_len, addr)) => {
                     let payload = buffer.as_slice().to_vec();
                     match Message::decode(payload) {
                         Ok(response) => {
-                            if response.message_id() == message_id {
+                            let response =
+                                reassembler.accept(Some(addr), response).map_err(|err| {
+                                    RegistryError::backend(format!(
+                                        "failed to reassemble registry response: {err}"
+                                    ))
+                                })?;
+                            let Some(response) = response else {
+                                continue;
+                            };
+                            let response = match verifier {
+                                Some(verifier) => match verifier.verify(&response) {
+                                    Ok((_, inner)) => inner,
+                                    Err(err) => {
+                                        warn!(%addr, %err, "dropping unauthenticated registry response");
+                                        rejected = Some(err);
+                                        continue;
+                                    }
+                                },
+                                None => response,
+                            };
+                            if response.message_id() == message_id
+                                || response.message_id() == sent_id
+                            {
                                 break response;
                             }
                         }
@@ -261,9 +332,12 @@ impl MxpRegistryClient {
                         debug!("registry receive interrupted; retrying");
                         continue;
                     }
-                    return Err(RegistryError::backend(
-                        "timed out waiting for registry response",
-                    ));
+                    return Err(match rejected {
+                        Some(err) => RegistryError::backend(format!(
+                            "timed out waiting for registry response; rejected unauthenticated response: {err}"
+                        )),
+                        None => RegistryError::backend("timed out waiting for registry response"),
+                    });
                 }
                 Err(SocketError::Io(err)) => {
                     return Err(RegistryError::backend(format!(
@@ -279,13 +353,53 @@ impl MxpRegistryClient {
     async fn send_request(&self, message: Message) -> RegistryResult<Message> {
         let handle = self.handle.clone();
         let registry_addr = self.registry_addr;
+        let fragments = self.fragments;
+        let identity = self.identity.clone();
+        let verifier = self.verifier.clone();
         tokio::task::spawn_blocking(move || {
-            Self::send_request_blocking(&handle, registry_addr, &message)
+            Self::send_request_blocking(
+                &handle,
+                registry_addr,
+                &message,
+                fragments,
+                identity.as_ref(),
+                verifier.as_deref(),
+            )
         })
         .await
         .map_err(|err| RegistryError::backend(format!("registry task join error: {err:?}")))?
     }
 
+    /// Looks up the agents advertising `capability`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RegistryError::Backend`] if the request fails or the registry
+    /// replies with an error.
+    pub async fn discover(&self, capability: &str) -> RegistryResult<Vec<AgentRecord>> {
+        let request = DiscoverRequest {
+            capability: capability.to_owned(),
+        };
+        let payload = serde_json::to_vec(&request)
+            .map_err(|err| RegistryError::backend(format!("encode discover payload: {err:?}")))?;
+        let message = Message::new(MessageType::AgentDiscover, payload);
+        let response = self.send_request(message).await?;
+
+        match response.message_type() {
+            Some(MessageType::Response) => {
+                let found = serde_json::from_slice::<DiscoverResponse>(response.payload())
+                    .map_err(|err| {
+                        RegistryError::backend(format!("parse discover response failed: {err:?}"))
+                    })?;
+                Ok(found.agents)
+            }
+            Some(MessageType::Error) => Self::handle_error_message(&response).map(|()| Vec::new()),
+            other => Err(RegistryError::backend(format!(
+                "unexpected message type {other:?} for discover response"
+            ))),
+        }
+    }
+
     fn handle_error_message(message: &Message) -> RegistryResult<()> {
         let payload =
             serde_json::from_slice::<ErrorResponse>(message.payload()).map_err(|err| {
@@ -366,8 +480,10 @@ impl RegistrationController {
                 self.ensure_worker(scheduler)?;
             }
             AgentState::Retiring | AgentState::Terminated => {
-                self.shutdown.store(true, Ordering::Release);
-                self.spawn_deregister(scheduler)?;
+                // Deregister once; the scheduler is drained after retiring.
+                if !self.shutdown.swap(true, Ordering::AcqRel) {
+                    self.spawn_deregister(scheduler)?;
+                }
                 if let Some(handle) = self.worker.take() {
                     handle.abort();
                 }
@@ -390,7 +506,7 @@ impl RegistrationController {
         let shutdown = Arc::clone(&self.shutdown);
         let config = self.config;
 
-        let handle = scheduler.spawn(async move {
+        let handle = scheduler.spawn_with(&control_task(), async move {
             run_registration_loop(registry, manifest, shutdown, config).await;
         })?;
 
@@ -401,7 +517,7 @@ impl RegistrationController {
     fn spawn_deregister(&self, scheduler: &TaskScheduler) -> RegistryResult<()> {
         let registry = Arc::clone(&self.registry);
         let manifest = Arc::clone(&self.manifest);
-        scheduler.spawn(async move {
+        scheduler.spawn_with(&control_task(), async move {
             if let Err(err) = registry.deregister(&manifest).await {
                 warn!(?err, "agent deregistration failed");
             } else {
@@ -412,6 +528,12 @@ impl RegistrationController {
     }
 }
 
+/// Registry traffic runs in the high-priority lane so call bursts cannot
+/// starve heartbeats.
+fn control_task() -> TaskOptions {
+    TaskOptions::new().with_priority(TaskPriority::High)
+}
+
 async fn run_registration_loop(
     registry: Arc<dyn AgentRegistry>,
     manifest: Arc<AgentManifest>,
@@ -487,94 +609,6 @@ async fn run_heartbeat_loop(
     true
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::atomic::AtomicUsize;
-
-    use agent_primitives::{AgentId, Capability, CapabilityId};
-
-    struct MockRegistry {
-        registers: Arc<AtomicUsize>,
-        heartbeats: Arc<AtomicUsize>,
-        deregistrations: Arc<AtomicUsize>,
-    }
-
-    #[async_trait]
-    impl AgentRegistry for MockRegistry {
-        async fn register(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
-            self.registers.fetch_add(1, Ordering::SeqCst);
-            Ok(())
-        }
-
-        async fn heartbeat(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
-            self.heartbeats.fetch_add(1, Ordering::SeqCst);
-            Ok(())
-        }
-
-        async fn deregister(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
-            self.deregistrations.fetch_add(1, Ordering::SeqCst);
-            Ok(())
-        }
-    }
-
-    fn manifest() -> AgentManifest {
-        let capability = Capability::builder(CapabilityId::new("mock.cap").unwrap())
-            .name("Mock")
-            .unwrap()
-            .version("1.0.0")
-            .unwrap()
-            .add_scope("read:mock")
-            .unwrap()
-            .build()
-            .unwrap();
-
-        AgentManifest::builder(AgentId::random())
-            .name("mock-agent")
-            .unwrap()
-            .version("0.1.0")
-            .unwrap()
-            .capabilities(vec![capability])
-            .build()
-            .unwrap()
-    }
-
-    #[tokio::test]
-    async fn lifecycle_starts_and_stops_heartbeat() {
-        let registry = Arc::new(MockRegistry {
-            registers: Arc::new(AtomicUsize::new(0)),
-            heartbeats: Arc::new(AtomicUsize::new(0)),
-            deregistrations: Arc::new(AtomicUsize::new(0)),
-        });
-
-        let manifest = manifest();
-        let config = RegistrationConfig::new(
-            Duration::from_millis(10),
-            Duration::from_millis(5),
-            Duration::from_millis(20),
-            NonZeroUsize::new(3).unwrap(),
-        );
-
-        let mut controller = RegistrationController::new(registry.clone(), manifest, config);
-        let scheduler = TaskScheduler::default();
-
-        controller
-            .on_state_change(AgentState::Ready, &scheduler)
-            .unwrap();
-
-        tokio::time::sleep(Duration::from_millis(40)).await;
-
-        assert!(registry.registers.load(Ordering::SeqCst) >= 1);
-        assert!(registry.heartbeats.load(Ordering::SeqCst) >= 1);
-
-        controller
-            .on_state_change(AgentState::Retiring, &scheduler)
-            .unwrap();
-        tokio::time::sleep(Duration::from_millis(20)).await;
-        assert!(registry.deregistrations.load(Ordering::SeqCst) >= 1);
-    }
-}
-
 #[async_trait]
 impl AgentRegistry for MxpRegistryClient {
     async fn register(&self, manifest: &AgentManifest) -> RegistryResult<()> {
@@ -675,3 +709,148 @@ impl AgentRegistry for MxpRegistryClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    use agent_primitives::{AgentId, Capability, CapabilityId};
+
+    struct MockRegistry {
+        registers: Arc<AtomicUsize>,
+        heartbeats: Arc<AtomicUsize>,
+        deregistrations: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AgentRegistry for MockRegistry {
+        async fn register(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
+            self.registers.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn heartbeat(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
+            self.heartbeats.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn deregister(&self, _manifest: &AgentManifest) -> RegistryResult<()> {
+            self.deregistrations.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn manifest() -> AgentManifest {
+        let capability = Capability::builder(CapabilityId::new("mock.cap").unwrap())
+            .name("Mock")
+            .unwrap()
+            .version("1.0.0")
+            .unwrap()
+            .add_scope("read:mock")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        AgentManifest::builder(AgentId::random())
+            .name("mock-agent")
+            .unwrap()
+            .version("0.1.0")
+            .unwrap()
+            .capabilities(vec![capability])
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn lifecycle_starts_and_stops_heartbeat() {
+        let registry = Arc::new(MockRegistry {
+            registers: Arc::new(AtomicUsize::new(0)),
+            heartbeats: Arc::new(AtomicUsize::new(0)),
+            deregistrations: Arc::new(AtomicUsize::new(0)),
+        });
+
+        let manifest = manifest();
+        let config = RegistrationConfig::new(
+            Duration::from_millis(10),
+            Duration::from_millis(5),
+            Duration::from_millis(20),
+            NonZeroUsize::new(3).unwrap(),
+        );
+
+        let mut controller = RegistrationController::new(registry.clone(), manifest, config);
+        let scheduler = TaskScheduler::default();
+
+        controller
+            .on_state_change(AgentState::Ready, &scheduler)
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        assert!(registry.registers.load(Ordering::SeqCst) >= 1);
+        assert!(registry.heartbeats.load(Ordering::SeqCst) >= 1);
+
+        controller
+            .on_state_change(AgentState::Retiring, &scheduler)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(registry.deregistrations.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[tokio::test]
+    async fn discovery_signs_requests_and_drops_unsigned_replies() {
+        let transport = Transport::new(default_transport_config());
+        let server = match transport.bind("127.0.0.1:0".parse().unwrap()) {
+            Ok(handle) => handle,
+            Err(SocketError::Io(err)) if err.kind() == ErrorKind::PermissionDenied => {
+                eprintln!("skipping registry test: bind requires elevated privileges");
+                return;
+            }
+            Err(err) => panic!("bind: {err:?}"),
+        };
+        let agent = AgentIdentity::generate(AgentId::random()).unwrap();
+        let registry = AgentIdentity::generate(AgentId::random()).unwrap();
+        let client = MxpRegistryClient::connect(
+            server.local_addr().unwrap(),
+            "127.0.0.1:4000".parse().unwrap(),
+            Some(TransportConfig {
+                read_timeout: Some(Duration::from_millis(300)),
+                ..default_transport_config()
+            }),
+        )
+        .unwrap()
+        .with_identity(agent.clone())
+        .with_registry_key(registry.agent_id(), registry.public_key());
+
+        let trust = TrustStore::new();
+        trust.trust(agent.agent_id(), agent.public_key());
+        let serve = tokio::task::spawn_blocking(move || {
+            let mut buffer = server.acquire_buffer();
+            let (len, addr) = server.receive(&mut buffer).unwrap();
+            let request = Message::decode(buffer.as_slice()[..len].to_vec()).unwrap();
+            let (peer, request) = SignatureVerifier::new(trust).verify(&request).unwrap();
+            assert_eq!(peer.agent_id(), agent.agent_id());
+            assert_eq!(request.message_type(), Some(MessageType::AgentDiscover));
+
+            let forged = DiscoverResponse {
+                capability: "billing.invoice".into(),
+                agents: Vec::new(),
+                count: 0,
+            };
+            let reply = Message::new(MessageType::Response, serde_json::to_vec(&forged).unwrap());
+            server.send(&reply.encode(), addr).unwrap();
+            let impostor = AgentIdentity::generate(registry.agent_id()).unwrap();
+            server
+                .send(&impostor.sign(&reply).unwrap().encode(), addr)
+                .unwrap();
+        });
+
+        let err = client.discover("billing.invoice").await.unwrap_err();
+        serve.await.unwrap();
+        assert!(
+            err.to_string()
+                .contains("rejected unauthenticated response"),
+            "{err}"
+        );
+    }
+}
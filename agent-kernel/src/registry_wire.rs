@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 
+use agent_primitives::PublicKey;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -65,6 +66,9 @@ pub struct AgentRecord {
     pub last_heartbeat: DateTime<Utc>,
     /// Timestamp the agent was first registered.
     pub registered_at: DateTime<Utc>,
+    /// Key the agent signs its messages with, when it advertises one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<PublicKey>,
 }
 
 /// Discovery response payload.
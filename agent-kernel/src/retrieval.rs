@@ -0,0 +1,280 @@
+//! Retrieval-augmented generation backed by the memory vector store.
+
+use std::fmt::{self, Write as _};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use agent_adapters::traits::{MessageRole, PromptMessage};
+use agent_memory::{Embedder, MemoryBus, MemoryResult, VectorMatch, VectorQuery};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Metadata keys checked, in order, for the text of a retrieved memory before
+/// falling back to the payload of the stored record.
+const CONTENT_KEYS: [&str; 2] = ["content", "text"];
+
+const DEFAULT_TOP_K: NonZeroUsize = NonZeroUsize::new(4).unwrap();
+
+/// Memory retrieved from the vector store and injected into a call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievedMemory {
+    id: Uuid,
+    score: f32,
+    content: String,
+    tags: Vec<String>,
+}
+
+impl RetrievedMemory {
+    /// Returns the id of the memory record.
+    #[must_use]
+    pub const fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the similarity score reported by the vector store.
+    #[must_use]
+    pub const fn score(&self) -> f32 {
+        self.score
+    }
+
+    /// Returns the text injected into the prompt.
+    #[must_use]
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Returns the tags of the matched vector point.
+    #[must_use]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// Opt-in stage that recalls relevant memories for the latest user message.
+#[derive(Clone)]
+pub struct RetrievalStage {
+    memory: Arc<MemoryBus>,
+    embedder: Arc<dyn Embedder>,
+    top_k: NonZeroUsize,
+    tags: Vec<String>,
+    min_score: Option<f32>,
+}
+
+impl fmt::Debug for RetrievalStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetrievalStage")
+            .field("top_k", &self.top_k)
+            .field("tags", &self.tags)
+            .field("min_score", &self.min_score)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RetrievalStage {
+    /// Creates a stage querying the vector store configured on `memory`,
+    /// returning up to four matches by default.
+    #[must_use]
+    pub fn new(memory: Arc<MemoryBus>, embedder: Arc<dyn Embedder>) -> Self {
+        Self {
+            memory,
+            embedder,
+            top_k: DEFAULT_TOP_K,
+            tags: Vec::new(),
+            min_score: None,
+        }
+    }
+
+    /// Sets the maximum number of memories injected per call.
+    #[must_use]
+    pub fn with_top_k(mut self, top_k: NonZeroUsize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Restricts recall to vector points carrying all of the supplied tags.
+    #[must_use]
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Drops matches scoring below `min_score`.
+    #[must_use]
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Returns the maximum number of memories injected per call.
+    #[must_use]
+    pub const fn top_k(&self) -> NonZeroUsize {
+        self.top_k
+    }
+
+    /// Returns the tag filter.
+    #[must_use]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns the minimum score, if any.
+    #[must_use]
+    pub const fn min_score(&self) -> Option<f32> {
+        self.min_score
+    }
+
+    /// Embeds `query` and returns the matching memories, best first.
+    ///
+    /// Matches whose text cannot be resolved are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`agent_memory::MemoryError`] when embedding, querying the
+    /// vector store, or reading the journal fails.
+    pub async fn retrieve(&self, query: &str) -> MemoryResult<Vec<RetrievedMemory>> {
+        let embedding = self.embedder.embed(query).await?;
+        let query = VectorQuery::new(embedding, self.top_k).with_tags(self.tags.clone());
+        let matches = self.memory.recall(query).await?;
+
+        let mut retrieved = Vec::with_capacity(matches.len());
+        for candidate in matches {
+            if self
+                .min_score
+                .is_some_and(|min_score| candidate.score() < min_score)
+            {
+                continue;
+            }
+            if let Some(content) = self.resolve_content(&candidate).await? {
+                retrieved.push(RetrievedMemory {
+                    id: candidate.id(),
+                    score: candidate.score(),
+                    content,
+                    tags: candidate.tags().to_vec(),
+                });
+            }
+        }
+        Ok(retrieved)
+    }
+
+    async fn resolve_content(&self, candidate: &VectorMatch) -> MemoryResult<Option<String>> {
+        if let Some(text) = CONTENT_KEYS
+            .iter()
+            .find_map(|key| candidate.metadata().get(key).and_then(Value::as_str))
+        {
+            return Ok(Some(text.to_owned()));
+        }
+        let record = self.memory.find(candidate.id()).await?;
+        Ok(record
+            .map(|record| String::from_utf8_lossy(record.payload()).into_owned())
+            .filter(|text| !text.trim().is_empty()))
+    }
+}
+
+/// Builds the context message listing retrieved memories with citation markers.
+pub(crate) fn context_message(retrieved: &[RetrievedMemory]) -> PromptMessage {
+    let mut content = String::from(
+        "Relevant context retrieved from memory. Cite passages you rely on by their [n] marker.\n",
+    );
+    for (idx, memory) in retrieved.iter().enumerate() {
+        let _ = write!(
+            content,
+            "\n[{}] (memory {}, score {:.3})\n{}\n",
+            idx + 1,
+            memory.id,
+            memory.score,
+            memory.content.trim()
+        );
+    }
+    PromptMessage::new(MessageRole::User, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use agent_memory::{
+        EmbeddingVector, FileJournal, Journal, LocalVectorStore, MemoryBusBuilder, MemoryChannel,
+        MemoryRecord, VectorStoreClient, VolatileConfig,
+    };
+    use agent_primitives::AgentId;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+
+    struct AxisEmbedder;
+
+    #[async_trait]
+    impl Embedder for AxisEmbedder {
+        async fn embed(&self, text: &str) -> MemoryResult<EmbeddingVector> {
+            let values = if text.contains("billing") {
+                vec![1.0, 0.0]
+            } else {
+                vec![0.0, 1.0]
+            };
+            EmbeddingVector::new(values)
+        }
+    }
+
+    #[tokio::test]
+    async fn retrieves_and_cites_tagged_memories() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("retrieval-test-{}.log", AgentId::random()));
+        let journal: Arc<dyn Journal> = Arc::new(FileJournal::open(&path).await.unwrap());
+        let store: Arc<dyn VectorStoreClient> = Arc::new(LocalVectorStore::new());
+        let memory = Arc::new(
+            MemoryBusBuilder::new(VolatileConfig::default())
+                .with_journal(journal)
+                .with_vector_store(store)
+                .build()
+                .unwrap(),
+        );
+
+        let billing = MemoryRecord::builder(
+            MemoryChannel::Input,
+            Bytes::from_static(b"Invoices are sent on the 1st."),
+        )
+        .tag("kb")
+        .unwrap()
+        .embedding(EmbeddingVector::new(vec![1.0, 0.1]).unwrap())
+        .build()
+        .unwrap();
+        let shipping = MemoryRecord::builder(MemoryChannel::Input, Bytes::new())
+            .tag("kb")
+            .unwrap()
+            .metadata("content", Value::from("Orders ship within two days."))
+            .embedding(EmbeddingVector::new(vec![0.1, 1.0]).unwrap())
+            .build()
+            .unwrap();
+        let untagged = MemoryRecord::builder(MemoryChannel::Input, Bytes::from_static(b"noise"))
+            .embedding(EmbeddingVector::new(vec![1.0, 0.0]).unwrap())
+            .build()
+            .unwrap();
+        for record in [billing.clone(), shipping.clone(), untagged] {
+            memory.record(record).await.unwrap();
+        }
+
+        let stage = RetrievalStage::new(memory, Arc::new(AxisEmbedder))
+            .with_top_k(NonZeroUsize::new(2).unwrap())
+            .with_tags(["kb"])
+            .with_min_score(0.5);
+
+        let retrieved = stage.retrieve("billing question").await.unwrap();
+        assert_eq!(retrieved.len(), 1);
+        assert_eq!(retrieved[0].id(), billing.id());
+        assert_eq!(retrieved[0].content(), "Invoices are sent on the 1st.");
+
+        let retrieved = stage.retrieve("shipping question").await.unwrap();
+        assert_eq!(retrieved[0].id(), shipping.id());
+        assert_eq!(retrieved[0].content(), "Orders ship within two days.");
+
+        let message = context_message(&retrieved);
+        assert_eq!(message.role(), MessageRole::User);
+        assert!(message.content().contains("[1] (memory "));
+        assert!(message.content().contains(&shipping.id().to_string()));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
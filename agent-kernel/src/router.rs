@@ -0,0 +1,698 @@
+//! Method-based routing of `Call` messages to typed handlers.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use mxp::{Message, MessageType, TransportHandle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::dedup::{DedupSlot, DuplicateCall};
+use crate::delivery::{ReliableSender, send_ack};
+use crate::encryption::{EncryptedPeer, SecureChannels};
+use crate::fragment::Fragmenter;
+use crate::signing::{AgentIdentity, sign_outbound};
+use crate::{AgentMessageHandler, HandlerContext, HandlerResult};
+
+/// Structured error returned to the caller of a routed method.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[error("{code}: {message}")]
+pub struct RpcError {
+    code: String,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// Creates an error with a machine-readable code and a description.
+    #[must_use]
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// The call payload was not valid JSON.
+    #[must_use]
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new("parse_error", message)
+    }
+
+    /// No handler is registered for the method.
+    #[must_use]
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(
+            "method_not_found",
+            format!("method `{method}` is not registered"),
+        )
+    }
+
+    /// The parameters did not match the method's request type.
+    #[must_use]
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new("invalid_params", message)
+    }
+
+    /// The handler failed unexpectedly.
+    #[must_use]
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new("internal_error", message)
+    }
+
+    /// Attaches structured details to the error.
+    #[must_use]
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Returns the machine-readable error code.
+    #[must_use]
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the human-readable description.
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the structured details, if any.
+    #[must_use]
+    pub const fn data(&self) -> Option<&Value> {
+        self.data.as_ref()
+    }
+}
+
+/// Reply to a routed call, correlated by the call's MXP message id.
+///
+/// Encoded as `{"call_id": ..., "method": ..., "result": ...}` in a `Response`
+/// message, or `{"call_id": ..., "method": ..., "error": {"code": ...,
+/// "message": ...}}` in an `Error` message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcResponse {
+    call_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(flatten)]
+    outcome: RpcOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RpcOutcome {
+    Result(Value),
+    Error(RpcError),
+}
+
+impl RpcResponse {
+    fn new(call_id: u64, method: Option<String>, outcome: Result<Value, RpcError>) -> Self {
+        let outcome = match outcome {
+            Ok(result) => RpcOutcome::Result(result),
+            Err(err) => RpcOutcome::Error(err),
+        };
+        Self {
+            call_id,
+            method,
+            outcome,
+        }
+    }
+
+    /// Returns the MXP message id of the call being answered.
+    #[must_use]
+    pub const fn call_id(&self) -> u64 {
+        self.call_id
+    }
+
+    /// Returns the method that was called, if the call named one.
+    #[must_use]
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    /// Returns the method's result, or the error it failed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`RpcError`] carried by an error response.
+    pub const fn result(&self) -> Result<&Value, &RpcError> {
+        match &self.outcome {
+            RpcOutcome::Result(result) => Ok(result),
+            RpcOutcome::Error(err) => Err(err),
+        }
+    }
+
+    /// Decodes a response from a `Response` or `Error` message payload.
+    #[must_use]
+    pub fn from_payload(payload: &[u8]) -> Option<Self> {
+        serde_json::from_slice(payload).ok()
+    }
+
+    /// Builds the MXP message carrying this response.
+    #[must_use]
+    pub fn to_message(&self) -> Message {
+        let kind = match self.outcome {
+            RpcOutcome::Result(_) => MessageType::Response,
+            RpcOutcome::Error(_) => MessageType::Error,
+        };
+        Message::new(kind, serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+/// Delivers replies produced by a [`CallRouter`].
+pub trait ResponseSink: Send + Sync {
+    /// Sends `response`, which answers the call described by `ctx`.
+    fn respond(&self, ctx: &HandlerContext, response: Message);
+
+    /// Confirms receipt of the call described by `ctx` before it is
+    /// answered. The default does nothing.
+    fn accept(&self, _ctx: &HandlerContext) {}
+}
+
+/// Sends replies back to the caller's transport address over MXP.
+#[derive(Debug, Clone)]
+pub struct MxpResponseSink {
+    transport: TransportHandle,
+    fragmenter: Fragmenter,
+    identity: Option<AgentIdentity>,
+    channels: Option<SecureChannels>,
+    reliable: Option<ReliableSender>,
+}
+
+impl MxpResponseSink {
+    /// Creates a sink that replies through `transport`.
+    #[must_use]
+    pub fn new(transport: TransportHandle) -> Self {
+        Self {
+            transport,
+            fragmenter: Fragmenter::default(),
+            identity: None,
+            channels: None,
+            reliable: None,
+        }
+    }
+
+    /// Sets how oversized replies are fragmented.
+    #[must_use]
+    pub const fn with_fragmenter(mut self, fragmenter: Fragmenter) -> Self {
+        self.fragmenter = fragmenter;
+        self
+    }
+
+    /// Signs every reply with `identity`.
+    #[must_use]
+    pub fn with_identity(mut self, identity: AgentIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Encrypts replies to calls that arrived encrypted, using the caller's
+    /// session in `channels`.
+    #[must_use]
+    pub fn with_channels(mut self, channels: SecureChannels) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Sends replies through `sender`, retransmitting each until the caller
+    /// acknowledges it, and acknowledges every call as it is accepted.
+    ///
+    /// Replies are signed and encrypted by `sender` as it is configured; the
+    /// sink's own identity, channels and fragmenter no longer apply. Install
+    /// the same sender on the handler receiving `Ack` messages.
+    #[must_use]
+    pub fn with_reliable(mut self, sender: ReliableSender) -> Self {
+        self.reliable = Some(sender);
+        self
+    }
+
+    fn send(&self, response: &Message, target: SocketAddr, peer: Option<&EncryptedPeer>) {
+        if let Some(reliable) = &self.reliable {
+            // The delivery observer reports replies that are never acknowledged.
+            drop(reliable.send(response.clone(), target));
+            return;
+        }
+        let mut response = match sign_outbound(self.identity.as_ref(), response) {
+            Ok(response) => response,
+            Err(err) => {
+                warn!(%err, %target, "failed to sign call response");
+                return;
+            }
+        };
+        if let (Some(channels), Some(peer)) = (&self.channels, peer) {
+            match channels.encrypt(peer.agent_id(), &response) {
+                Ok(sealed) => response = Cow::Owned(sealed),
+                Err(err) => {
+                    warn!(%err, %target, "failed to encrypt call response");
+                    return;
+                }
+            }
+        }
+        if let Err(err) = self.fragmenter.send(&self.transport, &response, target) {
+            warn!(%err, %target, "failed to send call response");
+        }
+    }
+}
+
+impl ResponseSink for MxpResponseSink {
+    fn respond(&self, ctx: &HandlerContext, response: Message) {
+        if let Some(target) = ctx.sender() {
+            self.send(&response, target, ctx.extension::<EncryptedPeer>());
+        } else {
+            debug!(
+                call_id = ctx.message().message_id(),
+                "dropping call response without a sender address"
+            );
+        }
+    }
+
+    fn accept(&self, ctx: &HandlerContext) {
+        if let (Some(reliable), Some(target)) = (&self.reliable, ctx.sender()) {
+            send_ack(reliable.transport(), ctx.message().message_id(), target);
+        }
+    }
+}
+
+type MethodHandler =
+    Arc<dyn Fn(HandlerContext, Value) -> BoxFuture<'static, Result<Value, RpcError>> + Send + Sync>;
+
+#[derive(Deserialize)]
+struct RoutedCall {
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Dispatches `Call` messages by their `method` field to typed handlers.
+///
+/// A call payload of `{"method": "inventory.lookup", "params": {...}}` runs
+/// the handler registered for `inventory.lookup` with `params` deserialized
+/// into its request type, and the serialized response is sent through the
+/// [`ResponseSink`]. Calls without a registered method go to the fallback
+/// handler (typically a [`KernelMessageHandler`](crate::KernelMessageHandler)),
+/// which also receives every other message type.
+pub struct CallRouter {
+    methods: HashMap<String, MethodHandler>,
+    fallback: Option<Arc<dyn AgentMessageHandler>>,
+    responses: Arc<dyn ResponseSink>,
+}
+
+impl fmt::Debug for CallRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallRouter")
+            .field("methods", &self.methods())
+            .field("fallback", &self.fallback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CallRouter {
+    /// Creates a router without methods or fallback that replies via `responses`.
+    #[must_use]
+    pub fn new(responses: Arc<dyn ResponseSink>) -> Self {
+        Self {
+            methods: HashMap::new(),
+            fallback: None,
+            responses,
+        }
+    }
+
+    /// Sends unmatched calls and all non-call messages to `fallback`.
+    #[must_use]
+    pub fn with_fallback<H>(mut self, fallback: Arc<H>) -> Self
+    where
+        H: AgentMessageHandler + 'static,
+    {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Registers `handler` for `method`, replacing any existing handler.
+    #[must_use]
+    pub fn with_method<Req, Resp, F, Fut>(mut self, method: impl Into<String>, handler: F) -> Self
+    where
+        Req: DeserializeOwned + Send + 'static,
+        Resp: Serialize + 'static,
+        F: Fn(HandlerContext, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, RpcError>> + Send + 'static,
+    {
+        self.add_method(method, handler);
+        self
+    }
+
+    /// Registers `handler` for `method` after construction.
+    pub fn add_method<Req, Resp, F, Fut>(&mut self, method: impl Into<String>, handler: F)
+    where
+        Req: DeserializeOwned + Send + 'static,
+        Resp: Serialize + 'static,
+        F: Fn(HandlerContext, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, RpcError>> + Send + 'static,
+    {
+        let erased: MethodHandler =
+            Arc::new(
+                move |ctx, params| match serde_json::from_value::<Req>(params) {
+                    Ok(request) => handler(ctx, request)
+                        .map(|result| {
+                            result.and_then(|response| {
+                                serde_json::to_value(response)
+                                    .map_err(|err| RpcError::internal(err.to_string()))
+                            })
+                        })
+                        .boxed(),
+                    Err(err) => {
+                        futures::future::ready(Err(RpcError::invalid_params(err.to_string())))
+                            .boxed()
+                    }
+                },
+            );
+        self.methods.insert(method.into(), erased);
+    }
+
+    /// Returns the registered method names, sorted.
+    #[must_use]
+    pub fn methods(&self) -> Vec<&str> {
+        let mut methods = self.methods.keys().map(String::as_str).collect::<Vec<_>>();
+        methods.sort_unstable();
+        methods
+    }
+
+    fn respond(
+        &self,
+        ctx: &HandlerContext,
+        method: Option<String>,
+        outcome: Result<Value, RpcError>,
+    ) {
+        if let Err(err) = &outcome {
+            debug!(?method, code = err.code(), %err, "routed call failed");
+        }
+        let response = RpcResponse::new(ctx.message().message_id(), method, outcome).to_message();
+        if let Some(dedup) = ctx.extension::<DedupSlot>() {
+            dedup.record_reply(&response);
+        }
+        self.responses.respond(ctx, response);
+    }
+
+    async fn fallback(&self, ctx: HandlerContext, method: Option<String>) -> HandlerResult {
+        if let Some(fallback) = &self.fallback {
+            return fallback.handle_call(ctx).await;
+        }
+        let err = match &method {
+            Some(name) => RpcError::method_not_found(name),
+            None => RpcError::invalid_params("call payload has no `method`"),
+        };
+        self.responses.accept(&ctx);
+        self.respond(&ctx, method, Err(err));
+        Ok(())
+    }
+
+    async fn forward(&self, ctx: HandlerContext, message_type: MessageType) -> HandlerResult {
+        match &self.fallback {
+            Some(fallback) => crate::mxp_handlers::dispatch_message(fallback.as_ref(), ctx).await,
+            None => Err(crate::HandlerError::Unsupported(message_type)),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentMessageHandler for CallRouter {
+    async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+        let call = match serde_json::from_slice::<RoutedCall>(ctx.message().payload()) {
+            Ok(call) => call,
+            Err(err) if self.fallback.is_none() => {
+                self.responses.accept(&ctx);
+                self.respond(&ctx, None, Err(RpcError::parse_error(err.to_string())));
+                return Ok(());
+            }
+            Err(_) => return self.fallback(ctx, None).await,
+        };
+        let Some(handler) = call
+            .method
+            .as_ref()
+            .and_then(|method| self.methods.get(method))
+        else {
+            return self.fallback(ctx, call.method).await;
+        };
+
+        self.responses.accept(&ctx);
+        let outcome = handler(ctx.clone(), call.params).await;
+        self.respond(&ctx, call.method, outcome);
+        Ok(())
+    }
+
+    async fn handle_duplicate(&self, ctx: HandlerContext) -> HandlerResult {
+        let replies = match ctx.extension::<DuplicateCall>() {
+            Some(DuplicateCall::Completed(call)) => call.replies(),
+            _ => &[],
+        };
+        if !replies.is_empty() {
+            self.responses.accept(&ctx);
+        }
+        for reply in replies {
+            self.responses.respond(&ctx, reply.clone());
+        }
+        match &self.fallback {
+            Some(fallback) => fallback.handle_duplicate(ctx).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn handle_unhandled(
+        &self,
+        ctx: HandlerContext,
+        message_type: MessageType,
+    ) -> HandlerResult {
+        self.forward(ctx, message_type).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use agent_primitives::AgentId;
+    use mxp::transport::{SocketError, Transport, TransportConfig};
+    use serde_json::json;
+
+    use crate::delivery::{DeliveryAck, DeliveryConfig};
+
+    #[derive(Default)]
+    struct CollectingResponses {
+        responses: Mutex<Vec<Message>>,
+    }
+
+    impl ResponseSink for CollectingResponses {
+        fn respond(&self, _ctx: &HandlerContext, response: Message) {
+            self.responses.lock().unwrap().push(response);
+        }
+    }
+
+    impl CollectingResponses {
+        fn take(&self) -> Vec<(MessageType, RpcResponse)> {
+            self.responses
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|message| {
+                    let response = RpcResponse::from_payload(message.payload()).unwrap();
+                    (message.message_type().unwrap(), response)
+                })
+                .collect()
+        }
+    }
+
+    #[derive(Default)]
+    struct Fallback {
+        calls: AtomicUsize,
+        events: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl AgentMessageHandler for Fallback {
+        async fn handle_call(&self, _ctx: HandlerContext) -> HandlerResult {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn handle_event(&self, _ctx: HandlerContext) -> HandlerResult {
+            self.events.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Add {
+        a: i64,
+        b: i64,
+    }
+
+    #[derive(Serialize)]
+    struct Sum {
+        sum: i64,
+    }
+
+    fn router(responses: Arc<CollectingResponses>) -> CallRouter {
+        CallRouter::new(responses).with_method("math.add", |_ctx, request: Add| async move {
+            request
+                .a
+                .checked_add(request.b)
+                .map(|sum| Sum { sum })
+                .ok_or_else(|| RpcError::new("overflow", "sum overflows i64"))
+        })
+    }
+
+    fn ctx(kind: MessageType, payload: &Value) -> HandlerContext {
+        let message = Message::new(kind, serde_json::to_vec(payload).unwrap());
+        HandlerContext::from_message(AgentId::random(), message)
+    }
+
+    #[tokio::test]
+    async fn routes_typed_methods_and_reports_errors() {
+        let responses = Arc::new(CollectingResponses::default());
+        let router = router(Arc::clone(&responses));
+        assert_eq!(router.methods(), ["math.add"]);
+
+        let call = ctx(
+            MessageType::Call,
+            &json!({"method": "math.add", "params": {"a": 2, "b": 3}}),
+        );
+        let call_id = call.message().message_id();
+        router.handle_call(call).await.unwrap();
+        router
+            .handle_call(ctx(
+                MessageType::Call,
+                &json!({"method": "math.add", "params": {"a": "two"}}),
+            ))
+            .await
+            .unwrap();
+        router
+            .handle_call(ctx(
+                MessageType::Call,
+                &json!({"method": "math.add", "params": {"a": i64::MAX, "b": 1}}),
+            ))
+            .await
+            .unwrap();
+        router
+            .handle_call(ctx(MessageType::Call, &json!({"method": "math.mul"})))
+            .await
+            .unwrap();
+
+        let replies = responses.take();
+        assert_eq!(replies[0].0, MessageType::Response);
+        assert_eq!(replies[0].1.call_id(), call_id);
+        assert_eq!(replies[0].1.method(), Some("math.add"));
+        assert_eq!(replies[0].1.result(), Ok(&json!({"sum": 5})));
+        let codes = replies[1..]
+            .iter()
+            .map(|(kind, response)| {
+                assert_eq!(*kind, MessageType::Error);
+                response.result().unwrap_err().code().to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["invalid_params", "overflow", "method_not_found"]);
+    }
+
+    #[tokio::test]
+    async fn unmatched_calls_and_other_messages_use_fallback() {
+        let responses = Arc::new(CollectingResponses::default());
+        let fallback = Arc::new(Fallback::default());
+        let router = router(Arc::clone(&responses)).with_fallback(Arc::clone(&fallback));
+
+        router
+            .handle_call(ctx(MessageType::Call, &json!({"messages": []})))
+            .await
+            .unwrap();
+        router
+            .handle_call(ctx(MessageType::Call, &json!({"method": "chat"})))
+            .await
+            .unwrap();
+        crate::mxp_handlers::dispatch_message(&router, ctx(MessageType::Event, &json!({})))
+            .await
+            .unwrap();
+
+        assert_eq!(fallback.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(fallback.events.load(Ordering::SeqCst), 1);
+        assert!(responses.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reliable_sink_acknowledges_calls_and_retransmits_replies() {
+        let transport = Transport::new(TransportConfig {
+            read_timeout: Some(Duration::from_millis(500)),
+            ..TransportConfig::default()
+        });
+        let bind = || match transport.bind("127.0.0.1:0".parse().unwrap()) {
+            Ok(handle) => Some(handle),
+            Err(SocketError::Io(err)) if err.kind() == ErrorKind::PermissionDenied => None,
+            Err(err) => panic!("bind: {err:?}"),
+        };
+        let (Some(agent), Some(caller)) = (bind(), bind()) else {
+            eprintln!("skipping reliable response test: bind requires elevated privileges");
+            return;
+        };
+        let caller_addr = caller.local_addr().unwrap();
+        let reliable = ReliableSender::new(
+            agent.clone(),
+            DeliveryConfig::new().with_initial_backoff(Duration::from_millis(20)),
+        );
+        let sink = MxpResponseSink::new(agent).with_reliable(reliable.clone());
+        let router = CallRouter::new(Arc::new(sink)).with_method(
+            "math.add",
+            |_ctx, request: Add| async move {
+                Ok(Sum {
+                    sum: request.a + request.b,
+                })
+            },
+        );
+
+        let call = ctx(
+            MessageType::Call,
+            &json!({"method": "math.add", "params": {"a": 2, "b": 3}}),
+        )
+        .with_sender(caller_addr);
+        let call_id = call.message().message_id();
+        router.handle_call(call).await.unwrap();
+
+        // The call is acknowledged, then the unacknowledged reply is resent.
+        let received = tokio::task::spawn_blocking(move || {
+            (0..3)
+                .map(|_| {
+                    let mut buffer = caller.acquire_buffer();
+                    let (len, _) = caller.receive(&mut buffer).unwrap();
+                    Message::decode(buffer.as_slice()[..len].to_vec()).unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap();
+        assert_eq!(received[0].message_type(), Some(MessageType::Ack));
+        let ack = DeliveryAck::from_payload(received[0].payload()).unwrap();
+        assert_eq!(ack.message_id(), call_id);
+        let reply_id = received[1].message_id();
+        assert_eq!(received[2].message_id(), reply_id);
+        let reply = RpcResponse::from_payload(received[1].payload()).unwrap();
+        assert_eq!(reply.call_id(), call_id);
+        assert_eq!(reply.result(), Ok(&json!({"sum": 5})));
+
+        assert!(reliable.acknowledge(caller_addr, reply_id));
+        assert_eq!(reliable.in_flight(), 0);
+    }
+}
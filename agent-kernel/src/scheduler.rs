@@ -1,25 +1,40 @@
 //! Cooperative scheduler facade for agent workloads.
 
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::future::Future;
-use std::num::NonZeroUsize;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 
 use thiserror::Error;
-use tokio::sync::Semaphore;
-use tokio::task::JoinHandle;
+use tokio::sync::{oneshot, watch};
+use tokio::task::{AbortHandle, JoinHandle};
 
 /// Maximum number of concurrent tasks allowed per agent.
 #[derive(Debug, Clone, Copy)]
 pub struct SchedulerConfig {
     max_concurrency: NonZeroUsize,
+    queue_capacity: Option<NonZeroUsize>,
 }
 
 impl SchedulerConfig {
     /// Creates a new configuration with the supplied concurrency limit.
     #[must_use]
     pub const fn new(max_concurrency: NonZeroUsize) -> Self {
-        Self { max_concurrency }
+        Self {
+            max_concurrency,
+            queue_capacity: None,
+        }
+    }
+
+    /// Bounds the number of tasks waiting for a concurrency slot; further
+    /// spawns fail with [`SchedulerError::QueueFull`].
+    #[must_use]
+    pub const fn with_queue_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
     }
 
     /// Returns the configured concurrency limit.
@@ -27,6 +42,12 @@ impl SchedulerConfig {
     pub const fn max_concurrency(self) -> NonZeroUsize {
         self.max_concurrency
     }
+
+    /// Returns the maximum number of queued tasks, if bounded.
+    #[must_use]
+    pub const fn queue_capacity(self) -> Option<NonZeroUsize> {
+        self.queue_capacity
+    }
 }
 
 impl Default for SchedulerConfig {
@@ -35,22 +56,411 @@ impl Default for SchedulerConfig {
     }
 }
 
+/// Priority lane a task is queued in. Higher lanes are always served first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TaskPriority {
+    /// Control-plane work such as heartbeats and deregistration.
+    High,
+    /// Interactive calls.
+    #[default]
+    Normal,
+    /// Batch work that may wait behind everything else.
+    Low,
+}
+
+impl TaskPriority {
+    /// All priorities, highest first.
+    pub const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Low];
+
+    const fn lane(self) -> usize {
+        match self {
+            Self::High => 0,
+            Self::Normal => 1,
+            Self::Low => 2,
+        }
+    }
+}
+
+/// Per-task scheduling options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskOptions {
+    priority: TaskPriority,
+    fairness_key: Option<String>,
+}
+
+impl TaskOptions {
+    /// Creates options for a [`TaskPriority::Normal`] task without a fairness key.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the priority lane.
+    #[must_use]
+    pub fn with_priority(mut self, priority: TaskPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the caller or tenant the task is queued under. Within a lane,
+    /// keys are served by weighted round-robin (see
+    /// [`TaskScheduler::set_weight`]).
+    #[must_use]
+    pub fn with_fairness_key(mut self, key: impl Into<String>) -> Self {
+        self.fairness_key = Some(key.into());
+        self
+    }
+
+    /// Returns the priority lane.
+    #[must_use]
+    pub const fn priority(&self) -> TaskPriority {
+        self.priority
+    }
+
+    /// Returns the fairness key, if any.
+    #[must_use]
+    pub fn fairness_key(&self) -> Option<&str> {
+        self.fairness_key.as_deref()
+    }
+}
+
+/// Snapshot of scheduler queue depth and wait times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedulerStats {
+    running: usize,
+    queued: [usize; 3],
+    admitted: u64,
+    rejected: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+impl SchedulerStats {
+    /// Returns the number of tasks holding a concurrency slot.
+    #[must_use]
+    pub const fn running(&self) -> usize {
+        self.running
+    }
+
+    /// Returns the number of tasks waiting for a slot across all lanes.
+    #[must_use]
+    pub const fn queued(&self) -> usize {
+        self.queued[0] + self.queued[1] + self.queued[2]
+    }
+
+    /// Returns the number of tasks waiting in the given lane.
+    #[must_use]
+    pub const fn queued_in(&self, priority: TaskPriority) -> usize {
+        self.queued[priority.lane()]
+    }
+
+    /// Returns the number of tasks that have been granted a slot.
+    #[must_use]
+    pub const fn admitted(&self) -> u64 {
+        self.admitted
+    }
+
+    /// Returns the number of spawns rejected because the queue was full.
+    #[must_use]
+    pub const fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Returns the mean time admitted tasks spent queued.
+    #[must_use]
+    pub fn mean_wait(&self) -> Duration {
+        u32::try_from(self.admitted)
+            .ok()
+            .filter(|admitted| *admitted > 0)
+            .map_or(Duration::ZERO, |admitted| self.total_wait / admitted)
+    }
+
+    /// Returns the longest time an admitted task spent queued.
+    #[must_use]
+    pub const fn max_wait(&self) -> Duration {
+        self.max_wait
+    }
+}
+
+struct Waiter {
+    id: u64,
+    enqueued_at: Instant,
+    grant: oneshot::Sender<()>,
+}
+
+/// Tasks queued under one fairness key, with the turns left in its round.
+struct Flow {
+    waiters: VecDeque<Waiter>,
+    credit: u32,
+}
+
+/// One priority lane, serving fairness keys by weighted round-robin.
+#[derive(Default)]
+struct Lane {
+    order: VecDeque<String>,
+    flows: HashMap<String, Flow>,
+}
+
+impl Lane {
+    fn push(&mut self, key: String, waiter: Waiter, weight: u32) {
+        if let Some(flow) = self.flows.get_mut(&key) {
+            flow.waiters.push_back(waiter);
+            return;
+        }
+        self.order.push_back(key.clone());
+        self.flows.insert(
+            key,
+            Flow {
+                waiters: VecDeque::from([waiter]),
+                credit: weight,
+            },
+        );
+    }
+
+    fn pop(&mut self, weights: &HashMap<String, NonZeroU32>) -> Option<Waiter> {
+        let key = self.order.front()?.clone();
+        let flow = self.flows.get_mut(&key)?;
+        let waiter = flow.waiters.pop_front()?;
+        flow.credit = flow.credit.saturating_sub(1);
+        if flow.waiters.is_empty() {
+            self.flows.remove(&key);
+            self.order.pop_front();
+        } else if flow.credit == 0 {
+            flow.credit = weight_of(weights, &key);
+            self.order.rotate_left(1);
+        }
+        Some(waiter)
+    }
+
+    fn remove(&mut self, key: &str, id: u64) -> bool {
+        let Some(flow) = self.flows.get_mut(key) else {
+            return false;
+        };
+        let Some(position) = flow.waiters.iter().position(|waiter| waiter.id == id) else {
+            return false;
+        };
+        flow.waiters.remove(position);
+        if flow.waiters.is_empty() {
+            self.flows.remove(key);
+            self.order.retain(|candidate| candidate != key);
+        }
+        true
+    }
+}
+
+fn weight_of(weights: &HashMap<String, NonZeroU32>, key: &str) -> u32 {
+    weights.get(key).map_or(1, |weight| weight.get())
+}
+
+/// Concurrency slots and the queues of tasks waiting for one.
+struct Dispatcher {
+    available: usize,
+    capacity: Option<NonZeroUsize>,
+    lanes: [Lane; 3],
+    weights: HashMap<String, NonZeroU32>,
+    next_waiter: u64,
+    stats: SchedulerStats,
+}
+
+impl Dispatcher {
+    fn new(config: SchedulerConfig) -> Self {
+        Self {
+            available: config.max_concurrency().get(),
+            capacity: config.queue_capacity(),
+            lanes: Default::default(),
+            weights: HashMap::new(),
+            next_waiter: 0,
+            stats: SchedulerStats::default(),
+        }
+    }
+
+    fn record_wait(&mut self, wait: Duration) {
+        self.stats.admitted += 1;
+        self.stats.total_wait += wait;
+        self.stats.max_wait = self.stats.max_wait.max(wait);
+    }
+
+    /// Hands a freed slot to the next waiter, or returns it to the pool.
+    fn release(&mut self) {
+        let next = self
+            .lanes
+            .iter_mut()
+            .enumerate()
+            .find_map(|(lane, queue)| queue.pop(&self.weights).map(|waiter| (lane, waiter)));
+        match next {
+            Some((lane, waiter)) => {
+                self.stats.queued[lane] -= 1;
+                self.record_wait(waiter.enqueued_at.elapsed());
+                // A dropped receiver is handled by the waiter's guard, which
+                // releases the slot again once it finds itself dequeued.
+                let _ = waiter.grant.send(());
+            }
+            None => self.available += 1,
+        }
+    }
+}
+
+type SharedDispatcher = Arc<Mutex<Dispatcher>>;
+
+fn lock_dispatcher(dispatcher: &Mutex<Dispatcher>) -> MutexGuard<'_, Dispatcher> {
+    dispatcher.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Concurrency slot held while a task runs.
+struct Permit {
+    dispatcher: SharedDispatcher,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        lock_dispatcher(&self.dispatcher).release();
+    }
+}
+
+/// Queue entry owned by a waiting task; dequeues it if the task is dropped.
+struct QueuedTask {
+    dispatcher: Option<SharedDispatcher>,
+    lane: usize,
+    key: String,
+    id: u64,
+}
+
+impl QueuedTask {
+    fn into_permit(mut self) -> Permit {
+        let dispatcher = self
+            .dispatcher
+            .take()
+            .expect("queued task already admitted");
+        Permit { dispatcher }
+    }
+}
+
+impl Drop for QueuedTask {
+    fn drop(&mut self) {
+        let Some(dispatcher) = self.dispatcher.take() else {
+            return;
+        };
+        let mut dispatcher = lock_dispatcher(&dispatcher);
+        if dispatcher.lanes[self.lane].remove(&self.key, self.id) {
+            dispatcher.stats.queued[self.lane] -= 1;
+        } else {
+            // Granted a slot but dropped before it could run.
+            dispatcher.release();
+        }
+    }
+}
+
+enum Admission {
+    Ready(Permit),
+    Queued(QueuedTask, oneshot::Receiver<()>),
+}
+
+impl Admission {
+    async fn acquire(self) -> Permit {
+        match self {
+            Self::Ready(permit) => permit,
+            Self::Queued(queued, granted) => {
+                // The sender is only dropped after sending or once the guard
+                // itself dequeued the task, so the result carries no signal.
+                let _ = granted.await;
+                queued.into_permit()
+            }
+        }
+    }
+}
+
+/// Outcome of [`TaskScheduler::drain`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrainReport {
+    completed: usize,
+    aborted: usize,
+}
+
+impl DrainReport {
+    /// Returns the number of tasks that finished before the drain timeout.
+    #[must_use]
+    pub const fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Returns the number of tasks aborted when the drain timeout elapsed.
+    #[must_use]
+    pub const fn aborted(&self) -> usize {
+        self.aborted
+    }
+}
+
+/// Tasks spawned through the scheduler that have not finished yet.
+#[derive(Debug)]
+struct TaskTracker {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, AbortHandle>>,
+    pending: watch::Sender<usize>,
+}
+
+impl TaskTracker {
+    fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            tasks: Mutex::new(HashMap::new()),
+            pending: watch::Sender::new(0),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, AbortHandle>> {
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn remove(&self, id: u64) {
+        let mut tasks = self.lock();
+        if tasks.remove(&id).is_some() {
+            self.pending.send_replace(tasks.len());
+        }
+    }
+}
+
+/// Removes a task from the tracker when it completes or is aborted.
+struct TrackedTask {
+    tracker: Arc<TaskTracker>,
+    id: u64,
+}
+
+impl Drop for TrackedTask {
+    fn drop(&mut self) {
+        self.tracker.remove(self.id);
+    }
+}
+
 /// Lightweight wrapper around `tokio::spawn` that enforces per-agent concurrency.
-#[derive(Debug, Clone)]
+///
+/// Tasks beyond the concurrency limit wait in priority lanes; within a lane,
+/// fairness keys are served by weighted round-robin.
+#[derive(Clone)]
 pub struct TaskScheduler {
-    semaphore: Arc<Semaphore>,
+    dispatcher: SharedDispatcher,
     closed: Arc<AtomicBool>,
+    tracker: Arc<TaskTracker>,
     config: SchedulerConfig,
 }
 
+impl fmt::Debug for TaskScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskScheduler")
+            .field("config", &self.config)
+            .field("closed", &self.is_closed())
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
 impl TaskScheduler {
     /// Constructs a scheduler using the provided configuration.
     #[must_use]
     pub fn new(config: SchedulerConfig) -> Self {
-        let permits = config.max_concurrency().get();
         Self {
-            semaphore: Arc::new(Semaphore::new(permits)),
+            dispatcher: Arc::new(Mutex::new(Dispatcher::new(config))),
             closed: Arc::new(AtomicBool::new(false)),
+            tracker: Arc::new(TaskTracker::new()),
             config,
         }
     }
@@ -67,25 +477,100 @@ impl TaskScheduler {
         self.closed.load(Ordering::Acquire)
     }
 
+    /// Sets how many consecutive turns `key` gets within a lane before the
+    /// next fairness key is served. Keys default to a weight of one.
+    pub fn set_weight(&self, key: impl Into<String>, weight: NonZeroU32) {
+        lock_dispatcher(&self.dispatcher)
+            .weights
+            .insert(key.into(), weight);
+    }
+
+    /// Returns queue depth and wait-time statistics.
+    #[must_use]
+    pub fn stats(&self) -> SchedulerStats {
+        let dispatcher = lock_dispatcher(&self.dispatcher);
+        SchedulerStats {
+            running: self.config.max_concurrency().get() - dispatcher.available,
+            ..dispatcher.stats
+        }
+    }
+
+    /// Returns the number of spawned tasks that are queued or running.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        *self.tracker.pending.borrow()
+    }
+
     /// Closes the scheduler, preventing new tasks from being spawned.
+    ///
+    /// Tasks that were already spawned keep running; use [`drain`](Self::drain)
+    /// to wait for them.
     pub fn close(&self) {
         self.closed.store(true, Ordering::Release);
-        self.semaphore.close();
     }
 
-    /// Spawns a future, respecting the configured concurrency limit.
+    /// Closes the scheduler and waits up to `timeout` for queued and running
+    /// tasks to finish, aborting any that remain afterwards.
+    pub async fn drain(&self, timeout: Duration) -> DrainReport {
+        self.close();
+        let pending = self.pending();
+
+        let mut receiver = self.tracker.pending.subscribe();
+        let finished = tokio::time::timeout(timeout, async move {
+            receiver.wait_for(|pending| *pending == 0).await.is_ok()
+        })
+        .await
+        .unwrap_or(false);
+        if finished {
+            return DrainReport {
+                completed: pending,
+                aborted: 0,
+            };
+        }
+
+        let remaining: Vec<AbortHandle> = {
+            let mut tasks = self.tracker.lock();
+            let remaining = tasks.drain().map(|(_, handle)| handle).collect();
+            self.tracker.pending.send_replace(0);
+            remaining
+        };
+        for handle in &remaining {
+            handle.abort();
+        }
+        DrainReport {
+            completed: pending.saturating_sub(remaining.len()),
+            aborted: remaining.len(),
+        }
+    }
+
+    /// Spawns a [`TaskPriority::Normal`] future, respecting the configured
+    /// concurrency limit.
     ///
     /// # Errors
     ///
     /// Returns [`SchedulerError::Closed`] when the scheduler is closed before the
-    /// task is enqueued.
+    /// task is enqueued, or [`SchedulerError::QueueFull`] when the task would
+    /// exceed the queue capacity.
+    pub fn spawn<F, T>(&self, future: F) -> SchedulerResult<JoinHandle<T>>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.spawn_with(&TaskOptions::default(), future)
+    }
+
+    /// Spawns a future in the lane and fairness queue selected by `options`.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if the scheduler is closed while a task is awaiting a concurrency
-    /// permit. This indicates that `close` was invoked concurrently with task
-    /// submission.
-    pub fn spawn<F, T>(&self, future: F) -> SchedulerResult<JoinHandle<T>>
+    /// Returns [`SchedulerError::Closed`] when the scheduler is closed before the
+    /// task is enqueued, or [`SchedulerError::QueueFull`] when the task would
+    /// exceed the queue capacity.
+    pub fn spawn_with<F, T>(
+        &self,
+        options: &TaskOptions,
+        future: F,
+    ) -> SchedulerResult<JoinHandle<T>>
     where
         F: Future<Output = T> + Send + 'static,
         T: Send + 'static,
@@ -94,20 +579,73 @@ impl TaskScheduler {
             return Err(SchedulerError::Closed);
         }
 
-        let semaphore = Arc::clone(&self.semaphore);
+        let admission = self.admit(options)?;
+        let tracked = TrackedTask {
+            tracker: Arc::clone(&self.tracker),
+            id: self.tracker.next_id.fetch_add(1, Ordering::Relaxed),
+        };
+        let id = tracked.id;
 
+        // Hold the lock while spawning so the task cannot finish and untrack
+        // itself before its abort handle is registered.
+        let mut tasks = self.tracker.lock();
         let handle = tokio::spawn(async move {
-            let permit = semaphore
-                .acquire_owned()
-                .await
-                .expect("scheduler closed while awaiting permit");
-            let output = future.await;
-            drop(permit);
-            output
+            let _tracked = tracked;
+            let _permit = admission.acquire().await;
+            future.await
         });
+        tasks.insert(id, handle.abort_handle());
+        self.tracker.pending.send_replace(tasks.len());
 
         Ok(handle)
     }
+
+    fn admit(&self, options: &TaskOptions) -> SchedulerResult<Admission> {
+        let mut dispatcher = lock_dispatcher(&self.dispatcher);
+        if dispatcher.available > 0 {
+            dispatcher.available -= 1;
+            dispatcher.record_wait(Duration::ZERO);
+            return Ok(Admission::Ready(Permit {
+                dispatcher: Arc::clone(&self.dispatcher),
+            }));
+        }
+
+        if let Some(capacity) = dispatcher.capacity
+            && dispatcher.stats.queued() >= capacity.get()
+        {
+            dispatcher.stats.rejected += 1;
+            return Err(SchedulerError::QueueFull {
+                capacity: capacity.get(),
+            });
+        }
+
+        let lane = options.priority.lane();
+        let key = options.fairness_key.clone().unwrap_or_default();
+        let id = dispatcher.next_waiter;
+        dispatcher.next_waiter += 1;
+        let (grant, granted) = oneshot::channel();
+        let weight = weight_of(&dispatcher.weights, &key);
+        dispatcher.lanes[lane].push(
+            key.clone(),
+            Waiter {
+                id,
+                enqueued_at: Instant::now(),
+                grant,
+            },
+            weight,
+        );
+        dispatcher.stats.queued[lane] += 1;
+
+        Ok(Admission::Queued(
+            QueuedTask {
+                dispatcher: Some(Arc::clone(&self.dispatcher)),
+                lane,
+                key,
+                id,
+            },
+            granted,
+        ))
+    }
 }
 
 impl Default for TaskScheduler {
@@ -122,6 +660,12 @@ pub enum SchedulerError {
     /// Scheduler is closed and will not accept new tasks.
     #[error("scheduler closed")]
     Closed,
+    /// The wait queue is full and the task was shed.
+    #[error("scheduler queue full (capacity {capacity})")]
+    QueueFull {
+        /// Configured queue capacity.
+        capacity: usize,
+    },
 }
 
 /// Result alias for scheduler operations.
@@ -164,6 +708,178 @@ mod tests {
         assert_eq!(max_seen.load(Ordering::SeqCst), 2);
     }
 
+    #[tokio::test]
+    async fn drain_waits_for_queued_tasks_after_close() {
+        let scheduler = TaskScheduler::new(SchedulerConfig::new(NonZeroUsize::new(1).unwrap()));
+        let finished = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..3 {
+            let finished = Arc::clone(&finished);
+            handles.push(
+                scheduler
+                    .spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        finished.fetch_add(1, Ordering::SeqCst);
+                    })
+                    .unwrap(),
+            );
+        }
+        scheduler.close();
+        assert_eq!(scheduler.pending(), 3);
+
+        let report = scheduler.drain(Duration::from_secs(5)).await;
+        assert_eq!(
+            report,
+            DrainReport {
+                completed: 3,
+                aborted: 0
+            }
+        );
+        assert_eq!(finished.load(Ordering::SeqCst), 3);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn drain_aborts_work_after_timeout() {
+        let scheduler = TaskScheduler::new(SchedulerConfig::new(NonZeroUsize::new(1).unwrap()));
+        scheduler
+            .spawn(tokio::time::sleep(Duration::from_millis(5)))
+            .unwrap();
+        let stuck = scheduler.spawn(std::future::pending::<()>()).unwrap();
+        let queued = scheduler.spawn(async {}).unwrap();
+
+        let report = scheduler.drain(Duration::from_millis(50)).await;
+        assert_eq!(report.completed(), 1);
+        assert_eq!(report.aborted(), 2);
+        assert_eq!(scheduler.pending(), 0);
+        assert!(stuck.await.unwrap_err().is_cancelled());
+        assert!(queued.await.unwrap_err().is_cancelled());
+        assert_eq!(
+            scheduler.spawn(async {}).unwrap_err(),
+            SchedulerError::Closed
+        );
+    }
+
+    /// Occupies the only slot of `scheduler` until the returned sender fires.
+    fn block(scheduler: &TaskScheduler) -> (oneshot::Sender<()>, JoinHandle<()>) {
+        let (release, released) = oneshot::channel::<()>();
+        let handle = scheduler
+            .spawn_with(
+                &TaskOptions::new().with_priority(TaskPriority::High),
+                async move {
+                    let _ = released.await;
+                },
+            )
+            .unwrap();
+        (release, handle)
+    }
+
+    fn spawn_recording(
+        scheduler: &TaskScheduler,
+        options: &TaskOptions,
+        order: &Arc<Mutex<Vec<String>>>,
+        label: &str,
+    ) -> JoinHandle<()> {
+        let order = Arc::clone(order);
+        let label = label.to_owned();
+        scheduler
+            .spawn_with(options, async move {
+                order.lock().unwrap().push(label);
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_higher_priority_lanes_first() {
+        let scheduler = TaskScheduler::new(SchedulerConfig::new(NonZeroUsize::new(1).unwrap()));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (release, blocker) = block(&scheduler);
+
+        let mut handles = Vec::new();
+        for (priority, label) in [
+            (TaskPriority::Low, "batch"),
+            (TaskPriority::Normal, "interactive"),
+            (TaskPriority::High, "heartbeat"),
+        ] {
+            let options = TaskOptions::new().with_priority(priority);
+            handles.push(spawn_recording(&scheduler, &options, &order, label));
+        }
+        let stats = scheduler.stats();
+        assert_eq!(stats.running(), 1);
+        assert_eq!(stats.queued(), 3);
+        assert_eq!(stats.queued_in(TaskPriority::Low), 1);
+
+        release.send(()).unwrap();
+        blocker.await.unwrap();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            ["heartbeat", "interactive", "batch"]
+        );
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.running(), 0);
+        assert_eq!(stats.queued(), 0);
+        assert_eq!(stats.admitted(), 4);
+        assert!(stats.max_wait() >= stats.mean_wait());
+    }
+
+    #[tokio::test]
+    async fn round_robins_fairness_keys_by_weight() {
+        let scheduler = TaskScheduler::new(SchedulerConfig::new(NonZeroUsize::new(1).unwrap()));
+        scheduler.set_weight("tenant-a", NonZeroU32::new(2).unwrap());
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (release, blocker) = block(&scheduler);
+
+        let mut handles = Vec::new();
+        for label in ["a1", "a2", "a3", "a4", "b1", "b2"] {
+            let tenant = if label.starts_with('a') {
+                "tenant-a"
+            } else {
+                "tenant-b"
+            };
+            let options = TaskOptions::new().with_fairness_key(tenant);
+            handles.push(spawn_recording(&scheduler, &options, &order, label));
+        }
+
+        release.send(()).unwrap();
+        blocker.await.unwrap();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["a1", "a2", "b1", "a3", "a4", "b2"]);
+    }
+
+    #[tokio::test]
+    async fn sheds_load_when_queue_is_full() {
+        let config = SchedulerConfig::new(NonZeroUsize::new(1).unwrap())
+            .with_queue_capacity(NonZeroUsize::new(1).unwrap());
+        let scheduler = TaskScheduler::new(config);
+        let (release, blocker) = block(&scheduler);
+
+        let queued = scheduler.spawn(async {}).unwrap();
+        assert_eq!(
+            scheduler.spawn(async {}).unwrap_err(),
+            SchedulerError::QueueFull { capacity: 1 }
+        );
+        assert_eq!(scheduler.stats().rejected(), 1);
+
+        queued.abort();
+        assert!(queued.await.unwrap_err().is_cancelled());
+        assert_eq!(scheduler.stats().queued(), 0);
+
+        let admitted = scheduler.spawn(async { 7 }).unwrap();
+        release.send(()).unwrap();
+        blocker.await.unwrap();
+        assert_eq!(admitted.await.unwrap(), 7);
+        assert_eq!(scheduler.stats().running(), 0);
+    }
+
     #[tokio::test]
     async fn close_prevents_new_tasks() {
         let scheduler = TaskScheduler::default();
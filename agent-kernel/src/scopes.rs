@@ -0,0 +1,244 @@
+//! Caller identity, granted scopes, and the scopes tools require.
+//!
+//! [`ScopeGrants`] attaches a [`Caller`] to every message, naming the peer
+//! that a [`SignatureVerifier`](crate::SignatureVerifier) or
+//! [`SecureChannels`](crate::SecureChannels) layer authenticated and the
+//! scopes granted to it. A call executor configured with [`ToolScopes`]
+//! refuses to run tools whose capabilities require scopes the caller lacks.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use agent_primitives::{AgentId, Capability, CapabilityId, Scope};
+use async_trait::async_trait;
+
+use crate::encryption::EncryptedPeer;
+use crate::middleware::{Middleware, Next};
+use crate::signing::VerifiedPeer;
+use crate::{HandlerContext, HandlerResult};
+
+/// Context extension naming who sent a message and what they may do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    principal: Option<AgentId>,
+    scopes: Vec<Scope>,
+}
+
+impl Caller {
+    /// Creates a caller; `principal` is `None` for unauthenticated peers.
+    #[must_use]
+    pub fn new(principal: Option<AgentId>, scopes: impl IntoIterator<Item = Scope>) -> Self {
+        Self {
+            principal,
+            scopes: scopes.into_iter().collect(),
+        }
+    }
+
+    /// Returns the authenticated agent, if the message was signed or sealed.
+    #[must_use]
+    pub const fn principal(&self) -> Option<AgentId> {
+        self.principal
+    }
+
+    /// Returns the scopes granted to the caller.
+    #[must_use]
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    /// Returns whether any granted scope covers `required`.
+    #[must_use]
+    pub fn grants(&self, required: &Scope) -> bool {
+        self.scopes.iter().any(|granted| granted.grants(required))
+    }
+}
+
+/// Scopes granted to authenticated agents and to anonymous callers.
+///
+/// As a [`Middleware`] it inserts a [`Caller`] extension, so it must run
+/// inside the layer that authenticates peers.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeGrants {
+    grants: Arc<Mutex<HashMap<AgentId, Vec<Scope>>>>,
+    anonymous: Vec<Scope>,
+}
+
+impl ScopeGrants {
+    /// Creates an empty grant table; anonymous callers get no scopes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants `scopes` to callers that are not authenticated.
+    #[must_use]
+    pub fn with_anonymous(mut self, scopes: impl IntoIterator<Item = Scope>) -> Self {
+        self.anonymous = scopes.into_iter().collect();
+        self
+    }
+
+    /// Returns the scopes granted to anonymous callers.
+    #[must_use]
+    pub fn anonymous(&self) -> &[Scope] {
+        &self.anonymous
+    }
+
+    /// Adds `scopes` to those granted to `agent_id`.
+    pub fn grant(&self, agent_id: AgentId, scopes: impl IntoIterator<Item = Scope>) {
+        let mut grants = self.lock();
+        let granted = grants.entry(agent_id).or_default();
+        for scope in scopes {
+            if !granted.contains(&scope) {
+                granted.push(scope);
+            }
+        }
+    }
+
+    /// Removes every scope granted to `agent_id`, returning them.
+    #[must_use]
+    pub fn revoke(&self, agent_id: AgentId) -> Vec<Scope> {
+        self.lock().remove(&agent_id).unwrap_or_default()
+    }
+
+    /// Returns the scopes granted to `agent_id`.
+    #[must_use]
+    pub fn scopes(&self, agent_id: AgentId) -> Vec<Scope> {
+        self.lock().get(&agent_id).cloned().unwrap_or_default()
+    }
+
+    /// Resolves the caller for `principal`; authenticated agents hold their
+    /// own grants plus the anonymous ones.
+    #[must_use]
+    pub fn caller(&self, principal: Option<AgentId>) -> Caller {
+        let mut scopes = self.anonymous.clone();
+        if let Some(agent_id) = principal {
+            scopes.extend(self.scopes(agent_id));
+        }
+        Caller::new(principal, scopes)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<AgentId, Vec<Scope>>> {
+        self.grants.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl Middleware for ScopeGrants {
+    async fn handle(&self, mut ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        let principal = ctx
+            .extension::<VerifiedPeer>()
+            .map(VerifiedPeer::agent_id)
+            .or_else(|| {
+                ctx.extension::<EncryptedPeer>()
+                    .map(EncryptedPeer::agent_id)
+            });
+        ctx.insert_extension(self.caller(principal));
+        next.run(ctx).await
+    }
+}
+
+/// Scopes a caller needs for each capability a tool declares.
+///
+/// A capability missing from the catalog requires a scope named after its
+/// id, so undeclared capabilities are never granted by accident.
+#[derive(Debug, Clone, Default)]
+pub struct ToolScopes {
+    required: HashMap<CapabilityId, Vec<Scope>>,
+}
+
+impl ToolScopes {
+    /// Creates an empty catalog.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a catalog from capability descriptors, typically those in the
+    /// agent's manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`agent_primitives::Error::InvalidScope`] if a descriptor
+    /// carries a scope that is not a valid [`Scope`].
+    pub fn from_capabilities<'a>(
+        capabilities: impl IntoIterator<Item = &'a Capability>,
+    ) -> agent_primitives::Result<Self> {
+        let mut catalog = Self::new();
+        for capability in capabilities {
+            let scopes = capability
+                .scopes()
+                .iter()
+                .map(Scope::new)
+                .collect::<agent_primitives::Result<_>>()?;
+            catalog.require(capability.id().clone(), scopes);
+        }
+        Ok(catalog)
+    }
+
+    /// Requires `scopes` for tools that declare `capability`.
+    pub fn require(&mut self, capability: CapabilityId, scopes: Vec<Scope>) {
+        self.required.insert(capability, scopes);
+    }
+
+    /// Returns every scope required by `capabilities`.
+    #[must_use]
+    pub fn required_for(&self, capabilities: &[CapabilityId]) -> Vec<Scope> {
+        let mut required = Vec::new();
+        for capability in capabilities {
+            let scopes = match self.required.get(capability) {
+                Some(scopes) => scopes.clone(),
+                // Capability ids are always valid single-segment scopes.
+                None => Scope::new(capability.as_str()).into_iter().collect(),
+            };
+            for scope in scopes {
+                if !required.contains(&scope) {
+                    required.push(scope);
+                }
+            }
+        }
+        required
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(value: &str) -> Scope {
+        Scope::new(value).expect("scope")
+    }
+
+    #[test]
+    fn authenticated_callers_hold_their_grants_and_anonymous_ones() {
+        let agent = AgentId::random();
+        let grants = ScopeGrants::new().with_anonymous([scope("catalog:read")]);
+        grants.grant(agent, [scope("inventory:*")]);
+
+        let caller = grants.caller(Some(agent));
+        assert!(caller.grants(&scope("inventory:write")));
+        assert!(caller.grants(&scope("catalog:read")));
+
+        let anonymous = grants.caller(None);
+        assert!(!anonymous.grants(&scope("inventory:write")));
+        assert_eq!(grants.revoke(agent), vec![scope("inventory:*")]);
+        assert!(!grants.caller(Some(agent)).grants(&scope("inventory:write")));
+    }
+
+    #[test]
+    fn unknown_capabilities_require_their_own_id() {
+        let known = CapabilityId::new("inventory.adjust").expect("id");
+        let unknown = CapabilityId::new("payments.refund").expect("id");
+        let capability = Capability::builder(known.clone())
+            .name("Adjust stock")
+            .and_then(|b| b.version("1.0"))
+            .and_then(|b| b.add_scope("inventory:write"))
+            .and_then(agent_primitives::CapabilityBuilder::build)
+            .expect("capability");
+        let catalog = ToolScopes::from_capabilities([&capability]).expect("catalog");
+
+        assert_eq!(
+            catalog.required_for(&[known, unknown]),
+            vec![scope("inventory:write"), scope("payments.refund")]
+        );
+    }
+}
@@ -0,0 +1,767 @@
+//! Conversation sessions persisted through the [`MemoryBus`].
+//!
+//! Each turn of a session is stored as a memory record tagged `session` whose
+//! payload is the serialized [`PromptMessage`]. Loading reads the volatile
+//! buffer first and falls back to the journal when turns have been evicted or
+//! the runtime restarted. Ending or expiring a session appends a marker record
+//! so that earlier turns are never replayed into a new conversation.
+//!
+//! A session belongs to the caller that stored its first turn. The owner is
+//! recorded with every turn, so the binding survives restarts.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use agent_adapters::traits::{MessageRole, PromptMessage};
+use agent_memory::{MemoryBus, MemoryChannel, MemoryError, MemoryRecord, MemoryResult};
+use agent_primitives::AgentId;
+use bytes::Bytes;
+use serde_json::Value;
+
+use crate::HandlerContext;
+
+const SESSION_TAG: &str = "session";
+const SESSION_ID_KEY: &str = "session_id";
+const TURN_KEY: &str = "turn";
+const EVENT_KEY: &str = "session_event";
+const OWNER_KEY: &str = "session_owner";
+
+/// Configuration for a [`SessionStore`].
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConfig {
+    idle_ttl: Option<Duration>,
+    max_history_tokens: usize,
+    journal_scan_limit: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle_ttl: None,
+            max_history_tokens: 8192,
+            journal_scan_limit: 10_000,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Creates the default configuration: no expiry, an 8192 token budget,
+    /// and a journal scan limit of 10 000 records.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expires sessions that have been idle for longer than `ttl`.
+    #[must_use]
+    pub fn with_idle_ttl(mut self, ttl: Duration) -> Self {
+        self.idle_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the estimated token budget for history plus the incoming turns.
+    #[must_use]
+    pub fn with_max_history_tokens(mut self, tokens: usize) -> Self {
+        self.max_history_tokens = tokens;
+        self
+    }
+
+    /// Sets how many journal records are scanned when restoring a session.
+    #[must_use]
+    pub fn with_journal_scan_limit(mut self, limit: usize) -> Self {
+        self.journal_scan_limit = limit;
+        self
+    }
+
+    /// Returns the idle expiry, if any.
+    #[must_use]
+    pub const fn idle_ttl(&self) -> Option<Duration> {
+        self.idle_ttl
+    }
+
+    /// Returns the history token budget.
+    #[must_use]
+    pub const fn max_history_tokens(&self) -> usize {
+        self.max_history_tokens
+    }
+
+    /// Returns the journal scan limit.
+    #[must_use]
+    pub const fn journal_scan_limit(&self) -> usize {
+        self.journal_scan_limit
+    }
+}
+
+/// Caller a session is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionOwner {
+    /// An authenticated agent.
+    Agent(AgentId),
+    /// An unauthenticated peer, identified by its transport address.
+    Address(SocketAddr),
+}
+
+impl SessionOwner {
+    /// Returns the owner a call would bind a session to: its principal,
+    /// falling back to its sender address. Local calls have neither.
+    #[must_use]
+    pub fn of(ctx: &HandlerContext) -> Option<Self> {
+        ctx.principal()
+            .map(Self::Agent)
+            .or_else(|| ctx.sender().map(Self::Address))
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.split_once(':')? {
+            ("agent", id) => id.parse().ok().map(Self::Agent),
+            ("addr", addr) => addr.parse().ok().map(Self::Address),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SessionOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Agent(id) => write!(f, "agent:{id}"),
+            Self::Address(addr) => write!(f, "addr:{addr}"),
+        }
+    }
+}
+
+/// Summary of a known session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    id: String,
+    turns: u64,
+    owner: Option<SessionOwner>,
+    created_at: SystemTime,
+    last_active: SystemTime,
+}
+
+impl SessionInfo {
+    /// Returns the session identifier.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the number of stored turns.
+    #[must_use]
+    pub const fn turns(&self) -> u64 {
+        self.turns
+    }
+
+    /// Returns the caller the session is bound to; `None` for sessions
+    /// started by local calls.
+    #[must_use]
+    pub const fn owner(&self) -> Option<SessionOwner> {
+        self.owner
+    }
+
+    /// Returns when the first turn was stored.
+    #[must_use]
+    pub const fn created_at(&self) -> SystemTime {
+        self.created_at
+    }
+
+    /// Returns when the most recent turn was stored.
+    #[must_use]
+    pub const fn last_active(&self) -> SystemTime {
+        self.last_active
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SessionState {
+    /// First turn number belonging to the current session generation.
+    first_turn: u64,
+    /// Turn number assigned to the next appended message.
+    next_turn: u64,
+    owner: Option<SessionOwner>,
+    created_at: SystemTime,
+    last_active: SystemTime,
+}
+
+impl SessionState {
+    fn fresh(first_turn: u64, now: SystemTime) -> Self {
+        Self {
+            first_turn,
+            next_turn: first_turn,
+            owner: None,
+            created_at: now,
+            last_active: now,
+        }
+    }
+}
+
+/// Stores and restores conversation turns keyed by session id.
+#[derive(Clone)]
+pub struct SessionStore {
+    memory: Arc<MemoryBus>,
+    config: SessionConfig,
+    sessions: Arc<Mutex<HashMap<String, SessionState>>>,
+}
+
+impl std::fmt::Debug for SessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionStore")
+            .field("config", &self.config)
+            .field("sessions", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionStore {
+    /// Creates a store persisting turns through the supplied memory bus.
+    #[must_use]
+    pub fn new(memory: Arc<MemoryBus>, config: SessionConfig) -> Self {
+        Self {
+            memory,
+            config,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the store configuration.
+    #[must_use]
+    pub const fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Loads the stored turns of a session, oldest first.
+    ///
+    /// Unknown sessions yield an empty history. A session idle for longer than
+    /// the configured TTL is expired and also yields an empty history.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when reading the journal, decoding a stored
+    /// turn, or recording the expiry marker fails.
+    pub async fn load(&self, session_id: &str) -> MemoryResult<Vec<PromptMessage>> {
+        validate_id(session_id)?;
+        let now = SystemTime::now();
+
+        let known = self.lock().get(session_id).copied();
+        if let Some(state) = known {
+            if self.is_stale(&state, now) {
+                self.close(session_id, state.next_turn, "expired").await?;
+                return Ok(Vec::new());
+            }
+            if let Some(history) = self.load_volatile(session_id, &state).await? {
+                return Ok(history);
+            }
+        }
+
+        let (state, records) = self.restore(session_id, now).await?;
+        if records.is_empty() {
+            self.lock().entry(session_id.to_owned()).or_insert(state);
+            return Ok(Vec::new());
+        }
+        if self.is_stale(&state, now) {
+            self.close(session_id, state.next_turn, "expired").await?;
+            return Ok(Vec::new());
+        }
+        self.lock().insert(session_id.to_owned(), state);
+        records.iter().map(decode_turn).collect()
+    }
+
+    /// Appends turns to a session.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when building or recording a turn fails.
+    pub async fn append(&self, session_id: &str, messages: &[PromptMessage]) -> MemoryResult<()> {
+        let records = self.prepare(session_id, messages).await?;
+        self.commit(records).await
+    }
+
+    /// Ends a session so later calls with the same id start a new conversation.
+    ///
+    /// Returns `false` when the session is unknown.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when recording the end marker fails.
+    pub async fn end(&self, session_id: &str) -> MemoryResult<bool> {
+        let Some(state) = self.lock().get(session_id).copied() else {
+            return Ok(false);
+        };
+        self.close(session_id, state.next_turn, "ended").await?;
+        Ok(true)
+    }
+
+    /// Checks whether `caller` may use a session, binding a session without
+    /// turns to it.
+    ///
+    /// Local callers (`None`) may use every session. Remote callers may use
+    /// only the sessions bound to them, so sessions started locally stay
+    /// local. Load the session first so that its owner is known.
+    #[must_use]
+    pub fn claim(&self, session_id: &str, caller: Option<SessionOwner>) -> bool {
+        let Some(caller) = caller else {
+            return true;
+        };
+        let mut sessions = self.lock();
+        let state = sessions
+            .entry(session_id.to_owned())
+            .or_insert_with(|| SessionState::fresh(0, SystemTime::now()));
+        match state.owner {
+            Some(owner) => owner == caller,
+            None if state.next_turn == state.first_turn => {
+                state.owner = Some(caller);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Expires every known session idle for longer than the configured TTL,
+    /// returning the expired ids.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when recording an expiry marker fails.
+    pub async fn expire_idle(&self) -> MemoryResult<Vec<String>> {
+        let now = SystemTime::now();
+        let stale: Vec<(String, u64)> = self
+            .lock()
+            .iter()
+            .filter(|(_, state)| state.next_turn > state.first_turn && self.is_stale(state, now))
+            .map(|(id, state)| (id.clone(), state.next_turn))
+            .collect();
+
+        let mut expired = Vec::with_capacity(stale.len());
+        for (id, next_turn) in stale {
+            self.close(&id, next_turn, "expired").await?;
+            expired.push(id);
+        }
+        expired.sort();
+        Ok(expired)
+    }
+
+    /// Lists sessions with at least one stored turn, ordered by id.
+    ///
+    /// Sessions are known once they have been loaded or appended to since
+    /// startup; call [`restore_all`](Self::restore_all) to discover sessions
+    /// recorded by a previous run.
+    #[must_use]
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self
+            .lock()
+            .iter()
+            .filter(|(_, state)| state.next_turn > state.first_turn)
+            .map(|(id, state)| SessionInfo {
+                id: id.clone(),
+                turns: state.next_turn - state.first_turn,
+                owner: state.owner,
+                created_at: state.created_at,
+                last_active: state.last_active,
+            })
+            .collect();
+        sessions.sort_by(|a, b| a.id.cmp(&b.id));
+        sessions
+    }
+
+    /// Rebuilds the session index from the journal, returning the number of
+    /// sessions discovered.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when reading the journal fails.
+    pub async fn restore_all(&self) -> MemoryResult<usize> {
+        let records = self
+            .memory
+            .journal_tail(self.config.journal_scan_limit)
+            .await?;
+        let now = SystemTime::now();
+
+        let mut restored: HashMap<String, SessionState> = HashMap::new();
+        for record in &records {
+            let Some(id) = session_of(record) else {
+                continue;
+            };
+            apply_record(
+                restored
+                    .entry(id.to_owned())
+                    .or_insert_with(|| SessionState::fresh(0, now)),
+                record,
+            );
+        }
+        restored.retain(|_, state| state.next_turn > state.first_turn);
+
+        let count = restored.len();
+        let mut sessions = self.lock();
+        for (id, state) in restored {
+            sessions.entry(id).or_insert(state);
+        }
+        Ok(count)
+    }
+
+    /// Merges stored history with incoming turns, dropping the oldest history
+    /// turns until the estimated token count fits the configured budget.
+    ///
+    /// System turns in the history are always kept, and tool results whose
+    /// originating tool-call turn was dropped are removed with it.
+    #[must_use]
+    pub fn merge(
+        &self,
+        history: Vec<PromptMessage>,
+        incoming: Vec<PromptMessage>,
+    ) -> Vec<PromptMessage> {
+        let budget = self.config.max_history_tokens;
+        let mut used: usize = history.iter().chain(&incoming).map(estimate_tokens).sum();
+
+        let mut kept = Vec::with_capacity(history.len() + incoming.len());
+        let mut trimming = true;
+        for message in history {
+            if trimming && message.role() != MessageRole::System {
+                let orphaned = message.role() == MessageRole::Tool;
+                if used > budget || orphaned {
+                    used = used.saturating_sub(estimate_tokens(&message));
+                    continue;
+                }
+                trimming = false;
+            }
+            kept.push(message);
+        }
+        kept.extend(incoming);
+        kept
+    }
+
+    /// Builds the memory records for new turns, reserving their turn numbers.
+    pub(crate) async fn prepare(
+        &self,
+        session_id: &str,
+        messages: &[PromptMessage],
+    ) -> MemoryResult<Vec<MemoryRecord>> {
+        validate_id(session_id)?;
+        if !self.lock().contains_key(session_id) {
+            self.load(session_id).await?;
+        }
+
+        let (first, owner) = {
+            let mut sessions = self.lock();
+            let now = SystemTime::now();
+            let state = sessions
+                .entry(session_id.to_owned())
+                .or_insert_with(|| SessionState::fresh(0, now));
+            let first = state.next_turn;
+            state.next_turn += messages.len() as u64;
+            state.last_active = now;
+            (first, state.owner)
+        };
+
+        messages
+            .iter()
+            .zip(first..)
+            .map(|(message, turn)| {
+                let payload = Bytes::from(serde_json::to_vec(message)?);
+                let mut builder = MemoryRecord::builder(channel_for(message.role()), payload)
+                    .tag(SESSION_TAG)?
+                    .metadata(SESSION_ID_KEY, Value::from(session_id))
+                    .metadata(TURN_KEY, Value::from(turn));
+                if let Some(owner) = owner {
+                    builder = builder.metadata(OWNER_KEY, Value::from(owner.to_string()));
+                }
+                builder.build()
+            })
+            .collect()
+    }
+
+    /// Records prepared turns through the memory bus.
+    pub(crate) async fn commit(&self, records: Vec<MemoryRecord>) -> MemoryResult<()> {
+        for record in records {
+            self.memory.record(record).await?;
+        }
+        Ok(())
+    }
+
+    async fn load_volatile(
+        &self,
+        session_id: &str,
+        state: &SessionState,
+    ) -> MemoryResult<Option<Vec<PromptMessage>>> {
+        let capacity = self.memory.stats().await.capacity;
+        let mut turns: Vec<(u64, MemoryRecord)> = self
+            .memory
+            .recent(capacity)
+            .await
+            .into_iter()
+            .filter(|record| session_of(record) == Some(session_id))
+            .filter_map(|record| {
+                let turn = turn_of(&record)?;
+                (turn >= state.first_turn && turn < state.next_turn).then_some((turn, record))
+            })
+            .collect();
+
+        // Fall back to the journal unless every turn is still buffered.
+        let expected = state.next_turn - state.first_turn;
+        if turns.len() as u64 != expected {
+            return Ok(None);
+        }
+        turns.sort_by_key(|(turn, _)| *turn);
+        turns
+            .iter()
+            .map(|(_, record)| decode_turn(record))
+            .collect::<MemoryResult<Vec<_>>>()
+            .map(Some)
+    }
+
+    async fn restore(
+        &self,
+        session_id: &str,
+        now: SystemTime,
+    ) -> MemoryResult<(SessionState, Vec<MemoryRecord>)> {
+        let records = self
+            .memory
+            .journal_tail(self.config.journal_scan_limit)
+            .await?;
+
+        let mut state = SessionState::fresh(0, now);
+        let mut turns: Vec<MemoryRecord> = Vec::new();
+        for record in records {
+            if session_of(&record) != Some(session_id) {
+                continue;
+            }
+            apply_record(&mut state, &record);
+            if record.metadata().contains_key(EVENT_KEY) {
+                turns.clear();
+            } else if turn_of(&record).is_some() {
+                turns.push(record);
+            }
+        }
+        turns.sort_by_key(|record| turn_of(record).unwrap_or_default());
+        Ok((state, turns))
+    }
+
+    async fn close(&self, session_id: &str, next_turn: u64, event: &str) -> MemoryResult<()> {
+        let record = MemoryRecord::builder(MemoryChannel::System, Bytes::new())
+            .tag(SESSION_TAG)?
+            .metadata(SESSION_ID_KEY, Value::from(session_id))
+            .metadata(TURN_KEY, Value::from(next_turn))
+            .metadata(EVENT_KEY, Value::from(event))
+            .build()?;
+        self.memory.record(record).await?;
+        self.lock().remove(session_id);
+        Ok(())
+    }
+
+    fn is_stale(&self, state: &SessionState, now: SystemTime) -> bool {
+        self.config.idle_ttl.is_some_and(|ttl| {
+            now.duration_since(state.last_active)
+                .is_ok_and(|idle| idle > ttl)
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionState>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Folds a journal record into the session state it belongs to.
+fn apply_record(state: &mut SessionState, record: &MemoryRecord) {
+    let Some(turn) = turn_of(record) else {
+        return;
+    };
+    if record.metadata().contains_key(EVENT_KEY) {
+        // End and expiry markers start a new generation at the recorded turn.
+        *state = SessionState::fresh(turn, record.timestamp());
+        return;
+    }
+    if state.next_turn == state.first_turn {
+        state.created_at = record.timestamp();
+    }
+    if let Some(owner) = record
+        .metadata()
+        .get(OWNER_KEY)
+        .and_then(Value::as_str)
+        .and_then(SessionOwner::parse)
+    {
+        state.owner = Some(owner);
+    }
+    state.next_turn = state.next_turn.max(turn + 1);
+    state.last_active = record.timestamp();
+}
+
+fn validate_id(session_id: &str) -> MemoryResult<()> {
+    if session_id.trim().is_empty() {
+        return Err(MemoryError::InvalidRecord("session id must not be empty"));
+    }
+    Ok(())
+}
+
+fn session_of(record: &MemoryRecord) -> Option<&str> {
+    if !record.tags().iter().any(|tag| tag == SESSION_TAG) {
+        return None;
+    }
+    record.metadata().get(SESSION_ID_KEY)?.as_str()
+}
+
+fn turn_of(record: &MemoryRecord) -> Option<u64> {
+    record.metadata().get(TURN_KEY)?.as_u64()
+}
+
+fn decode_turn(record: &MemoryRecord) -> MemoryResult<PromptMessage> {
+    Ok(serde_json::from_slice(record.payload())?)
+}
+
+fn channel_for(role: MessageRole) -> MemoryChannel {
+    match role {
+        MessageRole::User => MemoryChannel::Input,
+        MessageRole::Assistant => MemoryChannel::Output,
+        MessageRole::Tool => MemoryChannel::Tool,
+        MessageRole::System => MemoryChannel::System,
+    }
+}
+
+/// Rough token estimate (about four characters per token).
+fn estimate_tokens(message: &PromptMessage) -> usize {
+    let arguments: usize = message
+        .tool_calls()
+        .iter()
+        .map(|call| call.name().len() + call.arguments().to_string().len())
+        .sum();
+    ((message.content().len() + arguments) / 4).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::num::NonZeroUsize;
+
+    use agent_adapters::traits::ToolCall;
+    use agent_memory::{FileJournal, Journal, MemoryBusBuilder, VolatileConfig};
+    use agent_primitives::AgentId;
+
+    async fn bus(capacity: usize) -> (Arc<MemoryBus>, std::path::PathBuf) {
+        let mut path = std::env::temp_dir();
+        path.push(format!("session-test-{}.log", AgentId::random()));
+        let journal: Arc<dyn Journal> = Arc::new(FileJournal::open(&path).await.unwrap());
+        let bus = MemoryBusBuilder::new(VolatileConfig::new(NonZeroUsize::new(capacity).unwrap()))
+            .with_journal(journal)
+            .build()
+            .unwrap();
+        (Arc::new(bus), path)
+    }
+
+    fn user(content: &str) -> PromptMessage {
+        PromptMessage::new(MessageRole::User, content)
+    }
+
+    fn assistant(content: &str) -> PromptMessage {
+        PromptMessage::new(MessageRole::Assistant, content)
+    }
+
+    #[tokio::test]
+    async fn appended_turns_are_loaded_in_order() {
+        let (memory, path) = bus(32).await;
+        let store = SessionStore::new(memory, SessionConfig::new());
+
+        assert!(store.load("s1").await.unwrap().is_empty());
+        store
+            .append("s1", &[user("hi"), assistant("hello")])
+            .await
+            .unwrap();
+        store.append("s2", &[user("other")]).await.unwrap();
+        store.append("s1", &[user("again")]).await.unwrap();
+
+        let history = store.load("s1").await.unwrap();
+        let contents: Vec<&str> = history.iter().map(PromptMessage::content).collect();
+        assert_eq!(contents, ["hi", "hello", "again"]);
+
+        let sessions = store.sessions();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id(), "s1");
+        assert_eq!(sessions[0].turns(), 3);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn evicted_turns_are_restored_from_journal() {
+        let (memory, path) = bus(2).await;
+        let store = SessionStore::new(Arc::clone(&memory), SessionConfig::new());
+        store
+            .append("s1", &[user("one"), assistant("two"), user("three")])
+            .await
+            .unwrap();
+
+        // Only two records remain in the volatile buffer.
+        let history = store.load("s1").await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].content(), "one");
+
+        // A fresh store (as after a restart) rediscovers the session.
+        let restarted = SessionStore::new(memory, SessionConfig::new());
+        assert!(restarted.sessions().is_empty());
+        assert_eq!(restarted.restore_all().await.unwrap(), 1);
+        assert_eq!(restarted.sessions()[0].turns(), 3);
+        restarted.append("s1", &[assistant("four")]).await.unwrap();
+        let history = restarted.load("s1").await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[3].content(), "four");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn ended_and_expired_sessions_start_fresh() {
+        let (memory, path) = bus(32).await;
+        let store = SessionStore::new(
+            Arc::clone(&memory),
+            SessionConfig::new().with_idle_ttl(Duration::from_millis(20)),
+        );
+        store.append("s1", &[user("old")]).await.unwrap();
+        assert!(store.end("s1").await.unwrap());
+        assert!(!store.end("missing").await.unwrap());
+        assert!(store.load("s1").await.unwrap().is_empty());
+
+        store.append("s1", &[user("new")]).await.unwrap();
+        let history = store.load("s1").await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].content(), "new");
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(store.expire_idle().await.unwrap(), vec!["s1".to_owned()]);
+        assert!(store.sessions().is_empty());
+
+        // The journal markers keep a restarted store from replaying old turns.
+        let restarted = SessionStore::new(memory, SessionConfig::new());
+        assert!(restarted.load("s1").await.unwrap().is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn merge_trims_oldest_history_to_budget() {
+        let (memory, path) = bus(8).await;
+        let store = SessionStore::new(memory, SessionConfig::new().with_max_history_tokens(18));
+
+        let history = vec![
+            PromptMessage::new(MessageRole::System, "be brief"),
+            PromptMessage::assistant_tool_calls(
+                "",
+                vec![ToolCall::new("call_0", "lookup", Value::Null)],
+            ),
+            PromptMessage::tool_result("call_0", "x".repeat(16)),
+            user(&"a".repeat(16)),
+            assistant(&"b".repeat(16)),
+        ];
+        let merged = store.merge(history, vec![user(&"c".repeat(16))]);
+
+        // Dropping the tool-call turn also drops its now orphaned result.
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[0].content(), "be brief");
+        assert_eq!(merged[1].content(), "a".repeat(16));
+        assert_eq!(merged[3].content(), "c".repeat(16));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
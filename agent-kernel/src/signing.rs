@@ -0,0 +1,743 @@
+//! Ed25519 signing and verification of MXP messages between agents.
+//!
+//! A signed message has the same [`MessageType`] as the original and carries
+//! the encoded original message after a fixed header:
+//!
+//! | bytes | field                                     |
+//! |-------|-------------------------------------------|
+//! | 4     | magic `MXPS`                              |
+//! | 16    | signer agent id                           |
+//! | 8     | signing time, Unix milliseconds (LE)      |
+//! | 16    | random nonce                              |
+//! | 64    | Ed25519 signature                         |
+//!
+//! The signature covers the first 44 header bytes followed by the encoded
+//! original message. [`SignatureVerifier`] checks the signature against a
+//! [`TrustStore`], rejects stale timestamps and repeated nonces, and hands
+//! the original message, with its id, trace id, and flags intact, to the
+//! inner layers.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use agent_primitives::{AgentId, AgentManifest, PublicKey};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mxp::{Message, MessageType};
+use ring::rand::SystemRandom;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use thiserror::Error;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::middleware::{Middleware, Next};
+use crate::registry_wire::AgentRecord;
+use crate::timer::is_trigger_run;
+use crate::{HandlerContext, HandlerError, HandlerResult};
+
+const MAGIC: &[u8; 4] = b"MXPS";
+const SIGNED_LEN: usize = 44;
+const SIGNATURE_LEN: usize = 64;
+const HEADER_LEN: usize = SIGNED_LEN + SIGNATURE_LEN;
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_mins(1);
+const DEFAULT_REPLAY_CAPACITY: NonZeroUsize = NonZeroUsize::new(65_536).unwrap();
+
+/// Errors produced while signing or verifying messages.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum SigningError {
+    /// The message carries no signature.
+    #[error("message is not signed")]
+    Unsigned,
+    /// The signed message could not be parsed.
+    #[error("malformed signed message: {reason}")]
+    Malformed {
+        /// What was wrong with the message.
+        reason: &'static str,
+    },
+    /// The signer is not in the trust store.
+    #[error("agent {0} is not trusted")]
+    UntrustedSigner(AgentId),
+    /// The signature does not match the signer's key.
+    #[error("invalid signature from agent {0}")]
+    BadSignature(AgentId),
+    /// The signing time is outside the allowed clock skew.
+    #[error(
+        "message from agent {agent_id} was signed at {signed_at}, outside the allowed clock skew"
+    )]
+    ClockSkew {
+        /// Signer.
+        agent_id: AgentId,
+        /// Claimed signing time.
+        signed_at: DateTime<Utc>,
+    },
+    /// The nonce was already seen within the replay window.
+    #[error("replayed message from agent {0}")]
+    Replayed(AgentId),
+    /// Too many nonces are being tracked to accept another message.
+    #[error("replay cache is full (limit {limit})")]
+    ReplayCacheFull {
+        /// Configured capacity.
+        limit: usize,
+    },
+    /// A manifest or registry record does not advertise a signing key.
+    #[error("agent {0} does not advertise a public key")]
+    MissingPublicKey(AgentId),
+    /// A manifest does not match the identity it is checked against.
+    #[error("manifest for agent {0} does not match the signing identity")]
+    ManifestMismatch(AgentId),
+    /// A registry record advertises a key other than the one trusted for
+    /// the agent.
+    #[error("agent {0} advertises a key other than the trusted one")]
+    KeyMismatch(AgentId),
+    /// A key could not be generated or loaded.
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+}
+
+/// Result alias for signing operations.
+pub type SigningResult<T> = Result<T, SigningError>;
+
+/// Ed25519 key pair that an agent signs its outbound messages with.
+///
+/// Bind the identity to the agent by adding [`public_key`](Self::public_key)
+/// to its [`AgentManifest`], and persist [`pkcs8`](Self::pkcs8) so the agent
+/// keeps its identity across restarts.
+#[derive(Clone)]
+pub struct AgentIdentity {
+    agent_id: AgentId,
+    key_pair: Arc<Ed25519KeyPair>,
+    pkcs8: Arc<[u8]>,
+}
+
+impl fmt::Debug for AgentIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentIdentity")
+            .field("agent_id", &self.agent_id)
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentIdentity {
+    /// Generates a fresh key pair for `agent_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::InvalidKey`] when the system random number
+    /// generator fails.
+    pub fn generate(agent_id: AgentId) -> SigningResult<Self> {
+        let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| SigningError::InvalidKey("failed to generate key pair".into()))?;
+        Self::from_pkcs8(agent_id, document.as_ref())
+    }
+
+    /// Loads a key pair previously exported with [`pkcs8`](Self::pkcs8).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::InvalidKey`] when `pkcs8` is not a valid
+    /// Ed25519 PKCS#8 v2 document.
+    pub fn from_pkcs8(agent_id: AgentId, pkcs8: &[u8]) -> SigningResult<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+        Ok(Self {
+            agent_id,
+            key_pair: Arc::new(key_pair),
+            pkcs8: pkcs8.into(),
+        })
+    }
+
+    /// Returns the agent the identity belongs to.
+    #[must_use]
+    pub const fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Returns the public half of the key pair.
+    #[must_use]
+    pub fn public_key(&self) -> PublicKey {
+        let mut bytes = [0; PublicKey::LEN];
+        bytes.copy_from_slice(self.key_pair.public_key().as_ref());
+        PublicKey::from_bytes(bytes)
+    }
+
+    /// Returns the PKCS#8 document holding the private key. Store it as a
+    /// secret.
+    #[must_use]
+    pub fn pkcs8(&self) -> &[u8] {
+        &self.pkcs8
+    }
+
+    /// Checks that `manifest` describes this identity.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::MissingPublicKey`] when the manifest has no key
+    /// and [`SigningError::ManifestMismatch`] when its id or key differ.
+    pub fn check_manifest(&self, manifest: &AgentManifest) -> SigningResult<()> {
+        let key = manifest
+            .public_key()
+            .ok_or(SigningError::MissingPublicKey(manifest.id()))?;
+        if manifest.id() != self.agent_id || key != self.public_key() {
+            return Err(SigningError::ManifestMismatch(manifest.id()));
+        }
+        Ok(())
+    }
+
+    /// Wraps `message` in a signed message of the same type.
+    ///
+    /// Every call uses a fresh nonce, so retransmissions must be signed again.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::Malformed`] when the message has no type.
+    pub fn sign(&self, message: &Message) -> SigningResult<Message> {
+        let message_type = message.message_type().ok_or(SigningError::Malformed {
+            reason: "message missing type information",
+        })?;
+        let encoded = message.encode();
+        let mut payload = Vec::with_capacity(HEADER_LEN + encoded.len());
+        payload.extend_from_slice(MAGIC);
+        payload.extend_from_slice(self.agent_id.as_uuid().as_bytes());
+        payload.extend_from_slice(&Utc::now().timestamp_millis().to_le_bytes());
+        payload.extend_from_slice(Uuid::new_v4().as_bytes());
+        let signature = self.sign_bytes(&[&payload[..], &encoded].concat());
+        payload.extend_from_slice(&signature);
+        payload.extend_from_slice(&encoded);
+        Ok(Message::new(message_type, payload))
+    }
+
+    pub(crate) fn sign_bytes(&self, data: &[u8]) -> [u8; SIGNATURE_LEN] {
+        field(self.key_pair.sign(data).as_ref(), 0)
+    }
+}
+
+/// Returns `true` when `signature` is `key`'s signature over `data`.
+pub(crate) fn verify_bytes(key: PublicKey, data: &[u8], signature: &[u8]) -> bool {
+    UnparsedPublicKey::new(&ED25519, key.as_bytes())
+        .verify(data, signature)
+        .is_ok()
+}
+
+/// Returns `true` when `signed_at` is at most `skew` before or after `now`.
+pub(crate) fn within_skew(signed_at: DateTime<Utc>, now: DateTime<Utc>, skew: Duration) -> bool {
+    (now - signed_at)
+        .abs()
+        .to_std()
+        .is_ok_and(|difference| difference <= skew)
+}
+
+/// Signs `message` with `identity` when one is configured.
+pub(crate) fn sign_outbound<'a>(
+    identity: Option<&AgentIdentity>,
+    message: &'a Message,
+) -> SigningResult<Cow<'a, Message>> {
+    match identity {
+        Some(identity) => identity.sign(message).map(Cow::Owned),
+        None => Ok(Cow::Borrowed(message)),
+    }
+}
+
+/// Decodes the message a signed payload wraps, without verifying it.
+pub(crate) fn signed_body(payload: &[u8]) -> Option<Message> {
+    if !is_signed(payload) || payload.len() < HEADER_LEN {
+        return None;
+    }
+    Message::decode(payload[HEADER_LEN..].to_vec()).ok()
+}
+
+/// Returns `true` when `payload` starts like a signed message.
+#[must_use]
+pub fn is_signed(payload: &[u8]) -> bool {
+    payload.starts_with(MAGIC)
+}
+
+/// Public keys of the peers whose signed messages are accepted.
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    keys: Arc<Mutex<HashMap<AgentId, PublicKey>>>,
+}
+
+impl TrustStore {
+    /// Creates an empty trust store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts messages from `agent_id` signed with `key`, replacing any key
+    /// trusted for it before.
+    pub fn trust(&self, agent_id: AgentId, key: PublicKey) {
+        self.lock().insert(agent_id, key);
+    }
+
+    /// Trusts the key bound to `manifest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::MissingPublicKey`] when the manifest has no key.
+    pub fn trust_manifest(&self, manifest: &AgentManifest) -> SigningResult<()> {
+        let key = manifest
+            .public_key()
+            .ok_or(SigningError::MissingPublicKey(manifest.id()))?;
+        self.trust(manifest.id(), key);
+        Ok(())
+    }
+
+    /// Trusts the key a registry discovery record advertises.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError::MissingPublicKey`] when the record has no key
+    /// and [`SigningError::Malformed`] when its id is not an agent id.
+    pub fn trust_record(&self, record: &AgentRecord) -> SigningResult<()> {
+        let agent_id = record
+            .id
+            .parse::<Uuid>()
+            .map(AgentId::from_uuid)
+            .map_err(|_| SigningError::Malformed {
+                reason: "registry record id is not an agent id",
+            })?;
+        let key = record
+            .public_key
+            .ok_or(SigningError::MissingPublicKey(agent_id))?;
+        self.trust(agent_id, key);
+        Ok(())
+    }
+
+    /// Stops trusting `agent_id`, returning `false` when it was not trusted.
+    #[must_use]
+    pub fn revoke(&self, agent_id: AgentId) -> bool {
+        self.lock().remove(&agent_id).is_some()
+    }
+
+    /// Returns the key trusted for `agent_id`.
+    #[must_use]
+    pub fn get(&self, agent_id: AgentId) -> Option<PublicKey> {
+        self.lock().get(&agent_id).copied()
+    }
+
+    /// Returns the number of trusted agents.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` when no agent is trusted.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<AgentId, PublicKey>> {
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Limits applied by [`SignatureVerifier`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierConfig {
+    max_clock_skew: Duration,
+    replay_capacity: NonZeroUsize,
+    unsigned: Vec<MessageType>,
+}
+
+impl VerifierConfig {
+    /// Creates the default configuration: one minute of clock skew, at most
+    /// 65 536 tracked nonces, and unsigned `Ack` messages accepted.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            replay_capacity: DEFAULT_REPLAY_CAPACITY,
+            unsigned: vec![MessageType::Ack],
+        }
+    }
+
+    /// Sets how far a signing time may differ from the local clock.
+    #[must_use]
+    pub fn with_max_clock_skew(mut self, skew: Duration) -> Self {
+        self.max_clock_skew = skew;
+        self
+    }
+
+    /// Sets how many nonces are remembered for replay detection.
+    #[must_use]
+    pub fn with_replay_capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.replay_capacity = capacity;
+        self
+    }
+
+    /// Accepts unsigned messages of `message_type`.
+    #[must_use]
+    pub fn allow_unsigned(mut self, message_type: MessageType) -> Self {
+        if !self.unsigned.contains(&message_type) {
+            self.unsigned.push(message_type);
+        }
+        self
+    }
+
+    /// Rejects unsigned messages of every type, including `Ack`.
+    #[must_use]
+    pub fn require_all(mut self) -> Self {
+        self.unsigned.clear();
+        self
+    }
+
+    /// Returns the allowed clock skew.
+    #[must_use]
+    pub const fn max_clock_skew(&self) -> Duration {
+        self.max_clock_skew
+    }
+
+    /// Returns how many nonces are remembered.
+    #[must_use]
+    pub const fn replay_capacity(&self) -> NonZeroUsize {
+        self.replay_capacity
+    }
+
+    /// Returns the message types accepted without a signature.
+    #[must_use]
+    pub fn unsigned(&self) -> &[MessageType] {
+        &self.unsigned
+    }
+}
+
+impl Default for VerifierConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Context extension describing the verified signer of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedPeer {
+    agent_id: AgentId,
+    signed_at: DateTime<Utc>,
+}
+
+impl VerifiedPeer {
+    /// Returns the agent that signed the message.
+    #[must_use]
+    pub const fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Returns when the message was signed.
+    #[must_use]
+    pub const fn signed_at(&self) -> DateTime<Utc> {
+        self.signed_at
+    }
+}
+
+type SeenNonces = HashMap<(AgentId, [u8; 16]), DateTime<Utc>>;
+
+/// Middleware that rejects unsigned, forged, stale, and replayed messages
+/// with [`HandlerError::Unauthenticated`].
+///
+/// Verified messages reach the inner layers unwrapped, with a
+/// [`VerifiedPeer`] context extension naming the signer.
+#[derive(Debug)]
+pub struct SignatureVerifier {
+    trust: TrustStore,
+    config: VerifierConfig,
+    seen: Mutex<SeenNonces>,
+}
+
+impl SignatureVerifier {
+    /// Accepts messages signed by the agents in `trust`.
+    #[must_use]
+    pub fn new(trust: TrustStore) -> Self {
+        Self {
+            trust,
+            config: VerifierConfig::default(),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the configuration.
+    #[must_use]
+    pub fn with_config(mut self, config: VerifierConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the trust store.
+    #[must_use]
+    pub const fn trust(&self) -> &TrustStore {
+        &self.trust
+    }
+
+    /// Returns the configuration.
+    #[must_use]
+    pub const fn config(&self) -> &VerifierConfig {
+        &self.config
+    }
+
+    /// Verifies a signed message and returns its signer and the original
+    /// message.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SigningError`] when the message is unsigned, malformed,
+    /// signed by an untrusted agent or with a bad signature, outside the
+    /// allowed clock skew, or replayed.
+    pub fn verify(&self, message: &Message) -> SigningResult<(VerifiedPeer, Message)> {
+        let payload = message.payload();
+        if !is_signed(payload) {
+            return Err(SigningError::Unsigned);
+        }
+        if payload.len() < HEADER_LEN {
+            return Err(SigningError::Malformed {
+                reason: "truncated header",
+            });
+        }
+        let (header, encoded) = payload.split_at(HEADER_LEN);
+        let agent_id = AgentId::from_uuid(Uuid::from_bytes(field(header, 4)));
+        let millis = i64::from_le_bytes(field(header, 20));
+        let nonce: [u8; 16] = field(header, 28);
+
+        let key = self
+            .trust
+            .get(agent_id)
+            .ok_or(SigningError::UntrustedSigner(agent_id))?;
+        if !verify_bytes(
+            key,
+            &[&header[..SIGNED_LEN], encoded].concat(),
+            &header[SIGNED_LEN..],
+        ) {
+            return Err(SigningError::BadSignature(agent_id));
+        }
+
+        let now = Utc::now();
+        let signed_at = DateTime::from_timestamp_millis(millis).ok_or(SigningError::ClockSkew {
+            agent_id,
+            signed_at: DateTime::UNIX_EPOCH,
+        })?;
+        if !within_skew(signed_at, now, self.config.max_clock_skew) {
+            return Err(SigningError::ClockSkew {
+                agent_id,
+                signed_at,
+            });
+        }
+        self.remember(agent_id, nonce, signed_at, now)?;
+
+        let inner = Message::decode(encoded.to_vec()).map_err(|_| SigningError::Malformed {
+            reason: "invalid signed message body",
+        })?;
+        if inner.message_type() != message.message_type() {
+            return Err(SigningError::Malformed {
+                reason: "message type mismatch",
+            });
+        }
+        Ok((
+            VerifiedPeer {
+                agent_id,
+                signed_at,
+            },
+            inner,
+        ))
+    }
+
+    /// Records `nonce`, which stays remembered until its signing time falls
+    /// out of the clock skew window, including nonces signed in the future.
+    fn remember(
+        &self,
+        agent_id: AgentId,
+        nonce: [u8; 16],
+        signed_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> SigningResult<()> {
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if seen.contains_key(&(agent_id, nonce)) {
+            return Err(SigningError::Replayed(agent_id));
+        }
+        let limit = self.config.replay_capacity.get();
+        if seen.len() >= limit {
+            let skew = self.config.max_clock_skew;
+            seen.retain(|_, signed| within_skew(*signed, now, skew));
+        }
+        if seen.len() >= limit {
+            return Err(SigningError::ReplayCacheFull { limit });
+        }
+        seen.insert((agent_id, nonce), signed_at);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Middleware for SignatureVerifier {
+    async fn handle(&self, mut ctx: HandlerContext, next: Next<'_>) -> HandlerResult {
+        let message = ctx.message();
+        let exempt = message
+            .message_type()
+            .is_some_and(|message_type| self.config.unsigned.contains(&message_type));
+        // Trigger runs originate on this kernel and have nothing to verify.
+        if (exempt || is_trigger_run(&ctx)) && !is_signed(message.payload()) {
+            return next.run(ctx).await;
+        }
+        match self.verify(message) {
+            Ok((peer, inner)) => {
+                ctx.insert_extension(peer);
+                ctx.set_message(inner);
+                next.run(ctx).await
+            }
+            Err(err) => {
+                warn!(
+                    agent_id = %ctx.agent_id(),
+                    sender = ?ctx.sender(),
+                    message_id = message.message_id(),
+                    %err,
+                    "rejecting unauthenticated message"
+                );
+                Err(HandlerError::Unauthenticated(err))
+            }
+        }
+    }
+}
+
+pub(crate) fn field<const N: usize>(header: &[u8], offset: usize) -> [u8; N] {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(&header[offset..offset + N]);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeDelta;
+
+    use crate::{AgentMessageHandler, MiddlewareStack};
+
+    struct Recording(Mutex<Vec<(u64, Option<VerifiedPeer>)>>);
+
+    #[async_trait]
+    impl AgentMessageHandler for Recording {
+        async fn handle_call(&self, ctx: HandlerContext) -> HandlerResult {
+            let peer = ctx.extension::<VerifiedPeer>().copied();
+            self.0
+                .lock()
+                .unwrap()
+                .push((ctx.message().message_id(), peer));
+            Ok(())
+        }
+
+        async fn handle_ack(&self, _ctx: HandlerContext) -> HandlerResult {
+            Ok(())
+        }
+    }
+
+    fn ctx(message: Message) -> HandlerContext {
+        HandlerContext::from_message(AgentId::random(), message)
+    }
+
+    #[tokio::test]
+    async fn verifier_accepts_trusted_signatures_once() {
+        let identity = AgentIdentity::generate(AgentId::random()).unwrap();
+        let trust = TrustStore::new();
+        trust.trust(identity.agent_id(), identity.public_key());
+        let stack = MiddlewareStack::new().layer(SignatureVerifier::new(trust.clone()));
+        let handler = Recording(Mutex::new(Vec::new()));
+
+        let original = Message::new(MessageType::Call, b"{\"method\":\"ping\"}");
+        let signed = identity.sign(&original).unwrap();
+        assert!(is_signed(signed.payload()));
+        stack.dispatch(&handler, ctx(signed.clone())).await.unwrap();
+        let seen = handler.0.lock().unwrap().clone();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, original.message_id());
+        assert_eq!(seen[0].1.unwrap().agent_id(), identity.agent_id());
+
+        let err = stack.dispatch(&handler, ctx(signed)).await.unwrap_err();
+        assert_eq!(
+            err,
+            HandlerError::Unauthenticated(SigningError::Replayed(identity.agent_id()))
+        );
+        assert_eq!(err.code(), "unauthenticated");
+
+        // Unsigned acks pass by default; unsigned calls never do.
+        let ack = Message::new(MessageType::Ack, b"{}");
+        stack.dispatch(&handler, ctx(ack)).await.unwrap();
+        let unsigned = Message::new(MessageType::Call, b"{}");
+        assert_eq!(
+            stack.dispatch(&handler, ctx(unsigned)).await.unwrap_err(),
+            HandlerError::Unauthenticated(SigningError::Unsigned)
+        );
+        assert_eq!(handler.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn verifier_rejects_forged_untrusted_and_stale_messages() {
+        let identity = AgentIdentity::generate(AgentId::random()).unwrap();
+        let stranger = AgentIdentity::generate(AgentId::random()).unwrap();
+        let trust = TrustStore::new();
+        trust.trust(identity.agent_id(), identity.public_key());
+        let verifier = SignatureVerifier::new(trust);
+        let original = Message::new(MessageType::Call, b"transfer 10");
+
+        let signed = identity.sign(&original).unwrap();
+        let mut forged = signed.payload().to_vec();
+        *forged.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verifier.verify(&Message::new(MessageType::Call, forged)),
+            Err(SigningError::BadSignature(identity.agent_id()))
+        );
+
+        assert_eq!(
+            verifier.verify(&stranger.sign(&original).unwrap()),
+            Err(SigningError::UntrustedSigner(stranger.agent_id()))
+        );
+
+        let strict = SignatureVerifier::new(verifier.trust().clone())
+            .with_config(VerifierConfig::new().with_max_clock_skew(Duration::ZERO));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(matches!(
+            strict.verify(&signed),
+            Err(SigningError::ClockSkew { .. })
+        ));
+
+        let restored = AgentIdentity::from_pkcs8(identity.agent_id(), identity.pkcs8()).unwrap();
+        assert_eq!(restored.public_key(), identity.public_key());
+        let manifest = AgentManifest::builder(identity.agent_id())
+            .name("signer")
+            .unwrap()
+            .version("1.0.0")
+            .unwrap()
+            .public_key(identity.public_key())
+            .build()
+            .unwrap();
+        restored.check_manifest(&manifest).unwrap();
+        assert_eq!(
+            stranger.check_manifest(&manifest),
+            Err(SigningError::ManifestMismatch(identity.agent_id()))
+        );
+    }
+
+    #[test]
+    fn remembers_future_dated_nonces_until_they_leave_the_window() {
+        let verifier = SignatureVerifier::new(TrustStore::new())
+            .with_config(VerifierConfig::new().with_replay_capacity(NonZeroUsize::new(1).unwrap()));
+        let agent_id = AgentId::random();
+        let now = Utc::now();
+        let ahead = now + TimeDelta::seconds(30);
+
+        verifier.remember(agent_id, [1; 16], ahead, now).unwrap();
+        // The purge keeps the future-dated nonce, so the cache stays full.
+        assert_eq!(
+            verifier.remember(agent_id, [2; 16], now, now),
+            Err(SigningError::ReplayCacheFull { limit: 1 })
+        );
+        assert_eq!(
+            verifier.remember(agent_id, [1; 16], ahead, now),
+            Err(SigningError::Replayed(agent_id))
+        );
+
+        let later = ahead + TimeDelta::minutes(2);
+        verifier.remember(agent_id, [2; 16], later, later).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Gating of inbound messages while an agent is not active.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use mxp::transport::TransportHandle;
+use mxp::{Message, MessageType};
+
+use crate::approval::ApprovalDecision;
+use crate::cancellation::CancelRequest;
+use crate::encryption::{is_encrypted, is_handshake};
+use crate::fragment::Fragmenter;
+use crate::jobs::JobRequest;
+use crate::signing::signed_body;
+use crate::{AgentState, HandlerError, HandlerResult, TaskOptions};
+
+/// Delivers messages to a delegate while the agent is not active.
+pub trait MessageForwarder: Send + Sync {
+    /// Forwards the supplied message.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HandlerError`] when the message could not be delivered.
+    fn forward(&self, message: &Message) -> HandlerResult;
+}
+
+/// Forwards messages to a delegate agent using MXP transport.
+#[derive(Clone)]
+pub struct MxpForwarder {
+    transport: TransportHandle,
+    target: SocketAddr,
+    fragmenter: Fragmenter,
+}
+
+impl MxpForwarder {
+    /// Creates a forwarder that sends to `target`.
+    #[must_use]
+    pub fn new(transport: TransportHandle, target: SocketAddr) -> Self {
+        Self {
+            transport,
+            target,
+            fragmenter: Fragmenter::default(),
+        }
+    }
+
+    /// Sets how oversized messages are fragmented.
+    #[must_use]
+    pub const fn with_fragmenter(mut self, fragmenter: Fragmenter) -> Self {
+        self.fragmenter = fragmenter;
+        self
+    }
+}
+
+impl MessageForwarder for MxpForwarder {
+    fn forward(&self, message: &Message) -> HandlerResult {
+        self.fragmenter
+            .send(&self.transport, message, self.target)
+            .map(|_| ())
+            .map_err(|err| {
+                HandlerError::custom(format!("failed to forward to {}: {err:?}", self.target))
+            })
+    }
+}
+
+/// Limits applied to messages buffered while an agent is not active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferLimits {
+    max_messages: NonZeroUsize,
+    max_bytes: Option<NonZeroUsize>,
+}
+
+impl BufferLimits {
+    /// Buffers up to `max_messages` messages.
+    #[must_use]
+    pub const fn new(max_messages: NonZeroUsize) -> Self {
+        Self {
+            max_messages,
+            max_bytes: None,
+        }
+    }
+
+    /// Additionally caps the total payload bytes held in the buffer.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, max_bytes: NonZeroUsize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns the maximum number of buffered messages.
+    #[must_use]
+    pub const fn max_messages(&self) -> NonZeroUsize {
+        self.max_messages
+    }
+
+    /// Returns the maximum buffered payload bytes, if capped.
+    #[must_use]
+    pub const fn max_bytes(&self) -> Option<NonZeroUsize> {
+        self.max_bytes
+    }
+}
+
+/// How the kernel treats messages that arrive while the agent is in
+/// [`AgentState::Init`], [`AgentState::Ready`], or [`AgentState::Suspended`].
+#[derive(Clone, Default)]
+pub enum SuspensionPolicy {
+    /// Reject messages with [`HandlerError::NotAccepting`].
+    #[default]
+    Reject,
+    /// Hold messages and replay them once the agent becomes active; messages
+    /// beyond the limits are rejected.
+    Buffer(BufferLimits),
+    /// Hand messages to a delegate.
+    Forward(Arc<dyn MessageForwarder>),
+}
+
+impl fmt::Debug for SuspensionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject => f.write_str("Reject"),
+            Self::Buffer(limits) => f.debug_tuple("Buffer").field(limits).finish(),
+            Self::Forward(_) => f.write_str("Forward(..)"),
+        }
+    }
+}
+
+/// Outcome of offering a message to the gate.
+pub(crate) enum Admission {
+    /// The agent is active; dispatch the message.
+    Process(Message),
+    /// The gate handled the message.
+    Settled(HandlerResult),
+}
+
+/// Message held until the agent becomes active.
+pub(crate) struct Pending {
+    pub(crate) message: Message,
+    pub(crate) sender: Option<SocketAddr>,
+    pub(crate) options: TaskOptions,
+}
+
+#[derive(Default)]
+struct Buffered {
+    messages: VecDeque<Pending>,
+    bytes: usize,
+}
+
+/// Applies the [`SuspensionPolicy`] and holds buffered messages.
+#[derive(Default)]
+pub(crate) struct SuspensionGate {
+    policy: SuspensionPolicy,
+    buffered: Mutex<Buffered>,
+}
+
+/// Returns `true` for control traffic, which is dispatched in every
+/// lifecycle state: acks, encryption handshakes, and cancel, job, and
+/// approval decision events.
+///
+/// Signed events are classified by the message they wrap. Sealed events
+/// cannot be read before they are opened, so they are treated as control.
+fn is_control(message: &Message) -> bool {
+    match message.message_type() {
+        Some(MessageType::Ack) => true,
+        Some(MessageType::Event) => {
+            let payload = message.payload();
+            if is_handshake(payload) || is_encrypted(payload) {
+                return true;
+            }
+            let inner = signed_body(payload);
+            let payload = inner
+                .as_ref()
+                .map_or(&payload[..], |inner| &inner.payload()[..]);
+            CancelRequest::from_payload(payload).is_some()
+                || JobRequest::from_payload(payload).is_some()
+                || ApprovalDecision::from_payload(payload).is_some()
+        }
+        _ => false,
+    }
+}
+
+impl fmt::Debug for SuspensionGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuspensionGate")
+            .field("policy", &self.policy)
+            .field("buffered", &self.buffered_len())
+            .finish()
+    }
+}
+
+impl SuspensionGate {
+    pub(crate) fn set_policy(&mut self, policy: SuspensionPolicy) {
+        self.policy = policy;
+    }
+
+    pub(crate) const fn policy(&self) -> &SuspensionPolicy {
+        &self.policy
+    }
+
+    pub(crate) fn buffered_len(&self) -> usize {
+        self.lock().messages.len()
+    }
+
+    pub(crate) fn admit(&self, state: AgentState, pending: Pending) -> Admission {
+        if is_control(&pending.message) {
+            return Admission::Process(pending.message);
+        }
+        match state {
+            AgentState::Active => Admission::Process(pending.message),
+            AgentState::Retiring | AgentState::Terminated => Admission::Settled(Err(
+                HandlerError::not_accepting(state, "agent is shutting down"),
+            )),
+            AgentState::Init | AgentState::Ready | AgentState::Suspended => {
+                Admission::Settled(self.hold(state, pending))
+            }
+        }
+    }
+
+    fn hold(&self, state: AgentState, pending: Pending) -> HandlerResult {
+        match &self.policy {
+            SuspensionPolicy::Reject => {
+                Err(HandlerError::not_accepting(state, "agent is not active"))
+            }
+            SuspensionPolicy::Forward(forwarder) => forwarder.forward(&pending.message),
+            SuspensionPolicy::Buffer(limits) => {
+                let size = pending.message.payload().len();
+                let mut buffered = self.lock();
+                let over_count = buffered.messages.len() >= limits.max_messages.get();
+                let over_bytes = limits
+                    .max_bytes
+                    .is_some_and(|max| buffered.bytes + size > max.get());
+                if over_count || over_bytes {
+                    return Err(HandlerError::not_accepting(state, "suspension buffer full"));
+                }
+                buffered.bytes += size;
+                buffered.messages.push_back(pending);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes and returns buffered messages in arrival order.
+    pub(crate) fn take(&self) -> Vec<Pending> {
+        let mut buffered = self.lock();
+        buffered.bytes = 0;
+        buffered.messages.drain(..).collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Buffered> {
+        self.buffered.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,744 @@
+//! Interval and cron triggers that run calls without an inbound message.
+//!
+//! A [`Trigger`] registered on the kernel synthesizes a [`MessageType::Call`]
+//! carrying its configured payload every time its schedule comes due. The call
+//! is spawned on the kernel's scheduler and passes through the middleware
+//! stack and handler, so policy checks apply exactly as for remote calls.
+//! Handlers can tell a triggered call apart through the [`TriggerFire`]
+//! context extension.
+//!
+//! Triggers only fire while the agent is [`AgentState::Active`]: they pause
+//! while suspended and stop for good once the agent retires. Runs that come
+//! due while a trigger is paused, or while its previous run is still
+//! executing, are handled by its [`MissedRunPolicy`].
+//!
+//! Cron expressions use the classic five fields (minute, hour, day of month,
+//! month, day of week) evaluated in UTC, with `*`, lists, ranges, and steps,
+//! plus the `@hourly`, `@daily`, `@weekly`, `@monthly`, and `@yearly` macros.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use agent_primitives::AgentId;
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, TimeDelta, TimeZone, Timelike, Utc};
+use mxp::{Message, MessageType};
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::lifecycle::AgentState;
+use crate::middleware::MiddlewareStack;
+use crate::mxp_handlers::{AgentMessageHandler, HandlerContext};
+use crate::scheduler::{TaskOptions, TaskScheduler};
+
+/// Upper bound on overdue cron times counted after a long pause.
+const MAX_OVERDUE_SCAN: u64 = 10_000;
+
+/// Years searched for the next cron match before giving up.
+const CRON_SEARCH_YEARS: i32 = 5;
+
+/// Errors produced while defining or registering triggers.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum TimerError {
+    /// The trigger name is empty.
+    #[error("trigger name cannot be empty")]
+    EmptyName,
+    /// The interval is zero or too large to schedule.
+    #[error("invalid trigger interval {0:?}")]
+    InvalidInterval(Duration),
+    /// The cron expression could not be parsed.
+    #[error("invalid cron expression `{expression}`: {reason}")]
+    InvalidCron {
+        /// Expression as supplied.
+        expression: String,
+        /// What was wrong with it.
+        reason: String,
+    },
+    /// A trigger with the same name is already registered.
+    #[error("trigger `{0}` is already registered")]
+    Duplicate(String),
+}
+
+/// Result alias for trigger operations.
+pub type TimerResult<T> = Result<T, TimerError>;
+
+/// Parsed five-field cron expression, evaluated in UTC.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl fmt::Debug for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CronSchedule")
+            .field(&self.expression)
+            .finish()
+    }
+}
+
+impl CronSchedule {
+    /// Parses a cron expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimerError::InvalidCron`] when the expression does not have
+    /// five valid fields or a known `@` macro.
+    pub fn parse(expression: &str) -> TimerResult<Self> {
+        let trimmed = expression.trim();
+        let expanded = match trimmed {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let invalid = |reason: String| TimerError::InvalidCron {
+            expression: expression.to_owned(),
+            reason,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(format!(
+                "expected 5 fields, found {}",
+                fields.len()
+            )));
+        };
+
+        let mut weekdays =
+            parse_field(weekday, 0, 7).map_err(|r| invalid(format!("weekday: {r}")))?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            expression: trimmed.to_owned(),
+            minutes: parse_field(minute, 0, 59).map_err(|r| invalid(format!("minute: {r}")))?,
+            hours: parse_field(hour, 0, 23).map_err(|r| invalid(format!("hour: {r}")))?,
+            days: parse_field(day, 1, 31).map_err(|r| invalid(format!("day of month: {r}")))?,
+            months: parse_field(month, 1, 12).map_err(|r| invalid(format!("month: {r}")))?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    /// Returns the expression as supplied.
+    #[must_use]
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Returns the first matching minute strictly after `after`, or `None`
+    /// when the expression never matches (such as `0 0 30 2 *`).
+    #[must_use]
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut at = after.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let last_year = after.year() + CRON_SEARCH_YEARS;
+        while at.year() <= last_year {
+            if !bit(self.months, at.month()) {
+                let (year, month) = if at.month() == 12 {
+                    (at.year() + 1, 1)
+                } else {
+                    (at.year(), at.month() + 1)
+                };
+                at = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.day_matches(at) {
+                at = at.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+            } else if !bit(self.hours, at.hour()) {
+                at = at.with_minute(0)? + TimeDelta::hours(1);
+            } else if !bit(self.minutes, at.minute()) {
+                at += TimeDelta::minutes(1);
+            } else {
+                return Some(at);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, at: DateTime<Utc>) -> bool {
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = TimerError;
+
+    fn from_str(expression: &str) -> TimerResult<Self> {
+        Self::parse(expression)
+    }
+}
+
+const fn bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step `{step}`"))?;
+                if step == 0 {
+                    return Err("step cannot be zero".to_owned());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let start = parse_value(range, min, max)?;
+            (start, if step > 1 { max } else { start })
+        };
+        if start > end {
+            return Err(format!("range `{range}` is reversed"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(parsed) if (min..=max).contains(&parsed) => Ok(parsed),
+        _ => Err(format!("`{value}` is not between {min} and {max}")),
+    }
+}
+
+/// When a trigger comes due.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerSchedule {
+    /// Every `period`, starting one period after the trigger starts.
+    Interval(Duration),
+    /// Whenever the cron expression matches.
+    Cron(CronSchedule),
+}
+
+impl TriggerSchedule {
+    fn first_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval(period) => Some(now + TimeDelta::from_std(*period).ok()?),
+            Self::Cron(cron) => cron.next_after(now),
+        }
+    }
+
+    /// Collects the scheduled times from `due` up to `now`, keeping the most
+    /// recent `keep`, and the first time after `now`.
+    fn overdue(&self, due: DateTime<Utc>, now: DateTime<Utc>, keep: usize) -> Overdue {
+        match self {
+            Self::Interval(period) => {
+                let period = TimeDelta::from_std(*period).unwrap_or(TimeDelta::MAX);
+                let elapsed = (now - due).num_nanoseconds().unwrap_or(i64::MAX);
+                let steps = elapsed / period.num_nanoseconds().unwrap_or(i64::MAX).max(1);
+                let count = u64::try_from(steps).unwrap_or(0) + 1;
+                let at = |step: i64| {
+                    due + TimeDelta::nanoseconds(
+                        step.saturating_mul(period.num_nanoseconds().unwrap_or(i64::MAX)),
+                    )
+                };
+                let first_kept = (steps + 1 - i64::try_from(keep).unwrap_or(i64::MAX)).max(0);
+                Overdue {
+                    count,
+                    recent: (first_kept..=steps).map(at).collect(),
+                    next: Some(at(steps + 1)),
+                }
+            }
+            Self::Cron(cron) => {
+                let mut overdue = Overdue {
+                    count: 0,
+                    recent: VecDeque::with_capacity(keep),
+                    next: Some(due),
+                };
+                while let Some(at) = overdue.next.filter(|at| *at <= now) {
+                    if overdue.count == MAX_OVERDUE_SCAN {
+                        overdue.next = cron.next_after(now);
+                        break;
+                    }
+                    overdue.count += 1;
+                    if overdue.recent.len() == keep {
+                        overdue.recent.pop_front();
+                    }
+                    if keep > 0 {
+                        overdue.recent.push_back(at);
+                    }
+                    overdue.next = cron.next_after(at);
+                }
+                overdue
+            }
+        }
+    }
+}
+
+struct Overdue {
+    count: u64,
+    recent: VecDeque<DateTime<Utc>>,
+    next: Option<DateTime<Utc>>,
+}
+
+/// How a trigger treats runs that came due while it could not fire.
+///
+/// A run is missed when it comes due while the agent is suspended or while
+/// the trigger's previous run is still executing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissedRunPolicy {
+    /// Drop missed runs and wait for the next scheduled time.
+    Skip,
+    /// Coalesce missed runs into a single run as soon as possible.
+    #[default]
+    FireOnce,
+    /// Replay the most recent missed runs, oldest first, up to the limit.
+    FireAll(NonZeroU32),
+}
+
+/// Scheduled call registered on an [`AgentKernel`](crate::AgentKernel).
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    name: String,
+    schedule: TriggerSchedule,
+    payload: Value,
+    missed_runs: MissedRunPolicy,
+    options: TaskOptions,
+}
+
+impl Trigger {
+    /// Creates a trigger that fires every `period`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimerError::EmptyName`] for a blank name and
+    /// [`TimerError::InvalidInterval`] when `period` is zero or too large.
+    pub fn interval(name: impl Into<String>, period: Duration) -> TimerResult<Self> {
+        if period.is_zero() || TimeDelta::from_std(period).is_err() {
+            return Err(TimerError::InvalidInterval(period));
+        }
+        Self::new(name.into(), TriggerSchedule::Interval(period))
+    }
+
+    /// Creates a trigger that fires whenever the cron expression matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimerError::EmptyName`] for a blank name and
+    /// [`TimerError::InvalidCron`] when the expression is invalid.
+    pub fn cron(name: impl Into<String>, expression: &str) -> TimerResult<Self> {
+        let schedule = CronSchedule::parse(expression)?;
+        Self::new(name.into(), TriggerSchedule::Cron(schedule))
+    }
+
+    fn new(name: String, schedule: TriggerSchedule) -> TimerResult<Self> {
+        if name.trim().is_empty() {
+            return Err(TimerError::EmptyName);
+        }
+        Ok(Self {
+            name,
+            schedule,
+            payload: Value::Object(serde_json::Map::new()),
+            missed_runs: MissedRunPolicy::default(),
+            options: TaskOptions::default(),
+        })
+    }
+
+    /// Sets the JSON call payload sent on every run.
+    #[must_use]
+    pub fn with_payload(mut self, payload: Value) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sets how runs missed while paused or busy are handled.
+    #[must_use]
+    pub const fn with_missed_runs(mut self, policy: MissedRunPolicy) -> Self {
+        self.missed_runs = policy;
+        self
+    }
+
+    /// Sets the scheduler lane and fairness key used for runs.
+    #[must_use]
+    pub fn with_options(mut self, options: TaskOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the trigger name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the schedule.
+    #[must_use]
+    pub const fn schedule(&self) -> &TriggerSchedule {
+        &self.schedule
+    }
+
+    /// Returns the call payload.
+    #[must_use]
+    pub const fn payload(&self) -> &Value {
+        &self.payload
+    }
+
+    /// Returns the missed-run policy.
+    #[must_use]
+    pub const fn missed_runs(&self) -> MissedRunPolicy {
+        self.missed_runs
+    }
+
+    /// Returns the scheduler options used for runs.
+    #[must_use]
+    pub const fn options(&self) -> &TaskOptions {
+        &self.options
+    }
+
+    /// Picks the scheduled times to run now, oldest first, and the number of
+    /// missed runs that were dropped or coalesced.
+    fn runs(&self, overdue: &Overdue, paused: bool) -> (Vec<DateTime<Utc>>, u64) {
+        let latest = overdue.recent.back().copied();
+        let runs: Vec<_> = match self.missed_runs {
+            MissedRunPolicy::Skip if paused => Vec::new(),
+            MissedRunPolicy::Skip | MissedRunPolicy::FireOnce => latest.into_iter().collect(),
+            MissedRunPolicy::FireAll(_) => overdue.recent.iter().copied().collect(),
+        };
+        let missed = if paused {
+            overdue.count
+        } else {
+            overdue.count.saturating_sub(1)
+        };
+        let replayed = if paused {
+            runs.len()
+        } else {
+            runs.len().saturating_sub(1)
+        };
+        (runs, missed.saturating_sub(replayed as u64))
+    }
+}
+
+/// Context extension attached to calls synthesized by a [`Trigger`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerFire {
+    trigger: String,
+    scheduled_at: DateTime<Utc>,
+    skipped: u64,
+}
+
+impl TriggerFire {
+    /// Returns the name of the trigger that fired.
+    #[must_use]
+    pub fn trigger(&self) -> &str {
+        &self.trigger
+    }
+
+    /// Returns the time this run was scheduled for.
+    #[must_use]
+    pub const fn scheduled_at(&self) -> DateTime<Utc> {
+        self.scheduled_at
+    }
+
+    /// Returns how many missed runs were dropped or coalesced into this one.
+    #[must_use]
+    pub const fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+/// Returns `true` when `ctx` is a call synthesized by a trigger on this
+/// kernel rather than a message received from a peer.
+pub(crate) fn is_trigger_run(ctx: &HandlerContext) -> bool {
+    ctx.sender().is_none() && ctx.extension::<TriggerFire>().is_some()
+}
+
+/// Everything a running trigger needs to dispatch calls.
+#[derive(Clone)]
+pub(crate) struct TriggerDispatch {
+    pub(crate) agent_id: AgentId,
+    pub(crate) handler: Arc<dyn AgentMessageHandler>,
+    pub(crate) middleware: MiddlewareStack,
+    pub(crate) scheduler: TaskScheduler,
+}
+
+impl TriggerDispatch {
+    async fn fire(&self, trigger: &Trigger, scheduled_at: DateTime<Utc>, skipped: u64) {
+        let payload = match serde_json::to_vec(&trigger.payload) {
+            Ok(payload) => Bytes::from(payload),
+            Err(err) => {
+                warn!(trigger = trigger.name(), %err, "failed to encode trigger payload");
+                return;
+            }
+        };
+        let mut ctx =
+            HandlerContext::from_message(self.agent_id, Message::new(MessageType::Call, payload));
+        ctx.insert_extension(TriggerFire {
+            trigger: trigger.name.clone(),
+            scheduled_at,
+            skipped,
+        });
+        let handler = Arc::clone(&self.handler);
+        let middleware = self.middleware.clone();
+        let spawned = self.scheduler.spawn_with(&trigger.options, async move {
+            middleware.dispatch(handler.as_ref(), ctx).await
+        });
+        match spawned {
+            Ok(task) => match task.await {
+                Ok(Ok(())) => {
+                    debug!(trigger = trigger.name(), %scheduled_at, "trigger run completed");
+                }
+                Ok(Err(err)) => warn!(trigger = trigger.name(), %err, "trigger run failed"),
+                Err(err) => warn!(trigger = trigger.name(), %err, "trigger run aborted"),
+            },
+            Err(err) => warn!(trigger = trigger.name(), %err, "failed to schedule trigger run"),
+        }
+    }
+}
+
+/// Triggers registered on a kernel and the tasks driving them.
+#[derive(Default)]
+pub(crate) struct TriggerSet {
+    entries: Vec<TriggerEntry>,
+}
+
+struct TriggerEntry {
+    trigger: Arc<Trigger>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for TriggerSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|entry| {
+                let running = entry.task.as_ref().is_some_and(|task| !task.is_finished());
+                (entry.trigger.name(), running)
+            }))
+            .finish()
+    }
+}
+
+impl TriggerSet {
+    pub(crate) fn insert(&mut self, trigger: Trigger) -> TimerResult<()> {
+        if self.get(trigger.name()).is_some() {
+            return Err(TimerError::Duplicate(trigger.name));
+        }
+        self.entries.push(TriggerEntry {
+            trigger: Arc::new(trigger),
+            task: None,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> bool {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.trigger.name() == name)
+        else {
+            return false;
+        };
+        let entry = self.entries.remove(index);
+        if let Some(task) = entry.task {
+            task.abort();
+        }
+        true
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Trigger> {
+        self.iter().find(|trigger| trigger.name() == name)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Trigger> {
+        self.entries.iter().map(|entry| entry.trigger.as_ref())
+    }
+
+    /// Starts every trigger that is not already running.
+    pub(crate) fn start(&mut self, dispatch: &TriggerDispatch, state: &watch::Sender<AgentState>) {
+        for entry in &mut self.entries {
+            entry.start(dispatch, state);
+        }
+    }
+
+    /// Stops every running trigger.
+    pub(crate) fn stop(&mut self) {
+        for entry in &mut self.entries {
+            if let Some(task) = entry.task.take() {
+                task.abort();
+            }
+        }
+    }
+}
+
+impl TriggerEntry {
+    fn start(&mut self, dispatch: &TriggerDispatch, state: &watch::Sender<AgentState>) {
+        if self.task.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        let trigger = Arc::clone(&self.trigger);
+        let dispatch = dispatch.clone();
+        self.task = Some(tokio::spawn(drive(trigger, dispatch, state.subscribe())));
+    }
+}
+
+async fn drive(
+    trigger: Arc<Trigger>,
+    dispatch: TriggerDispatch,
+    mut state: watch::Receiver<AgentState>,
+) {
+    let mut next = trigger.schedule.first_after(Utc::now());
+    let mut paused = false;
+    loop {
+        loop {
+            match *state.borrow_and_update() {
+                AgentState::Active => break,
+                AgentState::Retiring | AgentState::Terminated => return,
+                _ => paused = true,
+            }
+            if state.changed().await.is_err() {
+                return;
+            }
+        }
+        let Some(due) = next else {
+            debug!(
+                trigger = trigger.name(),
+                "trigger schedule has no further runs"
+            );
+            return;
+        };
+
+        let wait = (due - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            () = tokio::time::sleep(wait) => {}
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                continue;
+            }
+        }
+
+        let keep = match trigger.missed_runs {
+            MissedRunPolicy::FireAll(limit) => limit.get() as usize,
+            MissedRunPolicy::Skip | MissedRunPolicy::FireOnce => 1,
+        };
+        let overdue = trigger.schedule.overdue(due, Utc::now(), keep);
+        let (runs, skipped) = trigger.runs(&overdue, paused);
+        if skipped > 0 {
+            debug!(trigger = trigger.name(), skipped, "trigger missed runs");
+        }
+        for (index, scheduled_at) in runs.iter().enumerate() {
+            let skipped = if index + 1 == runs.len() { skipped } else { 0 };
+            dispatch.fire(&trigger, *scheduled_at, skipped).await;
+        }
+        next = overdue.next;
+        paused = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn cron_finds_next_matching_minute() {
+        let every_quarter = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // Friday evening rolls over to Monday morning.
+        let next = every_quarter
+            .next_after(at("2026-10-16T17:50:00Z"))
+            .unwrap();
+        assert_eq!(next, at("2026-10-19T09:00:00Z"));
+        let next = every_quarter.next_after(next).unwrap();
+        assert_eq!(next, at("2026-10-19T09:15:00Z"));
+
+        let nightly: CronSchedule = "@daily".parse().unwrap();
+        assert_eq!(
+            nightly.next_after(at("2026-12-31T23:59:30Z")),
+            Some(at("2027-01-01T00:00:00Z"))
+        );
+        // Day of month and day of week widen each other when both are set.
+        let first_or_sunday = CronSchedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(
+            first_or_sunday.next_after(at("2026-10-19T00:00:00Z")),
+            Some(at("2026-10-25T00:00:00Z"))
+        );
+        assert_eq!(
+            CronSchedule::parse("0 0 30 2 *")
+                .unwrap()
+                .next_after(at("2026-01-01T00:00:00Z")),
+            None
+        );
+
+        assert!(matches!(
+            CronSchedule::parse("* * *"),
+            Err(TimerError::InvalidCron { .. })
+        ));
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn missed_run_policies_pick_runs() {
+        let cron = TriggerSchedule::Cron(CronSchedule::parse("0 * * * *").unwrap());
+        let overdue = cron.overdue(at("2026-10-18T01:00:00Z"), at("2026-10-18T04:30:00Z"), 2);
+        assert_eq!(overdue.count, 4);
+        assert_eq!(
+            overdue.recent,
+            [at("2026-10-18T03:00:00Z"), at("2026-10-18T04:00:00Z")]
+        );
+        assert_eq!(overdue.next, Some(at("2026-10-18T05:00:00Z")));
+
+        let trigger = Trigger::cron("hourly", "0 * * * *").unwrap();
+        let skip = trigger.clone().with_missed_runs(MissedRunPolicy::Skip);
+        assert_eq!(skip.runs(&overdue, true), (Vec::new(), 4));
+        assert_eq!(
+            skip.runs(&overdue, false),
+            (vec![at("2026-10-18T04:00:00Z")], 3)
+        );
+        assert_eq!(
+            trigger.runs(&overdue, true),
+            (vec![at("2026-10-18T04:00:00Z")], 3)
+        );
+        let all = trigger.with_missed_runs(MissedRunPolicy::FireAll(NonZeroU32::new(2).unwrap()));
+        assert_eq!(all.runs(&overdue, true).1, 2);
+
+        let interval = TriggerSchedule::Interval(Duration::from_secs(10));
+        let overdue = interval.overdue(at("2026-10-18T00:00:00Z"), at("2026-10-18T00:00:35Z"), 1);
+        assert_eq!(overdue.count, 4);
+        assert_eq!(overdue.recent, [at("2026-10-18T00:00:30Z")]);
+        assert_eq!(overdue.next, Some(at("2026-10-18T00:00:40Z")));
+
+        assert_eq!(
+            Trigger::interval("bad", Duration::ZERO).unwrap_err(),
+            TimerError::InvalidInterval(Duration::ZERO)
+        );
+        assert_eq!(
+            Trigger::interval(" ", Duration::from_secs(1)).unwrap_err(),
+            TimerError::EmptyName
+        );
+    }
+}
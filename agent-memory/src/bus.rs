@@ -3,6 +3,7 @@
 use std::sync::Arc;
 
 use serde_json::Value;
+use uuid::Uuid;
 
 use crate::journal::Journal;
 use crate::record::MemoryRecord;
@@ -132,6 +133,26 @@ impl MemoryBus {
         self.journal.tail(limit).await
     }
 
+    /// Looks up a record by id, checking volatile memory before the journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MemoryError`] when reading or decoding the journal fails.
+    pub async fn find(&self, id: Uuid) -> MemoryResult<Option<MemoryRecord>> {
+        let capacity = self.volatile.stats().await.capacity;
+        if let Some(record) = self
+            .volatile
+            .recent(capacity)
+            .await
+            .into_iter()
+            .find(|record| record.id() == id)
+        {
+            return Ok(Some(record));
+        }
+        let records = self.journal.tail(usize::MAX).await?;
+        Ok(records.into_iter().find(|record| record.id() == id))
+    }
+
     /// Queries the configured vector store.
     ///
     /// # Errors
@@ -198,6 +219,10 @@ mod tests {
         let journal_tail = bus.journal_tail(1).await.unwrap();
         assert_eq!(journal_tail.len(), 1);
 
+        let found = bus.find(record.id()).await.unwrap().unwrap();
+        assert_eq!(found.payload(), record.payload());
+        assert!(bus.find(uuid::Uuid::new_v4()).await.unwrap().is_none());
+
         // Without an embedding the vector store should remain empty.
         let matches = bus
             .recall(VectorQuery::new(
@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{MemoryError, MemoryResult};
@@ -82,6 +83,13 @@ impl EmbeddingVector {
     }
 }
 
+/// Produces embeddings for text so it can be compared against stored vectors.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embeds the supplied text.
+    async fn embed(&self, text: &str) -> MemoryResult<EmbeddingVector>;
+}
+
 impl std::fmt::Debug for EmbeddingVector {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EmbeddingVector")
@@ -11,7 +11,7 @@ mod vector_store_api;
 mod volatile;
 
 pub use bus::{MemoryBus, MemoryBusBuilder};
-pub use embeddings::EmbeddingVector;
+pub use embeddings::{Embedder, EmbeddingVector};
 pub use error::{MemoryError, MemoryResult};
 pub use journal::{FileJournal, Journal};
 pub use record::{MemoryChannel, MemoryRecord, MemoryRecordBuilder};
@@ -99,6 +99,26 @@ impl RuleMatcher {
         }
     }
 
+    /// Creates a matcher for emissions of a particular event type.
+    #[must_use]
+    pub fn for_event(event_type: impl Into<String>) -> Self {
+        Self {
+            action: ActionMatcher::Event {
+                event_type: Some(event_type.into()),
+            },
+            required_tags: BTreeSet::new(),
+        }
+    }
+
+    /// Creates a matcher for all event emissions.
+    #[must_use]
+    pub fn for_any_event() -> Self {
+        Self {
+            action: ActionMatcher::Event { event_type: None },
+            required_tags: BTreeSet::new(),
+        }
+    }
+
     /// Requires that the request carries the supplied tags.
     #[must_use]
     pub fn with_required_tags<I, S>(mut self, tags: I) -> Self
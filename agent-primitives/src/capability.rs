@@ -4,11 +4,11 @@ use std::collections::BTreeSet;
 
 use serde::{Deserialize, Serialize};
 
+use crate::Scope;
 use crate::error::{Error, Result};
 
 const MAX_ID_LEN: usize = 64;
 const MAX_NAME_LEN: usize = 96;
-const MAX_SCOPE_LEN: usize = 64;
 
 /// Identifier for a capability that an agent may expose.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
@@ -183,8 +183,8 @@ impl CapabilityBuilder {
     ///
     /// # Errors
     ///
-    /// Returns [`Error::InvalidCapability`] if the scope is empty or exceeds the
-    /// maximum supported length.
+    /// Returns [`Error::InvalidCapability`] if the scope is not a valid
+    /// [`Scope`].
     pub fn add_scope(mut self, scope: impl Into<String>) -> Result<Self> {
         let scope = scope.into();
         validate_scope(&scope)?;
@@ -226,17 +226,11 @@ impl CapabilityBuilder {
 }
 
 fn validate_scope(scope: &str) -> Result<()> {
-    if scope.trim().is_empty() {
-        return Err(Error::InvalidCapability {
-            reason: "scope cannot be empty".into(),
-        });
-    }
-    if scope.len() > MAX_SCOPE_LEN {
-        return Err(Error::InvalidCapability {
-            reason: format!("scope length must be <= {MAX_SCOPE_LEN}"),
-        });
-    }
-    Ok(())
+    Scope::new(scope)
+        .map(drop)
+        .map_err(|err| Error::InvalidCapability {
+            reason: err.to_string(),
+        })
 }
 
 #[cfg(test)]
@@ -32,4 +32,20 @@ pub enum Error {
         /// Human-readable reason for rejection.
         reason: String,
     },
+
+    /// Permission scope failed validation.
+    #[error("invalid scope `{scope}`: {reason}")]
+    InvalidScope {
+        /// The offending scope string.
+        scope: String,
+        /// Human-readable reason for rejection.
+        reason: String,
+    },
+
+    /// Public key failed to parse.
+    #[error("invalid public key: {reason}")]
+    InvalidPublicKey {
+        /// Human-readable reason for rejection.
+        reason: String,
+    },
 }
@@ -0,0 +1,90 @@
+//! Public signing keys that identify agents on the mesh.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Error, Result};
+
+/// Ed25519 public key an agent signs its messages with.
+///
+/// Displayed and serialized as 64 lowercase hex characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey([u8; PublicKey::LEN]);
+
+impl PublicKey {
+    /// Length of the key in bytes.
+    pub const LEN: usize = 32;
+
+    /// Wraps raw key bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; Self::LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the raw key bytes.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({self})")
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidPublicKey {
+            reason: reason.into(),
+        };
+        if s.len() != Self::LEN * 2 {
+            return Err(invalid("expected 64 hex characters"));
+        }
+        let mut bytes = [0; Self::LEN];
+        for (byte, pair) in bytes.iter_mut().zip(s.as_bytes().chunks_exact(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid("expected hex digits"))?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid("expected hex digits"))?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+impl Serialize for PublicKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        encoded.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_hex() {
+        let key = PublicKey::from_bytes([0xab; PublicKey::LEN]);
+        let encoded = key.to_string();
+        assert_eq!(encoded, "ab".repeat(32));
+        assert_eq!(encoded.parse::<PublicKey>().unwrap(), key);
+        assert!("abc".parse::<PublicKey>().is_err());
+        assert!("zz".repeat(32).parse::<PublicKey>().is_err());
+    }
+}
@@ -5,7 +5,9 @@
 mod capability;
 mod error;
 mod ids;
+mod key;
 mod manifest;
+mod scope;
 
 /// Capability descriptors and supporting builders.
 pub use capability::{Capability, CapabilityBuilder, CapabilityId};
@@ -13,5 +15,9 @@ pub use capability::{Capability, CapabilityBuilder, CapabilityId};
 pub use error::{Error, Result};
 /// Unique identifier for MXP agents within the mesh.
 pub use ids::AgentId;
+/// Public signing keys that identify agents on the mesh.
+pub use key::PublicKey;
 /// Agent metadata advertised to the MXP Nexus mesh directory.
 pub use manifest::{AgentManifest, AgentManifestBuilder};
+/// Hierarchical permission scopes granted to callers and required by capabilities.
+pub use scope::Scope;
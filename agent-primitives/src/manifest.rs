@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{AgentId, Capability};
+use crate::{AgentId, Capability, PublicKey};
 
 #[cfg(test)]
 use crate::{CapabilityBuilder, CapabilityId};
@@ -19,6 +19,8 @@ pub struct AgentManifest {
     capabilities: Vec<Capability>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<PublicKey>,
 }
 
 impl AgentManifest {
@@ -32,6 +34,7 @@ impl AgentManifest {
             description: None,
             capabilities: Vec::new(),
             tags: Vec::new(),
+            public_key: None,
         }
     }
 
@@ -70,6 +73,12 @@ impl AgentManifest {
     pub fn tags(&self) -> &[String] {
         &self.tags
     }
+
+    /// Returns the key the agent signs its messages with, if advertised.
+    #[must_use]
+    pub const fn public_key(&self) -> Option<PublicKey> {
+        self.public_key
+    }
 }
 
 /// Builder for [`AgentManifest`].
@@ -81,6 +90,7 @@ pub struct AgentManifestBuilder {
     description: Option<String>,
     capabilities: Vec<Capability>,
     tags: Vec<String>,
+    public_key: Option<PublicKey>,
 }
 
 impl AgentManifestBuilder {
@@ -132,6 +142,13 @@ impl AgentManifestBuilder {
         self
     }
 
+    /// Binds the agent's signing key to the manifest.
+    #[must_use]
+    pub const fn public_key(mut self, key: PublicKey) -> Self {
+        self.public_key = Some(key);
+        self
+    }
+
     /// Adds a tag label.
     ///
     /// # Errors
@@ -171,6 +188,7 @@ impl AgentManifestBuilder {
             description: self.description,
             capabilities: self.capabilities,
             tags: self.tags,
+            public_key: self.public_key,
         })
     }
 }
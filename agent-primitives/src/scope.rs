@@ -0,0 +1,139 @@
+//! Hierarchical permission scopes granted to callers and required by capabilities.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Error, Result};
+
+const MAX_SCOPE_LEN: usize = 64;
+const SEPARATOR: char = ':';
+const WILDCARD: &str = "*";
+
+/// Permission scope made of `:`-separated segments, such as `inventory:read`.
+///
+/// Scopes are hierarchical: a granted scope covers itself and every scope
+/// beneath it, so `inventory` covers `inventory:read:sku`. A `*` segment
+/// matches any single segment, so `*:read` covers `orders:read` and `*`
+/// alone covers everything.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Scope(String);
+
+impl Scope {
+    /// Validates and wraps a scope string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidScope`] if the scope is empty, longer than 64
+    /// bytes, contains whitespace, or has an empty segment.
+    pub fn new(scope: impl Into<String>) -> Result<Self> {
+        let scope = scope.into();
+        let invalid = |reason: &str| Error::InvalidScope {
+            scope: scope.clone(),
+            reason: reason.into(),
+        };
+        if scope.is_empty() {
+            return Err(invalid("scope cannot be empty"));
+        }
+        if scope.len() > MAX_SCOPE_LEN {
+            return Err(invalid("scope length must be <= 64"));
+        }
+        if scope.chars().any(char::is_whitespace) {
+            return Err(invalid("scope cannot contain whitespace"));
+        }
+        if scope.split(SEPARATOR).any(str::is_empty) {
+            return Err(invalid("scope segments cannot be empty"));
+        }
+        Ok(Self(scope))
+    }
+
+    /// Returns the scope as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns whether holding this scope entitles the holder to `required`.
+    ///
+    /// Wildcards are only interpreted in the granted scope; a `*` segment in
+    /// `required` must be granted literally or by another wildcard.
+    #[must_use]
+    pub fn grants(&self, required: &Self) -> bool {
+        let mut required = required.0.split(SEPARATOR);
+        self.0.split(SEPARATOR).all(|granted| {
+            required
+                .next()
+                .is_some_and(|segment| granted == WILDCARD || granted == segment)
+        })
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scope({})", self.0)
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl AsRef<str> for Scope {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let scope = String::deserialize(deserializer)?;
+        Self::new(scope).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(value: &str) -> Scope {
+        Scope::new(value).expect("scope")
+    }
+
+    #[test]
+    fn grants_descendants_and_wildcards() {
+        assert!(scope("inventory").grants(&scope("inventory:read:sku")));
+        assert!(scope("inventory:read").grants(&scope("inventory:read")));
+        assert!(scope("inventory:*").grants(&scope("inventory:write")));
+        assert!(scope("*:read").grants(&scope("orders:read:all")));
+        assert!(scope("*").grants(&scope("anything:at:all")));
+
+        assert!(!scope("inventory:read").grants(&scope("inventory")));
+        assert!(!scope("inventory:read").grants(&scope("inventory:write")));
+        assert!(!scope("inventory:*").grants(&scope("inventory")));
+        assert!(!scope("orders:read").grants(&scope("*:read")));
+    }
+
+    #[test]
+    fn rejects_malformed_scopes() {
+        for value in ["", "a::b", ":a", "a:", "has space", &"x".repeat(65)] {
+            assert!(Scope::new(value).is_err(), "{value:?} should be rejected");
+        }
+    }
+}
@@ -10,8 +10,8 @@ use syn::parse::{Parse, ParseStream};
 use syn::parse_macro_input;
 use syn::spanned::Spanned;
 use syn::{
-    Error, Expr, ExprArray, Ident, ItemFn, Lit, LitStr, MetaNameValue, PathArguments, Result,
-    ReturnType, Type, parse_quote,
+    Error, Expr, ExprArray, Ident, ItemFn, Lit, LitBool, LitStr, MetaNameValue, PathArguments,
+    Result, ReturnType, Type, parse_quote,
 };
 
 #[derive(Default)]
@@ -20,6 +20,7 @@ struct ToolArgs {
     version: Option<LitStr>,
     description: Option<LitStr>,
     capabilities: Vec<LitStr>,
+    idempotent: Option<LitBool>,
 }
 
 impl ToolArgs {
@@ -35,10 +36,12 @@ impl ToolArgs {
                 parsed.description = Some(expect_lit_str(value, "description")?);
             } else if path.is_ident("capabilities") {
                 parsed.capabilities = parse_capabilities(value)?;
+            } else if path.is_ident("idempotent") {
+                parsed.idempotent = Some(expect_lit_bool(value, "idempotent")?);
             } else {
                 return Err(Error::new(
                     path.span(),
-                    "unsupported attribute key; expected one of `name`, `version`, `description`, or `capabilities`",
+                    "unsupported attribute key; expected one of `name`, `version`, `description`, `capabilities`, or `idempotent`",
                 ));
             }
         }
@@ -73,6 +76,19 @@ fn expect_lit_str(expr: Expr, field: &str) -> Result<LitStr> {
     }
 }
 
+fn expect_lit_bool(expr: Expr, field: &str) -> Result<LitBool> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Bool(lit),
+            ..
+        }) => Ok(lit),
+        other => Err(Error::new(
+            other.span(),
+            format!("`{field}` must be a boolean literal"),
+        )),
+    }
+}
+
 fn parse_capabilities(expr: Expr) -> Result<Vec<LitStr>> {
     match expr {
         Expr::Array(ExprArray { elems, .. }) => {
@@ -267,6 +283,12 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
+    let idempotent_stmt = args.idempotent.map(|idempotent| {
+        quote! {
+            metadata = metadata.with_idempotent(#idempotent);
+        }
+    });
+
     let decode_arguments = if arguments.len() == 1 {
         let (ident, ty) = &arguments[0];
         quote! {
@@ -319,6 +341,7 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
             let mut metadata = ::agent_tools::registry::ToolMetadata::new(#name_lit, #version_lit)?;
             #description_stmt
             #capabilities_stmt
+            #idempotent_stmt
 
             Ok(::agent_tools::registry::ToolBinding::new(
                 metadata,
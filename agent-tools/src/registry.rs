@@ -127,6 +127,8 @@ pub struct ToolMetadata {
     description: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     capabilities: Vec<CapabilityId>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    idempotent: bool,
 }
 
 impl ToolMetadata {
@@ -155,6 +157,7 @@ impl ToolMetadata {
             version,
             description: None,
             capabilities: Vec::new(),
+            idempotent: false,
         })
     }
 
@@ -172,6 +175,14 @@ impl ToolMetadata {
         self
     }
 
+    /// Marks the tool as safe to run again with the same input, which lets an
+    /// interrupted call be resumed from the step that invoked it.
+    #[must_use]
+    pub const fn with_idempotent(mut self, idempotent: bool) -> Self {
+        self.idempotent = idempotent;
+        self
+    }
+
     /// Returns the tool name.
     #[must_use]
     pub fn name(&self) -> &str {
@@ -195,6 +206,12 @@ impl ToolMetadata {
     pub fn capabilities(&self) -> &[CapabilityId] {
         &self.capabilities
     }
+
+    /// Returns whether the tool may be run again with the same input.
+    #[must_use]
+    pub const fn is_idempotent(&self) -> bool {
+        self.idempotent
+    }
 }
 
 /// Trait implemented by tool executors.
@@ -143,7 +143,7 @@ async fn main() -> Result<()> {
                                         // Forward response to original client
                                         let response_msg = Message::new(
                                             MessageType::Response,
-                                            msg.payload().to_vec(),
+                                            msg.payload().as_ref(),
                                         );
                                         match handle_clone.send(&response_msg.encode(), client_addr)
                                         {
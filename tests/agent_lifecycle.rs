@@ -93,8 +93,8 @@ async fn kernel_handles_messages_and_registry_hooks() {
         ),
     );
 
-    kernel.transition(LifecycleEvent::Boot).unwrap();
-    kernel.transition(LifecycleEvent::Activate).unwrap();
+    kernel.transition(LifecycleEvent::Boot).await.unwrap();
+    kernel.transition(LifecycleEvent::Activate).await.unwrap();
 
     // Give registry loop time to register and emit heartbeats.
     tokio::time::sleep(Duration::from_millis(60)).await;
@@ -121,8 +121,8 @@ async fn kernel_handles_messages_and_registry_hooks() {
     assert_eq!(outcomes[0].response(), "static-response");
     assert_eq!(outcomes[0].tool_results().len(), 1);
 
-    kernel.transition(LifecycleEvent::Retire).unwrap();
-    kernel.transition(LifecycleEvent::Terminate).unwrap();
+    kernel.transition(LifecycleEvent::Retire).await.unwrap();
+    kernel.transition(LifecycleEvent::Terminate).await.unwrap();
 
     // Allow deregistration task to run.
     tokio::time::sleep(Duration::from_millis(40)).await;